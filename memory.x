@@ -1,6 +1,7 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    /* The last 64K, from 0x1F0000, holds the data log, preferences, events and stats; the image must not reach it */
+    FLASH : ORIGIN = 0x10000100, LENGTH = 0x1F0000 - 0x100
     RAM   : ORIGIN = 0x20000000, LENGTH = 256K
 }
 
@@ -0,0 +1,228 @@
+#[cfg(feature = "hardware")]
+use crate::rendering::duty_from_percent;
+#[cfg(feature = "hardware")]
+use embedded_hal::pwm::SetDutyCycle;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::bank0::Gpio6;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::{FunctionNull, Pin, PullDown};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::pwm::{FreeRunning, Pwm3, Slice};
+
+/// The tone each kind of alarm is played with on a passive buzzer, so they can be told apart by ear
+///
+/// - **Fire**: Smoke was detected; the highest and most piercing tone
+/// - **Heat**: The temperature rose above its alarm band
+/// - **Frost**: The temperature fell below its alarm band; the lowest tone
+/// - **Notification**: Anything less urgent, such as the escalation alarm or the low voltage chirp
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Tone {
+    Fire,
+    Heat,
+    Frost,
+    #[default]
+    Notification,
+}
+
+impl Tone {
+    /// Gets the frequency the tone is played at
+    ///
+    /// returns the frequency in Hz
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::buzzer::Tone;
+    ///
+    /// assert!(Tone::Fire.frequency() > Tone::Heat.frequency());
+    /// assert!(Tone::Frost.frequency() < Tone::Notification.frequency());
+    /// ```
+    pub fn frequency(self) -> u32 {
+        match self {
+            Tone::Fire => 3000,
+            Tone::Heat => 2500,
+            Tone::Frost => 1000,
+            Tone::Notification => 2000,
+        }
+    }
+}
+
+/// The divider and wrap of a PWM slice that produce a frequency.
+/// The slice counts from 0 to `top` at the system clock divided by `div_int + div_frac / 16`
+///
+/// - **div_int**: The integer part of the clock divider; 1 - 255
+/// - **div_frac**: The fractional part of the clock divider, in 16ths
+/// - **top**: The value the counter wraps at
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PwmConfig {
+    pub div_int: u8,
+    pub div_frac: u8,
+    pub top: u16,
+}
+
+/// Works out the [PwmConfig] that plays a frequency.
+/// The divider is kept as small as possible, so `top` is as large as possible and the duty cycle is fine grained
+///
+/// - param freq_hz: The frequency to play in Hz
+/// - param sys_clk_hz: The system clock the PWM slice runs from in Hz
+///
+/// returns the [PwmConfig], or None if the frequency can't be reached from the system clock
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::buzzer::{pwm_config, PwmConfig};
+///
+/// let sys_clk_hz = 125_000_000;
+/// // 125MHz / 1 / 62500 = 2kHz
+/// assert_eq!(pwm_config(2000, sys_clk_hz), Some(PwmConfig { div_int: 1, div_frac: 0, top: 62499 }));
+/// // Lower tones need the divider
+/// assert_eq!(pwm_config(1000, sys_clk_hz), Some(PwmConfig { div_int: 1, div_frac: 15, top: 64515 }));
+/// assert_eq!(pwm_config(440, sys_clk_hz), Some(PwmConfig { div_int: 4, div_frac: 6, top: 64934 }));
+/// // Frequencies that don't divide evenly are rounded to the nearest
+/// assert_eq!(pwm_config(3000, sys_clk_hz), Some(PwmConfig { div_int: 1, div_frac: 0, top: 41666 }));
+///
+/// // The slowest the divider and counter can go
+/// assert_eq!(pwm_config(8, sys_clk_hz), Some(PwmConfig { div_int: 238, div_frac: 7, top: 65530 }));
+/// assert_eq!(pwm_config(7, sys_clk_hz), None);
+/// // Too fast to leave a duty cycle
+/// assert_eq!(pwm_config(100_000_000, sys_clk_hz), None);
+/// assert_eq!(pwm_config(0, sys_clk_hz), None);
+/// ```
+pub fn pwm_config(freq_hz: u32, sys_clk_hz: u32) -> Option<PwmConfig> {
+    if freq_hz == 0 {
+        return None;
+    }
+    // Work in 16ths of the divider, so the fractional part comes for free
+    let clock = sys_clk_hz as u64 * 16;
+    let freq = freq_hz as u64;
+    let divider = clock.div_ceil(freq * (u16::MAX as u64 + 1)).max(16);
+    if divider > 0xFFF {
+        return None;
+    }
+    let wrap = (clock + divider * freq / 2) / (divider * freq);
+    if wrap < 2 {
+        return None;
+    }
+    Some(PwmConfig {
+        div_int: (divider >> 4) as u8,
+        div_frac: (divider & 0xF) as u8,
+        top: (wrap - 1) as u16,
+    })
+}
+
+/// Maps the buzzer volume onto the duty cycle a passive buzzer is played with.
+/// A square wave is loudest at half duty, so 100% volume is a 50% duty cycle
+///
+/// - param volume: The volume in percent
+///
+/// returns the duty cycle in percent
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::buzzer::volume_duty;
+///
+/// assert_eq!(volume_duty(100), 50);
+/// assert_eq!(volume_duty(50), 25);
+/// assert_eq!(volume_duty(10), 5);
+/// assert_eq!(volume_duty(200), 50);
+/// ```
+pub fn volume_duty(volume: u8) -> u8 {
+    volume.min(100) / 2
+}
+
+/// Drives the buzzer (GPIO6) from channel A of PWM slice 3.
+/// A passive buzzer is played as a [Tone], while an active buzzer is held fully on as it makes its own tone
+///
+/// - **slice**: The PWM slice; channel A is the buzzer
+/// - **sys_clk_hz**: The system clock the slice runs from in Hz
+/// - **playing**: The frequency and duty cycle being played, if any; kept so the slice isn't reconfigured every pass
+#[cfg(feature = "hardware")]
+pub struct Buzzer {
+    slice: Slice<Pwm3, FreeRunning>,
+    sys_clk_hz: u32,
+    playing: Option<(u32, u8)>,
+}
+
+#[cfg(feature = "hardware")]
+impl Buzzer {
+    /// Creates a new instance of Buzzer, silent until told otherwise
+    ///
+    /// - param slice: PWM slice 3
+    /// - param pin: GPIO6, wired to the buzzer
+    /// - param sys_clk_hz: The system clock in Hz
+    /// - param active_high: If the buzzer sounds while its pin is high
+    ///
+    /// returns a new instance of Buzzer
+    pub fn new(
+        mut slice: Slice<Pwm3, FreeRunning>,
+        pin: Pin<Gpio6, FunctionNull, PullDown>,
+        sys_clk_hz: u32,
+        active_high: bool,
+    ) -> Buzzer {
+        slice.channel_a.output_to(pin);
+        let mut buzzer = Buzzer {
+            slice,
+            sys_clk_hz,
+            playing: None,
+        };
+        buzzer.set_active_high(active_high);
+        buzzer.silence();
+        buzzer.slice.enable();
+        buzzer
+    }
+
+    /// Sets the level the buzzer sounds at; an active-low buzzer has its channel inverted,
+    /// so a duty cycle of 0 leaves its pin high
+    ///
+    /// - param active_high: If the buzzer sounds while its pin is high
+    pub fn set_active_high(&mut self, active_high: bool) {
+        if active_high {
+            self.slice.channel_a.clear_inverted();
+        } else {
+            self.slice.channel_a.set_inverted();
+        }
+    }
+
+    /// Plays a tone until told otherwise
+    ///
+    /// - param freq_hz: The frequency in Hz. See [pwm_config]
+    /// - param duty: The duty cycle in percent; 100 holds the buzzer on
+    pub fn tone(&mut self, freq_hz: u32, duty: u8) {
+        if self.playing == Some((freq_hz, duty)) {
+            return;
+        }
+        let Some(config) = pwm_config(freq_hz, self.sys_clk_hz) else {
+            return self.silence();
+        };
+        self.slice.set_div_int(config.div_int);
+        self.slice.set_div_frac(config.div_frac);
+        self.slice.set_top(config.top);
+        let channel = &mut self.slice.channel_a;
+        if duty >= 100 {
+            channel.set_duty_cycle_fully_on().unwrap();
+        } else {
+            let level = duty_from_percent(duty, channel.max_duty_cycle());
+            channel.set_duty_cycle(level).unwrap();
+        }
+        self.playing = Some((freq_hz, duty));
+    }
+
+    /// Stops the buzzer
+    pub fn silence(&mut self) {
+        self.slice.channel_a.set_duty_cycle(0).unwrap();
+        self.playing = None;
+    }
+
+    /// Sounds or silences the buzzer; called by the alarm patterns on every pass
+    ///
+    /// - param tone: The [Tone] to play, or None to stay silent
+    /// - param passive: If the buzzer is passive and needs a tone. See [Preferences::passive_buzzer](crate::preferences::Preferences::passive_buzzer)
+    /// - param volume: The volume of a passive buzzer in percent
+    pub fn play(&mut self, tone: Option<Tone>, passive: bool, volume: u8) {
+        match tone {
+            Some(tone) if passive => self.tone(tone.frequency(), volume_duty(volume)),
+            // An active buzzer only needs power
+            Some(tone) => self.tone(tone.frequency(), 100),
+            None => self.silence(),
+        }
+    }
+}
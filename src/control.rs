@@ -0,0 +1,1373 @@
+use crate::preferences::{Co2Policy, FaultPolicy, Preferences, TemperatureModel, TimeOfDay};
+use crate::rendering::LABELS;
+use crate::timer::CountDownTimer;
+
+/// The state the actuators should be driven to after a sensor refresh
+///
+/// - **roof_vent**: If the roof vent should be open
+/// - **sprinklers**: If the sprinklers should be on
+/// - **mister**: If the humidity calls for misting; a [MistPulser] turns this into pulses
+/// - **dehumidify**: If the humidity calls for drying out; the roof vent opens for it when allowed
+/// - **heat**: The [HeatStage] the heater should run at
+/// - **co2_vent**: If the CO2 calls for fresh air; the roof vent opens for it when allowed
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Actuators {
+    pub roof_vent: bool,
+    pub sprinklers: bool,
+    pub mister: bool,
+    pub dehumidify: bool,
+    pub heat: HeatStage,
+    pub co2_vent: bool,
+}
+
+/// Checks if the sensor has had time to settle since boot.
+/// Until then, readings are shown but not acted on
+///
+/// - param uptime_ms: The milliseconds since boot
+/// - param warmup_secs: The `warmup_secs` of the [Preferences]
+///
+/// returns true once the warm-up has passed
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::is_warmed_up;
+///
+/// assert!(!is_warmed_up(0, 30));
+/// assert!(!is_warmed_up(29_999, 30));
+/// assert!(is_warmed_up(30_000, 30));
+/// assert!(is_warmed_up(0, 0)); // No warm-up
+/// assert!(is_warmed_up(u64::MAX, 120));
+/// ```
+pub fn is_warmed_up(uptime_ms: u64, warmup_secs: u8) -> bool {
+    uptime_ms >= warmup_secs as u64 * 1000
+}
+
+/// Checks if the temperature is outside of the alarm band
+///
+/// - param temperature: The temperature in Fahrenheit
+/// - param band: The `temperature_alarm` band of the [Preferences]
+///
+/// returns true if the alarm should sound
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::is_temperature_alarm;
+///
+/// assert!(!is_temperature_alarm(70, (40, 95)));
+/// // The bounds themselves are still safe
+/// assert!(!is_temperature_alarm(40, (40, 95)));
+/// assert!(!is_temperature_alarm(95, (40, 95)));
+/// assert!(is_temperature_alarm(39, (40, 95)));
+/// assert!(is_temperature_alarm(96, (40, 95)));
+/// ```
+pub fn is_temperature_alarm(temperature: u8, band: (u8, u8)) -> bool {
+    temperature < band.0 || temperature > band.1
+}
+
+/// Decides if a reading outside of a band calls for action.
+/// Once active, the reading must move `hysteresis` back inside the band before it stops,
+/// so a reading sitting on the edge of the band doesn't toggle the output every poll.
+///
+/// **NOTE: The hysteresis is limited to half of the band**
+///
+/// - param value: The current reading
+/// - param band: The acceptable range of readings
+/// - param hysteresis: How far back inside the band the reading must move
+/// - param active: If the output is currently active
+///
+/// returns if the output should be active
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::band_demand;
+///
+/// // A flat reading exactly on the edge keeps whatever state it is in
+/// let mut active = false;
+/// for _ in 0..10 {
+///     active = band_demand(60, (60, 70), 3, active);
+///     assert!(!active);
+/// }
+/// active = true;
+/// for _ in 0..10 {
+///     active = band_demand(60, (60, 70), 3, active);
+///     assert!(active);
+/// }
+///
+/// // Below the band starts, and it keeps going until 3 inside the band
+/// assert!(band_demand(59, (60, 70), 3, false));
+/// assert!(band_demand(62, (60, 70), 3, true));
+/// assert!(!band_demand(63, (60, 70), 3, true));
+///
+/// // The upper bound behaves the same way
+/// assert!(!band_demand(70, (60, 70), 3, false));
+/// assert!(band_demand(71, (60, 70), 3, false));
+/// assert!(band_demand(68, (60, 70), 3, true));
+/// assert!(!band_demand(67, (60, 70), 3, true));
+///
+/// // A narrow band still stops in the middle
+/// assert!(!band_demand(61, (60, 62), 3, true));
+/// ```
+pub fn band_demand(value: u8, band: (u8, u8), hysteresis: u8, active: bool) -> bool {
+    let hysteresis = hysteresis.min(band.1.saturating_sub(band.0) / 2);
+    if active {
+        value < band.0.saturating_add(hysteresis) || value > band.1.saturating_sub(hysteresis)
+    } else {
+        value < band.0 || value > band.1
+    }
+}
+
+/// What the humidity calls for. See [humidity_action]
+///
+/// - **Humidify**: The humidity is below its range; the mister runs
+/// - **Hold**: The humidity is in its range, or settling back into it
+/// - **Dehumidify**: The humidity is above its range; the roof vent opens to dry the air
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HumidityAction {
+    Humidify,
+    #[default]
+    Hold,
+    Dehumidify,
+}
+
+/// Decides which way the humidity should be driven.
+/// Either action starts once the humidity leaves the band, and keeps going until it is
+/// back inside by the hysteresis; in between is a deadband that holds the previous action
+///
+/// **NOTE: The hysteresis is limited to half of the band**
+///
+/// - param humidity: The current relative humidity percentage
+/// - param band: The acceptable range of humidity
+/// - param hysteresis: How far back inside the band the humidity must move
+/// - param previous: The [HumidityAction] decided last time
+///
+/// returns the [HumidityAction]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{humidity_action, HumidityAction};
+///
+/// let band = (60, 70);
+///
+/// // Inside the band, including its edges, nothing starts
+/// for humidity in 60..=70 {
+///     assert_eq!(humidity_action(humidity, band, 3, HumidityAction::Hold), HumidityAction::Hold);
+/// }
+///
+/// // Below the band humidifies until 3% inside it
+/// assert_eq!(humidity_action(59, band, 3, HumidityAction::Hold), HumidityAction::Humidify);
+/// assert_eq!(humidity_action(60, band, 3, HumidityAction::Humidify), HumidityAction::Humidify);
+/// assert_eq!(humidity_action(62, band, 3, HumidityAction::Humidify), HumidityAction::Humidify);
+/// assert_eq!(humidity_action(63, band, 3, HumidityAction::Humidify), HumidityAction::Hold);
+///
+/// // Above the band dehumidifies until 3% inside it
+/// assert_eq!(humidity_action(71, band, 3, HumidityAction::Hold), HumidityAction::Dehumidify);
+/// assert_eq!(humidity_action(70, band, 3, HumidityAction::Dehumidify), HumidityAction::Dehumidify);
+/// assert_eq!(humidity_action(68, band, 3, HumidityAction::Dehumidify), HumidityAction::Dehumidify);
+/// assert_eq!(humidity_action(67, band, 3, HumidityAction::Dehumidify), HumidityAction::Hold);
+///
+/// // A jump straight across the band switches over
+/// assert_eq!(humidity_action(75, band, 3, HumidityAction::Humidify), HumidityAction::Dehumidify);
+/// assert_eq!(humidity_action(55, band, 3, HumidityAction::Dehumidify), HumidityAction::Humidify);
+///
+/// // A narrow band still stops in the middle
+/// assert_eq!(humidity_action(61, (60, 62), 3, HumidityAction::Humidify), HumidityAction::Hold);
+/// assert_eq!(humidity_action(61, (60, 62), 3, HumidityAction::Dehumidify), HumidityAction::Hold);
+/// ```
+pub fn humidity_action(
+    humidity: u8,
+    band: (u8, u8),
+    hysteresis: u8,
+    previous: HumidityAction,
+) -> HumidityAction {
+    let hysteresis = hysteresis.min(band.1.saturating_sub(band.0) / 2);
+    let (low, high) = match previous {
+        HumidityAction::Humidify => (band.0.saturating_add(hysteresis), band.1),
+        HumidityAction::Hold => band,
+        HumidityAction::Dehumidify => (band.0, band.1.saturating_sub(hysteresis)),
+    };
+    if humidity < low {
+        HumidityAction::Humidify
+    } else if humidity > high {
+        HumidityAction::Dehumidify
+    } else {
+        HumidityAction::Hold
+    }
+}
+
+/// Decides if the mister should be on.
+/// It starts once the humidity falls below the range and stops once it is back inside
+/// by the hysteresis. See [humidity_action]
+///
+/// - param humidity: The current relative humidity percentage
+/// - param misting: If the mister is currently on
+/// - param preferences: [Preferences] instance
+///
+/// returns if the mister should be on
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::decide_misting;
+/// use gem_rs::preferences::Preferences;
+///
+/// let mut preferences = Preferences::default(); // 60% - 70%, 3% hysteresis
+///
+/// assert!(decide_misting(40, false, &preferences));
+/// assert!(decide_misting(60, true, &preferences));
+/// assert!(!decide_misting(60, false, &preferences));
+/// assert!(!decide_misting(65, true, &preferences));
+///
+/// // Too humid is for the vent, not the mister
+/// assert!(!decide_misting(80, false, &preferences));
+/// assert!(!decide_misting(80, true, &preferences));
+///
+/// // The schedule has no say over the mister
+/// preferences.set_default_watering_time();
+/// assert!(!decide_misting(65, false, &preferences));
+///
+/// preferences.misting_enabled = false;
+/// assert!(!decide_misting(40, true, &preferences));
+/// ```
+pub fn decide_misting(humidity: u8, misting: bool, preferences: &Preferences) -> bool {
+    let previous = if misting {
+        HumidityAction::Humidify
+    } else {
+        HumidityAction::Hold
+    };
+    preferences.misting_enabled
+        && humidity_action(
+            humidity,
+            preferences.humidity,
+            preferences.humidity_hysteresis,
+            previous,
+        ) == HumidityAction::Humidify
+}
+
+/// The phase of a [MistPulser]
+///
+/// - **Idle**: Waiting for the humidity to call for misting
+/// - **Pulse**: The mister is on
+/// - **Dwell**: The mister is off while the reading responds
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MistPhase {
+    Idle,
+    Pulse,
+    Dwell,
+}
+
+/// Breaks misting into short pulses, each followed by a dwell so the humidity reading can
+/// catch up before the next pulse. This keeps the mister from overshooting the band
+///
+/// - **phase**: The current [MistPhase]
+/// - **countdown**: The time left in the current phase
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::MistPulser;
+///
+/// // 2 second pulses with 3 second dwells, stepped every second
+/// let mut pulser = MistPulser::default();
+/// let mut mister = [false; 10];
+/// for state in mister.iter_mut() {
+///     *state = pulser.step(1000, true, 2, 3);
+/// }
+/// assert_eq!(mister, [true, true, false, false, false, true, true, false, false, false]);
+///
+/// // Humidity back in range cuts a pulse short, but the dwell still runs
+/// let mut pulser = MistPulser::default();
+/// assert!(pulser.step(1000, true, 5, 2));
+/// assert!(!pulser.step(1000, false, 5, 2));
+/// assert!(!pulser.step(1000, true, 5, 2));
+/// assert!(pulser.step(1000, true, 5, 2));
+///
+/// // No dwell keeps the mister on solid
+/// let mut pulser = MistPulser::default();
+/// for _ in 0..5 {
+///     assert!(pulser.step(1000, true, 1, 0));
+/// }
+/// assert!(!pulser.step(1000, false, 1, 0));
+/// ```
+pub struct MistPulser {
+    phase: MistPhase,
+    countdown: CountDownTimer,
+}
+
+impl Default for MistPulser {
+    fn default() -> Self {
+        MistPulser {
+            phase: MistPhase::Idle,
+            countdown: CountDownTimer::new(0),
+        }
+    }
+}
+
+impl MistPulser {
+    /// Advances the pulse and dwell timing
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    /// - param demand: If the humidity calls for misting; see [decide_misting]
+    /// - param pulse_secs: How long each pulse lasts in seconds
+    /// - param dwell_secs: How long to wait after each pulse in seconds
+    ///
+    /// returns if the mister should be on
+    pub fn step(&mut self, elapsed_ms: u16, demand: bool, pulse_secs: u8, dwell_secs: u8) -> bool {
+        self.countdown.tick_by(elapsed_ms);
+        match self.phase {
+            MistPhase::Pulse if !demand || self.countdown.is_finished() => {
+                self.start(MistPhase::Dwell, dwell_secs)
+            }
+            MistPhase::Dwell if self.countdown.is_finished() => self.phase = MistPhase::Idle,
+            _ => {}
+        }
+        if self.phase == MistPhase::Idle && demand {
+            self.start(MistPhase::Pulse, pulse_secs);
+        }
+        self.phase == MistPhase::Pulse
+    }
+
+    /// Starts a phase; phases lasting 0 seconds are skipped
+    ///
+    /// - param phase: The [MistPhase] to start
+    /// - param secs: How long the phase lasts in seconds
+    fn start(&mut self, phase: MistPhase, secs: u8) {
+        self.phase = if secs == 0 { MistPhase::Idle } else { phase };
+        self.countdown.set_time(secs as u16 * 1000);
+    }
+}
+
+/// Checks if a time falls inside the daytime window.
+/// The start is included and the end is not; a window whose start is after its end
+/// wraps past midnight, and a window that starts and ends at the same time covers the whole day
+///
+/// - param time: The current [TimeOfDay]
+/// - param window: The start and end of the daytime
+///
+/// returns if it is daytime
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::is_daytime;
+/// use gem_rs::preferences::TimeOfDay;
+///
+/// let day = (TimeOfDay(0, 6), TimeOfDay(0, 20)); // 06:00 - 20:00
+/// assert!(!is_daytime(TimeOfDay(59, 5), day));
+/// assert!(is_daytime(TimeOfDay(0, 6), day));
+/// assert!(is_daytime(TimeOfDay(59, 19), day));
+/// assert!(!is_daytime(TimeOfDay(0, 20), day));
+/// assert!(!is_daytime(TimeOfDay(0, 0), day));
+///
+/// let night_shift = (TimeOfDay(0, 22), TimeOfDay(0, 4)); // 22:00 - 04:00
+/// assert!(!is_daytime(TimeOfDay(59, 21), night_shift));
+/// assert!(is_daytime(TimeOfDay(0, 22), night_shift));
+/// assert!(is_daytime(TimeOfDay(59, 23), night_shift));
+/// assert!(is_daytime(TimeOfDay(0, 0), night_shift));
+/// assert!(is_daytime(TimeOfDay(59, 3), night_shift));
+/// assert!(!is_daytime(TimeOfDay(0, 4), night_shift));
+/// assert!(!is_daytime(TimeOfDay(0, 12), night_shift));
+///
+/// let always = (TimeOfDay(0, 6), TimeOfDay(0, 6));
+/// assert!(is_daytime(TimeOfDay(0, 0), always));
+/// assert!(is_daytime(TimeOfDay(59, 5), always));
+/// ```
+pub fn is_daytime(time: TimeOfDay, window: (TimeOfDay, TimeOfDay)) -> bool {
+    let (now, start, end) = (time.minutes(), window.0.minutes(), window.1.minutes());
+    if start < end {
+        now >= start && now < end
+    } else if start > end {
+        // The window wraps past midnight
+        now >= start || now < end
+    } else {
+        true
+    }
+}
+
+/// Checks if the schedule wants to water but it is outside of the daytime window
+///
+/// - param preferences: [Preferences] instance
+///
+/// returns if scheduled watering is being held
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::is_watering_held;
+/// use gem_rs::preferences::{Preferences, TimeOfDay};
+///
+/// let mut preferences = Preferences::default(); // 00:00:00 Jan 1 2000
+/// preferences.set_default_watering_time(); // 00:00 - 01:00
+/// assert!(!is_watering_held(&preferences));
+///
+/// preferences.daytime_only = true; // 06:00 - 20:00
+/// assert!(is_watering_held(&preferences));
+///
+/// preferences.daytime_window = (TimeOfDay(0, 20), TimeOfDay(0, 6));
+/// assert!(!is_watering_held(&preferences));
+/// ```
+pub fn is_watering_held(preferences: &Preferences) -> bool {
+    preferences.daytime_only
+        && preferences.is_watering_time()
+        && !is_daytime(preferences.date.time_of_day(), preferences.daytime_window)
+}
+
+/// Decides if the sprinklers should be on from the watering schedule,
+/// holding it outside of the daytime window when `daytime_only` is set
+///
+/// - param preferences: [Preferences] instance
+///
+/// returns if the sprinklers should be on
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::decide_watering;
+/// use gem_rs::preferences::{DateTime, Preferences};
+///
+/// let mut preferences = Preferences::default(); // 00:00:00 Jan 1 2000, no schedule
+/// assert!(!decide_watering(&preferences));
+///
+/// preferences.set_default_watering_time(); // 00:00 - 01:00
+/// assert!(decide_watering(&preferences));
+///
+/// preferences.date = DateTime(0, 0, 2, 1, 1, 2000);
+/// assert!(!decide_watering(&preferences));
+///
+/// preferences.date = DateTime(0, 30, 0, 1, 1, 2000);
+/// preferences.daytime_only = true; // 06:00 - 20:00
+/// assert!(!decide_watering(&preferences));
+///
+/// preferences.daytime_only = false;
+/// preferences.watering_enabled = false;
+/// assert!(!decide_watering(&preferences));
+/// ```
+pub fn decide_watering(preferences: &Preferences) -> bool {
+    preferences.watering_enabled && preferences.is_watering_time() && !is_watering_held(preferences)
+}
+
+/// The temperature in Fahrenheit at or below which the roof vent is held closed and the heater runs at full heat against frost
+pub const FROST_F: u8 = 34;
+
+/// The position of the roof vent and what decided it
+///
+/// - **Open**: Too hot, so the vent is open
+/// - **Closed**: The temperature doesn't call for the vent
+/// - **RainClosed**: Rain is holding the vent closed
+/// - **FrostClosed**: Frost is holding the vent closed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VentState {
+    Open,
+    Closed,
+    RainClosed,
+    FrostClosed,
+}
+
+impl VentState {
+    /// Checks if the vent should be open
+    ///
+    /// returns true only for [VentState::Open]
+    pub fn is_open(self) -> bool {
+        self == VentState::Open
+    }
+}
+
+/// The temperature range the roof vent keeps
+///
+/// - **Day**: [Preferences::temperature]
+/// - **Night**: [Preferences::night_temperature]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TemperatureBand {
+    Day,
+    Night,
+}
+
+impl TemperatureBand {
+    /// Gets the label shown for the band
+    ///
+    /// returns the label
+    pub fn label(self) -> &'static str {
+        match self {
+            TemperatureBand::Day => LABELS.day_band,
+            TemperatureBand::Night => LABELS.night_band,
+        }
+    }
+}
+
+/// Selects the temperature band for an hour of the day.
+/// The night may wrap past midnight, and equal start hours keep the day band all day
+///
+/// - param hour: The current hour
+/// - param day_start: The hour the day band takes over
+/// - param night_start: The hour the night band takes over
+///
+/// returns the active [TemperatureBand]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{active_band, TemperatureBand};
+///
+/// // Day from 06:00, night from 20:00
+/// assert_eq!(active_band(5, 6, 20), TemperatureBand::Night);
+/// assert_eq!(active_band(6, 6, 20), TemperatureBand::Day);
+/// assert_eq!(active_band(19, 6, 20), TemperatureBand::Day);
+/// assert_eq!(active_band(20, 6, 20), TemperatureBand::Night);
+/// assert_eq!(active_band(0, 6, 20), TemperatureBand::Night);
+///
+/// // A night shift greenhouse runs its day band past midnight
+/// assert_eq!(active_band(23, 18, 8), TemperatureBand::Day);
+/// assert_eq!(active_band(12, 18, 8), TemperatureBand::Night);
+///
+/// // Equal hours never switch to the night band
+/// assert_eq!(active_band(0, 6, 6), TemperatureBand::Day);
+/// ```
+pub fn active_band(hour: u8, day_start: u8, night_start: u8) -> TemperatureBand {
+    if is_daytime(
+        TimeOfDay(0, hour),
+        (TimeOfDay(0, day_start), TimeOfDay(0, night_start)),
+    ) {
+        TemperatureBand::Day
+    } else {
+        TemperatureBand::Night
+    }
+}
+
+/// Where a reading sits against a configured range
+///
+/// - **Below**: Under the lower bound
+/// - **Within**: Between the bounds, inclusive
+/// - **Above**: Over the upper bound
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BandPosition {
+    Below,
+    Within,
+    Above,
+}
+
+/// Classifies a reading against a range
+///
+/// - param value: The reading
+/// - param band: The range as `(min, max)`
+///
+/// returns the [BandPosition] of the reading
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{classify_band, BandPosition};
+///
+/// assert_eq!(classify_band(59, (60, 80)), BandPosition::Below);
+/// assert_eq!(classify_band(60, (60, 80)), BandPosition::Within);
+/// assert_eq!(classify_band(80, (60, 80)), BandPosition::Within);
+/// assert_eq!(classify_band(81, (60, 80)), BandPosition::Above);
+/// // A single value range still has an inside
+/// assert_eq!(classify_band(70, (70, 70)), BandPosition::Within);
+/// ```
+pub fn classify_band(value: u8, band: (u8, u8)) -> BandPosition {
+    if value < band.0 {
+        BandPosition::Below
+    } else if value > band.1 {
+        BandPosition::Above
+    } else {
+        BandPosition::Within
+    }
+}
+
+/// Tracks how long a reading has been outside of its band, so an excursion the automation
+/// can't cope with is escalated rather than left to the instantaneous control
+///
+/// - **out_ms**: How long the reading has been out of band in milliseconds; 0 while within it
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{BandPosition, OutOfBandTracker};
+///
+/// // Escalate after a minute out of band
+/// let mut tracker = OutOfBandTracker::default();
+/// assert!(!tracker.step(30_000, BandPosition::Above, 1));
+/// assert!(!tracker.step(29_999, BandPosition::Above, 1));
+/// assert!(tracker.step(1, BandPosition::Above, 1));
+/// assert_eq!(tracker.out_ms(), 60_000);
+/// // Swinging to the other side doesn't restart it
+/// assert!(tracker.step(1000, BandPosition::Below, 1));
+///
+/// // Back in band clears the escalation and starts over
+/// assert!(!tracker.step(1000, BandPosition::Within, 1));
+/// assert_eq!(tracker.out_ms(), 0);
+/// assert!(!tracker.step(59_000, BandPosition::Above, 1));
+///
+/// // 0 minutes never escalates
+/// let mut tracker = OutOfBandTracker::default();
+/// assert!(!tracker.step(u16::MAX, BandPosition::Above, 0));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OutOfBandTracker {
+    out_ms: u32,
+}
+
+impl OutOfBandTracker {
+    /// Advances the time out of band
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    /// - param position: Where the reading sits; see [classify_band]
+    /// - param limit_mins: How many minutes out of band escalate; 0 never escalates
+    ///
+    /// returns if the reading has been out of band for at least the limit
+    pub fn step(&mut self, elapsed_ms: u16, position: BandPosition, limit_mins: u8) -> bool {
+        if position == BandPosition::Within {
+            self.out_ms = 0;
+        } else {
+            self.out_ms = self.out_ms.saturating_add(elapsed_ms as u32);
+        }
+        limit_mins > 0 && self.out_ms >= limit_mins as u32 * 60_000
+    }
+
+    /// Gets how long the reading has been out of band
+    ///
+    /// returns the time in milliseconds
+    pub fn out_ms(&self) -> u32 {
+        self.out_ms
+    }
+}
+
+/// Gets the temperature range the roof vent keeps right now
+///
+/// - param preferences: [Preferences] instance
+///
+/// returns the active [TemperatureBand] and its range in Fahrenheit
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{active_temperature, TemperatureBand};
+/// use gem_rs::preferences::{DateTime, Preferences};
+///
+/// let mut preferences = Preferences::default(); // 60F - 80F from 06:00, 55F - 75F from 20:00
+/// preferences.date = DateTime(0, 0, 12, 1, 1, 2000);
+/// assert_eq!(active_temperature(&preferences), (TemperatureBand::Day, (60, 80)));
+/// preferences.date = DateTime(0, 0, 22, 1, 1, 2000);
+/// assert_eq!(active_temperature(&preferences), (TemperatureBand::Night, (55, 75)));
+/// ```
+pub fn active_temperature(preferences: &Preferences) -> (TemperatureBand, (u8, u8)) {
+    let band = active_band(
+        preferences.date.2,
+        preferences.day_start_hour,
+        preferences.night_start_hour,
+    );
+    match band {
+        TemperatureBand::Day => (band, preferences.temperature),
+        TemperatureBand::Night => (band, preferences.night_temperature),
+    }
+}
+
+/// Gets how far open the vent should be for a temperature, from the [Preferences::vent_curve]
+/// and the top of the active temperature range. See [Preferences::vent_percent_for]
+///
+/// - param temperature: The current temperature in Fahrenheit
+/// - param preferences: [Preferences] instance
+///
+/// returns the percent the vent should be open
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::vent_opening;
+/// use gem_rs::preferences::{DateTime, Preferences};
+///
+/// let mut preferences = Preferences::default(); // 0F: 0%, 1F: 25%, 3F: 50%, 5F: 100% over the range
+/// preferences.date = DateTime(0, 0, 12, 1, 1, 2000); // Midday, so the curve starts at 80F
+/// assert_eq!(vent_opening(70, &preferences), 0);
+/// assert_eq!(vent_opening(83, &preferences), 50);
+/// assert_eq!(vent_opening(90, &preferences), 100);
+///
+/// // At night the curve starts at 75F
+/// preferences.date = DateTime(0, 0, 22, 1, 1, 2000);
+/// assert_eq!(vent_opening(80, &preferences), 100);
+/// ```
+pub fn vent_opening(temperature: u8, preferences: &Preferences) -> u8 {
+    let (_, (_, high)) = active_temperature(preferences);
+    preferences.vent_percent_for(temperature as i16 - high as i16)
+}
+
+/// Decides the roof vent position.
+/// Frost comes first, then rain if [Preferences::rain_closes_vent] is set, then the active temperature range.
+/// Once open, the vent stays open until the temperature falls [Preferences::temperature_hysteresis] back inside the range,
+/// so neither a reading on the edge nor the switch between the day and night ranges flaps the vent.
+/// With [TemperatureModel::Setpoint] the vent instead stays open until the temperature falls below `target - swing`
+///
+/// **NOTE: The hysteresis is limited to half of the range**
+///
+/// - param temperature: The current temperature in Fahrenheit
+/// - param rain: If the rain sensor is wet
+/// - param open: If the vent is currently open
+/// - param preferences: [Preferences] instance
+///
+/// returns the [VentState]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{decide_vent, VentState, FROST_F};
+/// use gem_rs::preferences::{DateTime, Preferences, TemperatureModel};
+///
+/// let mut preferences = Preferences::default(); // 2F hysteresis, rain closes the vent
+/// preferences.date = DateTime(0, 0, 12, 1, 1, 2000); // Midday, so the vent opens above 80F
+///
+/// assert_eq!(decide_vent(70, false, false, &preferences), VentState::Closed);
+/// assert_eq!(decide_vent(85, false, false, &preferences), VentState::Open);
+///
+/// // Once open, it stays open until 78F
+/// assert_eq!(decide_vent(80, false, false, &preferences), VentState::Closed);
+/// assert_eq!(decide_vent(79, false, true, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(78, false, true, &preferences), VentState::Closed);
+///
+/// // Rain beats the temperature, and is shown even when the vent would be closed anyway
+/// assert_eq!(decide_vent(85, true, true, &preferences), VentState::RainClosed);
+/// assert_eq!(decide_vent(70, true, false, &preferences), VentState::RainClosed);
+///
+/// // Frost beats rain
+/// assert_eq!(decide_vent(FROST_F, true, false, &preferences), VentState::FrostClosed);
+///
+/// // Frost beats the temperature, even with a range set below freezing
+/// preferences.temperature = (20, 30);
+/// assert_eq!(decide_vent(FROST_F, false, true, &preferences), VentState::FrostClosed);
+/// assert_eq!(decide_vent(FROST_F + 1, false, false, &preferences), VentState::Open);
+///
+/// // Turning the rain override off leaves the temperature in charge
+/// preferences.temperature = (60, 80);
+/// preferences.rain_closes_vent = false;
+/// assert_eq!(decide_vent(85, true, false, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(70, true, false, &preferences), VentState::Closed);
+///
+/// // At night the vent opens above 75F, and the hysteresis carries an open vent through the switch to day
+/// preferences.date = DateTime(0, 0, 22, 1, 1, 2000);
+/// assert_eq!(decide_vent(77, false, false, &preferences), VentState::Open);
+/// preferences.date = DateTime(0, 0, 6, 2, 1, 2000);
+/// assert_eq!(decide_vent(79, false, true, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(79, false, false, &preferences), VentState::Closed);
+///
+/// // As a setpoint of 70F +- 10F, the vent opens above 80F and closes below 60F
+/// preferences.date = DateTime(0, 0, 12, 1, 1, 2000);
+/// preferences.temperature_model = TemperatureModel::Setpoint;
+/// assert_eq!(decide_vent(80, false, false, &preferences), VentState::Closed);
+/// assert_eq!(decide_vent(81, false, false, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(65, false, true, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(60, false, true, &preferences), VentState::Open);
+/// assert_eq!(decide_vent(59, false, true, &preferences), VentState::Closed);
+/// ```
+pub fn decide_vent(
+    temperature: u8,
+    rain: bool,
+    open: bool,
+    preferences: &Preferences,
+) -> VentState {
+    let (_, (low, high)) = active_temperature(preferences);
+    let stays_open = match preferences.temperature_model {
+        TemperatureModel::Range => {
+            let hysteresis = preferences
+                .temperature_hysteresis
+                .min(high.saturating_sub(low) / 2);
+            temperature > high.saturating_sub(hysteresis)
+        }
+        // The swing is the hysteresis: open above target + swing, closed below target - swing
+        TemperatureModel::Setpoint => temperature >= low,
+    };
+    if temperature <= FROST_F {
+        VentState::FrostClosed
+    } else if rain && preferences.rain_closes_vent {
+        VentState::RainClosed
+    } else if temperature > high || (open && stays_open) {
+        VentState::Open
+    } else {
+        VentState::Closed
+    }
+}
+
+/// How hard the heater runs
+///
+/// - **Off**: The temperature doesn't call for heat
+/// - **Low**: A mild deficit; only the first heater stage runs
+/// - **High**: A large deficit or frost; both heater stages run
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum HeatStage {
+    #[default]
+    Off,
+    Low,
+    High,
+}
+
+/// How the heater is staged below the temperature range. See [heating_stage]
+///
+/// - **hysteresis**: How far in Fahrenheit the temperature must rise back before a stage stops
+/// - **full_below**: How far in Fahrenheit below the range full heat starts
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HeatMargins {
+    pub hysteresis: u8,
+    pub full_below: u8,
+}
+
+/// Decides how hard the heater runs.
+/// Low heat starts below the range, and full heat once the temperature is `full_below` below it.
+/// Each stage keeps running until the temperature rises `hysteresis` past where it started, so a
+/// reading sitting on either edge doesn't flap the heater. At or below [FROST_F] it always runs at full heat
+///
+/// **NOTE: The hysteresis is limited to half of the range**
+///
+/// - param temperature: The current temperature in Fahrenheit
+/// - param band: The active temperature range in Fahrenheit
+/// - param margins: The [HeatMargins] the heater is staged with
+/// - param previous: The [HeatStage] the heater is running at
+///
+/// returns the [HeatStage] to run at
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{heating_stage, HeatMargins, HeatStage, FROST_F};
+///
+/// let margins = HeatMargins { hysteresis: 2, full_below: 5 };
+/// let stage = |temperature, previous| heating_stage(temperature, (60, 80), margins, previous);
+///
+/// // In the range there is nothing to do
+/// assert_eq!(stage(60, HeatStage::Off), HeatStage::Off);
+/// assert_eq!(stage(70, HeatStage::Off), HeatStage::Off);
+///
+/// // A mild deficit runs low heat, a large one full heat
+/// assert_eq!(stage(59, HeatStage::Off), HeatStage::Low);
+/// assert_eq!(stage(55, HeatStage::Off), HeatStage::Low);
+/// assert_eq!(stage(54, HeatStage::Off), HeatStage::High);
+///
+/// // Full heat drops to low heat at 57F, and low heat stops at 62F
+/// assert_eq!(stage(56, HeatStage::High), HeatStage::High);
+/// assert_eq!(stage(57, HeatStage::High), HeatStage::Low);
+/// assert_eq!(stage(61, HeatStage::Low), HeatStage::Low);
+/// assert_eq!(stage(62, HeatStage::Low), HeatStage::Off);
+/// assert_eq!(stage(61, HeatStage::High), HeatStage::Low);
+/// assert_eq!(stage(62, HeatStage::High), HeatStage::Off);
+///
+/// // A flat reading on either edge keeps whatever stage it is in
+/// for previous in [HeatStage::Off, HeatStage::Low] {
+///     assert_eq!(stage(60, previous), previous);
+/// }
+/// for previous in [HeatStage::Low, HeatStage::High] {
+///     assert_eq!(stage(55, previous), previous);
+/// }
+///
+/// // Frost always runs full heat, even with a range set below freezing
+/// assert_eq!(stage(FROST_F, HeatStage::Off), HeatStage::High);
+/// assert_eq!(heating_stage(FROST_F, (20, 30), margins, HeatStage::Off), HeatStage::High);
+/// assert_eq!(heating_stage(FROST_F + 1, (20, 30), margins, HeatStage::High), HeatStage::Off);
+///
+/// // No margin goes straight to full heat
+/// let margins = HeatMargins { hysteresis: 2, full_below: 0 };
+/// assert_eq!(heating_stage(59, (60, 80), margins, HeatStage::Off), HeatStage::High);
+/// assert_eq!(heating_stage(61, (60, 80), margins, HeatStage::High), HeatStage::High);
+/// assert_eq!(heating_stage(62, (60, 80), margins, HeatStage::High), HeatStage::Off);
+///
+/// // A narrow range still stops in the middle
+/// let margins = HeatMargins { hysteresis: 3, full_below: 5 };
+/// assert_eq!(heating_stage(61, (60, 62), margins, HeatStage::Low), HeatStage::Off);
+/// ```
+pub fn heating_stage(
+    temperature: u8,
+    band: (u8, u8),
+    margins: HeatMargins,
+    previous: HeatStage,
+) -> HeatStage {
+    let hysteresis = margins.hysteresis.min(band.1.saturating_sub(band.0) / 2);
+    // A running stage holds until the temperature is the hysteresis past where it starts
+    let starts = |edge: u8, stage: HeatStage| {
+        let edge = if previous >= stage {
+            edge.saturating_add(hysteresis)
+        } else {
+            edge
+        };
+        temperature < edge
+    };
+    if temperature <= FROST_F || starts(band.0.saturating_sub(margins.full_below), HeatStage::High)
+    {
+        HeatStage::High
+    } else if starts(band.0, HeatStage::Low) {
+        HeatStage::Low
+    } else {
+        HeatStage::Off
+    }
+}
+
+/// How far in ppm the CO2 must fall back below [Preferences::co2_vent_ppm] before the vent closes for it
+pub const CO2_HYSTERESIS_PPM: u16 = 100;
+
+/// Decides if the CO2 calls for fresh air.
+/// Only [Co2Policy::Vent] vents for CO2; once venting, it carries on until the CO2 is [CO2_HYSTERESIS_PPM] below the threshold
+///
+/// - param co2_ppm: The latest CO2 in ppm, or None if there is no fresh reading
+/// - param policy: The [Co2Policy]
+/// - param vent_ppm: The CO2 in ppm above which the vent opens
+/// - param venting: If the vent is currently open for CO2
+///
+/// returns true if the vent should open for CO2
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::decide_co2_vent;
+/// use gem_rs::preferences::Co2Policy;
+///
+/// // Above the threshold opens the vent, and it stays open until 100ppm below it
+/// assert!(!decide_co2_vent(Some(1500), Co2Policy::Vent, 1500, false));
+/// assert!(decide_co2_vent(Some(1501), Co2Policy::Vent, 1500, false));
+/// assert!(decide_co2_vent(Some(1401), Co2Policy::Vent, 1500, true));
+/// assert!(!decide_co2_vent(Some(1400), Co2Policy::Vent, 1500, true));
+///
+/// // Without a fresh reading there is nothing to vent for
+/// assert!(!decide_co2_vent(None, Co2Policy::Vent, 1500, true));
+///
+/// // Neither the other policies vent for CO2, however high it is
+/// for policy in [Co2Policy::Off, Co2Policy::Enrich] {
+///     assert!(!decide_co2_vent(Some(4000), policy, 1500, false));
+///     assert!(!decide_co2_vent(Some(4000), policy, 1500, true));
+/// }
+/// ```
+pub fn decide_co2_vent(
+    co2_ppm: Option<u16>,
+    policy: Co2Policy,
+    vent_ppm: u16,
+    venting: bool,
+) -> bool {
+    let Some(ppm) = co2_ppm else {
+        return false;
+    };
+    policy == Co2Policy::Vent
+        && (ppm > vent_ppm || (venting && ppm > vent_ppm.saturating_sub(CO2_HYSTERESIS_PPM)))
+}
+
+/// The relative humidity in percent at and above which mildew can take hold
+pub const MILDEW_HUMIDITY: u8 = 85;
+
+/// Estimates the dew point with `Td = T - (100 - RH) / 5` in Celsius.
+/// The approximation is within about 1C above 50% humidity, which covers where mildew is a concern
+///
+/// - param temperature: The temperature in Fahrenheit
+/// - param humidity: The relative humidity percentage
+///
+/// returns the dew point in whole Fahrenheit
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::dew_point;
+///
+/// assert_eq!(dew_point(70, 100), 70); // Saturated air is at its dew point
+/// assert_eq!(dew_point(70, 90), 66); // 66.4F
+/// assert_eq!(dew_point(86, 50), 68);
+/// assert_eq!(dew_point(5, 0), -31); // Below 0F
+/// ```
+pub fn dew_point(temperature: u8, humidity: u8) -> i16 {
+    // Each percent below saturation is 0.2C, or 0.36F
+    let deficit = 100 - humidity.min(100) as i16;
+    temperature as i16 - (deficit * 9 + 12) / 25
+}
+
+/// Checks for the conditions mildew grows in: humid air that is close to its dew point,
+/// so any cooling leaves condensation on the plants
+///
+/// - param temperature: The temperature in Fahrenheit
+/// - param humidity: The relative humidity percentage
+/// - param dew_point: The dew point in Fahrenheit. See [dew_point]
+/// - param spread: How close to the dew point in Fahrenheit the temperature must be; 0 never warns
+///
+/// returns true if the humidity is at least [MILDEW_HUMIDITY] and the temperature is within the spread of the dew point
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{dew_point, is_mildew_risk};
+///
+/// // A damp, still night
+/// assert!(is_mildew_risk(60, 95, dew_point(60, 95), 4));
+/// // 66F dew point at 70F is just within 4F, but not 3F
+/// assert!(is_mildew_risk(70, 90, dew_point(70, 90), 4));
+/// assert!(!is_mildew_risk(70, 90, dew_point(70, 90), 3));
+/// // Close to the dew point, but not humid enough
+/// assert!(!is_mildew_risk(70, 84, 68, 4));
+/// // Humid, but far from the dew point
+/// assert!(!is_mildew_risk(70, 90, 60, 4));
+/// // Turned off
+/// assert!(!is_mildew_risk(60, 100, 60, 0));
+/// ```
+pub fn is_mildew_risk(temperature: u8, humidity: u8, dew_point: i16, spread: u8) -> bool {
+    spread > 0 && humidity >= MILDEW_HUMIDITY && temperature as i16 - dew_point <= spread as i16
+}
+
+/// Checks for a mildew risk at the latest readings with [Preferences::mildew_spread]. See [is_mildew_risk]
+///
+/// - param temperature: The temperature in Fahrenheit
+/// - param humidity: The relative humidity percentage
+/// - param preferences: [Preferences] instance
+///
+/// returns true if mildew is a risk
+pub fn has_mildew_risk(temperature: u8, humidity: u8, preferences: &Preferences) -> bool {
+    is_mildew_risk(
+        temperature,
+        humidity,
+        dew_point(temperature, humidity),
+        preferences.mildew_spread,
+    )
+}
+
+/// Decides the state of the actuators from the latest readings.
+/// Frost and rain hold the vent closed first, then the temperature opens it,
+/// then CO2, humidity and mildew may open it as the [Co2Policy] allows
+///
+/// - param temperature: The current temperature in Fahrenheit
+/// - param humidity: The current relative humidity percentage
+/// - param co2_ppm: The latest CO2 in ppm, or None if there is no fresh reading
+/// - param rain: If the rain sensor is wet
+/// - param previous: The [Actuators] state applied after the last refresh
+/// - param preferences: [Preferences] instance
+///
+/// returns the [Actuators] state to apply
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{decide_actuators, Actuators, HeatStage};
+/// use gem_rs::preferences::{Co2Policy, Preferences};
+///
+/// let preferences = Preferences::default(); // 55F - 75F at midnight, 60% - 70%, 3% hysteresis
+///
+/// let hot = decide_actuators(85, 65, None, false, Actuators::default(), &preferences);
+/// assert!(hot.roof_vent);
+/// assert!(!hot.mister);
+///
+/// // Rain holds the vent closed
+/// assert!(!decide_actuators(85, 65, None, true, Actuators::default(), &preferences).roof_vent);
+///
+/// let dry = decide_actuators(70, 40, None, false, Actuators::default(), &preferences);
+/// assert!(!dry.roof_vent);
+/// assert!(dry.mister);
+/// assert!(!dry.sprinklers);
+///
+/// // Humidity back at the edge of the range keeps the mister on
+/// let edge = decide_actuators(70, 60, None, false, dry, &preferences);
+/// assert!(edge.mister);
+/// assert_eq!(decide_actuators(70, 60, None, false, edge, &preferences), edge);
+/// assert!(!decide_actuators(70, 63, None, false, edge, &preferences).mister);
+///
+/// // Too humid opens the vent until the humidity is 3% back inside the range
+/// let humid = decide_actuators(70, 75, None, false, Actuators::default(), &preferences);
+/// assert!(humid.roof_vent && humid.dehumidify);
+/// assert!(!humid.mister);
+/// let drying = decide_actuators(70, 68, None, false, humid, &preferences);
+/// assert!(drying.roof_vent);
+/// let dry_enough = decide_actuators(70, 67, None, false, drying, &preferences);
+/// assert!(!dry_enough.roof_vent && !dry_enough.dehumidify);
+/// // Rain still holds it closed, but the humidity keeps calling for it
+/// let raining = decide_actuators(70, 75, None, true, Actuators::default(), &preferences);
+/// assert!(!raining.roof_vent && raining.dehumidify);
+///
+/// // A mildew risk opens the vent if asked to, unless rain holds it closed
+/// let mut preferences = Preferences::default();
+/// preferences.dehumidify_vent = false;
+/// assert!(!decide_actuators(70, 95, None, false, Actuators::default(), &preferences).roof_vent);
+/// preferences.mildew_vent = true;
+/// assert!(decide_actuators(70, 95, None, false, Actuators::default(), &preferences).roof_vent);
+/// assert!(!decide_actuators(70, 95, None, true, Actuators::default(), &preferences).roof_vent);
+///
+/// // Below the night range the heater runs, harder the colder it is
+/// let cool = decide_actuators(53, 65, None, false, Actuators::default(), &preferences);
+/// assert_eq!(cool.heat, HeatStage::Low);
+/// assert!(!cool.roof_vent);
+/// assert_eq!(decide_actuators(49, 65, None, false, cool, &preferences).heat, HeatStage::High);
+/// assert_eq!(decide_actuators(56, 65, None, false, cool, &preferences).heat, HeatStage::Low);
+/// assert_eq!(decide_actuators(57, 65, None, false, cool, &preferences).heat, HeatStage::Off);
+///
+/// // Venting for CO2 opens the vent above 1500ppm, unless rain holds it closed
+/// let mut preferences = Preferences::default();
+/// preferences.co2_policy = Co2Policy::Vent;
+/// let stuffy = decide_actuators(70, 65, Some(1600), false, Actuators::default(), &preferences);
+/// assert!(stuffy.roof_vent && stuffy.co2_vent);
+/// assert!(decide_actuators(70, 65, Some(1450), false, stuffy, &preferences).roof_vent);
+/// assert!(!decide_actuators(70, 65, Some(1400), false, stuffy, &preferences).roof_vent);
+/// let raining = decide_actuators(70, 65, Some(1600), true, Actuators::default(), &preferences);
+/// assert!(!raining.roof_vent && raining.co2_vent);
+///
+/// // Enrichment keeps the CO2 in, so only heat opens the vent
+/// preferences.co2_policy = Co2Policy::Enrich;
+/// preferences.mildew_vent = true;
+/// assert!(!decide_actuators(70, 65, Some(1600), false, Actuators::default(), &preferences).roof_vent);
+/// assert!(!decide_actuators(70, 95, Some(900), false, Actuators::default(), &preferences).roof_vent);
+/// assert!(decide_actuators(85, 65, Some(900), false, Actuators::default(), &preferences).roof_vent);
+/// ```
+pub fn decide_actuators(
+    temperature: u8,
+    humidity: u8,
+    co2_ppm: Option<u16>,
+    rain: bool,
+    previous: Actuators,
+    preferences: &Preferences,
+) -> Actuators {
+    let vent = decide_vent(temperature, rain, previous.roof_vent, preferences);
+    let margins = HeatMargins {
+        hysteresis: preferences.temperature_hysteresis,
+        full_below: preferences.heat_full_below,
+    };
+    let previous_action = if previous.dehumidify {
+        HumidityAction::Dehumidify
+    } else if previous.mister {
+        HumidityAction::Humidify
+    } else {
+        HumidityAction::Hold
+    };
+    let dehumidify = humidity_action(
+        humidity,
+        preferences.humidity,
+        preferences.humidity_hysteresis,
+        previous_action,
+    ) == HumidityAction::Dehumidify;
+    let co2_vent = decide_co2_vent(
+        co2_ppm,
+        preferences.co2_policy,
+        preferences.co2_vent_ppm,
+        previous.co2_vent,
+    );
+    // Enriched CO2 would be lost through the vent, so humidity and mildew can't open it
+    let enriching = preferences.co2_policy == Co2Policy::Enrich;
+    Actuators {
+        // Open the vent once it is too hot, too stuffy, too humid or mildew threatens, unless frost or rain hold it closed
+        roof_vent: vent.is_open()
+            || (vent == VentState::Closed
+                && (co2_vent
+                    || (!enriching
+                        && ((preferences.dehumidify_vent && dehumidify)
+                            || (preferences.mildew_vent
+                                && has_mildew_risk(temperature, humidity, preferences)))))),
+        // Water the soil when the schedule says so
+        sprinklers: decide_watering(preferences),
+        // Mist when the humidity calls for it
+        mister: decide_misting(humidity, previous.mister, preferences),
+        dehumidify,
+        // Heat when it is too cold for the active range
+        heat: heating_stage(
+            temperature,
+            active_temperature(preferences).1,
+            margins,
+            previous.heat,
+        ),
+        co2_vent,
+    }
+}
+
+/// Decides what the actuators do while the sensors have failed, according to the [FaultPolicy].
+/// The sprinklers keep to the watering schedule whatever the policy, as it doesn't need a reading
+///
+/// - param policy: The [FaultPolicy] to apply
+/// - param fault: If the sensors have failed and the reading has gone stale
+/// - param last: The [Actuators] state applied before this refresh
+/// - param decided: The [Actuators] state decided from the last good reading
+/// - param safe_vent_open: If the vent is left open in the safe state. See [Preferences::safe_vent_open]
+///
+/// returns the [Actuators] state to apply
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{fault_actuators, Actuators, HeatStage};
+/// use gem_rs::preferences::FaultPolicy;
+///
+/// let last = Actuators {
+///     roof_vent: true,
+///     sprinklers: false,
+///     mister: false,
+///     dehumidify: true,
+///     heat: HeatStage::Low,
+///     co2_vent: true,
+/// };
+/// let decided = Actuators {
+///     roof_vent: false,
+///     sprinklers: true,
+///     mister: true,
+///     dehumidify: false,
+///     heat: HeatStage::High,
+///     co2_vent: false,
+/// };
+///
+/// // Without a fault the decision stands, whatever the policy
+/// for policy in [FaultPolicy::Hold, FaultPolicy::SafeState, FaultPolicy::LastGood] {
+///     assert_eq!(fault_actuators(policy, false, last, decided, false), decided);
+/// }
+///
+/// // Holding keeps the vent, mister and heater as they were, but the schedule still waters
+/// assert_eq!(
+///     fault_actuators(FaultPolicy::Hold, true, last, decided, false),
+///     Actuators { sprinklers: true, ..last }
+/// );
+///
+/// // The safe state stops the mister and heater and puts the vent where the preferences say
+/// assert_eq!(
+///     fault_actuators(FaultPolicy::SafeState, true, last, decided, false),
+///     Actuators { sprinklers: true, ..Actuators::default() }
+/// );
+/// assert!(fault_actuators(FaultPolicy::SafeState, true, last, decided, true).roof_vent);
+///
+/// // Carrying on uses the last good reading
+/// assert_eq!(fault_actuators(FaultPolicy::LastGood, true, last, decided, false), decided);
+/// ```
+pub fn fault_actuators(
+    policy: FaultPolicy,
+    fault: bool,
+    last: Actuators,
+    decided: Actuators,
+    safe_vent_open: bool,
+) -> Actuators {
+    if !fault {
+        return decided;
+    }
+    match policy {
+        FaultPolicy::Hold => Actuators {
+            sprinklers: decided.sprinklers,
+            ..last
+        },
+        FaultPolicy::SafeState => Actuators {
+            roof_vent: safe_vent_open,
+            sprinklers: decided.sprinklers,
+            mister: false,
+            dehumidify: false,
+            // Heating without a reading could overheat the greenhouse, or worse
+            heat: HeatStage::Off,
+            co2_vent: false,
+        },
+        FaultPolicy::LastGood => decided,
+    }
+}
+
+/// How long a prime run drives the sprinklers in milliseconds
+pub const PRIME_MS: u16 = 10_000;
+
+/// The state of a prime run
+///
+/// - **Running**: The sprinklers should stay on
+/// - **Finished**: The run reached the end of [PRIME_MS]
+/// - **Cancelled**: SELECT stopped the run early
+/// - **Aborted**: The smoke alarm stopped the run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PrimeStatus {
+    Running,
+    Finished,
+    Cancelled,
+    Aborted,
+}
+
+/// Advances a prime run of the sprinklers.
+/// The smoke alarm takes priority over SELECT, which takes priority over the countdown
+///
+/// - param countdown: [CountDownTimer] holding the time left in the run
+/// - param elapsed_ms: The milliseconds since the last step
+/// - param select: If SELECT is pressed
+/// - param smoke: If the smoke alarm is triggered
+///
+/// returns the [PrimeStatus] after the step
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{prime_step, PrimeStatus, PRIME_MS};
+/// use gem_rs::timer::CountDownTimer;
+///
+/// let mut countdown = CountDownTimer::new(PRIME_MS);
+/// for _ in 0..19 {
+///     assert_eq!(prime_step(&mut countdown, 500, false, false), PrimeStatus::Running);
+/// }
+/// assert_eq!(countdown.remaining(), 500);
+/// assert_eq!(prime_step(&mut countdown, 500, false, false), PrimeStatus::Finished);
+///
+/// let mut countdown = CountDownTimer::new(PRIME_MS);
+/// assert_eq!(prime_step(&mut countdown, 500, true, false), PrimeStatus::Cancelled);
+/// assert_eq!(prime_step(&mut countdown, 500, true, true), PrimeStatus::Aborted);
+/// ```
+pub fn prime_step(
+    countdown: &mut CountDownTimer,
+    elapsed_ms: u16,
+    select: bool,
+    smoke: bool,
+) -> PrimeStatus {
+    if smoke {
+        PrimeStatus::Aborted
+    } else if select {
+        PrimeStatus::Cancelled
+    } else {
+        countdown.tick_by(elapsed_ms);
+        if countdown.is_finished() {
+            PrimeStatus::Finished
+        } else {
+            PrimeStatus::Running
+        }
+    }
+}
+
+/// How long SELECT must be held to confirm a destructive action, in milliseconds
+pub const CONFIRM_HOLD_MS: u16 = 2000;
+
+/// The state of a hold to confirm prompt
+///
+/// - **Waiting**: Nothing is being held
+/// - **Holding**: SELECT has been held for the given milliseconds
+/// - **Confirmed**: SELECT was held for [CONFIRM_HOLD_MS]
+/// - **Cancelled**: UP or DOWN was pressed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HoldStatus {
+    Waiting,
+    Holding(u16),
+    Confirmed,
+    Cancelled,
+}
+
+/// Tracks a hold to confirm prompt.
+/// Buttons are ignored until every button has been let go, so the press that opened the prompt
+/// can neither confirm nor cancel it. Letting go of SELECT early starts the hold over
+///
+/// - **armed**: If every button has been let go since the prompt opened
+/// - **held_ms**: How long SELECT has been held
+#[derive(Clone, Copy, Default)]
+pub struct HoldConfirm {
+    armed: bool,
+    held_ms: u16,
+}
+
+impl HoldConfirm {
+    /// Advances the prompt. Cancelling takes priority over holding
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    /// - param select: If SELECT is pressed
+    /// - param cancel: If UP or DOWN is pressed
+    ///
+    /// returns the [HoldStatus] after the step
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::control::{HoldConfirm, HoldStatus, CONFIRM_HOLD_MS};
+    ///
+    /// let mut hold = HoldConfirm::default();
+    /// // The buttons that opened the prompt are still down
+    /// assert_eq!(hold.step(100, true, true), HoldStatus::Waiting);
+    /// assert_eq!(hold.step(100, false, false), HoldStatus::Waiting);
+    ///
+    /// assert_eq!(hold.step(100, true, false), HoldStatus::Holding(100));
+    /// assert_eq!(hold.step(100, true, false), HoldStatus::Holding(200));
+    /// // Letting go starts over
+    /// assert_eq!(hold.step(100, false, false), HoldStatus::Waiting);
+    /// assert_eq!(hold.step(100, true, false), HoldStatus::Holding(100));
+    ///
+    /// for held in (200..CONFIRM_HOLD_MS).step_by(100) {
+    ///     assert_eq!(hold.step(100, true, false), HoldStatus::Holding(held));
+    /// }
+    /// assert_eq!(hold.step(100, true, false), HoldStatus::Confirmed);
+    ///
+    /// let mut hold = HoldConfirm::default();
+    /// hold.step(100, false, false);
+    /// hold.step(100, true, false);
+    /// assert_eq!(hold.step(100, true, true), HoldStatus::Cancelled);
+    /// ```
+    pub fn step(&mut self, elapsed_ms: u16, select: bool, cancel: bool) -> HoldStatus {
+        if !self.armed {
+            self.armed = !select && !cancel;
+            return HoldStatus::Waiting;
+        }
+        if cancel {
+            return HoldStatus::Cancelled;
+        }
+        if !select {
+            self.held_ms = 0;
+            return HoldStatus::Waiting;
+        }
+        self.held_ms = self.held_ms.saturating_add(elapsed_ms);
+        if self.held_ms >= CONFIRM_HOLD_MS {
+            HoldStatus::Confirmed
+        } else {
+            HoldStatus::Holding(self.held_ms)
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use crate::flash::{is_erased, Storage, SECTOR_SIZE};
+use crate::preferences::{DateTime, Preferences};
+use crate::sensors::{get_humidity, get_pressure, get_temperature, Reading};
+use heapless::String;
+use ufmt::uwrite;
+
+/// The amount of sectors the log rotates through
+pub const LOG_SECTORS: u32 = 8;
+/// The size of the log region in bytes
+pub const LOG_LEN: u32 = LOG_SECTORS * SECTOR_SIZE;
+/// Where the log region starts, counted from the start of flash.
+/// The log sits in the last 64KB of the 2MB flash, which `memory.x` keeps the firmware out of
+pub const LOG_FLASH_OFFSET: u32 = 0x1F_0000;
+/// The size of an encoded [Record] in bytes
+pub const RECORD_LEN: usize = 16;
+/// The amount of records that fit in a sector
+pub const RECORDS_PER_SECTOR: u32 = SECTOR_SIZE / RECORD_LEN as u32;
+/// The amount of records the log holds before it wraps; about a week at 5 minutes
+pub const LOG_RECORDS: u32 = LOG_SECTORS * RECORDS_PER_SECTOR;
+/// The first line of the CSV export
+pub const CSV_HEADER: &str = "timestamp,temperature_f,humidity,pressure_hpa";
+
+/// A single logged reading
+///
+/// - **timestamp**: When the reading was taken
+/// - **temperature**: The temperature in Fahrenheit
+/// - **humidity**: The relative humidity percentage
+/// - **pressure**: The pressure in millibars/hPa
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Record {
+    pub timestamp: DateTime,
+    pub temperature: u8,
+    pub humidity: u8,
+    pub pressure: u16,
+}
+
+impl Record {
+    /// Creates a record from a [Reading]
+    ///
+    /// - param timestamp: When the reading was taken
+    /// - param reading: the [Reading] to log
+    ///
+    /// returns a new [Record]
+    pub fn new(timestamp: &DateTime, reading: &Reading) -> Record {
+        Record {
+            timestamp: *timestamp,
+            temperature: get_temperature(reading),
+            humidity: get_humidity(reading),
+            pressure: get_pressure(reading),
+        }
+    }
+
+    /// Encodes the record with its sequence number.
+    /// Multibyte values are little-endian.
+    ///
+    /// | Offset | Field                |
+    /// |--------|----------------------|
+    /// | 0..4   | sequence number      |
+    /// | 4..11  | timestamp            |
+    /// | 11     | temperature          |
+    /// | 12     | humidity             |
+    /// | 13..15 | pressure             |
+    /// | 15     | checksum             |
+    ///
+    /// - param sequence: the record's position in the log since it was first written
+    ///
+    /// returns the encoded bytes
+    pub fn encode(&self, sequence: u32) -> [u8; RECORD_LEN] {
+        let mut bytes = [0u8; RECORD_LEN];
+        let timestamp = self.timestamp;
+        bytes[0..4].copy_from_slice(&sequence.to_le_bytes());
+        bytes[4] = timestamp.0;
+        bytes[5] = timestamp.1;
+        bytes[6] = timestamp.2;
+        bytes[7] = timestamp.3;
+        bytes[8] = timestamp.4;
+        bytes[9..11].copy_from_slice(&timestamp.5.to_le_bytes());
+        bytes[11] = self.temperature;
+        bytes[12] = self.humidity;
+        bytes[13..15].copy_from_slice(&self.pressure.to_le_bytes());
+        bytes[15] = checksum(&bytes[..RECORD_LEN - 1]);
+        bytes
+    }
+
+    /// Decodes a record written by [Record::encode]
+    ///
+    /// - param bytes: the encoded record
+    ///
+    /// returns the sequence number and [Record],
+    /// or None if the slot is erased or was only partly written
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::datalog::{Record, RECORD_LEN};
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let record = Record {
+    ///     timestamp: DateTime(9, 5, 13, 29, 2, 2024),
+    ///     temperature: 72,
+    ///     humidity: 55,
+    ///     pressure: 1013,
+    /// };
+    /// let bytes = record.encode(42);
+    /// assert_eq!(Record::decode(&bytes), Some((42, record)));
+    ///
+    /// // Erased flash
+    /// assert_eq!(Record::decode(&[0xFF; RECORD_LEN]), None);
+    /// // Power was lost before the checksum was written
+    /// let mut partial = bytes;
+    /// partial[15] = 0xFF;
+    /// assert_eq!(Record::decode(&partial), None);
+    /// ```
+    pub fn decode(bytes: &[u8; RECORD_LEN]) -> Option<(u32, Record)> {
+        let sequence = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if sequence == u32::MAX || bytes[15] != checksum(&bytes[..RECORD_LEN - 1]) {
+            return None;
+        }
+        let record = Record {
+            timestamp: DateTime(
+                bytes[4],
+                bytes[5],
+                bytes[6],
+                bytes[7],
+                bytes[8],
+                u16::from_le_bytes([bytes[9], bytes[10]]),
+            ),
+            temperature: bytes[11],
+            humidity: bytes[12],
+            pressure: u16::from_le_bytes([bytes[13], bytes[14]]),
+        };
+        Some((sequence, record))
+    }
+
+    /// Formats the record as a CSV row matching [CSV_HEADER]
+    ///
+    /// returns the row: `YYYY-MM-DD HH:MM:SS,temperature,humidity,pressure`
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::datalog::Record;
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let record = Record {
+    ///     timestamp: DateTime(9, 5, 3, 1, 2, 2024),
+    ///     temperature: 72,
+    ///     humidity: 55,
+    ///     pressure: 1013,
+    /// };
+    /// assert_eq!(record.to_csv(), "2024-02-01 03:05:09,72,55,1013");
+    /// ```
+    pub fn to_csv(&self) -> String<32> {
+        let mut row: String<32> = String::new();
+        let timestamp = self.timestamp;
+        uwrite!(
+            row,
+            "{}-{}-{} {}:{}:{},{},{},{}",
+            Preferences::pad_year(timestamp.5).as_str(),
+            Preferences::pad_number(timestamp.4).as_str(),
+            Preferences::pad_number(timestamp.3).as_str(),
+            Preferences::pad_number(timestamp.2).as_str(),
+            Preferences::pad_number(timestamp.1).as_str(),
+            Preferences::pad_number(timestamp.0).as_str(),
+            self.temperature,
+            self.humidity,
+            self.pressure
+        )
+        .unwrap();
+        row
+    }
+}
+
+/// Calculates the checksum stored in the last byte of a record.
+/// The result is inverted so that all-zero bytes don't pass
+///
+/// - param bytes: the record bytes before the checksum
+///
+/// returns the checksum
+fn checksum(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// A ring buffer of [Record]s kept in [Storage].
+/// Records are appended into erased space, and a sector is only erased
+/// once the log wraps back around to it, dropping its oldest records
+///
+/// - **storage**: The [Storage] holding the records
+/// - **next_slot**: The slot the next record is written to
+/// - **next_sequence**: The sequence number of the next record
+/// - **last_logged**: When the last record was taken, to the minute
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::datalog::{DataLog, Record, LOG_LEN, LOG_RECORDS, RECORDS_PER_SECTOR};
+/// use gem_rs::flash::Storage;
+/// use gem_rs::preferences::DateTime;
+///
+/// struct MemoryStorage(Vec<u8>);
+///
+/// impl Storage for MemoryStorage {
+///     fn read(&self, offset: u32, buffer: &mut [u8]) {
+///         let offset = offset as usize;
+///         buffer.copy_from_slice(&self.0[offset..offset + buffer.len()]);
+///     }
+///     fn erase_sector(&mut self, offset: u32) {
+///         let offset = offset as usize;
+///         self.0[offset..offset + 4096].fill(0xFF);
+///     }
+///     fn program(&mut self, offset: u32, data: &[u8]) {
+///         for (index, byte) in data.iter().enumerate() {
+///             self.0[offset as usize + index] &= byte; // Flash can only clear bits
+///         }
+///     }
+/// }
+///
+/// // The pressure doubles as a counter to tell the records apart
+/// let record = |pressure: u16| Record {
+///     timestamp: DateTime(0, 0, 12, 1, 1, 2024),
+///     temperature: 70,
+///     humidity: 50,
+///     pressure,
+/// };
+///
+/// let mut log = DataLog::new(MemoryStorage(vec![0xFF; LOG_LEN as usize]));
+/// for count in 0..3 {
+///     log.append(&record(count));
+/// }
+/// let mut pressures = Vec::new();
+/// log.for_each(|record| pressures.push(record.pressure));
+/// assert_eq!(pressures, [0, 1, 2]);
+///
+/// // Fill the log, then wrap into the first sector
+/// for count in 3..LOG_RECORDS + 1 {
+///     log.append(&record(count as u16));
+/// }
+/// let mut count = 0;
+/// let mut oldest = None;
+/// log.for_each(|record| {
+///     oldest.get_or_insert(record.pressure);
+///     count += 1;
+/// });
+/// // Wrapping erased the first sector, so only the newest record is left in it
+/// assert_eq!(count, LOG_RECORDS - RECORDS_PER_SECTOR + 1);
+/// assert_eq!(oldest, Some(RECORDS_PER_SECTOR as u16));
+///
+/// // A restart picks up where the log left off
+/// let mut log = DataLog::new(log.into_storage());
+/// log.append(&record(7));
+/// let mut newest = None;
+/// log.for_each(|record| newest = Some(record.pressure));
+/// assert_eq!(newest, Some(7));
+/// ```
+pub struct DataLog<S: Storage> {
+    storage: S,
+    next_slot: u32,
+    next_sequence: u32,
+    last_logged: Option<DateTime>,
+}
+
+impl<S: Storage> DataLog<S> {
+    /// Opens the log, finding the newest record in the [Storage]
+    ///
+    /// - param storage: [Storage] instance
+    ///
+    /// returns a new [DataLog]
+    pub fn new(storage: S) -> DataLog<S> {
+        let mut log = DataLog {
+            storage,
+            next_slot: 0,
+            next_sequence: 0,
+            last_logged: None,
+        };
+        let mut newest: Option<(u32, u32)> = None;
+        for slot in 0..LOG_RECORDS {
+            if let Some((sequence, _)) = log.read_slot(slot) {
+                if newest.map_or(true, |(newest, _)| sequence > newest) {
+                    newest = Some((sequence, slot));
+                }
+            }
+        }
+        if let Some((sequence, slot)) = newest {
+            log.next_slot = (slot + 1) % LOG_RECORDS;
+            log.next_sequence = sequence.wrapping_add(1);
+        }
+        log
+    }
+
+    /// Gives back the [Storage]
+    ///
+    /// returns the [Storage] instance
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Reads and decodes a slot
+    ///
+    /// - param slot: the slot index
+    ///
+    /// returns the sequence number and [Record] if the slot holds one
+    fn read_slot(&self, slot: u32) -> Option<(u32, Record)> {
+        let mut bytes = [0u8; RECORD_LEN];
+        self.storage.read(slot * RECORD_LEN as u32, &mut bytes);
+        Record::decode(&bytes)
+    }
+
+    /// Appends a record, erasing the next sector first if the log has wrapped into it
+    ///
+    /// - param record: the [Record] to append
+    pub fn append(&mut self, record: &Record) {
+        if self.next_slot % RECORDS_PER_SECTOR == 0 {
+            let sector = self.next_slot / RECORDS_PER_SECTOR;
+            if !is_erased(&self.storage, sector * SECTOR_SIZE, SECTOR_SIZE) {
+                self.storage.erase_sector(sector * SECTOR_SIZE);
+            }
+        }
+        self.storage.program(
+            self.next_slot * RECORD_LEN as u32,
+            &record.encode(self.next_sequence),
+        );
+        self.next_slot = (self.next_slot + 1) % LOG_RECORDS;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+    }
+
+    /// Appends a record if one is due.
+    /// A record is taken once the interval has passed since the last one, counted in whole minutes,
+    /// so any interval up to an hour keeps an even spacing across the hours.
+    /// The first record after a restart, or after the clock is set back, is taken straight away
+    ///
+    /// - param record: the [Record] to append
+    /// - param interval: the minutes between records
+    ///
+    /// returns true if the record was appended
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::datalog::{DataLog, Record, LOG_LEN};
+    /// use gem_rs::flash::Storage;
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// struct MemoryStorage(Vec<u8>);
+    ///
+    /// impl Storage for MemoryStorage {
+    ///     fn read(&self, offset: u32, buffer: &mut [u8]) {
+    ///         let offset = offset as usize;
+    ///         buffer.copy_from_slice(&self.0[offset..offset + buffer.len()]);
+    ///     }
+    ///     fn erase_sector(&mut self, offset: u32) {
+    ///         let offset = offset as usize;
+    ///         self.0[offset..offset + 4096].fill(0xFF);
+    ///     }
+    ///     fn program(&mut self, offset: u32, data: &[u8]) {
+    ///         for (index, byte) in data.iter().enumerate() {
+    ///             self.0[offset as usize + index] &= byte;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let at = |sec: u8, min: u8, hour: u8| Record {
+    ///     timestamp: DateTime(sec, min, hour, 1, 1, 2024),
+    ///     temperature: 70,
+    ///     humidity: 50,
+    ///     pressure: 1013,
+    /// };
+    ///
+    /// // Hourly, which keeps logging past the first hour
+    /// let mut log = DataLog::new(MemoryStorage(vec![0xFF; LOG_LEN as usize]));
+    /// assert!(log.append_if_due(&at(5, 0, 12), 60));
+    /// assert!(!log.append_if_due(&at(7, 0, 12), 60)); // Same minute
+    /// assert!(!log.append_if_due(&at(59, 59, 12), 60));
+    /// assert!(log.append_if_due(&at(1, 0, 13), 60));
+    /// assert!(log.append_if_due(&at(3, 0, 14), 60));
+    ///
+    /// // Every 7 minutes, without a short gap at the hour
+    /// let mut log = DataLog::new(MemoryStorage(vec![0xFF; LOG_LEN as usize]));
+    /// assert!(log.append_if_due(&at(0, 56, 12), 7));
+    /// assert!(!log.append_if_due(&at(0, 0, 13), 7));
+    /// assert!(!log.append_if_due(&at(59, 2, 13), 7));
+    /// assert!(log.append_if_due(&at(0, 3, 13), 7));
+    /// assert!(log.append_if_due(&at(0, 10, 13), 7));
+    ///
+    /// // Setting the clock back logs straight away
+    /// assert!(log.append_if_due(&at(0, 0, 9), 7));
+    /// ```
+    pub fn append_if_due(&mut self, record: &Record, interval: u8) -> bool {
+        let to_minute = |date: &DateTime| DateTime(0, date.1, date.2, date.3, date.4, date.5);
+        let now = to_minute(&record.timestamp);
+        let due = self.last_logged.is_none_or(|last| {
+            last.seconds_since(&now) > 0 || now.seconds_since(&last) >= interval.max(1) as u32 * 60
+        });
+        if due {
+            self.append(record);
+            self.last_logged = Some(now);
+        }
+        due
+    }
+
+    /// Visits every record, oldest first
+    ///
+    /// - param visit: called with each [Record]
+    pub fn for_each(&self, mut visit: impl FnMut(&Record)) {
+        for index in 0..LOG_RECORDS {
+            if let Some((_, record)) = self.read_slot((self.next_slot + index) % LOG_RECORDS) {
+                visit(&record);
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use crate::flash::{is_erased, Storage, SECTOR_SIZE};
+use crate::preferences::{DateTime, Preferences};
+use crate::rendering::{Truncating, LABELS};
+use heapless::{HistoryBuffer, String};
+use ufmt::uwrite;
+
+/// The amount of events kept in memory and shown on screen
+pub const EVENT_CAPACITY: usize = 32;
+/// The amount of sectors the event store rotates through
+pub const EVENT_SECTORS: u32 = 2;
+/// The size of the event store region in bytes
+pub const EVENT_REGION_LEN: u32 = EVENT_SECTORS * SECTOR_SIZE;
+/// Where the event store starts, counted from the start of flash.
+/// It sits right after the preferences, in the last 16KB of the 2MB flash
+pub const EVENT_FLASH_OFFSET: u32 = 0x1F_C000;
+/// The size of an encoded [Event] in bytes
+pub const EVENT_LEN: usize = 16;
+/// The amount of events that fit in a sector
+pub const EVENTS_PER_SECTOR: u32 = SECTOR_SIZE / EVENT_LEN as u32;
+/// The amount of events the store holds before it wraps
+pub const EVENT_SLOTS: u32 = EVENT_SECTORS * EVENTS_PER_SECTOR;
+
+/// What happened
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EventKind {
+    AlarmOn,
+    AlarmOff,
+    SensorFault,
+    PowerWarning,
+    WateringStart,
+    WateringStop,
+}
+
+impl EventKind {
+    /// Gets the kind from its encoded value
+    ///
+    /// - param value: the encoded kind
+    ///
+    /// returns the [EventKind], or None if the value is unknown
+    pub fn from_u8(value: u8) -> Option<EventKind> {
+        match value {
+            0 => Some(EventKind::AlarmOn),
+            1 => Some(EventKind::AlarmOff),
+            2 => Some(EventKind::SensorFault),
+            3 => Some(EventKind::PowerWarning),
+            4 => Some(EventKind::WateringStart),
+            5 => Some(EventKind::WateringStop),
+            _ => None,
+        }
+    }
+
+    /// Gets the on-screen name of the kind
+    ///
+    /// returns the name of the kind
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::AlarmOn => LABELS.alarm_on,
+            EventKind::AlarmOff => LABELS.alarm_off,
+            EventKind::SensorFault => LABELS.sensor_fault,
+            EventKind::PowerWarning => LABELS.power_warning,
+            EventKind::WateringStart => LABELS.watering_start,
+            EventKind::WateringStop => LABELS.watering_stop,
+        }
+    }
+}
+
+/// Something that happened, and when
+///
+/// - **timestamp**: When the event happened
+/// - **kind**: The [EventKind]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Event {
+    pub timestamp: DateTime,
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// Formats the timestamp to fit on a line: `DD/MM/YYYY HH:MM`
+    ///
+    /// returns the formatted timestamp
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::events::{Event, EventKind};
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let event = Event {
+    ///     timestamp: DateTime(30, 5, 9, 3, 4, 2024),
+    ///     kind: EventKind::AlarmOn,
+    /// };
+    /// assert_eq!(event.format_timestamp(), "03/04/2024 09:05");
+    /// ```
+    pub fn format_timestamp(&self) -> String<16> {
+        let mut str: String<16> = String::new();
+        let timestamp = self.timestamp;
+        uwrite!(
+            Truncating(&mut str),
+            "{}/{}/{} {}:{}",
+            Preferences::pad_number(timestamp.3).as_str(),
+            Preferences::pad_number(timestamp.4).as_str(),
+            Preferences::pad_year(timestamp.5).as_str(),
+            Preferences::pad_number(timestamp.2).as_str(),
+            Preferences::pad_number(timestamp.1).as_str(),
+        )
+        .unwrap();
+        str
+    }
+
+    /// Formats the event's position and kind to fit on a line: `N Kind`
+    ///
+    /// - param position: the event's position, where 1 is the newest
+    ///
+    /// returns the formatted position and kind
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::events::{Event, EventKind};
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let event = Event {
+    ///     timestamp: DateTime(0, 0, 0, 1, 1, 2000),
+    ///     kind: EventKind::SensorFault,
+    /// };
+    /// assert_eq!(event.format_kind(3), "3 Sensor Fault");
+    /// ```
+    pub fn format_kind(&self, position: u8) -> String<16> {
+        let mut str: String<16> = String::new();
+        uwrite!(Truncating(&mut str), "{} {}", position, self.kind.label()).unwrap();
+        str
+    }
+
+    /// Encodes the event with its sequence number.
+    /// Multibyte values are little-endian.
+    ///
+    /// | Offset | Field                |
+    /// |--------|----------------------|
+    /// | 0..4   | sequence number      |
+    /// | 4..11  | timestamp            |
+    /// | 11     | kind                 |
+    /// | 12..15 | reserved             |
+    /// | 15     | checksum             |
+    ///
+    /// - param sequence: the event's position in the store since it was first written
+    ///
+    /// returns the encoded bytes
+    pub fn encode(&self, sequence: u32) -> [u8; EVENT_LEN] {
+        let mut bytes = [0u8; EVENT_LEN];
+        let timestamp = self.timestamp;
+        bytes[0..4].copy_from_slice(&sequence.to_le_bytes());
+        bytes[4] = timestamp.0;
+        bytes[5] = timestamp.1;
+        bytes[6] = timestamp.2;
+        bytes[7] = timestamp.3;
+        bytes[8] = timestamp.4;
+        bytes[9..11].copy_from_slice(&timestamp.5.to_le_bytes());
+        bytes[11] = self.kind as u8;
+        bytes[15] = checksum(&bytes[..EVENT_LEN - 1]);
+        bytes
+    }
+
+    /// Decodes an event written by [Event::encode]
+    ///
+    /// - param bytes: the encoded event
+    ///
+    /// returns the sequence number and [Event],
+    /// or None if the slot is erased or was only partly written
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::events::{Event, EventKind, EVENT_LEN};
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let event = Event {
+    ///     timestamp: DateTime(9, 5, 13, 29, 2, 2024),
+    ///     kind: EventKind::WateringStop,
+    /// };
+    /// let bytes = event.encode(42);
+    /// assert_eq!(Event::decode(&bytes), Some((42, event)));
+    ///
+    /// assert_eq!(Event::decode(&[0xFF; EVENT_LEN]), None);
+    /// let mut partial = bytes;
+    /// partial[15] = 0xFF;
+    /// assert_eq!(Event::decode(&partial), None);
+    /// ```
+    pub fn decode(bytes: &[u8; EVENT_LEN]) -> Option<(u32, Event)> {
+        let sequence = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if sequence == u32::MAX || bytes[15] != checksum(&bytes[..EVENT_LEN - 1]) {
+            return None;
+        }
+        let event = Event {
+            timestamp: DateTime(
+                bytes[4],
+                bytes[5],
+                bytes[6],
+                bytes[7],
+                bytes[8],
+                u16::from_le_bytes([bytes[9], bytes[10]]),
+            ),
+            kind: EventKind::from_u8(bytes[11])?,
+        };
+        Some((sequence, event))
+    }
+}
+
+/// Calculates the checksum stored in the last byte of an event.
+/// The result is inverted so that all-zero bytes don't pass
+///
+/// - param bytes: the event bytes before the checksum
+///
+/// returns the checksum
+fn checksum(bytes: &[u8]) -> u8 {
+    !bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// The most recent [Event]s; once full, the oldest event is dropped
+///
+/// - **events**: The recent events, oldest first
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::events::{EventKind, EventLog, EVENT_CAPACITY};
+/// use gem_rs::preferences::DateTime;
+///
+/// let mut log = EventLog::default();
+/// assert!(log.newest(0).is_none());
+///
+/// // The minute tells the events apart
+/// for minute in 0..EVENT_CAPACITY as u8 + 2 {
+///     log.log_event(&DateTime(0, minute, 0, 1, 1, 2024), EventKind::PowerWarning);
+/// }
+/// assert_eq!(log.len(), EVENT_CAPACITY);
+/// assert_eq!(log.newest(0).unwrap().timestamp.1, EVENT_CAPACITY as u8 + 1);
+/// // The two oldest events were dropped
+/// assert_eq!(log.newest(EVENT_CAPACITY - 1).unwrap().timestamp.1, 2);
+/// assert!(log.newest(EVENT_CAPACITY).is_none());
+/// ```
+pub struct EventLog {
+    events: HistoryBuffer<Event, EVENT_CAPACITY>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            events: HistoryBuffer::new(),
+        }
+    }
+}
+
+impl EventLog {
+    /// Records an event
+    ///
+    /// - param timestamp: When the event happened
+    /// - param kind: The [EventKind]
+    ///
+    /// returns the recorded [Event], so it can be saved to an [EventStore]
+    pub fn log_event(&mut self, timestamp: &DateTime, kind: EventKind) -> Event {
+        let event = Event {
+            timestamp: *timestamp,
+            kind,
+        };
+        self.push(event);
+        event
+    }
+
+    /// Adds an existing event, such as one loaded from an [EventStore]
+    ///
+    /// - param event: the [Event] to add
+    pub fn push(&mut self, event: Event) {
+        self.events.write(event);
+    }
+
+    /// Gets the amount of events held
+    ///
+    /// returns the amount of events
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Checks if no events have been recorded
+    ///
+    /// returns true if there are no events
+    pub fn is_empty(&self) -> bool {
+        self.events.len() == 0
+    }
+
+    /// Gets an event counting back from the newest
+    ///
+    /// - param index: how many events back to go, where 0 is the newest
+    ///
+    /// returns the [Event], or None if there are not that many events
+    pub fn newest(&self, index: usize) -> Option<&Event> {
+        let len = self.events.len();
+        if index >= len {
+            return None;
+        }
+        self.events.oldest_ordered().nth(len - 1 - index)
+    }
+}
+
+/// A ring buffer of [Event]s kept in [Storage], so they survive a restart.
+/// Events are appended into erased space, and a sector is only erased
+/// once the store wraps back around to it
+///
+/// - **storage**: The [Storage] holding the events
+/// - **next_slot**: The slot the next event is written to
+/// - **next_sequence**: The sequence number of the next event
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::events::{EventKind, EventLog, EventStore, EVENT_REGION_LEN};
+/// use gem_rs::flash::Storage;
+/// use gem_rs::preferences::DateTime;
+///
+/// struct MemoryStorage(Vec<u8>);
+///
+/// impl Storage for MemoryStorage {
+///     fn read(&self, offset: u32, buffer: &mut [u8]) {
+///         let offset = offset as usize;
+///         buffer.copy_from_slice(&self.0[offset..offset + buffer.len()]);
+///     }
+///     fn erase_sector(&mut self, offset: u32) {
+///         let offset = offset as usize;
+///         self.0[offset..offset + 4096].fill(0xFF);
+///     }
+///     fn program(&mut self, offset: u32, data: &[u8]) {
+///         for (index, byte) in data.iter().enumerate() {
+///             self.0[offset as usize + index] &= byte; // Flash can only clear bits
+///         }
+///     }
+/// }
+///
+/// let mut log = EventLog::default();
+/// let mut store = EventStore::new(MemoryStorage(vec![0xFF; EVENT_REGION_LEN as usize]));
+/// let date = DateTime(0, 0, 12, 1, 1, 2024);
+/// store.append(&log.log_event(&date, EventKind::AlarmOn));
+/// store.append(&log.log_event(&date, EventKind::AlarmOff));
+///
+/// // A restart loads the events back, oldest first
+/// let store = EventStore::new(store.into_storage());
+/// let mut restored = EventLog::default();
+/// store.for_each(|event| restored.push(*event));
+/// assert_eq!(restored.len(), 2);
+/// assert_eq!(restored.newest(0).unwrap().kind, EventKind::AlarmOff);
+/// ```
+pub struct EventStore<S: Storage> {
+    storage: S,
+    next_slot: u32,
+    next_sequence: u32,
+}
+
+impl<S: Storage> EventStore<S> {
+    /// Opens the store, finding the newest event in the [Storage]
+    ///
+    /// - param storage: [Storage] instance
+    ///
+    /// returns a new [EventStore]
+    pub fn new(storage: S) -> EventStore<S> {
+        let mut store = EventStore {
+            storage,
+            next_slot: 0,
+            next_sequence: 0,
+        };
+        let mut newest: Option<(u32, u32)> = None;
+        for slot in 0..EVENT_SLOTS {
+            if let Some((sequence, _)) = store.read_slot(slot) {
+                if newest.map_or(true, |(newest, _)| sequence > newest) {
+                    newest = Some((sequence, slot));
+                }
+            }
+        }
+        if let Some((sequence, slot)) = newest {
+            store.next_slot = (slot + 1) % EVENT_SLOTS;
+            store.next_sequence = sequence.wrapping_add(1);
+        }
+        store
+    }
+
+    /// Gives back the [Storage]
+    ///
+    /// returns the [Storage] instance
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Reads and decodes a slot
+    ///
+    /// - param slot: the slot index
+    ///
+    /// returns the sequence number and [Event] if the slot holds one
+    fn read_slot(&self, slot: u32) -> Option<(u32, Event)> {
+        let mut bytes = [0u8; EVENT_LEN];
+        self.storage.read(slot * EVENT_LEN as u32, &mut bytes);
+        Event::decode(&bytes)
+    }
+
+    /// Appends an event, erasing the next sector first if the store has wrapped into it
+    ///
+    /// - param event: the [Event] to append
+    pub fn append(&mut self, event: &Event) {
+        if self.next_slot % EVENTS_PER_SECTOR == 0 {
+            let sector = self.next_slot / EVENTS_PER_SECTOR;
+            if !is_erased(&self.storage, sector * SECTOR_SIZE, SECTOR_SIZE) {
+                self.storage.erase_sector(sector * SECTOR_SIZE);
+            }
+        }
+        self.storage.program(
+            self.next_slot * EVENT_LEN as u32,
+            &event.encode(self.next_sequence),
+        );
+        self.next_slot = (self.next_slot + 1) % EVENT_SLOTS;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+    }
+
+    /// Visits every stored event, oldest first
+    ///
+    /// - param visit: called with each [Event]
+    pub fn for_each(&self, mut visit: impl FnMut(&Event)) {
+        for index in 0..EVENT_SLOTS {
+            if let Some((_, event)) = self.read_slot((self.next_slot + index) % EVENT_SLOTS) {
+                visit(&event);
+            }
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use crate::preferences::{Output, OUTPUTS};
+use crate::safety::{drive, output_level, safe_state, Outputs};
+#[cfg(feature = "hardware")]
+use crate::sensors::{I2C_RECOVERY_PULSES, I2C_SCL_GPIO, I2C_SDA_GPIO};
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::sync::atomic::{AtomicU8, Ordering};
+use critical_section::Mutex;
+use embedded_hal::digital::{ErrorType, OutputPin};
+use embedded_hal::i2c::I2c;
+
+/// The number of pins on a PCF8574
+pub const EXPANDER_PINS: usize = 8;
+/// The I2C address of a PCF8574 with A0 - A2 tied low
+pub const PCF8574_ADDRESS: u8 = 0x20;
+/// The byte a PCF8574 powers up with. Every pin is pulled high, which is off for the usual active-low relay boards
+pub const PCF8574_IDLE: u8 = 0xFF;
+
+/// Checks if an I2C address can belong to a PCF8574 (0x20 - 0x27) or a PCF8574A (0x38 - 0x3F)
+///
+/// - param address: The 7-bit I2C address
+///
+/// returns true if the address is one an expander can be strapped to
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::is_valid_expander_address;
+///
+/// assert!(is_valid_expander_address(0x20));
+/// assert!(is_valid_expander_address(0x27));
+/// assert!(is_valid_expander_address(0x38));
+/// assert!(!is_valid_expander_address(0x28));
+/// assert!(!is_valid_expander_address(0x77)); // The BME680
+/// ```
+pub fn is_valid_expander_address(address: u8) -> bool {
+    matches!(address, 0x20..=0x27 | 0x38..=0x3F)
+}
+
+/// Sets the level of one pin in the byte written to a PCF8574
+///
+/// - param byte: The byte before the change
+/// - param pin: The pin, P0 - P7
+/// - param high: If the pin should be high
+///
+/// returns the changed byte; unchanged if the pin doesn't exist
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::{with_level, PCF8574_IDLE};
+///
+/// assert_eq!(with_level(PCF8574_IDLE, 0, false), 0b1111_1110);
+/// assert_eq!(with_level(0b1111_1110, 7, false), 0b0111_1110);
+/// assert_eq!(with_level(0b0111_1110, 0, true), 0b0111_1111);
+/// assert_eq!(with_level(PCF8574_IDLE, 8, false), PCF8574_IDLE);
+/// ```
+pub fn with_level(byte: u8, pin: u8, high: bool) -> u8 {
+    let Some(mask) = 1u8.checked_shl(pin as u32) else {
+        return byte;
+    };
+    if high {
+        byte | mask
+    } else {
+        byte & !mask
+    }
+}
+
+/// Packs the level of every pin into the byte written to a PCF8574, with P0 in the lowest bit.
+/// Pins that aren't used are left high, as the PCF8574 powers up
+///
+/// - param levels: The level of each pin, or None if it isn't used
+///
+/// returns the byte to write
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::{pack_levels, PCF8574_IDLE};
+/// use gem_rs::safety::output_level;
+///
+/// assert_eq!(pack_levels([None; 8]), PCF8574_IDLE);
+///
+/// // Active-low relays on P0 and P3; the one on P0 is switched on
+/// let mut levels = [None; 8];
+/// levels[0] = Some(output_level(true, false));
+/// levels[3] = Some(output_level(false, false));
+/// assert_eq!(pack_levels(levels), 0b1111_1110);
+///
+/// // Active-high relays on P1 and P7, both off
+/// let mut levels = [None; 8];
+/// levels[1] = Some(output_level(false, true));
+/// levels[7] = Some(output_level(false, true));
+/// assert_eq!(pack_levels(levels), 0b0111_1101);
+/// ```
+pub fn pack_levels(levels: [Option<bool>; EXPANDER_PINS]) -> u8 {
+    levels
+        .iter()
+        .enumerate()
+        .fold(PCF8574_IDLE, |byte, (pin, level)| {
+            with_level(byte, pin as u8, level.unwrap_or(true))
+        })
+}
+
+/// Encodes the [Output] an expander pin follows as one byte, for storage
+///
+/// - param output: The output the pin follows, or None if it isn't used
+///
+/// returns 0 for an unused pin, otherwise 1 more than the output's place in [OUTPUTS]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::{pin_code, pin_from_code};
+/// use gem_rs::preferences::Output;
+///
+/// assert_eq!(pin_code(None), 0);
+/// assert_eq!(pin_code(Some(Output::Sprinklers)), 1);
+/// assert_eq!(pin_code(Some(Output::Buzzer)), 4);
+/// for output in [None, Some(Output::RoofVent), Some(Output::Mister)] {
+///     assert_eq!(pin_from_code(pin_code(output)), Some(output));
+/// }
+/// assert_eq!(pin_code(Some(Output::HeaterHigh)), 6);
+/// assert_eq!(pin_from_code(7), None);
+/// ```
+pub fn pin_code(output: Option<Output>) -> u8 {
+    output.map_or(0, |output| output as u8 + 1)
+}
+
+/// Decodes a byte from [pin_code]
+///
+/// - param code: The stored byte
+///
+/// returns the output the pin follows, or None if no output has that code
+pub fn pin_from_code(code: u8) -> Option<Option<Output>> {
+    match code {
+        0 => Some(None),
+        code => OUTPUTS.get(code as usize - 1).copied().map(Some),
+    }
+}
+
+/// Packs the byte that drives every expander pin to the [safe state](crate::safety::safe_state).
+/// Core0 writes it straight to the PCF8574 at boot, while power is failing and when it stops on a sensor fault,
+/// rather than waiting on the byte core1 latches, and [expander_shutdown] writes it on a panic.
+///
+/// **NOTE: The PCF8574 keeps its last byte through a reset of the RP2040 until it is written again,
+/// and the write on a panic is best effort, as the bus may be what failed**
+///
+/// - param map: The [Output] each pin follows. See [Preferences::expander_pins](crate::preferences::Preferences::expander_pins)
+/// - param vent_open: If the roof vent is left open. See [Preferences::safe_vent_open](crate::preferences::Preferences::safe_vent_open)
+/// - param active_high: If the expander's relays switch on while their pin is high
+///
+/// returns the byte to write
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::{safe_byte, PCF8574_IDLE};
+/// use gem_rs::preferences::Output;
+///
+/// let mut map = [None; 8];
+/// map[0] = Some(Output::Sprinklers);
+/// map[1] = Some(Output::RoofVent);
+/// map[2] = Some(Output::HeaterLow);
+///
+/// // Active-low relays: everything off is every pin high, apart from a vent left open
+/// assert_eq!(safe_byte(&map, false, false), PCF8574_IDLE);
+/// assert_eq!(safe_byte(&map, true, false), 0b1111_1101);
+/// // Active-high relays are switched off low; unused pins stay high either way
+/// assert_eq!(safe_byte(&map, true, true), 0b1111_1010);
+/// assert_eq!(safe_byte(&[None; 8], true, true), PCF8574_IDLE);
+/// ```
+pub fn safe_byte(map: &[Option<Output>; EXPANDER_PINS], vent_open: bool, active_high: bool) -> u8 {
+    let state = safe_state(vent_open);
+    pack_levels(map.map(|output| output.map(|output| output_level(state.get(output), active_high))))
+}
+
+/// The address [expander_shutdown] writes to, or 0 if no pin is used
+static SHUTDOWN_ADDRESS: AtomicU8 = AtomicU8::new(0);
+/// The byte [expander_shutdown] writes. See [safe_byte]
+static SHUTDOWN_BYTE: AtomicU8 = AtomicU8::new(PCF8574_IDLE);
+
+/// Sets what [expander_shutdown] writes to the PCF8574.
+/// Like [configure_shutdown](crate::safety::configure_shutdown), it is kept outside of the
+/// [Preferences](crate::preferences::Preferences), as a panic may strike while they are borrowed
+///
+/// - param address: The expander's I2C address, or None if no pin is used
+/// - param byte: The byte to write. See [safe_byte]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::expander::{configure_expander_shutdown, expander_shutdown_write, PCF8574_ADDRESS};
+///
+/// // Nothing is written until an expander pin is in use
+/// assert_eq!(expander_shutdown_write(), None);
+/// configure_expander_shutdown(Some(PCF8574_ADDRESS), 0b1111_1101);
+/// assert_eq!(expander_shutdown_write(), Some((PCF8574_ADDRESS, 0b1111_1101)));
+/// configure_expander_shutdown(None, 0b1111_1101);
+/// assert_eq!(expander_shutdown_write(), None);
+/// ```
+pub fn configure_expander_shutdown(address: Option<u8>, byte: u8) {
+    SHUTDOWN_ADDRESS.store(address.unwrap_or(0), Ordering::Relaxed);
+    SHUTDOWN_BYTE.store(byte, Ordering::Relaxed);
+}
+
+/// Gets what [expander_shutdown] writes
+///
+/// returns the address and byte, or None if nothing is written
+pub fn expander_shutdown_write() -> Option<(u8, u8)> {
+    let address = SHUTDOWN_ADDRESS.load(Ordering::Relaxed);
+    (address != 0).then(|| (address, SHUTDOWN_BYTE.load(Ordering::Relaxed)))
+}
+
+/// Writes the [configured](configure_expander_shutdown) safe byte to the PCF8574 by driving the bus lines directly,
+/// as the PIO driving the bus may be mid-transfer on the core that was stopped.
+/// Called by the panic handler after [safe_shutdown](crate::safety::safe_shutdown).
+/// It takes a bounded time: the clock is only waited on for so long, and the acknowledgements are not checked,
+/// so an expander that doesn't respond keeps its last byte
+///
+/// **NOTE: Both lines are open-drain; they are driven low or released to the pull-ups, never driven high**
+#[cfg(feature = "hardware")]
+pub fn expander_shutdown() {
+    const FUNCSEL_SIO: u8 = 5;
+    // About 5us at 125MHz, so the bus runs no faster than 100kHz
+    const HALF_PERIOD_CYCLES: u32 = 625;
+    // How many half periods a device may hold the clock low for
+    const STRETCH_LIMIT: u16 = 200;
+    let Some((address, byte)) = expander_shutdown_write() else {
+        return;
+    };
+    let sda = 1 << I2C_SDA_GPIO;
+    let scl = 1 << I2C_SCL_GPIO;
+    // Safety: only the bus pins are touched, and nothing else uses the bus once the panic handler runs
+    let io = unsafe { &*rp_pico::pac::IO_BANK0::ptr() };
+    let sio = unsafe { &*rp_pico::pac::SIO::ptr() };
+    let release = |mask: u32| sio.gpio_oe_clr().write(|w| unsafe { w.bits(mask) });
+    let pull_low = |mask: u32| sio.gpio_oe_set().write(|w| unsafe { w.bits(mask) });
+    let is_high = |mask: u32| sio.gpio_in().read().bits() & mask != 0;
+    let wait = || cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+    let clock = || {
+        release(scl);
+        for _ in 0..STRETCH_LIMIT {
+            if is_high(scl) {
+                break;
+            }
+            wait();
+        }
+        wait();
+        pull_low(scl);
+        wait();
+    };
+
+    release(sda | scl);
+    sio.gpio_out_clr().write(|w| unsafe { w.bits(sda | scl) });
+    for gpio in [I2C_SDA_GPIO, I2C_SCL_GPIO] {
+        io.gpio(gpio as usize)
+            .gpio_ctrl()
+            .modify(|_, w| unsafe { w.funcsel().bits(FUNCSEL_SIO) });
+    }
+    wait();
+
+    // Let a device stuck mid-byte finish it, as recover_i2c_bus does
+    for _ in 0..I2C_RECOVERY_PULSES {
+        if is_high(sda) {
+            break;
+        }
+        pull_low(scl);
+        wait();
+        release(scl);
+        wait();
+    }
+
+    // START: the data line falls while the clock is high
+    pull_low(sda);
+    wait();
+    pull_low(scl);
+    wait();
+    for frame in [address << 1, byte] {
+        for bit in (0..8).rev() {
+            if frame >> bit & 1 == 1 {
+                release(sda);
+            } else {
+                pull_low(sda);
+            }
+            clock();
+        }
+        // The acknowledgement isn't checked; there is nothing left to do if it is missing
+        release(sda);
+        clock();
+    }
+    // STOP: the data line rises while the clock is high
+    pull_low(sda);
+    wait();
+    release(scl);
+    wait();
+    release(sda);
+    wait();
+}
+
+/// The byte core1 wants on the PCF8574. Only core0 talks to the I2C bus, so core1 latches the
+/// byte here and core0 writes it out with [Pcf8574::flush]
+static LATCH: Mutex<Cell<u8>> = Mutex::new(Cell::new(PCF8574_IDLE));
+
+/// Gets the byte latched by the [ExpanderPin]s
+///
+/// returns the byte to write to the PCF8574
+pub fn latched() -> u8 {
+    critical_section::with(|cs| LATCH.borrow(cs).get())
+}
+
+/// A pin on the PCF8574, driven like a native pin so [drive] can target either.
+/// Setting it only changes the latched byte; see [latched]
+pub struct ExpanderPin(u8);
+
+impl ExpanderPin {
+    /// Creates a new instance of ExpanderPin
+    ///
+    /// - param pin: The pin, P0 - P7
+    ///
+    /// returns the pin, or None if the PCF8574 doesn't have it
+    pub fn new(pin: u8) -> Option<ExpanderPin> {
+        ((pin as usize) < EXPANDER_PINS).then_some(ExpanderPin(pin))
+    }
+
+    /// Latches the pin's level
+    ///
+    /// - param high: If the pin should be high
+    fn latch(&self, high: bool) {
+        critical_section::with(|cs| {
+            let latch = LATCH.borrow(cs);
+            latch.set(with_level(latch.get(), self.0, high));
+        });
+    }
+}
+
+impl ErrorType for ExpanderPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for ExpanderPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.latch(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.latch(true);
+        Ok(())
+    }
+}
+
+/// Drives every expander pin from the [Output] it follows, through [drive].
+/// Pins that don't follow an output are left high
+///
+/// - param map: The [Output] each pin follows. See [Preferences::expander_pins](crate::preferences::Preferences::expander_pins)
+/// - param outputs: The [Outputs] core1 is driving
+/// - param active_high: If the expander's relays switch on while their pin is high
+pub fn drive_expander(map: &[Option<Output>; EXPANDER_PINS], outputs: &Outputs, active_high: bool) {
+    for (pin, output) in (0u8..).zip(map) {
+        let mut expander_pin = ExpanderPin(pin);
+        match output {
+            Some(output) => drive(&mut expander_pin, outputs.get(*output), active_high),
+            None => expander_pin.set_high().unwrap(),
+        }
+    }
+}
+
+/// A PCF8574 I2C GPIO expander, sharing the bus with the BME680s on core0
+///
+/// - **i2c**: The expander's I2C device
+/// - **written**: The address and byte last written, or None if the byte must be written again
+pub struct Pcf8574<I> {
+    i2c: I,
+    written: Option<(u8, u8)>,
+}
+
+impl<I: I2c> Pcf8574<I> {
+    /// Creates a new instance of Pcf8574; nothing is written until it is flushed
+    ///
+    /// - param i2c: The expander's I2C device
+    ///
+    /// returns a new instance of Pcf8574
+    pub fn new(i2c: I) -> Pcf8574<I> {
+        Pcf8574 { i2c, written: None }
+    }
+
+    /// Writes the [latched] byte if it, or the address, changed since the last write.
+    /// A failed write is tried again on the next flush
+    ///
+    /// - param address: The expander's I2C address. See [is_valid_expander_address]
+    ///
+    /// returns an error if the write failed
+    pub fn flush(&mut self, address: u8) -> Result<(), I::Error> {
+        self.write(address, latched())
+    }
+
+    /// Writes a byte instead of the [latched] one, such as the [safe_byte], if it or the address changed since the last write
+    ///
+    /// - param address: The expander's I2C address. See [is_valid_expander_address]
+    /// - param byte: The byte to write
+    ///
+    /// returns an error if the write failed
+    pub fn write(&mut self, address: u8, byte: u8) -> Result<(), I::Error> {
+        if self.written == Some((address, byte)) {
+            return Ok(());
+        }
+        self.written = None;
+        self.i2c.write(address, &[byte])?;
+        self.written = Some((address, byte));
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// The smallest erasable unit of flash in bytes
+pub const SECTOR_SIZE: u32 = 4096;
+/// The smallest programmable unit of flash in bytes
+pub const PAGE_SIZE: u32 = 256;
+/// Where flash is mapped into memory
+#[cfg(feature = "hardware")]
+const XIP_BASE: u32 = 0x1000_0000;
+
+/// Set by core0 while it needs core1 kept off flash
+static PARK_REQUEST: AtomicBool = AtomicBool::new(false);
+/// Set by core1 while it is spinning in RAM
+static PARKED: AtomicBool = AtomicBool::new(false);
+/// Set once core1 checks for park requests
+static CORE1_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Marks core1 as running, so flash writes wait for it to park first.
+/// Must be called by core1 before its first [park_if_requested]
+pub fn mark_core1_running() {
+    CORE1_RUNNING.store(true, Ordering::Release);
+}
+
+/// Parks core1 in RAM if core0 is about to write flash; called by core1 on every pass
+pub fn park_if_requested() {
+    if PARK_REQUEST.load(Ordering::Acquire) {
+        park_in_ram();
+    }
+}
+
+/// Spins in RAM until core0 has finished with flash.
+/// Flash can't be read while it is erased or programmed, so core1 must not run code from it meanwhile.
+/// Core1 has no interrupts enabled, so none can pull it back onto flash
+#[inline(never)]
+#[cfg_attr(feature = "hardware", link_section = ".data.ram_func")]
+fn park_in_ram() {
+    PARKED.store(true, Ordering::Release);
+    while PARK_REQUEST.load(Ordering::Acquire) {
+        core::hint::spin_loop();
+    }
+    PARKED.store(false, Ordering::Release);
+}
+
+/// Runs a flash operation with core1 parked in RAM
+///
+/// - param f: The flash operation
+#[cfg(feature = "hardware")]
+fn with_core1_parked(f: impl FnOnce()) {
+    let running = CORE1_RUNNING.load(Ordering::Acquire);
+    if running {
+        PARK_REQUEST.store(true, Ordering::Release);
+        while !PARKED.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+    }
+    f();
+    if running {
+        PARK_REQUEST.store(false, Ordering::Release);
+        // Wait for core1 to leave, so a quick second write can't miss it
+        while PARKED.load(Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// A region of erasable storage; offsets are counted from the start of the region
+pub trait Storage {
+    /// Reads bytes from the region
+    ///
+    /// - param offset: where to start reading
+    /// - param buffer: filled with the bytes read
+    fn read(&self, offset: u32, buffer: &mut [u8]);
+
+    /// Erases a whole sector back to `0xFF`
+    ///
+    /// - param offset: the start of the sector
+    fn erase_sector(&mut self, offset: u32);
+
+    /// Programs bytes into erased space
+    ///
+    /// - param offset: where to start writing
+    /// - param data: the bytes to write
+    fn program(&mut self, offset: u32, data: &[u8]);
+}
+
+/// Checks if part of a [Storage] is fully erased
+///
+/// - param storage: [Storage] instance
+/// - param offset: where to start checking
+/// - param len: the amount of bytes to check
+///
+/// returns true if every byte is `0xFF`
+pub fn is_erased(storage: &impl Storage, offset: u32, len: u32) -> bool {
+    let mut bytes = [0u8; 16];
+    (offset..offset + len).step_by(bytes.len()).all(|start| {
+        let remaining = (offset + len - start) as usize;
+        let chunk = &mut bytes[..remaining.min(16)];
+        storage.read(start, chunk);
+        chunk.iter().all(|byte| *byte == 0xFF)
+    })
+}
+
+/// A region of the on-board flash
+///
+/// - **offset**: Where the region starts, counted from the start of flash
+#[cfg(feature = "hardware")]
+pub struct FlashStorage {
+    offset: u32,
+}
+
+#[cfg(feature = "hardware")]
+impl FlashStorage {
+    /// Creates a new instance of FlashStorage
+    ///
+    /// **NOTE: The region must be sector-aligned and hold no code**
+    ///
+    /// - param offset: Where the region starts, counted from the start of flash
+    ///
+    /// returns a new instance of FlashStorage
+    pub fn new(offset: u32) -> FlashStorage {
+        FlashStorage { offset }
+    }
+}
+
+#[cfg(feature = "hardware")]
+impl Storage for FlashStorage {
+    fn read(&self, offset: u32, buffer: &mut [u8]) {
+        let start = (XIP_BASE + self.offset + offset) as *const u8;
+        for (index, byte) in buffer.iter_mut().enumerate() {
+            // Safety: the region is inside the memory-mapped flash
+            *byte = unsafe { core::ptr::read_volatile(start.add(index)) };
+        }
+    }
+
+    fn erase_sector(&mut self, offset: u32) {
+        with_core1_parked(|| {
+            cortex_m::interrupt::free(|_| {
+                // Safety: interrupts are disabled, core1 is parked and the region holds no code
+                unsafe {
+                    rp2040_flash::flash::flash_range_erase(self.offset + offset, SECTOR_SIZE, true);
+                }
+            })
+        });
+    }
+
+    fn program(&mut self, offset: u32, data: &[u8]) {
+        // Flash is programmed a page at a time.
+        // Programming 0xFF leaves a byte untouched, so only `data` changes
+        let page_start = offset - offset % PAGE_SIZE;
+        let start = (offset - page_start) as usize;
+        let mut page = [0xFF; PAGE_SIZE as usize];
+        page[start..start + data.len()].copy_from_slice(data);
+        with_core1_parked(|| {
+            cortex_m::interrupt::free(|_| {
+                // Safety: interrupts are disabled, core1 is parked and the region holds no code
+                unsafe {
+                    rp2040_flash::flash::flash_range_program(self.offset + page_start, &page, true);
+                }
+            })
+        });
+    }
+}
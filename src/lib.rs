@@ -1,21 +1,68 @@
+// The firmware entry lives in main.rs. Without the `hardware` feature only the logic is built,
+// so the doc tests can run on the host
 #![no_std]
-#![no_main]
 
 //! # GEM-rs: Greenhouse Environmental Monitor
 //! ## A greenhouse monitoring system solution in Rust
 //!
 //! Features:
-//! - Temperature monitoring and safety range
+//! - Temperature monitoring and safety range, with separate day and night ranges
+//! - Temperature ranges edited as a min/max pair or as a target with a swing
 //! - Humidity monitoring and safety range
+//! - Mildew risk warning from the dew point
 //! - Pressure monitoring
+//! - Metric and imperial unit presets, and Fahrenheit, Celsius or Kelvin temperatures
+//! - Optional second sensor for larger greenhouses
+//! - I2C bus recovery when a sensor locks up, and sensor timeouts
+//! - Selectable policy for the actuators once the sensors fail
 //! - Uptime tracker
-//! - Watering system scheduler
-//! - Smoke/fire detection support
+//! - Watering system scheduler, optionally to the second
+//! - Humidity misting on its own output below the humidity range, and venting above it
+//! - Two-stage heater below the temperature range
+//! - Optional CO2 sensor, with CO2 venting or enrichment
+//! - Growing degree day tracking
+//! - Smoke/fire detection support, confirmed over several samples to ignore glitches
+//! - Fire alarm test that runs the fire response without smoke
+//! - Escalating alarm for readings that stay out of range
+//! - Distinct alarm tones and a volume setting for passive buzzers
+//! - Rain sensor that holds the roof vent closed
+//! - Extra relays on a PCF8574 I2C expander
+//! - Safety monitoring and actuators on the second core
+//! - Supply voltage monitoring
+//! - UART command interface, with every preference copied between controllers as one config blob
+//! - Flash data logging with CSV export
+//! - Event history
+//! - Daily and all-time extremes that survive restarts
+//! - LCD contrast and backlight control
+//! - The clock is redrawn every second, and the readings only when they change
+//! - Boot self-test of the outputs, sensor and smoke detector
 //!
 //! Links:
 //! [GitHub](https://github.com/QPCrummer/GEM-rs)
 
+#[cfg(feature = "serde")]
+extern crate alloc;
+
+pub mod buzzer;
+pub mod control;
+pub mod datalog;
+pub mod events;
+pub mod expander;
+pub mod flash;
+pub mod persist;
+#[cfg(feature = "hardware")]
+pub mod power;
 pub mod preferences;
 pub mod rendering;
+pub mod safety;
+pub mod screens;
+pub mod selftest;
 pub mod sensors;
+pub mod shared;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod stats;
 pub mod timer;
+pub mod uart;
+#[cfg(feature = "hardware")]
+pub mod watchdog;
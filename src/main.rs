@@ -1,18 +1,18 @@
 #![no_std]
 #![no_main]
 
-use bme680::{
-    Bme680, FieldData, I2CAddress, IIRFilterSize, OversamplingSetting, PowerMode, SettingsBuilder,
-};
 use bsp::entry;
-use core::time::Duration;
+#[cfg(not(feature = "sim"))]
+use core::cell::RefCell;
+use core::panic::PanicInfo;
 use defmt_rtt as _;
 use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::InputPin;
 use embedded_hal::digital::OutputPin;
-use embedded_hal::digital::StatefulOutputPin;
-use panic_probe as _;
-use rp_pico::hal::Timer;
+use embedded_hal::digital::PinState;
+#[cfg(not(feature = "sim"))]
+use embedded_hal_bus::i2c::RefCellDevice;
+use rp_pico::hal::{Adc, Timer};
 
 // Provide an alias for our BSP so we can switch targets quickly.
 // Uncomment the BSP you included in Cargo.toml, the rest of the code does not need to change.
@@ -23,33 +23,91 @@ use bsp::hal::{
     pac,
     watchdog::Watchdog,
 };
-use gem_rs::preferences::{inclusive_iterator, Preferences};
+use gem_rs::buzzer::Buzzer;
+use gem_rs::control::{has_mildew_risk, is_warmed_up, is_watering_held};
+use gem_rs::datalog::{DataLog, Record, CSV_HEADER, LOG_FLASH_OFFSET};
+use gem_rs::events::{EventKind, EventLog, EventStore, EVENT_FLASH_OFFSET};
+#[cfg(not(feature = "sim"))]
+use gem_rs::expander::{configure_expander_shutdown, safe_byte, Pcf8574};
+use gem_rs::expander::{drive_expander, expander_shutdown};
+use gem_rs::flash::{mark_core1_running, park_if_requested, FlashStorage};
+use gem_rs::persist::{PendingSave, PreferenceStore, PREFERENCES_FLASH_OFFSET};
+use gem_rs::power::Sleeper;
+use gem_rs::preferences::{DateTime, Output, Preferences};
 use gem_rs::rendering::{
-    render_date_edit_screen, render_edit_screen, render_screen, render_selector,
-    render_time_config_screen, render_watering_edit_screen, Lcd,
+    clear_display, confirm_hold, load_glyphs, render_age, render_progress, render_screen,
+    DisplayPwm, Lcd, LABELS,
+};
+use gem_rs::safety::{
+    configure_shutdown, drive, is_brownout, output_level, safe_shutdown, AlarmTest, SafetyMonitor,
+    SmokeFilter, SAFETY_POLL_MS,
+};
+use gem_rs::screens::{Context, RenderTimes, Screen};
+#[cfg(not(feature = "sim"))]
+use gem_rs::selftest::Component;
+use gem_rs::selftest::{halt_on_fault, last_hung_component, run_self_test};
+use gem_rs::sensors::{
+    get_humidity, get_temperature, handle_sensor_failure, read_supply_voltage_mv, DualSource,
+    FailureCounter, GasBaseline, GasSettings, PressureHistory, Reading, SensorSource, VsysPin,
+};
+#[cfg(not(feature = "sim"))]
+use gem_rs::sensors::{
+    init_co2_sensor, probe_address, Bme680Source, SensorDeadline, BME_ADDRESSES, SCD4X_INTERVAL_MS,
+};
+use gem_rs::shared::{self, status_events, BuzzerRequest, Status};
+#[cfg(feature = "sim")]
+use gem_rs::sim::{ScriptedSource, TEMPERATURE_RAMP};
+use gem_rs::stats::{StatsStore, STATS_FLASH_OFFSET};
+use gem_rs::timer::{
+    is_stale, sleep_duration, AutoRotate, CountDownTimer, Measurement, Stopwatch,
+    SCREEN_BUTTON_DELAY, SENSOR_DELAY, TICK_TIME_DELAY,
 };
-use gem_rs::sensors::{get_bme_data, get_humidity, get_pressure, get_temperature};
-use gem_rs::timer::{CountDownTimer, SCREEN_BUTTON_DELAY, SENSOR_DELAY, TICK_TIME_DELAY};
+use gem_rs::uart::{execute, parse_command, poll_line, write_reply, Command, LineBuffer, Uart};
+use gem_rs::watchdog::{feed_watchdog, start_loop_watchdog};
 use hd44780_driver::bus::FourBitBusPins;
 use hd44780_driver::memory_map::MemoryMap1602;
 use hd44780_driver::setup::DisplayOptions4Bit;
 use hd44780_driver::{Cursor, CursorBlink, HD44780};
-use heapless::String;
+#[cfg(not(feature = "sim"))]
 use i2c_pio::I2C;
 use rp_pico::hal;
+use rp_pico::hal::adc::AdcPin;
 use rp_pico::hal::fugit::RateExtU32;
 use rp_pico::hal::gpio::bank0::{Gpio10, Gpio11, Gpio12};
-use rp_pico::hal::gpio::{FunctionSio, Pin, PullDown, SioInput};
+use rp_pico::hal::gpio::{FunctionSio, Interrupt, Pin, PullDown, SioInput};
+use rp_pico::hal::multicore::{Multicore, Stack};
+#[cfg(not(feature = "sim"))]
 use rp_pico::hal::pio::PIOExt;
-use ufmt::uwrite;
+use rp_pico::hal::pwm::Slices;
+use rp_pico::hal::sio::CoreId;
+use rp_pico::hal::uart::{DataBits, StopBits, UartConfig, UartPeripheral};
 
-const FIRE: &str = "Fire Present";
+/// The stack of core1, which runs [core1_task]
+static CORE1_STACK: Stack<4096> = Stack::new();
+
+/// Makes the outputs safe before halting, so a panic can't leave the sprinklers running or the vent open.
+/// The other core is stopped first, so it can't drive the outputs again
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    // Safety: nothing else runs once the other core is stopped, and stopping it only touches its own bit
+    let psm = unsafe { &*pac::PSM::ptr() };
+    match hal::Sio::core() {
+        CoreId::Core0 => psm.frce_off().modify(|_, w| w.proc1().set_bit()),
+        CoreId::Core1 => psm.frce_off().modify(|_, w| w.proc0().set_bit()),
+    }
+    safe_shutdown();
+    expander_shutdown();
+    defmt::error!("{}", defmt::Display2Format(info));
+    // Halt in a way the debugger reports, like panic-probe does
+    cortex_m::asm::udf()
+}
 
 #[entry]
 fn main() -> ! {
     // Grab our singleton objects
     let mut pac = pac::Peripherals::take().unwrap();
-    let _core = pac::CorePeripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
 
     // Set up the watchdog driver - needed by the clock setup code
     let mut watchdog = Watchdog::new(pac.WATCHDOG);
@@ -70,7 +128,7 @@ fn main() -> ! {
     .unwrap();
 
     // The single-cycle I/O block controls our GPIO pins
-    let sio = hal::Sio::new(pac.SIO);
+    let mut sio = hal::Sio::new(pac.SIO);
 
     // Set the pins up according to their function on this particular board
     let pins = rp_pico::Pins::new(
@@ -82,38 +140,33 @@ fn main() -> ! {
 
     // Set up delays
     let mut delay = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut sleeper = Sleeper::new(delay.alarm_0().unwrap(), &mut core.SCB);
+    let mut stopwatch = Stopwatch::new(delay.get_counter().ticks());
     let mut button_countdown = CountDownTimer::new(0);
     let mut sensor_countdown = CountDownTimer::new(0);
     let mut time_countdown = CountDownTimer::new(0);
 
+    // Load the preferences first; the sensors and actuators are set up from them
+    let mut preference_store = PreferenceStore::new(FlashStorage::new(PREFERENCES_FLASH_OFFSET));
+    let mut preferences: Preferences = preference_store.load().unwrap_or_default();
+    preferences.validate_and_fix();
+
+    #[cfg(not(feature = "sim"))]
     let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
 
-    let i2c_pio = I2C::new(
+    // Set up the BME680s; both share one I2C bus
+    #[cfg(not(feature = "sim"))]
+    let i2c_bus = RefCell::new(I2C::new(
         &mut pio,
         pins.gpio8,
         pins.gpio9,
         sm0,
         100.kHz(),
         clocks.system_clock.freq(),
-    );
-
-    // Set up BME680
-    let mut bme = Bme680::init(i2c_pio, &mut delay, I2CAddress::Secondary).unwrap();
-    let settings = SettingsBuilder::new()
-        .with_humidity_oversampling(OversamplingSetting::OS2x)
-        .with_pressure_oversampling(OversamplingSetting::OS4x)
-        .with_temperature_oversampling(OversamplingSetting::OS8x)
-        .with_temperature_filter(IIRFilterSize::Size3)
-        .with_temperature_offset(-8.9)
-        .with_gas_measurement(Duration::from_millis(1500), 320, 25)
-        .with_run_gas(true)
-        .build();
-
-    bme.set_sensor_settings(&mut delay, settings).unwrap();
-
-    bme.set_sensor_mode(&mut delay, PowerMode::ForcedMode)
-        .unwrap();
-
+    ));
+    // A misbehaving bus fails the sensor operation once its time is up, rather than holding up the loop
+    #[cfg(not(feature = "sim"))]
+    let sensor_deadline = SensorDeadline::new(preferences.sensor_timeout_ms);
     // Set up LCD1602
     let rs = pins.gpio0.into_push_pull_output();
     let en = pins.gpio1.into_push_pull_output();
@@ -146,35 +199,331 @@ fn main() -> ! {
     lcd.set_cursor_visibility(Cursor::Invisible, &mut delay)
         .unwrap();
     lcd.set_cursor_blink(CursorBlink::Off, &mut delay).unwrap();
+    load_glyphs(&mut lcd, &mut delay);
+
+    // Find the sensors once the LCD is up, so a missing sensor can be shown
+    #[cfg(not(feature = "sim"))]
+    let (mut sensor_source, sensor_addresses) = {
+        let gas = GasSettings::new(&preferences);
+        let (bus, deadline) = (&i2c_bus, &sensor_deadline);
+        let init = move |address| Bme680Source::init(bus, deadline, address, delay, gas);
+        // Use whichever address the sensor is strapped to; only a bus with no sensor at all is a fault
+        let Ok((address, first, remaining)) = probe_address(&BME_ADDRESSES, init) else {
+            defmt::error!("No sensor responded on either address");
+            halt_on_fault(Component::Sensor, &mut lcd, &mut delay);
+        };
+        defmt::info!("Sensor found at {=u8:#x}", address.addr());
+        // The second sensor is optional; it can only sit at an address after the first
+        let second = probe_address(remaining, init)
+            .ok()
+            .map(|(_, second, _)| second);
+        let addresses = [
+            Some(address.addr()),
+            second.as_ref().map(|second| second.address().addr()),
+        ];
+        (DualSource::new(first, second), addresses)
+    };
+
+    // The SCD4x CO2 sensor is optional and shares the bus too
+    #[cfg(not(feature = "sim"))]
+    let mut co2_sensor = init_co2_sensor(&i2c_bus, &sensor_deadline, &mut delay);
+    #[cfg(not(feature = "sim"))]
+    if co2_sensor.is_some() {
+        defmt::info!("CO2 sensor found");
+    }
+
+    // Relays on a PCF8574 share the sensors' bus, so only this core writes to it; core1 latches their levels
+    #[cfg(not(feature = "sim"))]
+    let mut expander = Pcf8574::new(RefCellDevice::new(&i2c_bus));
+    #[cfg(not(feature = "sim"))]
+    let mut expander_failed = false;
+    // It keeps its last byte through a reset, so it is put in the safe state until core1 drives it
+    #[cfg(not(feature = "sim"))]
+    if preferences.expander_pins.iter().any(Option::is_some) {
+        let safe = safe_byte(
+            &preferences.expander_pins,
+            preferences.safe_vent_open,
+            preferences.expander_active_high,
+        );
+        if expander.write(preferences.expander_address, safe).is_err() {
+            defmt::warn!("PCF8574 expander did not respond");
+            expander_failed = true;
+        }
+    }
+
+    // Play back scripted readings instead
+    #[cfg(feature = "sim")]
+    let (mut sensor_source, sensor_addresses) = (
+        DualSource::new(
+            ScriptedSource::new(&TEMPERATURE_RAMP),
+            None::<ScriptedSource>,
+        ),
+        [None; 2],
+    );
 
     // Set up button up
     let mut up_button = pins.gpio10.into_pull_down_input();
+    up_button.set_interrupt_enabled(Interrupt::EdgeHigh, true);
 
     // Set up button down
     let mut down_button = pins.gpio11.into_pull_down_input();
+    down_button.set_interrupt_enabled(Interrupt::EdgeHigh, true);
 
     // Set up button select
     let mut select_button = pins.gpio12.into_pull_down_input();
-
-    // Set up buzzer
-    let mut buzzer = pins.gpio6.into_push_pull_output();
+    select_button.set_interrupt_enabled(Interrupt::EdgeHigh, true);
 
     // Set up smoke detector
     let mut smoke_detector = pins.gpio7.into_pull_down_input();
 
+    // Set up rain sensor; its digital output goes high while wet
+    let rain_sensor = pins.gpio20.into_pull_down_input();
+
+    // Set up LCD contrast (GPIO18) and backlight (GPIO19)
+    let pwm_slices = Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut display = DisplayPwm::new(pwm_slices.pwm1, pins.gpio18, pins.gpio19);
+
+    // Set up supply voltage monitor
+    let mut adc = Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut vsys: VsysPin = AdcPin::new(pins.voltage_monitor.into_floating_input()).unwrap();
+
+    // Set up the UART command interface
+    let uart_pins = (pins.gpio16.into_function(), pins.gpio17.into_function());
+    let mut uart: Uart = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+    // Received bytes wake the core from sleep
+    uart.enable_rx_interrupt();
+    let mut line_buffer = LineBuffer::default();
+
+    let mut current_screen: Screen = preferences
+        .default_screen
+        .or_next_visible(preferences.hidden_screens);
+    let mut auto_rotate = AutoRotate::default();
+    let mut render_times = RenderTimes::default();
+    let mut measurement = Measurement::default();
+    let mut data: Reading = Reading::default();
+    let mut pressure_history = PressureHistory::default();
+    let mut data_log = DataLog::new(FlashStorage::new(LOG_FLASH_OFFSET));
+    let mut gas_baseline = GasBaseline::default();
+    let mut gas_settings = GasSettings::new(&preferences);
+    let mut sensor_failures = FailureCounter::default();
+    let mut pending_save = PendingSave::default();
+    let mut supply_mv: u16 = 0;
+    let mut brownout = false;
+    #[cfg(not(feature = "sim"))]
+    let mut co2: Option<u16> = None;
+    #[cfg(not(feature = "sim"))]
+    let mut co2_read = preferences.date;
+    let mut manual_watering_ms: u32 = 0;
+    let mut alarm_test = AlarmTest::default();
+    let mut low_voltage_logged = false;
+    let mut status = Status::default();
+    // The buzzer belongs to core1, so core0 sounds it through the shared state
+    let mut buzzer_request = BuzzerRequest;
+    let mut warming_up = true;
+    let mut mildew_risk = false;
+    let mut degraded_logged = false;
+    let mut events = EventLog::default();
+    let mut event_store = EventStore::new(FlashStorage::new(EVENT_FLASH_OFFSET));
+    event_store.for_each(|event| events.push(*event));
+    display.apply(&preferences);
+    let mut last_read = preferences.date;
+    // The first boot has no stats saved, so they start empty
+    let mut stats_store = StatsStore::new(FlashStorage::new(STATS_FLASH_OFFSET));
+    let mut stats = stats_store.load().unwrap_or_default();
+
+    // Set up the actuators once their active levels are known, so no relay switches on at boot
+    let levels = preferences.active_levels;
+    let off = |active_high: bool| PinState::from(output_level(false, active_high));
+
+    // Set up buzzer (GPIO6) on PWM slice 3, so passive buzzers can be played as tones
+    let mut buzzer = Buzzer::new(
+        pwm_slices.pwm3,
+        pins.gpio6,
+        clocks.system_clock.freq().to_Hz(),
+        levels.buzzer,
+    );
+
     // Set up sprinklers
-    let mut sprinklers = pins.gpio13.into_push_pull_output();
+    let mut sprinklers = pins
+        .gpio13
+        .into_push_pull_output_in_state(off(levels.sprinklers));
 
     // Set up roof vent
-    let mut roof_vent = pins.gpio14.into_push_pull_output();
+    let mut roof_vent = pins
+        .gpio14
+        .into_push_pull_output_in_state(off(levels.roof_vent));
+
+    // Set up mister; kept apart from the sprinklers so misting and watering don't override each other
+    let mut mister = pins
+        .gpio15
+        .into_push_pull_output_in_state(off(levels.mister));
+
+    // Set up the heater stages; the second only runs alongside the first, for full heat
+    let heater_low = pins
+        .gpio21
+        .into_push_pull_output_in_state(off(levels.heater_low));
+    let heater_high = pins
+        .gpio22
+        .into_push_pull_output_in_state(off(levels.heater_high));
 
-    let mut current_screen_index: u8 = 0;
-    let mut data: FieldData = FieldData::default();
-    let mut preferences: Preferences = Preferences::default();
+    // Test the hardware before trusting it with the greenhouse.
+    // A step that hung is reported rather than run again, so a dead sensor can't cause a reset loop
+    if preferences.self_test {
+        if let Some(component) = last_hung_component() {
+            halt_on_fault(component, &mut lcd, &mut delay);
+        }
+        let report = run_self_test(
+            &preferences,
+            &mut sensor_source,
+            &mut smoke_detector,
+            &mut sprinklers,
+            &mut roof_vent,
+            &mut mister,
+            &mut buzzer,
+            &mut watchdog,
+            &mut lcd,
+            &mut delay,
+        );
+        if let Some(component) = report.failure() {
+            halt_on_fault(component, &mut lcd, &mut delay);
+        }
+    }
+
+    // A pass of the main loop that hangs, such as on a stuck I2C transaction, resets the board
+    start_loop_watchdog(watchdog);
+
+    // Hand the smoke detector and actuators over to core1, so the UI can't hold up the smoke alarm
+    shared::init(&preferences);
+    configure_shutdown(preferences.safe_vent_open, &preferences.active_levels);
+    let mut multicore = Multicore::new(&mut pac.PSM, &mut pac.PPB, &mut sio.fifo);
+    let cores = multicore.cores();
+    let core1_delay = delay;
+    cores[1]
+        .spawn(CORE1_STACK.take().unwrap(), move || {
+            core1_task(
+                core1_delay,
+                smoke_detector,
+                rain_sensor,
+                sprinklers,
+                roof_vent,
+                mister,
+                heater_low,
+                heater_high,
+                buzzer,
+            )
+        })
+        .unwrap();
 
     loop {
-        // Delay loop
-        delay.delay_ms(1);
+        // Sleep until a countdown runs out or a button or UART wakes the core
+        sleeper.sleep_ms(
+            sleep_duration(&button_countdown, &sensor_countdown, &time_countdown)
+                .min(measurement.sleep_ms())
+                .min(match manual_watering_ms {
+                    0 => u16::MAX,
+                    ms => ms.min(u16::MAX as u32) as u16,
+                }),
+        );
+        up_button.clear_interrupt(Interrupt::EdgeHigh);
+        down_button.clear_interrupt(Interrupt::EdgeHigh);
+        select_button.clear_interrupt(Interrupt::EdgeHigh);
+        let elapsed_ms = stopwatch.lap_ms(delay.get_counter().ticks());
+        feed_watchdog();
+        // Manual watering from UART runs on top of the automatic control on core1.
+        // It counts down every pass rather than every reading, so short bursts are accurate
+        manual_watering_ms = manual_watering_ms.saturating_sub(elapsed_ms as u32);
+
+        // Save once the preferences have settled, so a burst of edits is written once
+        if pending_save.step(elapsed_ms) {
+            preference_store.save(&preferences);
+        }
+
+        // Log what core1 has done since the last pass
+        let latest = shared::status();
+        for kind in status_events(status, latest).into_iter().flatten() {
+            event_store.append(&events.log_event(&preferences.date, kind));
+        }
+        // Redraw as soon as an alarm test starts or stops, so it is labelled as a test straight away
+        let test_changed = latest.alarm_test != status.alarm_test;
+        status = latest;
+        let alarm_testing = alarm_test.step(elapsed_ms, latest.fire);
+        // Readings left stale by failed sensors hand the actuators over to the fault policy
+        let sensor_fault = is_stale(
+            preferences.date.seconds_since(&last_read),
+            SENSOR_DELAY,
+            preferences.stale_factor,
+        );
+        // A CO2 reading that stopped updating is dropped rather than acted on
+        #[cfg(not(feature = "sim"))]
+        let fresh_co2 = co2.filter(|_| {
+            !is_stale(
+                preferences.date.seconds_since(&co2_read),
+                SCD4X_INTERVAL_MS,
+                preferences.stale_factor,
+            )
+        });
+        #[cfg(feature = "sim")]
+        let fresh_co2: Option<u16> = None;
+
+        // Show that a measurement is in progress
+        if let Some(frame) = measurement.step(elapsed_ms) {
+            render_progress(frame, &mut lcd, &mut delay);
+        }
+
+        // Keep the age of the readings current between screen updates
+        if current_screen.shows_age()
+            && bottom_warning(
+                supply_mv,
+                &preferences,
+                sensor_source.degraded(),
+                sensor_fault,
+                status,
+                warming_up,
+                mildew_risk,
+            )
+            .is_none()
+        {
+            render_reading_age(&preferences, &last_read, &mut lcd, &mut delay);
+        }
+
+        // Answer any commands received over UART
+        if let Some(line) = poll_line(&uart, &mut line_buffer) {
+            match line.and_then(|line| parse_command(&line)) {
+                Ok(command) => {
+                    match command {
+                        Command::WaterNow(seconds) => manual_watering_ms = seconds as u32 * 1000,
+                        Command::GetLog => {
+                            write_reply(&uart, CSV_HEADER);
+                            // A full dump takes seconds at 115200 baud, longer than the loop watchdog allows
+                            data_log.for_each(|record| {
+                                write_reply(&uart, &record.to_csv());
+                                feed_watchdog();
+                            });
+                        }
+                        Command::ResetGdd => {
+                            stats.reset_gdd();
+                            stats_store.save(&stats);
+                        }
+                        _ => {}
+                    }
+                    let before = preferences.clone();
+                    write_reply(&uart, &execute(&command, &mut preferences));
+                    preferences.validate_and_fix();
+                    if preferences.differs_from(&before, 0) {
+                        display.apply(&preferences);
+                        pending_save.mark();
+                    }
+                    // Move off the current screen if it was just hidden
+                    current_screen = current_screen.or_next_visible(preferences.hidden_screens);
+                }
+                Err(error) => write_reply(&uart, error.reply()),
+            }
+        }
 
         let action = should_update(
             &mut up_button,
@@ -184,484 +533,467 @@ fn main() -> ! {
             &mut button_countdown,
             &mut sensor_countdown,
             &mut time_countdown,
+            &mut auto_rotate,
+            elapsed_ms,
         );
+        // Check if power is failing; while it is, the supply is checked on every pass.
+        // A failed read leaves the last good reading and the brownout state as they were
+        let mut recovered = false;
+        if brownout || matches!(action, RefreshAction::Sensor) {
+            if let Some(mv) = read_supply_voltage_mv(&mut adc, &mut vsys) {
+                supply_mv = mv;
+                let failing = is_brownout(supply_mv, preferences.critical_voltage);
+                if failing && !brownout {
+                    render_screen(LABELS.low_power, true, &mut lcd, &mut delay);
+                }
+                recovered = brownout && !failing;
+                brownout = failing;
+            }
+        }
+        // A finished measurement is read once nothing else needs handling
+        let action = match action {
+            RefreshAction::None if measurement.take_done() => RefreshAction::Measured,
+            RefreshAction::None if test_changed || recovered => RefreshAction::Redraw,
+            action => action,
+        };
+
+        // Keep core1 acting on the latest preferences and clock
+        shared::request(|requests| {
+            requests.preferences.clone_from(&preferences);
+            requests.manual_watering = manual_watering_ms > 0;
+            requests.alarm_test = alarm_testing;
+            requests.sensor_fault = sensor_fault;
+            requests.co2 = fresh_co2.filter(|_| !warming_up);
+            requests.safe_state = brownout;
+        });
+        configure_shutdown(preferences.safe_vent_open, &preferences.active_levels);
+        #[cfg(not(feature = "sim"))]
+        let expander_used = preferences.expander_pins.iter().any(Option::is_some);
+        #[cfg(not(feature = "sim"))]
+        let expander_safe = safe_byte(
+            &preferences.expander_pins,
+            preferences.safe_vent_open,
+            preferences.expander_active_high,
+        );
+        #[cfg(not(feature = "sim"))]
+        configure_expander_shutdown(
+            expander_used.then_some(preferences.expander_address),
+            expander_safe,
+        );
+        // Write out the expander pins latched by core1; the sensors are never mid-transaction here.
+        // While power is failing the safe byte goes out instead, without waiting for core1 to latch it
+        #[cfg(not(feature = "sim"))]
+        if expander_used {
+            let address = preferences.expander_address;
+            let failed = if brownout {
+                expander.write(address, expander_safe)
+            } else {
+                expander.flush(address)
+            }
+            .is_err();
+            if failed && !expander_failed {
+                defmt::warn!("PCF8574 expander did not respond");
+            }
+            expander_failed = failed;
+        }
+        // The outputs are held safe until the supply recovers; the clock, UART and logging keep running
+        if brownout {
+            continue;
+        }
 
+        // Called when the sensors can't be recovered, by either a failed start or a failed read.
+        // Core0 stops there, so the fault policy is applied straight away
+        let mut on_sensor_fault = || {
+            shared::request(|requests| requests.sensor_fault = true);
+            // Nothing flushes the expander once core0 stops, so it is left in the safe state
+            #[cfg(not(feature = "sim"))]
+            if expander_used {
+                let _ = expander.write(preferences.expander_address, expander_safe);
+            }
+            event_store.append(&events.log_event(&preferences.date, EventKind::SensorFault));
+        };
         match action {
             RefreshAction::Up => {
-                current_screen_index = next_screen(current_screen_index, true);
+                current_screen = current_screen.next_visible(preferences.hidden_screens);
             }
             RefreshAction::Down => {
-                current_screen_index = next_screen(current_screen_index, false);
+                current_screen = current_screen.prev_visible(preferences.hidden_screens);
+            }
+            RefreshAction::Rotate => {
+                current_screen = current_screen.next_dashboard(preferences.hidden_screens);
+            }
+            RefreshAction::Redraw => {}
+            RefreshAction::AlarmTest => {
+                // Hidden diagnostic for technicians: runs the fire response for a few seconds without smoke
+                if confirm_hold(
+                    LABELS.test_alarm_prompt,
+                    &mut preferences,
+                    &mut lcd,
+                    &mut delay,
+                    &mut up_button,
+                    &mut down_button,
+                    &mut select_button,
+                ) {
+                    defmt::info!("Alarm test started");
+                    alarm_test.start();
+                    shared::request(|requests| requests.alarm_test = true);
+                }
             }
             RefreshAction::Select => {
                 // Handle SELECT action
-                lcd.clear(&mut delay).unwrap();
-                let mut editing_lower: bool = true;
-                let mut update_date: bool = false;
-                let mut refresh: bool = true;
-                let mut info_str: String<11> = String::new();
-                match current_screen_index {
-                    0 => {
-                        // Temp
-                        for _ in 0..2 {
-                            loop {
-                                if refresh {
-                                    uwrite!(
-                                        &mut info_str,
-                                        "{} - {}",
-                                        preferences.temperature.0,
-                                        preferences.temperature.1
-                                    )
-                                    .unwrap();
-                                    render_edit_screen(
-                                        &info_str,
-                                        editing_lower,
-                                        &mut lcd,
-                                        &mut delay,
-                                    );
-                                    info_str.clear();
-                                    refresh = false;
-                                }
-
-                                delay.delay_ms(500);
-
-                                if update_date {
-                                    preferences.tick_time();
-                                }
-                                update_date = !update_date;
-
-                                if up_button.is_high().unwrap() {
-                                    if editing_lower {
-                                        if preferences.temperature.0 < 100 {
-                                            preferences.temperature.0 += 1;
-                                        }
-                                    } else if preferences.temperature.1 < 100 {
-                                        preferences.temperature.1 += 1;
-                                    }
-                                    refresh = true;
-                                } else if down_button.is_high().unwrap() {
-                                    if editing_lower {
-                                        if preferences.temperature.0 > 0 {
-                                            preferences.temperature.0 -= 1;
-                                        }
-                                    } else if preferences.temperature.1 > 0 {
-                                        preferences.temperature.1 -= 1;
-                                    }
-                                    refresh = true;
-                                } else if select_button.is_high().unwrap() {
-                                    editing_lower = false;
-                                    render_selector(false, 15, &mut lcd, &mut delay);
-
-                                    refresh = true;
-                                    break;
-                                }
-                            }
-                        }
-                        // Check legality
-                        if preferences.temperature.0 > preferences.temperature.1 {
-                            core::mem::swap(
-                                &mut preferences.temperature.0,
-                                &mut preferences.temperature.1,
-                            );
-                        }
-                    }
-                    1 => {
-                        // Humidity
-                        for _ in 0..2 {
-                            loop {
-                                if refresh {
-                                    uwrite!(
-                                        &mut info_str,
-                                        "{}% - {}%",
-                                        preferences.humidity.0,
-                                        preferences.humidity.1
-                                    )
-                                    .unwrap();
-                                    render_edit_screen(
-                                        &info_str,
-                                        editing_lower,
-                                        &mut lcd,
-                                        &mut delay,
-                                    );
-                                    info_str.clear();
-                                    refresh = false;
-                                }
-
-                                delay.delay_ms(500);
-
-                                if update_date {
-                                    preferences.tick_time();
-                                }
-                                update_date = !update_date;
-
-                                if up_button.is_high().unwrap() {
-                                    if editing_lower {
-                                        if preferences.humidity.0 < 100 {
-                                            preferences.humidity.0 += 1;
-                                        }
-                                    } else if preferences.humidity.1 < 100 {
-                                        preferences.humidity.1 += 1;
-                                    }
-                                    refresh = true;
-                                } else if down_button.is_high().unwrap() {
-                                    if editing_lower {
-                                        if preferences.humidity.0 > 0 {
-                                            preferences.humidity.0 -= 1;
-                                        }
-                                    } else if preferences.humidity.1 > 0 {
-                                        preferences.humidity.1 -= 1;
-                                    }
-                                    refresh = true;
-                                } else if select_button.is_high().unwrap() {
-                                    editing_lower = false;
-                                    render_selector(false, 15, &mut lcd, &mut delay);
-                                    refresh = true;
-                                    break;
-                                }
-                            }
-                        }
-                        // Check legality
-                        if preferences.humidity.0 > preferences.humidity.1 {
-                            core::mem::swap(
-                                &mut preferences.humidity.0,
-                                &mut preferences.humidity.1,
-                            );
+                clear_display(&mut lcd, &mut delay);
+                let before = preferences.clone();
+                let edit_started = delay.get_counter().ticks();
+                current_screen.handler().edit(&mut Context {
+                    lcd: &mut lcd,
+                    delay: &mut delay,
+                    up_button: &mut up_button,
+                    down_button: &mut down_button,
+                    select_button: &mut select_button,
+                    preferences: &mut preferences,
+                    data: &data,
+                    pressure_history: &pressure_history,
+                    sensors: sensor_source.readings(),
+                    sensor_addresses,
+                    stats: &stats,
+                    events: &events,
+                    display: &mut display,
+                    supply_mv,
+                    co2: fresh_co2,
+                });
+                // Only touch flash when something was edited; the clock ticked while editing
+                let edit_secs = ((delay.get_counter().ticks() - edit_started) / 1_000_000) as u32;
+                if preferences.differs_from(&before, edit_secs) {
+                    display.apply(&preferences);
+                    pending_save.mark();
+                }
+            }
+            RefreshAction::Sensor => {
+                #[cfg(not(feature = "sim"))]
+                sensor_deadline.set_timeout_ms(preferences.sensor_timeout_ms);
+                // The SCD4x measures on its own, so it is only read here once it has something new
+                #[cfg(not(feature = "sim"))]
+                if let Some(sensor) = co2_sensor.as_mut() {
+                    let now_us = delay.get_counter().ticks();
+                    match sensor_deadline.run(now_us, 0, || sensor.read_co2_ppm(&mut delay)) {
+                        Ok(Some(ppm)) => {
+                            co2 = Some(ppm);
+                            co2_read = preferences.date;
                         }
+                        Ok(None) => {}
+                        Err(_) => defmt::warn!("CO2 sensor read failed"),
                     }
-                    3 => {
-                        // Date
-
-                        preferences.date.1 = render_time_config_screen(
-                            "Minute",
-                            &mut info_str,
-                            0,
-                            59,
-                            preferences.date.1,
-                            &mut preferences,
-                            &mut lcd,
-                            &mut delay,
-                            &mut up_button,
-                            &mut down_button,
-                            &mut select_button,
-                        );
-                        info_str.clear();
-
-                        preferences.date.2 = render_time_config_screen(
-                            "Hour",
-                            &mut info_str,
-                            0,
-                            23,
-                            preferences.date.2,
-                            &mut preferences,
-                            &mut lcd,
-                            &mut delay,
-                            &mut up_button,
-                            &mut down_button,
-                            &mut select_button,
-                        );
-                        info_str.clear();
-
-                        preferences.date.3 = render_time_config_screen(
-                            "Day",
-                            &mut info_str,
-                            1,
-                            preferences.get_days_in_month(),
-                            preferences.date.3,
-                            &mut preferences,
-                            &mut lcd,
-                            &mut delay,
-                            &mut up_button,
-                            &mut down_button,
-                            &mut select_button,
-                        );
-                        info_str.clear();
-
-                        preferences.date.4 = render_time_config_screen(
-                            "Month",
-                            &mut info_str,
-                            1,
-                            12,
-                            preferences.date.4,
-                            &mut preferences,
-                            &mut lcd,
-                            &mut delay,
-                            &mut up_button,
-                            &mut down_button,
-                            &mut select_button,
-                        );
-                        info_str.clear();
-
-                        // Year
-                        loop {
-                            if refresh {
-                                uwrite!(&mut info_str, "Year: {}", preferences.date.5).unwrap();
-                                render_date_edit_screen(&info_str, &mut lcd, &mut delay);
-                                info_str.clear();
-                                refresh = false;
-                            }
-                            delay.delay_ms(500);
-
-                            if update_date {
-                                preferences.tick_time();
-                            }
-                            update_date = !update_date;
-
-                            if up_button.is_high().unwrap() {
-                                // Assuming the integer limit cannot be reached
-                                preferences.date.5 += 1;
-                                refresh = true;
-                            } else if down_button.is_high().unwrap() {
-                                if preferences.date.5 != 0 {
-                                    preferences.date.5 -= 1;
-                                }
-                                refresh = true;
-                            } else if select_button.is_high().unwrap() {
-                                break;
-                            }
-                        }
-
-                        // Validate day
-                        if preferences.date.3 > preferences.get_days_in_month() {
-                            preferences.date.3 = preferences.get_days_in_month();
-                        }
+                }
+                // Apply changed gas settings between measurements
+                let gas = GasSettings::new(&preferences);
+                if gas != gas_settings && sensor_source.configure(&gas).is_ok() {
+                    gas_settings = gas;
+                }
 
-                        render_selector(false, 7, &mut lcd, &mut delay);
+                // The measurement is read once it completes, so the loop keeps running meanwhile
+                match sensor_source.start() {
+                    Ok(measurement_ms) => {
+                        measurement.start(measurement_ms);
+                        render_progress(measurement.frame(), &mut lcd, &mut delay);
                     }
-                    4 => {
-                        let mut remove: bool = false;
-                        for index in 0..4 {
-                            loop {
-                                if refresh {
-                                    render_watering_edit_screen(
-                                        &preferences.format_watering_time(),
-                                        index,
-                                        &mut lcd,
-                                        &mut delay,
-                                    );
-                                    refresh = false;
-                                }
-
-                                delay.delay_ms(500);
-
-                                if update_date {
-                                    preferences.tick_time();
-                                }
-                                update_date = !update_date;
-
-                                if up_button.is_high().unwrap() && down_button.is_high().unwrap() {
-                                    remove = true;
-                                    break;
-                                }
-
-                                if up_button.is_high().unwrap() {
-                                    if preferences.watering.is_none() {
-                                        preferences.set_default_watering_time();
-                                    } else if let Some((
-                                        ref mut min_low,
-                                        ref mut hr_low,
-                                        ref mut min_high,
-                                        ref mut hr_high,
-                                    )) = preferences.watering
-                                    {
-                                        match index {
-                                            0 => *hr_low = inclusive_iterator(*hr_low, 0, 23, true),
-                                            1 => {
-                                                *min_low = inclusive_iterator(*min_low, 0, 59, true)
-                                            }
-                                            2 => {
-                                                *hr_high = inclusive_iterator(*hr_high, 0, 23, true)
-                                            }
-                                            3 => {
-                                                *min_high =
-                                                    inclusive_iterator(*min_high, 0, 59, true)
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                    refresh = true;
-                                } else if down_button.is_high().unwrap() {
-                                    if preferences.watering.is_none() {
-                                        preferences.set_default_watering_time();
-                                    } else if let Some((
-                                        ref mut min_low,
-                                        ref mut hr_low,
-                                        ref mut min_high,
-                                        ref mut hr_high,
-                                    )) = preferences.watering
-                                    {
-                                        match index {
-                                            0 => {
-                                                *hr_low = inclusive_iterator(*hr_low, 0, 23, false)
-                                            }
-                                            1 => {
-                                                *min_low =
-                                                    inclusive_iterator(*min_low, 0, 59, false)
-                                            }
-                                            2 => {
-                                                *hr_high =
-                                                    inclusive_iterator(*hr_high, 0, 23, false)
-                                            }
-                                            3 => {
-                                                *min_high =
-                                                    inclusive_iterator(*min_high, 0, 59, false)
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                    refresh = true;
-                                } else if select_button.is_high().unwrap() {
-                                    remove = preferences.watering.is_none();
-                                    refresh = true;
-                                    break;
-                                }
-                            }
-                            if remove {
-                                break;
-                            }
-                        }
-                        // Check legality
-                        if remove {
-                            preferences.watering = None;
-                        } else if (preferences.watering.unwrap().1 > preferences.watering.unwrap().3) || // Hours are incorrect
-                                    (preferences.watering.unwrap().1 == preferences.watering.unwrap().3 && // Minutes are incorrect assuming hours are equal
-                                        preferences.watering.unwrap().0 > preferences.watering.unwrap().2)
-                        {
-                            preferences.watering = Some((
-                                preferences.watering.unwrap().2,
-                                preferences.watering.unwrap().3,
-                                preferences.watering.unwrap().0,
-                                preferences.watering.unwrap().1,
-                            ));
+                    Err(_) => {
+                        defmt::warn!("Sensor failed to start a measurement");
+                        if handle_sensor_failure(
+                            &mut sensor_source,
+                            &mut sensor_failures,
+                            preferences.recovery_threshold,
+                            &mut delay,
+                            &mut buzzer_request,
+                            &mut on_sensor_fault,
+                        ) {
+                            defmt::info!("Recovered the I2C bus and sensors");
                         }
                     }
-                    _ => {
-                        // Pressure has no configuration
-                    }
                 }
+                continue;
             }
-            RefreshAction::Sensor => {
-                if smoke_detector.is_high().unwrap() {
-                    // Panic!!!
-                    let roof_open = &roof_vent.is_set_high().unwrap();
-                    render_screen(FIRE, true, &mut lcd, &mut delay);
-                    while smoke_detector.is_high().unwrap() {
-                        // Enable sprinklers
-                        sprinklers.set_high().unwrap();
-                        // Ensure windows are closed
-                        roof_vent.set_low().unwrap();
-                        // Sound alarm
-                        buzzer.set_high().unwrap();
-                        delay.delay_ms(1000);
-                        // Still keep track of time though
-                        preferences.tick_time();
+            RefreshAction::Measured => {
+                sensor_source.set_combine(preferences.sensor_combine);
+                // A failed read keeps the last reading, which shows as stale until the sensors recover
+                let reading = match sensor_source.read() {
+                    Ok(reading) => {
+                        sensor_failures.record(true, preferences.recovery_threshold);
+                        reading
                     }
-                    // Safe; Disable sprinklers and open vent if it was open before
-                    buzzer.set_low().unwrap();
-                    sprinklers.set_low().unwrap();
-                    if *roof_open {
-                        roof_vent.set_high().unwrap();
+                    Err(_) => {
+                        defmt::warn!("Sensor read failed");
+                        if handle_sensor_failure(
+                            &mut sensor_source,
+                            &mut sensor_failures,
+                            preferences.recovery_threshold,
+                            &mut delay,
+                            &mut buzzer_request,
+                            &mut on_sensor_fault,
+                        ) {
+                            defmt::info!("Recovered the I2C bus and sensors");
+                        }
+                        continue;
                     }
+                };
+                data = reading.with_offsets(
+                    preferences.temp_offset,
+                    preferences.humidity_offset,
+                    preferences.pressure_offset,
+                );
+                last_read = preferences.date;
+                // Log a sensor dropping out while the other keeps working
+                if sensor_source.degraded() && !degraded_logged {
+                    event_store
+                        .append(&events.log_event(&preferences.date, EventKind::SensorFault));
                 }
-
-                data = get_bme_data(&mut bme, &mut delay, &mut buzzer);
-
-                // Check if temperature is valid
-                let temp = get_temperature(&data);
-                if temp > preferences.temperature.1 {
-                    // open vent
-                    roof_vent.set_high().unwrap();
-                } else {
-                    roof_vent.set_low().unwrap();
+                degraded_logged = sensor_source.degraded();
+                // Readings are shown while the sensor warms up, but nothing acts on them
+                warming_up =
+                    !is_warmed_up(delay.get_counter().ticks() / 1000, preferences.warmup_secs);
+                let reading = (get_temperature(&data), get_humidity(&data));
+                mildew_risk = has_mildew_risk(reading.0, reading.1, &preferences);
+                shared::request(|requests| requests.reading = (!warming_up).then_some(reading));
+                if !warming_up
+                    && stats.update(
+                        &preferences.date,
+                        get_temperature(&data),
+                        get_humidity(&data),
+                        preferences.gdd_base,
+                    )
+                {
+                    stats_store.save(&stats);
+                }
+                pressure_history.update(&preferences.date, data.pressure);
+                data_log.append_if_due(
+                    &Record::new(&preferences.date, &data),
+                    preferences.log_interval,
+                );
+                if preferences.gas_enabled && preferences.gas_baseline == 0 && !warming_up {
+                    if let Some(baseline) = gas_baseline.update(data.gas_resistance, SENSOR_DELAY) {
+                        preferences.gas_baseline = baseline;
+                        pending_save.mark();
+                    }
                 }
 
-                // Check if humidity is valid
-                let humidity = get_humidity(&data);
-                if humidity < preferences.humidity.0 || humidity > preferences.humidity.1 {
-                    // enable sprinklers
-                    sprinklers.set_high().unwrap();
-                } else {
-                    sprinklers.set_low().unwrap();
+                // Check if the supply voltage is too low
+                if supply_mv < preferences.low_voltage {
+                    // Chirp the alarm
+                    buzzer_request.set_high().unwrap();
+                    delay.delay_ms(100);
+                    buzzer_request.set_low().unwrap();
+                    if !low_voltage_logged {
+                        event_store
+                            .append(&events.log_event(&preferences.date, EventKind::PowerWarning));
+                    }
                 }
+                low_voltage_logged = supply_mv < preferences.low_voltage;
 
-                // Check if it is watering time
-                if preferences.is_watering_time() {
-                    sprinklers.set_high().unwrap();
-                } else {
-                    sprinklers.set_low().unwrap();
+                // The clock is drawn on its own second, not whenever a reading arrives
+                if !render_times.should_render(current_screen, &preferences.date, true) {
+                    continue;
                 }
             }
-            _ => {
-                // Nothing is needed to do, so just continue
-                continue;
+            RefreshAction::None => {
+                // Only the clock needs drawing while nothing happens, and only once the second changes
+                if !render_times.should_render(current_screen, &preferences.date, false) {
+                    continue;
+                }
             }
         }
 
-        let mut data_str: String<12> = String::new();
-        match current_screen_index {
-            0 => {
-                // Temp
-                uwrite!(&mut data_str, "Temp: {}F", get_temperature(&data)).unwrap();
-                render_screen(&data_str, true, &mut lcd, &mut delay);
-                data_str.clear();
-                uwrite!(
-                    &mut data_str,
-                    "({}, {})",
-                    preferences.temperature.0,
-                    preferences.temperature.1
-                )
-                .unwrap();
-                render_screen(&data_str, false, &mut lcd, &mut delay);
-            }
-            1 => {
-                // Humidity
-                uwrite!(&mut data_str, "RH: {}%", get_humidity(&data)).unwrap();
-                render_screen(&data_str, true, &mut lcd, &mut delay);
-                data_str.clear();
-                uwrite!(
-                    &mut data_str,
-                    "({}%, {}%)",
-                    preferences.humidity.0,
-                    preferences.humidity.1
-                )
-                .unwrap();
-                render_screen(&data_str, false, &mut lcd, &mut delay);
-            }
-            2 => {
-                // Pressure
-                uwrite!(&mut data_str, "PRS: {} mb", get_pressure(&data)).unwrap();
-                render_screen(&data_str, true, &mut lcd, &mut delay);
-            }
-            3 => {
-                // Date
-                let (time, date) = preferences.get_date_formatted();
-                render_screen(&time, true, &mut lcd, &mut delay);
-                render_screen(&date, false, &mut lcd, &mut delay);
-            }
-            _ => {
-                // Water Schedule
-                render_screen(
-                    &preferences.format_watering_time(),
-                    true,
-                    &mut lcd,
-                    &mut delay,
-                );
-            }
+        current_screen.handler().render(&mut Context {
+            lcd: &mut lcd,
+            delay: &mut delay,
+            up_button: &mut up_button,
+            down_button: &mut down_button,
+            select_button: &mut select_button,
+            preferences: &mut preferences,
+            data: &data,
+            pressure_history: &pressure_history,
+            sensors: sensor_source.readings(),
+            sensor_addresses,
+            stats: &stats,
+            events: &events,
+            display: &mut display,
+            supply_mv,
+            co2: fresh_co2,
+        });
+        render_times.rendered(current_screen, &preferences.date);
+
+        // Show the most urgent warning on every screen
+        if let Some(warning) = bottom_warning(
+            supply_mv,
+            &preferences,
+            sensor_source.degraded(),
+            sensor_fault,
+            status,
+            warming_up,
+            mildew_risk,
+        ) {
+            render_screen(warning, false, &mut lcd, &mut delay);
+        } else if current_screen.shows_age() {
+            render_reading_age(&preferences, &last_read, &mut lcd, &mut delay);
         }
     }
 }
 
+/// Picks the warning shown on the bottom line of every screen, most urgent first
+///
+/// - param supply_mv: The supply voltage in millivolts
+/// - param preferences: [Preferences] instance
+/// - param degraded: If one of the sensors has stopped responding
+/// - param sensor_fault: If the sensors have failed, so the [FaultPolicy](gem_rs::preferences::FaultPolicy) is being applied
+/// - param status: The latest [Status] from core1
+/// - param warming_up: If the readings aren't acted on yet
+/// - param mildew_risk: If the latest reading is humid and close to its dew point
+///
+/// returns the warning to show, or None if all is well
+fn bottom_warning(
+    supply_mv: u16,
+    preferences: &Preferences,
+    degraded: bool,
+    sensor_fault: bool,
+    status: Status,
+    warming_up: bool,
+    mildew_risk: bool,
+) -> Option<&'static str> {
+    // A test is labelled as one, so it isn't mistaken for a real fire
+    if status.alarm_test {
+        Some(LABELS.test_alarm)
+    } else if status.fire {
+        Some(LABELS.fire)
+    } else if sensor_fault {
+        Some(preferences.fault_policy.warning())
+    } else if status.temperature_alarm {
+        Some(LABELS.temperature_alarm)
+    } else if status.escalated {
+        Some(LABELS.escalated)
+    } else if supply_mv < preferences.low_voltage {
+        Some(LABELS.low_voltage)
+    } else if degraded {
+        Some(LABELS.sensor_degraded)
+    } else if status.rain {
+        Some(LABELS.rain)
+    } else if warming_up {
+        Some(LABELS.warming_up)
+    } else if mildew_risk {
+        Some(LABELS.mildew)
+    } else if is_watering_held(preferences) {
+        Some(LABELS.watering_held)
+    } else {
+        None
+    }
+}
+
+/// Renders the age of the last reading, flashing it every other second once it is stale
+///
+/// - param preferences: [Preferences] instance
+/// - param last_read: When the last reading was taken
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+fn render_reading_age(
+    preferences: &Preferences,
+    last_read: &DateTime,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+) {
+    let age = preferences.date.seconds_since(last_read);
+    let stale = is_stale(age, SENSOR_DELAY, preferences.stale_factor);
+    render_age(age, !stale || preferences.date.0 % 2 == 0, lcd, delay);
+}
+
+/// Runs the safety monitoring on core1.
+/// It polls the smoke detector every [SAFETY_POLL_MS], confirming it with a [SmokeFilter], and drives every actuator from the
+/// [Requests](gem_rs::shared::Requests) of core0, reporting back the [Status]
+///
+/// - param delay: [Timer] instance
+/// - param smoke_detector: Smoke detector Pin
+/// - param rain_sensor: Rain sensor Pin
+/// - param sprinklers: Sprinkler Pin
+/// - param roof_vent: Roof vent Pin
+/// - param mister: Mister Pin
+/// - param heater_low: First heater stage Pin
+/// - param heater_high: Second heater stage Pin
+/// - param buzzer: [Buzzer] instance
+#[allow(clippy::too_many_arguments)]
+fn core1_task(
+    mut delay: Timer,
+    mut smoke_detector: impl InputPin,
+    mut rain_sensor: impl InputPin,
+    mut sprinklers: impl OutputPin,
+    mut roof_vent: impl OutputPin,
+    mut mister: impl OutputPin,
+    mut heater_low: impl OutputPin,
+    mut heater_high: impl OutputPin,
+    mut buzzer: Buzzer,
+) -> ! {
+    mark_core1_running();
+    let mut monitor = SafetyMonitor::default();
+    let mut smoke_filter = SmokeFilter::default();
+    let mut stopwatch = Stopwatch::new(delay.get_counter().ticks());
+    loop {
+        // Stay off flash while core0 writes it
+        park_if_requested();
+        let elapsed_ms = stopwatch.lap_ms(delay.get_counter().ticks());
+        if let Some(requests) = shared::requests() {
+            let smoke = smoke_filter.step(
+                smoke_detector.is_high().unwrap(),
+                requests.preferences.smoke_samples,
+            );
+            let rain = rain_sensor.is_high().unwrap();
+            let (outputs, status) = monitor.step(&requests, smoke, rain, elapsed_ms);
+            let levels = &requests.preferences.active_levels;
+            drive(&mut sprinklers, outputs.sprinklers, levels.sprinklers);
+            drive(&mut roof_vent, outputs.roof_vent, levels.roof_vent);
+            drive(&mut mister, outputs.mister, levels.mister);
+            drive(
+                &mut heater_low,
+                outputs.get(Output::HeaterLow),
+                levels.heater_low,
+            );
+            drive(
+                &mut heater_high,
+                outputs.get(Output::HeaterHigh),
+                levels.heater_high,
+            );
+            let preferences = &requests.preferences;
+            drive_expander(
+                &preferences.expander_pins,
+                &outputs,
+                preferences.expander_active_high,
+            );
+            buzzer.set_active_high(levels.buzzer);
+            buzzer.play(
+                outputs.buzzer.then_some(outputs.tone),
+                preferences.passive_buzzer,
+                preferences.buzzer_volume,
+            );
+            shared::report(status);
+        }
+        delay.delay_ms(SAFETY_POLL_MS as u32);
+    }
+}
+
 /// What type of update is required when refreshing the screen
 ///
 /// - **Up**: The Up button was pressed
 /// - **Down**: The Down button was pressed
 /// - **Select**: The Select button was pressed
+/// - **AlarmTest**: The Up and Down buttons were pressed together to start an alarm test
 /// - **Sensor**: The sensors need to be refreshed
+/// - **Measured**: The sensor measurement is ready to be read
+/// - **Rotate**: The dashboard should advance to the next screen
+/// - **Redraw**: The screen should be drawn again, as its warning changed
 /// - **None**: Ignore the refresh
 enum RefreshAction {
     Up,
     Down,
     Select,
+    AlarmTest,
     Sensor,
+    Measured,
+    Rotate,
+    Redraw,
     None,
 }
 
@@ -674,8 +1006,11 @@ enum RefreshAction {
 /// - param button_cd: button countdown
 /// - param sensor_cd: sensor countdown
 /// - param time_cd: uptime countdown
+/// - param rotate: [AutoRotate] instance
+/// - param elapsed_ms: milliseconds since the last call, measured by the hardware timer
 ///
 /// returns: if the LCD needs an update
+#[allow(clippy::too_many_arguments)]
 fn should_update(
     up: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
     down: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
@@ -684,27 +1019,39 @@ fn should_update(
     button_cd: &mut CountDownTimer,
     sensor_cd: &mut CountDownTimer,
     time_cd: &mut CountDownTimer,
+    rotate: &mut AutoRotate,
+    elapsed_ms: u16,
 ) -> RefreshAction {
     // Tick
-    time_cd.tick();
+    let overshoot = time_cd.tick_by(elapsed_ms);
     if time_cd.is_finished() {
         preferences.tick_time();
-        time_cd.set_time(TICK_TIME_DELAY);
+        rotate.tick(preferences.auto_rotate, preferences.rotate_interval);
+        // Carry the overshoot into the next second so the clock doesn't drift.
+        // Whole seconds are dropped, as screens that block for that long tick the clock themselves
+        time_cd.set_time(TICK_TIME_DELAY - overshoot % TICK_TIME_DELAY);
     }
 
-    button_cd.tick();
-    sensor_cd.tick();
+    button_cd.tick_by(elapsed_ms);
+    sensor_cd.tick_by(elapsed_ms);
 
     // Only tick buttons if they aren't on delay
     if button_cd.is_finished() {
-        if up.is_high().unwrap() {
+        if up.is_high().unwrap() && down.is_high().unwrap() {
+            button_cd.set_time(SCREEN_BUTTON_DELAY);
+            rotate.press();
+            return RefreshAction::AlarmTest;
+        } else if up.is_high().unwrap() {
             button_cd.set_time(SCREEN_BUTTON_DELAY);
+            rotate.press();
             return RefreshAction::Up;
         } else if down.is_high().unwrap() {
             button_cd.set_time(SCREEN_BUTTON_DELAY);
+            rotate.press();
             return RefreshAction::Down;
         } else if select.is_high().unwrap() {
             button_cd.set_time(SCREEN_BUTTON_DELAY);
+            rotate.press();
             return RefreshAction::Select;
         }
     }
@@ -715,16 +1062,11 @@ fn should_update(
         return RefreshAction::Sensor;
     }
 
+    // Rotations that land on a sensor refresh wait for the next call
+    if rotate.take_due() {
+        return RefreshAction::Rotate;
+    }
+
     // If there is nothing to tick, then return None
     RefreshAction::None
 }
-
-/// Iterates forwards or backwards through Screens
-///
-/// - param current_screen_index: The current screen being displayed
-/// - param next: Whether to iterate forward; If false, iterate backwards
-///
-/// returns: The next Screen
-fn next_screen(current_screen_index: u8, next: bool) -> u8 {
-    (current_screen_index + if next { 1 } else { 4 }) % 5
-}
@@ -0,0 +1,1286 @@
+use crate::expander::{pin_code, pin_from_code, EXPANDER_PINS};
+use crate::flash::{is_erased, Storage, SECTOR_SIZE};
+use crate::preferences::{
+    ActiveLevels, Co2Policy, DateFormat, DateTime, FaultPolicy, Preferences, PressureUnit,
+    SensorCombine, TemperatureModel, TemperatureUnit, TimeOfDay, UnitSystem,
+};
+use crate::screens::Screen;
+use heapless::String;
+
+/// The version of the [Preferences] byte layout; bumped whenever the layout changes
+pub const PREFERENCES_VERSION: u8 = 35;
+/// The amount of bytes [Preferences] are encoded into
+pub const PREFERENCES_LEN: usize = 114;
+/// Where the preferences region starts, counted from the start of flash.
+/// It sits right after the data log, near the end of the 2MB flash
+pub const PREFERENCES_FLASH_OFFSET: u32 = 0x1F_8000;
+/// The amount of sectors preference writes rotate through
+pub const PREFERENCES_SECTORS: u32 = 4;
+/// The size of the preferences region in bytes
+pub const PREFERENCES_REGION_LEN: u32 = PREFERENCES_SECTORS * SECTOR_SIZE;
+/// The size of a saved preferences slot in bytes; leaves room for the layout to grow
+pub const SLOT_LEN: usize = 128;
+/// The amount of slots that fit in a sector
+pub const SLOTS_PER_SECTOR: u32 = SECTOR_SIZE / SLOT_LEN as u32;
+/// The amount of slots writes rotate through
+pub const SLOTS: u32 = PREFERENCES_SECTORS * SLOTS_PER_SECTOR;
+
+// A slot holds a sequence number, the preferences and a CRC
+const _: () = assert!(4 + PREFERENCES_LEN + 4 <= SLOT_LEN);
+
+/// Why a byte slice could not be decoded into [Preferences]
+///
+/// - **WrongLength**: The slice is not [PREFERENCES_LEN] bytes long
+/// - **UnknownVersion**: The slice was written by an unsupported layout version
+/// - **InvalidValue**: A field holds a value that cannot be represented
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    WrongLength,
+    UnknownVersion,
+    InvalidValue,
+}
+
+impl Preferences {
+    /// Encodes the preferences into the byte layout stored in flash.
+    /// Multibyte values are little-endian.
+    ///
+    /// | Offset | Field                        |
+    /// |--------|------------------------------|
+    /// | 0      | layout version               |
+    /// | 1..3   | temperature                  |
+    /// | 3..5   | humidity                     |
+    /// | 5..12  | date (sec .. year)           |
+    /// | 12     | 1 if a watering time is set  |
+    /// | 13..17 | watering                     |
+    /// | 17     | watering_days                |
+    /// | 18..20 | low_voltage                  |
+    /// | 20..22 | critical_voltage             |
+    /// | 22     | safe_vent_open               |
+    /// | 23     | pressure_unit                |
+    /// | 24..26 | sea_level_pressure           |
+    /// | 26     | pressure_trend_threshold     |
+    /// | 27     | temp_offset                  |
+    /// | 28     | humidity_offset              |
+    /// | 29..31 | pressure_offset              |
+    /// | 31..35 | gas_baseline                 |
+    /// | 35     | log_interval                 |
+    /// | 36     | humidity_hysteresis          |
+    /// | 37     | misting_enabled              |
+    /// | 38     | watering_enabled             |
+    /// | 39     | daytime_only                 |
+    /// | 40..44 | daytime_window               |
+    /// | 44     | auto_rotate                  |
+    /// | 45     | rotate_interval              |
+    /// | 46     | date_format                  |
+    /// | 47     | contrast                     |
+    /// | 48     | brightness                   |
+    /// | 49     | sensor_combine               |
+    /// | 50..52 | hidden_screens, low half     |
+    /// | 52     | mist_pulse                   |
+    /// | 53     | mist_dwell                   |
+    /// | 54     | stale_factor                 |
+    /// | 55..57 | temperature_alarm            |
+    /// | 57     | warmup_secs                  |
+    /// | 58     | rain_closes_vent             |
+    /// | 59..61 | night_temperature            |
+    /// | 61     | temperature_hysteresis       |
+    /// | 62     | day_start_hour               |
+    /// | 63     | night_start_hour             |
+    /// | 64     | unit_system                  |
+    /// | 65     | temperature_unit             |
+    /// | 66     | active_levels                |
+    /// | 67     | gas_enabled                  |
+    /// | 68..70 | gas_heater_temp              |
+    /// | 70..72 | gas_heater_ms                |
+    /// | 72     | self_test                    |
+    /// | 73     | escalation_mins              |
+    /// | 74     | recovery_threshold           |
+    /// | 75     | smoke_samples                |
+    /// | 76..84 | vent_curve                   |
+    /// | 84     | default_screen ([Screen::code]) |
+    /// | 85     | mildew_spread                |
+    /// | 86     | mildew_vent                  |
+    /// | 87     | passive_buzzer               |
+    /// | 88     | buzzer_volume                |
+    /// | 89..91 | sensor_timeout_ms            |
+    /// | 91     | dehumidify_vent              |
+    /// | 92     | fault_policy                 |
+    /// | 93     | 1 if watering seconds set    |
+    /// | 94..96 | watering_seconds             |
+    /// | 96     | temperature_model            |
+    /// | 97..105| expander_pins ([pin_code])   |
+    /// | 105    | expander_address             |
+    /// | 106    | expander_active_high         |
+    /// | 107    | heat_full_below              |
+    /// | 108..110| hidden_screens, high half    |
+    /// | 110    | co2_policy                   |
+    /// | 111..113| co2_vent_ppm                 |
+    /// | 113    | gdd_base                     |
+    ///
+    /// returns the encoded bytes
+    pub fn to_bytes(&self) -> [u8; PREFERENCES_LEN] {
+        let mut bytes = [0u8; PREFERENCES_LEN];
+        let watering = self.watering.unwrap_or((0, 0, 0, 0));
+        let watering_seconds = self.watering_seconds.unwrap_or((0, 0));
+        bytes[0] = PREFERENCES_VERSION;
+        bytes[1] = self.temperature.0;
+        bytes[2] = self.temperature.1;
+        bytes[3] = self.humidity.0;
+        bytes[4] = self.humidity.1;
+        bytes[5] = self.date.0;
+        bytes[6] = self.date.1;
+        bytes[7] = self.date.2;
+        bytes[8] = self.date.3;
+        bytes[9] = self.date.4;
+        bytes[10..12].copy_from_slice(&self.date.5.to_le_bytes());
+        bytes[12] = self.watering.is_some() as u8;
+        bytes[13] = watering.0;
+        bytes[14] = watering.1;
+        bytes[15] = watering.2;
+        bytes[16] = watering.3;
+        bytes[17] = self.watering_days;
+        bytes[18..20].copy_from_slice(&self.low_voltage.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.critical_voltage.to_le_bytes());
+        bytes[22] = self.safe_vent_open as u8;
+        bytes[23] = self.pressure_unit as u8;
+        bytes[24..26].copy_from_slice(&self.sea_level_pressure.to_le_bytes());
+        bytes[26] = self.pressure_trend_threshold;
+        bytes[27] = self.temp_offset as u8;
+        bytes[28] = self.humidity_offset as u8;
+        bytes[29..31].copy_from_slice(&self.pressure_offset.to_le_bytes());
+        bytes[31..35].copy_from_slice(&self.gas_baseline.to_le_bytes());
+        bytes[35] = self.log_interval;
+        bytes[36] = self.humidity_hysteresis;
+        bytes[37] = self.misting_enabled as u8;
+        bytes[38] = self.watering_enabled as u8;
+        bytes[39] = self.daytime_only as u8;
+        bytes[40] = self.daytime_window.0 .0;
+        bytes[41] = self.daytime_window.0 .1;
+        bytes[42] = self.daytime_window.1 .0;
+        bytes[43] = self.daytime_window.1 .1;
+        bytes[44] = self.auto_rotate as u8;
+        bytes[45] = self.rotate_interval;
+        bytes[46] = self.date_format as u8;
+        bytes[47] = self.contrast;
+        bytes[48] = self.brightness;
+        bytes[49] = self.sensor_combine as u8;
+        let hidden_screens = self.hidden_screens.to_le_bytes();
+        bytes[50..52].copy_from_slice(&hidden_screens[..2]);
+        bytes[52] = self.mist_pulse;
+        bytes[53] = self.mist_dwell;
+        bytes[54] = self.stale_factor;
+        bytes[55] = self.temperature_alarm.0;
+        bytes[56] = self.temperature_alarm.1;
+        bytes[57] = self.warmup_secs;
+        bytes[58] = self.rain_closes_vent as u8;
+        bytes[59] = self.night_temperature.0;
+        bytes[60] = self.night_temperature.1;
+        bytes[61] = self.temperature_hysteresis;
+        bytes[62] = self.day_start_hour;
+        bytes[63] = self.night_start_hour;
+        bytes[64] = self.unit_system as u8;
+        bytes[65] = self.temperature_unit as u8;
+        bytes[66] = self.active_levels.active_low_bits();
+        bytes[67] = self.gas_enabled as u8;
+        bytes[68..70].copy_from_slice(&self.gas_heater_temp.to_le_bytes());
+        bytes[70..72].copy_from_slice(&self.gas_heater_ms.to_le_bytes());
+        bytes[72] = self.self_test as u8;
+        bytes[73] = self.escalation_mins;
+        bytes[74] = self.recovery_threshold;
+        bytes[75] = self.smoke_samples;
+        for (index, (delta, percent)) in self.vent_curve.iter().enumerate() {
+            bytes[76 + index * 2] = *delta;
+            bytes[77 + index * 2] = *percent;
+        }
+        bytes[84] = self.default_screen.code();
+        bytes[85] = self.mildew_spread;
+        bytes[86] = self.mildew_vent as u8;
+        bytes[87] = self.passive_buzzer as u8;
+        bytes[88] = self.buzzer_volume;
+        bytes[89..91].copy_from_slice(&self.sensor_timeout_ms.to_le_bytes());
+        bytes[91] = self.dehumidify_vent as u8;
+        bytes[92] = self.fault_policy as u8;
+        bytes[93] = self.watering_seconds.is_some() as u8;
+        bytes[94] = watering_seconds.0;
+        bytes[95] = watering_seconds.1;
+        bytes[96] = self.temperature_model as u8;
+        for (byte, output) in bytes[97..105].iter_mut().zip(self.expander_pins) {
+            *byte = pin_code(output);
+        }
+        bytes[105] = self.expander_address;
+        bytes[106] = self.expander_active_high as u8;
+        bytes[107] = self.heat_full_below;
+        bytes[108..110].copy_from_slice(&hidden_screens[2..]);
+        bytes[110] = self.co2_policy as u8;
+        bytes[111..113].copy_from_slice(&self.co2_vent_ppm.to_le_bytes());
+        bytes[113] = self.gdd_base;
+        bytes
+    }
+
+    /// Decodes preferences from the byte layout written by [Preferences::to_bytes]
+    ///
+    /// - param bytes: the encoded preferences
+    ///
+    /// returns the decoded [Preferences] or a [DecodeError]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::persist::DecodeError;
+    /// use gem_rs::preferences::{DateTime, Preferences, PressureUnit};
+    /// use gem_rs::screens::Screen;
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.temperature = (55, 85);
+    /// preferences.date = DateTime(12, 34, 5, 29, 2, 2024);
+    /// preferences.watering = Some((30, 6, 0, 7));
+    /// preferences.pressure_unit = PressureUnit::InHg;
+    /// preferences.temp_offset = -3;
+    /// preferences.pressure_offset = -12;
+    /// preferences.gas_baseline = 120_000;
+    ///
+    /// let bytes = preferences.to_bytes();
+    /// assert!(Preferences::from_bytes(&bytes).unwrap() == preferences);
+    ///
+    /// assert_eq!(Preferences::from_bytes(&bytes[1..]).err(), Some(DecodeError::WrongLength));
+    /// let mut future = bytes;
+    /// future[0] = 0xFF;
+    /// assert_eq!(Preferences::from_bytes(&future).err(), Some(DecodeError::UnknownVersion));
+    /// let mut corrupt = bytes;
+    /// corrupt[23] = 7; // No such pressure unit
+    /// assert_eq!(Preferences::from_bytes(&corrupt).err(), Some(DecodeError::InvalidValue));
+    ///
+    /// // The default screen is saved as its code, so it doesn't move with the enabled screens
+    /// let mut other_build = Preferences { default_screen: Screen::Gdd, ..Preferences::default() }.to_bytes();
+    /// assert_eq!(other_build[84], Screen::Gdd.code());
+    /// assert_eq!(Preferences::from_bytes(&other_build).unwrap().default_screen, Screen::Gdd);
+    /// other_build[84] = 30; // A screen this build doesn't have
+    /// assert_eq!(Preferences::from_bytes(&other_build).unwrap().default_screen, Screen::Temperature);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Preferences, DecodeError> {
+        if bytes.len() != PREFERENCES_LEN {
+            return Err(DecodeError::WrongLength);
+        }
+        if bytes[0] != PREFERENCES_VERSION {
+            return Err(DecodeError::UnknownVersion);
+        }
+        let watering = match bytes[12] {
+            0 => None,
+            1 => Some((bytes[13], bytes[14], bytes[15], bytes[16])),
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let watering_seconds = match bytes[93] {
+            0 => None,
+            1 => Some((bytes[94], bytes[95])),
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let safe_vent_open = match bytes[22] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let rain_closes_vent = match bytes[58] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let gas_enabled = match bytes[67] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let self_test = match bytes[72] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let mildew_vent = match bytes[86] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let passive_buzzer = match bytes[87] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let dehumidify_vent = match bytes[91] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let fault_policy = match bytes[92] {
+            0 => FaultPolicy::Hold,
+            1 => FaultPolicy::SafeState,
+            2 => FaultPolicy::LastGood,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let co2_policy = match bytes[110] {
+            0 => Co2Policy::Off,
+            1 => Co2Policy::Vent,
+            2 => Co2Policy::Enrich,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let temperature_model = match bytes[96] {
+            0 => TemperatureModel::Range,
+            1 => TemperatureModel::Setpoint,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let mut expander_pins = [None; EXPANDER_PINS];
+        for (output, byte) in expander_pins.iter_mut().zip(&bytes[97..105]) {
+            *output = pin_from_code(*byte).ok_or(DecodeError::InvalidValue)?;
+        }
+        let expander_active_high = match bytes[106] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let misting_enabled = match bytes[37] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let watering_enabled = match bytes[38] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let daytime_only = match bytes[39] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let auto_rotate = match bytes[44] {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let date_format = match bytes[46] {
+            0 => DateFormat::Numeric,
+            1 => DateFormat::Named,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let sensor_combine = match bytes[49] {
+            0 => SensorCombine::Average,
+            1 => SensorCombine::Lowest,
+            2 => SensorCombine::Highest,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let pressure_unit = match bytes[23] {
+            0 => PressureUnit::Hpa,
+            1 => PressureUnit::InHg,
+            2 => PressureUnit::MmHg,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let unit_system = match bytes[64] {
+            0 => UnitSystem::Imperial,
+            1 => UnitSystem::Metric,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let temperature_unit = match bytes[65] {
+            0 => TemperatureUnit::Fahrenheit,
+            1 => TemperatureUnit::Celsius,
+            2 => TemperatureUnit::Kelvin,
+            _ => return Err(DecodeError::InvalidValue),
+        };
+        let active_levels =
+            ActiveLevels::from_active_low_bits(bytes[66]).ok_or(DecodeError::InvalidValue)?;
+        Ok(Preferences {
+            temperature: (bytes[1], bytes[2]),
+            night_temperature: (bytes[59], bytes[60]),
+            temperature_hysteresis: bytes[61],
+            heat_full_below: bytes[107],
+            day_start_hour: bytes[62],
+            night_start_hour: bytes[63],
+            temperature_alarm: (bytes[55], bytes[56]),
+            humidity: (bytes[3], bytes[4]),
+            humidity_hysteresis: bytes[36],
+            mist_pulse: bytes[52],
+            mist_dwell: bytes[53],
+            misting_enabled,
+            watering_enabled,
+            daytime_only,
+            daytime_window: (
+                TimeOfDay(bytes[40], bytes[41]),
+                TimeOfDay(bytes[42], bytes[43]),
+            ),
+            date: DateTime(
+                bytes[5],
+                bytes[6],
+                bytes[7],
+                bytes[8],
+                bytes[9],
+                u16::from_le_bytes([bytes[10], bytes[11]]),
+            ),
+            watering,
+            watering_days: bytes[17],
+            watering_seconds,
+            low_voltage: u16::from_le_bytes([bytes[18], bytes[19]]),
+            critical_voltage: u16::from_le_bytes([bytes[20], bytes[21]]),
+            safe_vent_open,
+            pressure_unit,
+            sea_level_pressure: u16::from_le_bytes([bytes[24], bytes[25]]),
+            pressure_trend_threshold: bytes[26],
+            temp_offset: bytes[27] as i8,
+            humidity_offset: bytes[28] as i8,
+            pressure_offset: i16::from_le_bytes([bytes[29], bytes[30]]),
+            gas_baseline: u32::from_le_bytes([bytes[31], bytes[32], bytes[33], bytes[34]]),
+            log_interval: bytes[35],
+            auto_rotate,
+            rotate_interval: bytes[45],
+            date_format,
+            contrast: bytes[47],
+            brightness: bytes[48],
+            sensor_combine,
+            hidden_screens: u32::from_le_bytes([bytes[50], bytes[51], bytes[108], bytes[109]]),
+            stale_factor: bytes[54],
+            warmup_secs: bytes[57],
+            rain_closes_vent,
+            unit_system,
+            temperature_unit,
+            active_levels,
+            gas_enabled,
+            gas_heater_temp: u16::from_le_bytes([bytes[68], bytes[69]]),
+            gas_heater_ms: u16::from_le_bytes([bytes[70], bytes[71]]),
+            self_test,
+            escalation_mins: bytes[73],
+            recovery_threshold: bytes[74],
+            smoke_samples: bytes[75],
+            vent_curve: core::array::from_fn(|index| {
+                (bytes[76 + index * 2], bytes[77 + index * 2])
+            }),
+            // Screens this build doesn't have fall back to the first
+            default_screen: Screen::from_code(bytes[84]).unwrap_or(Screen::Temperature),
+            mildew_spread: bytes[85],
+            mildew_vent,
+            passive_buzzer,
+            buzzer_volume: bytes[88],
+            sensor_timeout_ms: u16::from_le_bytes([bytes[89], bytes[90]]),
+            dehumidify_vent,
+            fault_policy,
+            temperature_model,
+            expander_pins,
+            expander_address: bytes[105],
+            expander_active_high,
+            co2_policy,
+            co2_vent_ppm: u16::from_le_bytes([bytes[111], bytes[112]]),
+            gdd_base: bytes[113],
+        })
+    }
+}
+
+/// Calculates the CRC-32 (IEEE) of some bytes
+///
+/// - param bytes: the bytes to check
+///
+/// returns the CRC
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::persist::crc32;
+///
+/// assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+/// assert_eq!(crc32(b""), 0);
+/// ```
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Encodes a saved preferences slot.
+/// The slot holds the sequence number, the [Preferences::to_bytes] layout,
+/// then a CRC of both; the rest is left erased
+///
+/// - param sequence: the slot's write number, counting up with every save
+/// - param preferences: [Preferences] instance
+///
+/// returns the encoded slot
+pub fn encode_slot(sequence: u32, preferences: &Preferences) -> [u8; SLOT_LEN] {
+    let mut slot = [0xFF; SLOT_LEN];
+    slot[0..4].copy_from_slice(&sequence.to_le_bytes());
+    slot[4..4 + PREFERENCES_LEN].copy_from_slice(&preferences.to_bytes());
+    let crc = crc32(&slot[..4 + PREFERENCES_LEN]);
+    slot[4 + PREFERENCES_LEN..8 + PREFERENCES_LEN].copy_from_slice(&crc.to_le_bytes());
+    slot
+}
+
+/// Decodes a slot written by [encode_slot]
+///
+/// - param slot: the encoded slot
+///
+/// returns the sequence number and [Preferences],
+/// or None if the slot is erased, half-written or from another layout
+pub fn decode_slot(slot: &[u8; SLOT_LEN]) -> Option<(u32, Preferences)> {
+    let sequence = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]);
+    let crc = &slot[4 + PREFERENCES_LEN..8 + PREFERENCES_LEN];
+    if sequence == u32::MAX || crc32(&slot[..4 + PREFERENCES_LEN]).to_le_bytes() != crc {
+        return None;
+    }
+    let preferences = Preferences::from_bytes(&slot[4..4 + PREFERENCES_LEN]).ok()?;
+    Some((sequence, preferences))
+}
+
+/// The length of a config blob in hex digits: the [Preferences::to_bytes] layout and its CRC
+pub const CONFIG_BLOB_LEN: usize = (PREFERENCES_LEN + 4) * 2;
+
+/// Why a config blob could not be applied
+///
+/// - **WrongLength**: The blob is not [CONFIG_BLOB_LEN] hex digits long
+/// - **NotHex**: The blob has a character that isn't a hex digit
+/// - **BadChecksum**: The CRC doesn't match, so the blob was mistyped or cut short
+/// - **Decode**: The bytes could not be decoded. See [DecodeError]
+/// - **OutOfRange**: A value decodes, but is one the controller would have to fix. See [Preferences::validate_and_fix]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlobError {
+    WrongLength,
+    NotHex,
+    BadChecksum,
+    Decode(DecodeError),
+    OutOfRange,
+}
+
+/// Encodes the preferences as a config blob, so they can be copied to another controller over UART.
+/// The blob is the [Preferences::to_bytes] layout followed by its CRC-32, in uppercase hex
+///
+/// - param preferences: [Preferences] instance
+///
+/// returns the config blob
+pub fn encode_config_blob(preferences: &Preferences) -> String<CONFIG_BLOB_LEN> {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    let bytes = preferences.to_bytes();
+    let crc = crc32(&bytes).to_le_bytes();
+    let mut blob = String::new();
+    for byte in bytes.iter().chain(crc.iter()) {
+        blob.push(DIGITS[(byte >> 4) as usize] as char).unwrap();
+        blob.push(DIGITS[(byte & 0xF) as usize] as char).unwrap();
+    }
+    blob
+}
+
+/// Decodes and validates a config blob written by [encode_config_blob].
+/// Nothing is fixed up; a blob is either applied whole or rejected
+///
+/// - param blob: the hex digits, in either case
+///
+/// returns the decoded [Preferences] or a [BlobError]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::persist::{decode_config_blob, encode_config_blob, BlobError, DecodeError};
+/// use gem_rs::preferences::Preferences;
+///
+/// let mut preferences = Preferences::default();
+/// preferences.temperature = (50, 85);
+/// preferences.temp_offset = -2;
+/// preferences.watering = Some((0, 6, 30, 6));
+/// let blob = encode_config_blob(&preferences);
+/// assert!(decode_config_blob(&blob).unwrap() == preferences);
+/// assert!(decode_config_blob(&blob.to_ascii_lowercase()).unwrap() == preferences);
+///
+/// // Partial blobs
+/// assert_eq!(decode_config_blob("").err(), Some(BlobError::WrongLength));
+/// assert_eq!(decode_config_blob(&blob[..blob.len() - 2]).err(), Some(BlobError::WrongLength));
+///
+/// // Mistyped blobs
+/// let mut typo = blob.to_string();
+/// typo.replace_range(10..11, "G");
+/// assert_eq!(decode_config_blob(&typo).err(), Some(BlobError::NotHex));
+/// let flipped = if &blob[10..11] == "0" { "1" } else { "0" };
+/// let mut typo = blob.to_string();
+/// typo.replace_range(10..11, flipped);
+/// assert_eq!(decode_config_blob(&typo).err(), Some(BlobError::BadChecksum));
+///
+/// // Blobs with a good checksum but bad contents
+/// let reencode = |change: fn(&mut [u8])| {
+///     let mut bytes = Preferences::default().to_bytes().to_vec();
+///     change(&mut bytes);
+///     let crc = gem_rs::persist::crc32(&bytes);
+///     bytes.extend_from_slice(&crc.to_le_bytes());
+///     bytes.iter().map(|byte| format!("{byte:02X}")).collect::<String>()
+/// };
+/// assert_eq!(
+///     decode_config_blob(&reencode(|bytes| bytes[0] = 0xFF)).err(),
+///     Some(BlobError::Decode(DecodeError::UnknownVersion))
+/// );
+/// assert_eq!(
+///     decode_config_blob(&reencode(|bytes| bytes[23] = 7)).err(), // No such pressure unit
+///     Some(BlobError::Decode(DecodeError::InvalidValue))
+/// );
+/// assert_eq!(
+///     decode_config_blob(&reencode(|bytes| bytes[4] = 150)).err(), // 150% humidity
+///     Some(BlobError::OutOfRange)
+/// );
+/// ```
+pub fn decode_config_blob(blob: &str) -> Result<Preferences, BlobError> {
+    if blob.len() != CONFIG_BLOB_LEN {
+        return Err(BlobError::WrongLength);
+    }
+    let mut bytes = [0u8; PREFERENCES_LEN + 4];
+    for (byte, pair) in bytes.iter_mut().zip(blob.as_bytes().chunks_exact(2)) {
+        let digit = |c: u8| (c as char).to_digit(16).ok_or(BlobError::NotHex);
+        *byte = (digit(pair[0])? << 4 | digit(pair[1])?) as u8;
+    }
+    let (layout, crc) = bytes.split_at(PREFERENCES_LEN);
+    if crc32(layout).to_le_bytes() != crc {
+        return Err(BlobError::BadChecksum);
+    }
+    let preferences = Preferences::from_bytes(layout).map_err(BlobError::Decode)?;
+    let mut fixed = preferences.clone();
+    fixed.validate_and_fix();
+    if fixed != preferences {
+        return Err(BlobError::OutOfRange);
+    }
+    Ok(preferences)
+}
+
+/// Picks the newest valid slot; erased, half-written and corrupt slots are skipped
+///
+/// - param slots: every slot in the preferences region
+///
+/// returns the index, sequence number and [Preferences] of the newest valid slot
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::persist::{encode_slot, newest_valid, SLOT_LEN};
+/// use gem_rs::preferences::Preferences;
+///
+/// let with_temperature = |temperature: (u8, u8)| Preferences {
+///     temperature,
+///     ..Preferences::default()
+/// };
+///
+/// // Nothing saved yet
+/// assert!(newest_valid([[0xFF; SLOT_LEN]; 4].into_iter()).is_none());
+///
+/// // Power was lost halfway through writing the newest slot
+/// let mut interrupted = encode_slot(4, &with_temperature((50, 90)));
+/// interrupted[SLOT_LEN / 4..].fill(0xFF);
+/// let slots = [
+///     encode_slot(2, &with_temperature((55, 85))),
+///     encode_slot(3, &with_temperature((60, 75))),
+///     interrupted,
+///     [0xFF; SLOT_LEN],
+/// ];
+/// let (index, sequence, preferences) = newest_valid(slots.into_iter()).unwrap();
+/// assert_eq!((index, sequence), (1, 3));
+/// assert_eq!(preferences.temperature, (60, 75));
+///
+/// // A flipped bit is caught by the CRC
+/// let mut corrupt = encode_slot(9, &with_temperature((10, 20)));
+/// corrupt[6] ^= 0x01;
+/// let slots = [encode_slot(5, &with_temperature((60, 80))), corrupt];
+/// assert_eq!(newest_valid(slots.into_iter()).unwrap().1, 5);
+/// ```
+pub fn newest_valid(
+    slots: impl Iterator<Item = [u8; SLOT_LEN]>,
+) -> Option<(usize, u32, Preferences)> {
+    let mut newest: Option<(usize, u32, Preferences)> = None;
+    for (index, slot) in slots.enumerate() {
+        if let Some((sequence, preferences)) = decode_slot(&slot) {
+            if newest
+                .as_ref()
+                .map_or(true, |(_, newest, _)| sequence > *newest)
+            {
+                newest = Some((index, sequence, preferences));
+            }
+        }
+    }
+    newest
+}
+
+/// Saves and loads [Preferences] with wear-leveling.
+/// Every save goes to the next slot, rotating through [PREFERENCES_SECTORS] sectors,
+/// and a sector is only erased once the writes wrap back around to it
+///
+/// - **storage**: The [Storage] holding the slots
+/// - **next_slot**: The slot the next save is written to
+/// - **next_sequence**: The sequence number of the next save
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::flash::Storage;
+/// use gem_rs::persist::{PreferenceStore, PREFERENCES_REGION_LEN, SLOTS};
+/// use gem_rs::preferences::Preferences;
+///
+/// struct MemoryStorage(Vec<u8>);
+///
+/// impl Storage for MemoryStorage {
+///     fn read(&self, offset: u32, buffer: &mut [u8]) {
+///         let offset = offset as usize;
+///         buffer.copy_from_slice(&self.0[offset..offset + buffer.len()]);
+///     }
+///     fn erase_sector(&mut self, offset: u32) {
+///         let offset = offset as usize;
+///         self.0[offset..offset + 4096].fill(0xFF);
+///     }
+///     fn program(&mut self, offset: u32, data: &[u8]) {
+///         for (index, byte) in data.iter().enumerate() {
+///             self.0[offset as usize + index] &= byte; // Flash can only clear bits
+///         }
+///     }
+/// }
+///
+/// let storage = MemoryStorage(vec![0xFF; PREFERENCES_REGION_LEN as usize]);
+/// let mut store = PreferenceStore::new(storage);
+/// assert!(store.load().is_none());
+///
+/// // Save enough times to wrap around every sector
+/// let mut preferences = Preferences::default();
+/// for low in 0..SLOTS + 3 {
+///     preferences.temperature = (low as u8, 100);
+///     store.save(&preferences);
+/// }
+/// assert!(store.load().unwrap() == preferences);
+///
+/// // A restart finds the newest save
+/// let store = PreferenceStore::new(store.into_storage());
+/// assert!(store.load().unwrap() == preferences);
+/// ```
+pub struct PreferenceStore<S: Storage> {
+    storage: S,
+    next_slot: u32,
+    next_sequence: u32,
+}
+
+impl<S: Storage> PreferenceStore<S> {
+    /// Opens the store, finding the newest save in the [Storage]
+    ///
+    /// - param storage: [Storage] instance
+    ///
+    /// returns a new [PreferenceStore]
+    pub fn new(storage: S) -> PreferenceStore<S> {
+        let mut store = PreferenceStore {
+            storage,
+            next_slot: 0,
+            next_sequence: 0,
+        };
+        if let Some((index, sequence, _)) = store.newest() {
+            store.next_slot = (index as u32 + 1) % SLOTS;
+            store.next_sequence = sequence.wrapping_add(1);
+        }
+        store
+    }
+
+    /// Gives back the [Storage]
+    ///
+    /// returns the [Storage] instance
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Finds the newest valid save
+    ///
+    /// returns the index, sequence number and [Preferences] of the newest save
+    fn newest(&self) -> Option<(usize, u32, Preferences)> {
+        newest_valid((0..SLOTS).map(|index| {
+            let mut slot = [0u8; SLOT_LEN];
+            self.storage.read(index * SLOT_LEN as u32, &mut slot);
+            slot
+        }))
+    }
+
+    /// Loads the newest valid save
+    ///
+    /// returns the saved [Preferences], or None if nothing valid has been saved
+    pub fn load(&self) -> Option<Preferences> {
+        self.newest().map(|(_, _, preferences)| preferences)
+    }
+
+    /// Saves the preferences into the next slot,
+    /// erasing the next sector first if the writes have wrapped into it
+    ///
+    /// - param preferences: [Preferences] instance
+    pub fn save(&mut self, preferences: &Preferences) {
+        if self.next_slot % SLOTS_PER_SECTOR == 0 {
+            let sector = self.next_slot / SLOTS_PER_SECTOR * SECTOR_SIZE;
+            if !is_erased(&self.storage, sector, SECTOR_SIZE) {
+                self.storage.erase_sector(sector);
+            }
+        }
+        self.storage.program(
+            self.next_slot * SLOT_LEN as u32,
+            &encode_slot(self.next_sequence, preferences),
+        );
+        self.next_slot = (self.next_slot + 1) % SLOTS;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+    }
+}
+
+/// How long preferences must stay unchanged before they are saved, in milliseconds
+pub const SAVE_IDLE_MS: u32 = 5000;
+
+/// Coalesces preference changes into a single save once they stop for [SAVE_IDLE_MS],
+/// so a burst of edits costs one flash write instead of one each
+///
+/// - **dirty**: If there are changes that haven't been saved
+/// - **idle_ms**: The milliseconds since the last change
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::persist::{PendingSave, SAVE_IDLE_MS};
+///
+/// let mut pending = PendingSave::default();
+/// assert!(!pending.step(60_000)); // Nothing to save
+///
+/// // Edits in quick succession are saved once, after the last
+/// pending.mark();
+/// assert!(!pending.step(3000));
+/// pending.mark();
+/// assert!(!pending.step(3000));
+/// assert!(pending.is_dirty());
+/// assert!(pending.step(2000));
+/// assert!(!pending.is_dirty());
+/// assert!(!pending.step(SAVE_IDLE_MS as u16));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PendingSave {
+    dirty: bool,
+    idle_ms: u32,
+}
+
+impl PendingSave {
+    /// Records a change, restarting the wait before it is saved
+    pub fn mark(&mut self) {
+        self.dirty = true;
+        self.idle_ms = 0;
+    }
+
+    /// Counts the time passed since the last step
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    ///
+    /// returns true if the preferences should be saved now
+    pub fn step(&mut self, elapsed_ms: u16) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.idle_ms = self.idle_ms.saturating_add(elapsed_ms as u32);
+        if self.idle_ms >= SAVE_IDLE_MS {
+            self.dirty = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks if there are changes waiting to be saved
+    ///
+    /// returns true if the preferences changed since they were last saved
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+}
+
+/// A serializable mirror of [Preferences] for companion tools.
+/// The fields match [Preferences] one to one, with the date as
+/// `[sec, min, hour, day, month]` plus `year`, the active levels as
+/// [ActiveLevels::active_low_bits], and the expander pins as [pin_code]s
+#[cfg(feature = "serde")]
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct PreferencesConfig {
+    pub temperature: (u8, u8),
+    pub night_temperature: (u8, u8),
+    pub temperature_hysteresis: u8,
+    pub heat_full_below: u8,
+    pub day_start_hour: u8,
+    pub night_start_hour: u8,
+    pub temperature_alarm: (u8, u8),
+    pub humidity: (u8, u8),
+    pub humidity_hysteresis: u8,
+    pub mist_pulse: u8,
+    pub mist_dwell: u8,
+    pub misting_enabled: bool,
+    pub watering_enabled: bool,
+    pub daytime_only: bool,
+    pub daytime_window: ((u8, u8), (u8, u8)),
+    pub date: [u8; 5],
+    pub year: u16,
+    pub watering: Option<(u8, u8, u8, u8)>,
+    pub watering_days: u8,
+    pub watering_seconds: Option<(u8, u8)>,
+    pub low_voltage: u16,
+    pub critical_voltage: u16,
+    pub safe_vent_open: bool,
+    pub pressure_unit: PressureUnitConfig,
+    pub sea_level_pressure: u16,
+    pub pressure_trend_threshold: u8,
+    pub temp_offset: i8,
+    pub humidity_offset: i8,
+    pub pressure_offset: i16,
+    pub gas_baseline: u32,
+    pub log_interval: u8,
+    pub auto_rotate: bool,
+    pub rotate_interval: u8,
+    pub date_format: DateFormatConfig,
+    pub contrast: u8,
+    pub brightness: u8,
+    pub sensor_combine: SensorCombineConfig,
+    pub hidden_screens: u32,
+    pub stale_factor: u8,
+    pub warmup_secs: u8,
+    pub rain_closes_vent: bool,
+    pub unit_system: UnitSystemConfig,
+    pub temperature_unit: TemperatureUnitConfig,
+    pub active_levels: u8,
+    pub gas_enabled: bool,
+    pub gas_heater_temp: u16,
+    pub gas_heater_ms: u16,
+    pub self_test: bool,
+    pub escalation_mins: u8,
+    pub recovery_threshold: u8,
+    pub smoke_samples: u8,
+    pub vent_curve: [(u8, u8); crate::preferences::VENT_CURVE_POINTS],
+    pub default_screen: u8,
+    pub mildew_spread: u8,
+    pub mildew_vent: bool,
+    pub dehumidify_vent: bool,
+    pub passive_buzzer: bool,
+    pub buzzer_volume: u8,
+    pub sensor_timeout_ms: u16,
+    pub fault_policy: FaultPolicyConfig,
+    pub temperature_model: TemperatureModelConfig,
+    pub expander_pins: [u8; EXPANDER_PINS],
+    pub expander_address: u8,
+    pub expander_active_high: bool,
+    pub co2_policy: Co2PolicyConfig,
+    pub co2_vent_ppm: u16,
+    pub gdd_base: u8,
+}
+
+/// A serializable mirror of [PressureUnit]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum PressureUnitConfig {
+    Hpa,
+    InHg,
+    MmHg,
+}
+
+/// A serializable mirror of [UnitSystem]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum UnitSystemConfig {
+    Imperial,
+    Metric,
+}
+
+/// A serializable mirror of [TemperatureUnit]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum TemperatureUnitConfig {
+    Fahrenheit,
+    Celsius,
+    Kelvin,
+}
+
+/// A serializable mirror of [SensorCombine]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum SensorCombineConfig {
+    Average,
+    Lowest,
+    Highest,
+}
+
+/// A serializable mirror of [FaultPolicy]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum FaultPolicyConfig {
+    Hold,
+    SafeState,
+    LastGood,
+}
+
+/// A serializable mirror of [Co2Policy]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Co2PolicyConfig {
+    Off,
+    Vent,
+    Enrich,
+}
+
+/// A serializable mirror of [TemperatureModel]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum TemperatureModelConfig {
+    Range,
+    Setpoint,
+}
+
+/// A serializable mirror of [DateFormat]
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum DateFormatConfig {
+    Numeric,
+    Named,
+}
+
+#[cfg(feature = "serde")]
+impl From<&Preferences> for PreferencesConfig {
+    fn from(preferences: &Preferences) -> Self {
+        let date = preferences.date;
+        let window = preferences.daytime_window;
+        PreferencesConfig {
+            temperature: preferences.temperature,
+            night_temperature: preferences.night_temperature,
+            temperature_hysteresis: preferences.temperature_hysteresis,
+            heat_full_below: preferences.heat_full_below,
+            day_start_hour: preferences.day_start_hour,
+            night_start_hour: preferences.night_start_hour,
+            temperature_alarm: preferences.temperature_alarm,
+            humidity: preferences.humidity,
+            humidity_hysteresis: preferences.humidity_hysteresis,
+            mist_pulse: preferences.mist_pulse,
+            mist_dwell: preferences.mist_dwell,
+            misting_enabled: preferences.misting_enabled,
+            watering_enabled: preferences.watering_enabled,
+            daytime_only: preferences.daytime_only,
+            daytime_window: ((window.0 .0, window.0 .1), (window.1 .0, window.1 .1)),
+            date: [date.0, date.1, date.2, date.3, date.4],
+            year: date.5,
+            watering: preferences.watering,
+            watering_days: preferences.watering_days,
+            watering_seconds: preferences.watering_seconds,
+            low_voltage: preferences.low_voltage,
+            critical_voltage: preferences.critical_voltage,
+            safe_vent_open: preferences.safe_vent_open,
+            pressure_unit: match preferences.pressure_unit {
+                PressureUnit::Hpa => PressureUnitConfig::Hpa,
+                PressureUnit::InHg => PressureUnitConfig::InHg,
+                PressureUnit::MmHg => PressureUnitConfig::MmHg,
+            },
+            sea_level_pressure: preferences.sea_level_pressure,
+            pressure_trend_threshold: preferences.pressure_trend_threshold,
+            temp_offset: preferences.temp_offset,
+            humidity_offset: preferences.humidity_offset,
+            pressure_offset: preferences.pressure_offset,
+            gas_baseline: preferences.gas_baseline,
+            log_interval: preferences.log_interval,
+            auto_rotate: preferences.auto_rotate,
+            rotate_interval: preferences.rotate_interval,
+            date_format: match preferences.date_format {
+                DateFormat::Numeric => DateFormatConfig::Numeric,
+                DateFormat::Named => DateFormatConfig::Named,
+            },
+            contrast: preferences.contrast,
+            brightness: preferences.brightness,
+            sensor_combine: match preferences.sensor_combine {
+                SensorCombine::Average => SensorCombineConfig::Average,
+                SensorCombine::Lowest => SensorCombineConfig::Lowest,
+                SensorCombine::Highest => SensorCombineConfig::Highest,
+            },
+            hidden_screens: preferences.hidden_screens,
+            stale_factor: preferences.stale_factor,
+            warmup_secs: preferences.warmup_secs,
+            rain_closes_vent: preferences.rain_closes_vent,
+            unit_system: match preferences.unit_system {
+                UnitSystem::Imperial => UnitSystemConfig::Imperial,
+                UnitSystem::Metric => UnitSystemConfig::Metric,
+            },
+            temperature_unit: match preferences.temperature_unit {
+                TemperatureUnit::Fahrenheit => TemperatureUnitConfig::Fahrenheit,
+                TemperatureUnit::Celsius => TemperatureUnitConfig::Celsius,
+                TemperatureUnit::Kelvin => TemperatureUnitConfig::Kelvin,
+            },
+            active_levels: preferences.active_levels.active_low_bits(),
+            gas_enabled: preferences.gas_enabled,
+            gas_heater_temp: preferences.gas_heater_temp,
+            gas_heater_ms: preferences.gas_heater_ms,
+            self_test: preferences.self_test,
+            escalation_mins: preferences.escalation_mins,
+            recovery_threshold: preferences.recovery_threshold,
+            smoke_samples: preferences.smoke_samples,
+            vent_curve: preferences.vent_curve,
+            default_screen: preferences.default_screen.code(),
+            mildew_spread: preferences.mildew_spread,
+            mildew_vent: preferences.mildew_vent,
+            dehumidify_vent: preferences.dehumidify_vent,
+            passive_buzzer: preferences.passive_buzzer,
+            buzzer_volume: preferences.buzzer_volume,
+            sensor_timeout_ms: preferences.sensor_timeout_ms,
+            fault_policy: match preferences.fault_policy {
+                FaultPolicy::Hold => FaultPolicyConfig::Hold,
+                FaultPolicy::SafeState => FaultPolicyConfig::SafeState,
+                FaultPolicy::LastGood => FaultPolicyConfig::LastGood,
+            },
+            temperature_model: match preferences.temperature_model {
+                TemperatureModel::Range => TemperatureModelConfig::Range,
+                TemperatureModel::Setpoint => TemperatureModelConfig::Setpoint,
+            },
+            expander_pins: preferences.expander_pins.map(pin_code),
+            expander_address: preferences.expander_address,
+            expander_active_high: preferences.expander_active_high,
+            co2_policy: match preferences.co2_policy {
+                Co2Policy::Off => Co2PolicyConfig::Off,
+                Co2Policy::Vent => Co2PolicyConfig::Vent,
+                Co2Policy::Enrich => Co2PolicyConfig::Enrich,
+            },
+            co2_vent_ppm: preferences.co2_vent_ppm,
+            gdd_base: preferences.gdd_base,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<&PreferencesConfig> for Preferences {
+    fn from(config: &PreferencesConfig) -> Self {
+        let date = config.date;
+        let window = config.daytime_window;
+        Preferences {
+            temperature: config.temperature,
+            night_temperature: config.night_temperature,
+            temperature_hysteresis: config.temperature_hysteresis,
+            heat_full_below: config.heat_full_below,
+            day_start_hour: config.day_start_hour,
+            night_start_hour: config.night_start_hour,
+            temperature_alarm: config.temperature_alarm,
+            humidity: config.humidity,
+            humidity_hysteresis: config.humidity_hysteresis,
+            mist_pulse: config.mist_pulse,
+            mist_dwell: config.mist_dwell,
+            misting_enabled: config.misting_enabled,
+            watering_enabled: config.watering_enabled,
+            daytime_only: config.daytime_only,
+            daytime_window: (
+                TimeOfDay(window.0 .0, window.0 .1),
+                TimeOfDay(window.1 .0, window.1 .1),
+            ),
+            date: DateTime(date[0], date[1], date[2], date[3], date[4], config.year),
+            watering: config.watering,
+            watering_days: config.watering_days,
+            watering_seconds: config.watering_seconds,
+            low_voltage: config.low_voltage,
+            critical_voltage: config.critical_voltage,
+            safe_vent_open: config.safe_vent_open,
+            pressure_unit: match config.pressure_unit {
+                PressureUnitConfig::Hpa => PressureUnit::Hpa,
+                PressureUnitConfig::InHg => PressureUnit::InHg,
+                PressureUnitConfig::MmHg => PressureUnit::MmHg,
+            },
+            sea_level_pressure: config.sea_level_pressure,
+            pressure_trend_threshold: config.pressure_trend_threshold,
+            temp_offset: config.temp_offset,
+            humidity_offset: config.humidity_offset,
+            pressure_offset: config.pressure_offset,
+            gas_baseline: config.gas_baseline,
+            log_interval: config.log_interval,
+            auto_rotate: config.auto_rotate,
+            rotate_interval: config.rotate_interval,
+            date_format: match config.date_format {
+                DateFormatConfig::Numeric => DateFormat::Numeric,
+                DateFormatConfig::Named => DateFormat::Named,
+            },
+            contrast: config.contrast,
+            brightness: config.brightness,
+            sensor_combine: match config.sensor_combine {
+                SensorCombineConfig::Average => SensorCombine::Average,
+                SensorCombineConfig::Lowest => SensorCombine::Lowest,
+                SensorCombineConfig::Highest => SensorCombine::Highest,
+            },
+            hidden_screens: config.hidden_screens,
+            stale_factor: config.stale_factor,
+            warmup_secs: config.warmup_secs,
+            rain_closes_vent: config.rain_closes_vent,
+            unit_system: match config.unit_system {
+                UnitSystemConfig::Imperial => UnitSystem::Imperial,
+                UnitSystemConfig::Metric => UnitSystem::Metric,
+            },
+            temperature_unit: match config.temperature_unit {
+                TemperatureUnitConfig::Fahrenheit => TemperatureUnit::Fahrenheit,
+                TemperatureUnitConfig::Celsius => TemperatureUnit::Celsius,
+                TemperatureUnitConfig::Kelvin => TemperatureUnit::Kelvin,
+            },
+            // Unknown bits fall back to every output being active-high
+            active_levels: ActiveLevels::from_active_low_bits(config.active_levels)
+                .unwrap_or_default(),
+            gas_enabled: config.gas_enabled,
+            gas_heater_temp: config.gas_heater_temp,
+            gas_heater_ms: config.gas_heater_ms,
+            self_test: config.self_test,
+            escalation_mins: config.escalation_mins,
+            recovery_threshold: config.recovery_threshold,
+            smoke_samples: config.smoke_samples,
+            vent_curve: config.vent_curve,
+            // Screens this build doesn't have fall back to the first
+            default_screen: Screen::from_code(config.default_screen).unwrap_or(Screen::Temperature),
+            mildew_spread: config.mildew_spread,
+            mildew_vent: config.mildew_vent,
+            dehumidify_vent: config.dehumidify_vent,
+            passive_buzzer: config.passive_buzzer,
+            buzzer_volume: config.buzzer_volume,
+            sensor_timeout_ms: config.sensor_timeout_ms,
+            fault_policy: match config.fault_policy {
+                FaultPolicyConfig::Hold => FaultPolicy::Hold,
+                FaultPolicyConfig::SafeState => FaultPolicy::SafeState,
+                FaultPolicyConfig::LastGood => FaultPolicy::LastGood,
+            },
+            temperature_model: match config.temperature_model {
+                TemperatureModelConfig::Range => TemperatureModel::Range,
+                TemperatureModelConfig::Setpoint => TemperatureModel::Setpoint,
+            },
+            // Unknown outputs leave their pin unused
+            expander_pins: config
+                .expander_pins
+                .map(|code| pin_from_code(code).flatten()),
+            expander_address: config.expander_address,
+            expander_active_high: config.expander_active_high,
+            co2_policy: match config.co2_policy {
+                Co2PolicyConfig::Off => Co2Policy::Off,
+                Co2PolicyConfig::Vent => Co2Policy::Vent,
+                Co2PolicyConfig::Enrich => Co2Policy::Enrich,
+            },
+            co2_vent_ppm: config.co2_vent_ppm,
+            gdd_base: config.gdd_base,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl PreferencesConfig {
+    /// Serializes the config as JSON
+    ///
+    /// returns the JSON text
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::persist::PreferencesConfig;
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.humidity = (40, 90);
+    /// preferences.date = DateTime(0, 15, 18, 31, 12, 2023);
+    /// preferences.watering = Some((0, 20, 30, 20));
+    /// preferences.humidity_offset = -5;
+    ///
+    /// // Desktop JSON round trip
+    /// let json = PreferencesConfig::from(&preferences).to_json().unwrap();
+    /// let config = PreferencesConfig::from_json(&json).unwrap();
+    /// assert!(Preferences::from(&config) == preferences);
+    ///
+    /// // The desktop side reads the same bytes the firmware writes
+    /// let bytes = Preferences::from(&config).to_bytes();
+    /// assert!(Preferences::from_bytes(&bytes).unwrap() == preferences);
+    /// ```
+    pub fn to_json(&self) -> Result<alloc::string::String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Parses a config from JSON
+    ///
+    /// - param json: the JSON text
+    ///
+    /// returns the parsed [PreferencesConfig]
+    pub fn from_json(json: &str) -> Result<PreferencesConfig, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
@@ -0,0 +1,49 @@
+use cortex_m::peripheral::{NVIC, SCB};
+use rp_pico::hal::fugit::ExtU32;
+use rp_pico::hal::pac::Interrupt;
+use rp_pico::hal::timer::{Alarm, Alarm0};
+
+/// Puts the core to sleep between polls.
+///
+/// The core halts in `wfe` until the alarm fires or a button, UART or timer interrupt
+/// becomes pending. SEVONPEND turns any pending interrupt into a wake-up event, even
+/// though none of them are enabled in the NVIC, so no interrupt handlers are needed.
+///
+/// Busy-waiting keeps the core running flat out at 125MHz, drawing roughly 20mA.
+/// Halted, the core's clock is gated and the board is expected to idle at around
+/// 10mA, since the peripherals and PLLs keep running.
+///
+/// - **alarm**: The hardware alarm that ends each sleep
+pub struct Sleeper {
+    alarm: Alarm0,
+}
+
+impl Sleeper {
+    /// Creates a new instance of Sleeper
+    ///
+    /// - param alarm: [Alarm0] instance
+    /// - param scb: The System Control Block, used to enable SEVONPEND
+    ///
+    /// returns a new instance of Sleeper
+    pub fn new(mut alarm: Alarm0, scb: &mut SCB) -> Sleeper {
+        scb.set_sevonpend();
+        alarm.enable_interrupt();
+        Sleeper { alarm }
+    }
+
+    /// Sleeps until the time passes or an interrupt becomes pending.
+    /// Callers should clear their peripherals' interrupts after waking
+    ///
+    /// - param ms: The longest time to sleep in milliseconds
+    pub fn sleep_ms(&mut self, ms: u16) {
+        if ms == 0 || self.alarm.schedule((ms as u32).millis()).is_err() {
+            return;
+        }
+        cortex_m::asm::wfe();
+        self.alarm.clear_interrupt();
+        // Clear the pending interrupts so they can raise a wake-up event again
+        NVIC::unpend(Interrupt::TIMER_IRQ_0);
+        NVIC::unpend(Interrupt::IO_IRQ_BANK0);
+        NVIC::unpend(Interrupt::UART0_IRQ);
+    }
+}
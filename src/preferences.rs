@@ -1,35 +1,1404 @@
+use crate::expander::{is_valid_expander_address, EXPANDER_PINS, PCF8574_ADDRESS};
+use crate::rendering::{Truncating, LABELS, MIN_CONTRAST};
+use crate::screens::Screen;
+use crate::sensors::{hpa_to_inhg, hpa_to_mmhg, round_positive};
 use heapless::String;
 use ufmt::uwrite;
 
-use panic_probe as _;
+/// Preferences defines the consumer-selected range of acceptable values for each category.
+///
+/// - **temperature**: The acceptable temperature range in Fahrenheit during the day
+/// - **temperature_alarm**: The temperature range in Fahrenheit outside of which the alarm sounds; never narrower than either temperature range
+/// - **night_temperature**: The acceptable temperature range in Fahrenheit during the night
+/// - **temperature_model**: If the temperature ranges are edited and shown as a range or as a [Setpoint]. See [TemperatureModel]
+/// - **temperature_hysteresis**: How far in Fahrenheit the temperature must fall back before the vent closes when edited as a range, or rise back before a heater stage stops
+/// - **heat_full_below**: How far in Fahrenheit below the active range the heater runs at full heat; 0 never runs it at low heat. See [heating_stage](crate::control::heating_stage)
+/// - **day_start_hour**: The hour the day temperature range takes over
+/// - **night_start_hour**: The hour the night temperature range takes over
+/// - **humidity**: The acceptable relative humidity percentage range
+/// - **humidity_hysteresis**: How far in percent humidity must move back into range before the mister stops
+/// - **mist_pulse**: How long each misting pulse lasts in seconds
+/// - **mist_dwell**: How long to wait after each misting pulse in seconds; 0 mists continuously
+/// - **misting_enabled**: If the mister is driven by the humidity range
+/// - **watering_enabled**: If the sprinklers are driven by the watering schedule
+/// - **daytime_only**: If scheduled watering is held outside of the daytime window
+/// - **daytime_window**: The start and end of the daytime; it may wrap past midnight
+/// - **date**: The current date and time: Sec, Min, Hour, Day, Month, Year
+/// - **watering**: The minute and hour range for when watering should occur
+/// - **watering_days**: Bitmask of the [Weekday]s watering is allowed on
+/// - **watering_seconds**: The start and end second of the watering time; without them whole minutes are watered
+/// - **low_voltage**: The supply voltage in millivolts below which a warning is raised
+/// - **critical_voltage**: The supply voltage in millivolts below which the outputs are made safe
+/// - **safe_vent_open**: If the roof vent should be left open when the outputs are made safe
+/// - **pressure_unit**: The [PressureUnit] pressure is displayed in
+/// - **sea_level_pressure**: The local sea-level reference pressure in millibars/hPa
+/// - **pressure_trend_threshold**: The tenths of a millibar/hPa per hour that counts as rising or falling
+/// - **temp_offset**: The calibration offset for temperature in Fahrenheit
+/// - **humidity_offset**: The calibration offset for relative humidity in percent
+/// - **pressure_offset**: The calibration offset for pressure in millibars/hPa
+/// - **gas_baseline**: The clean-air gas resistance in ohms; 0 until calibrated
+/// - **log_interval**: The minutes between readings saved to the data log
+/// - **auto_rotate**: If the dashboard screens advance on their own while the buttons are idle
+/// - **rotate_interval**: The seconds each dashboard screen is shown for
+/// - **date_format**: The [DateFormat] the date screen uses
+/// - **contrast**: The LCD contrast in percent
+/// - **brightness**: The LCD backlight brightness in percent
+/// - **sensor_combine**: How the two sensors' readings are combined
+/// - **hidden_screens**: The screens skipped when cycling; one bit per [Screen::mask]
+/// - **stale_factor**: How many polling intervals a reading may age before its age flashes
+/// - **warmup_secs**: How long after boot readings are shown but not acted on, while the sensor settles
+/// - **rain_closes_vent**: If the rain sensor holds the roof vent closed while it is wet
+/// - **unit_system**: The [UnitSystem] last applied; the units below it may be changed on their own
+/// - **temperature_unit**: The [TemperatureUnit] temperatures are displayed in
+/// - **active_levels**: The [ActiveLevels] each output's relay switches on at
+/// - **gas_enabled**: If the BME680 measures gas; off skips the heater, shortening each measurement and saving power
+/// - **gas_heater_temp**: The temperature the BME680 heats its gas sensor to in Celsius
+/// - **gas_heater_ms**: How long the BME680 heats its gas sensor for in milliseconds
+/// - **self_test**: If the outputs, sensor and smoke detector are tested at boot
+/// - **recovery_threshold**: How many sensor failures in a row recover the I2C bus and set the sensors up again
+/// - **escalation_mins**: How many minutes the temperature or humidity may stay out of range before the alarm escalates; 0 never escalates
+/// - **smoke_samples**: How many smoke detector samples in a row must agree before the fire response starts or stops
+/// - **mildew_spread**: How close in Fahrenheit the temperature may come to the dew point in high humidity before mildew is warned about; 0 never warns
+/// - **mildew_vent**: If the roof vent is opened while there is a mildew risk
+/// - **dehumidify_vent**: If the roof vent is opened while the humidity is above its range. See [HumidityAction](crate::control::HumidityAction)
+/// - **passive_buzzer**: If the buzzer is passive, so it is played as a tone rather than switched on
+/// - **buzzer_volume**: How loud a passive buzzer plays, in percent
+/// - **fault_policy**: The [FaultPolicy] applied once the reading has gone stale. See [Preferences::stale_factor]
+/// - **sensor_timeout_ms**: How long a sensor operation may take in milliseconds before it is abandoned; reads also get the measurement's time
+/// - **expander_pins**: The [Output] each pin of a PCF8574 I2C expander follows, or None if the pin is unused. See [crate::expander]
+/// - **expander_address**: The I2C address of the PCF8574
+/// - **expander_active_high**: If the expander's relays switch on while their pin is high
+/// - **co2_policy**: The [Co2Policy] deciding how CO2 affects the roof vent
+/// - **co2_vent_ppm**: The CO2 in ppm above which the roof vent opens under [Co2Policy::Vent]
+/// - **gdd_base**: The base temperature in Fahrenheit growing degree days are counted from. See [growing_degree_days](crate::stats::growing_degree_days)
+/// - **default_screen**: The [Screen] shown after boot; the next visible one if it has been hidden
+/// - **vent_curve**: How far open the vent should be at each temperature above the active range, as `(F over, percent open)` breakpoints. See [Preferences::vent_percent_for]
+#[derive(Clone, PartialEq)]
+pub struct Preferences {
+    pub temperature: (u8, u8),
+    pub temperature_alarm: (u8, u8),
+    pub night_temperature: (u8, u8),
+    pub temperature_hysteresis: u8,
+    pub heat_full_below: u8,
+    pub day_start_hour: u8,
+    pub night_start_hour: u8,
+    pub humidity: (u8, u8),
+    pub humidity_hysteresis: u8,
+    pub mist_pulse: u8,
+    pub mist_dwell: u8,
+    pub misting_enabled: bool,
+    pub watering_enabled: bool,
+    pub daytime_only: bool,
+    pub daytime_window: (TimeOfDay, TimeOfDay),
+    pub date: DateTime,
+    pub watering: Option<(u8, u8, u8, u8)>, // Start (Min, Hour), End (Min, Hour)
+    pub watering_days: u8,
+    pub watering_seconds: Option<(u8, u8)>, // Start second, End second
+    pub low_voltage: u16,
+    pub critical_voltage: u16,
+    pub safe_vent_open: bool,
+    pub pressure_unit: PressureUnit,
+    pub sea_level_pressure: u16,
+    pub pressure_trend_threshold: u8,
+    pub temp_offset: i8,
+    pub humidity_offset: i8,
+    pub pressure_offset: i16,
+    pub gas_baseline: u32,
+    pub log_interval: u8,
+    pub auto_rotate: bool,
+    pub rotate_interval: u8,
+    pub date_format: DateFormat,
+    pub contrast: u8,
+    pub brightness: u8,
+    pub sensor_combine: SensorCombine,
+    pub hidden_screens: u32,
+    pub stale_factor: u8,
+    pub warmup_secs: u8,
+    pub rain_closes_vent: bool,
+    pub unit_system: UnitSystem,
+    pub temperature_unit: TemperatureUnit,
+    pub active_levels: ActiveLevels,
+    pub gas_enabled: bool,
+    pub gas_heater_temp: u16,
+    pub gas_heater_ms: u16,
+    pub self_test: bool,
+    pub escalation_mins: u8,
+    pub recovery_threshold: u8,
+    pub smoke_samples: u8,
+    pub vent_curve: [(u8, u8); VENT_CURVE_POINTS],
+    pub default_screen: Screen,
+    pub mildew_spread: u8,
+    pub mildew_vent: bool,
+    pub dehumidify_vent: bool,
+    pub passive_buzzer: bool,
+    pub buzzer_volume: u8,
+    pub sensor_timeout_ms: u16,
+    pub fault_policy: FaultPolicy,
+    pub temperature_model: TemperatureModel,
+    pub expander_pins: [Option<Output>; EXPANDER_PINS],
+    pub expander_address: u8,
+    pub expander_active_high: bool,
+    pub co2_policy: Co2Policy,
+    pub co2_vent_ppm: u16,
+    pub gdd_base: u8,
+}
+
+/// Every day of the week; used as the default `watering_days` mask
+pub const ALL_DAYS: u8 = 0b0111_1111;
+/// The last year the clock can show; it stops at the end of this year
+pub const MAX_YEAR: u16 = 9999;
+/// The longest sensor warm-up in seconds
+pub const MAX_WARMUP_SECS: u8 = 120;
+/// The longest time out of range before the alarm escalates, in minutes
+pub const MAX_ESCALATION_MINS: u8 = 240;
+/// The most sensor failures in a row before the I2C bus is recovered
+pub const MAX_RECOVERY_THRESHOLD: u8 = 20;
+/// The most smoke detector samples in a row needed to confirm smoke; 1 second of polling
+pub const MAX_SMOKE_SAMPLES: u8 = 20;
+/// The widest dew point spread that warns of mildew, in Fahrenheit
+pub const MAX_MILDEW_SPREAD: u8 = 20;
+/// The furthest below the temperature range full heat may start, in Fahrenheit
+pub const MAX_HEAT_FULL_BELOW: u8 = 20;
+/// The quietest a passive buzzer plays, in percent, so an alarm can't be turned down to nothing
+pub const MIN_BUZZER_VOLUME: u8 = 10;
+/// The lowest CO2 the vent may open above, in ppm; outdoor air is about 420ppm, so anything lower never closes
+pub const MIN_CO2_VENT_PPM: u16 = 600;
+/// The highest CO2 the vent may open above, in ppm; the top of the SCD41's range
+pub const MAX_CO2_VENT_PPM: u16 = 5000;
+/// The lowest growing degree day base temperature, in Fahrenheit; nothing grows below freezing
+pub const MIN_GDD_BASE: u8 = 32;
+/// The highest growing degree day base temperature, in Fahrenheit
+pub const MAX_GDD_BASE: u8 = 86;
+/// The shortest a sensor operation may take before it is abandoned, in milliseconds; a BME680 reset takes 10ms
+pub const MIN_SENSOR_TIMEOUT_MS: u16 = 50;
+/// The longest a sensor operation may take before it is abandoned, in milliseconds
+pub const MAX_SENSOR_TIMEOUT_MS: u16 = 2000;
+/// How far the clock may drift from the time that passed before it counts as set, in seconds
+pub const CLOCK_SLACK_SECS: u32 = 2;
+/// The coolest gas heater temperature in Celsius the BME680 is specified for
+pub const MIN_HEATER_TEMP: u16 = 200;
+/// The hottest gas heater temperature in Celsius; the BME680 driver caps anything above it
+pub const MAX_HEATER_TEMP: u16 = 400;
+/// The shortest gas heater duration in milliseconds that still gives a stable reading
+pub const MIN_HEATER_MS: u16 = 50;
+/// The longest gas heater duration in milliseconds.
+/// The driver accepts up to 4032ms, but a measurement must finish within one sensor poll
+pub const MAX_HEATER_MS: u16 = 1800;
+/// The lowest low supply voltage warning, in millivolts
+pub const MIN_LOW_VOLTAGE: u16 = 1800;
+/// The highest low supply voltage warning, in millivolts
+pub const MAX_LOW_VOLTAGE: u16 = 5500;
+/// How far the supply voltages are edited at a time, and the least the low warning sits above the critical cutoff, in millivolts
+pub const VOLTAGE_STEP: u16 = 100;
+
+/// Checks user-entered gas heater settings against what the BME680 accepts
+///
+/// - param temp: The heater temperature in Celsius
+/// - param ms: The heater duration in milliseconds
+///
+/// returns true if both are within [MIN_HEATER_TEMP] - [MAX_HEATER_TEMP] and [MIN_HEATER_MS] - [MAX_HEATER_MS]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::is_valid_gas_heater;
+///
+/// assert!(is_valid_gas_heater(320, 1500));
+/// assert!(is_valid_gas_heater(200, 50));
+/// assert!(is_valid_gas_heater(400, 1800));
+/// assert!(!is_valid_gas_heater(199, 1500));
+/// assert!(!is_valid_gas_heater(401, 1500));
+/// assert!(!is_valid_gas_heater(320, 49));
+/// assert!(!is_valid_gas_heater(320, 4032)); // Accepted by the driver, but outlasts a poll
+/// ```
+pub fn is_valid_gas_heater(temp: u16, ms: u16) -> bool {
+    (MIN_HEATER_TEMP..=MAX_HEATER_TEMP).contains(&temp)
+        && (MIN_HEATER_MS..=MAX_HEATER_MS).contains(&ms)
+}
+
+/// How many breakpoints the vent curve has
+pub const VENT_CURVE_POINTS: usize = 4;
+
+/// Checks that a vent curve can be interpolated
+///
+/// - param curve: The `(F over, percent open)` breakpoints
+///
+/// returns true if the temperatures strictly increase, and the percentages never fall and stay within 0% - 100%
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::is_valid_vent_curve;
+///
+/// assert!(is_valid_vent_curve(&[(0, 0), (1, 25), (3, 50), (5, 100)]));
+/// assert!(is_valid_vent_curve(&[(0, 50), (2, 50), (4, 50), (6, 50)])); // Flat
+/// assert!(!is_valid_vent_curve(&[(0, 0), (3, 25), (3, 50), (5, 100)])); // Repeated temperature
+/// assert!(!is_valid_vent_curve(&[(0, 0), (4, 25), (3, 50), (5, 100)])); // Temperatures out of order
+/// assert!(!is_valid_vent_curve(&[(0, 0), (1, 50), (3, 25), (5, 100)])); // Closes as it warms
+/// assert!(!is_valid_vent_curve(&[(0, 0), (1, 25), (3, 50), (5, 120)]));
+/// ```
+pub fn is_valid_vent_curve(curve: &[(u8, u8); VENT_CURVE_POINTS]) -> bool {
+    curve.iter().all(|&(_, percent)| percent <= 100)
+        && curve
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 <= pair[1].1)
+}
+
+/// The units pressure can be displayed in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PressureUnit {
+    Hpa,
+    InHg,
+    MmHg,
+}
+
+impl PressureUnit {
+    /// Gets the next unit to cycle to
+    ///
+    /// returns the next [PressureUnit]
+    pub fn next(self) -> PressureUnit {
+        match self {
+            PressureUnit::Hpa => PressureUnit::InHg,
+            PressureUnit::InHg => PressureUnit::MmHg,
+            PressureUnit::MmHg => PressureUnit::Hpa,
+        }
+    }
+
+    /// Gets the symbol put after a pressure
+    ///
+    /// returns `mb`, `inHg` or `mmHg`
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PressureUnit::Hpa => "mb",
+            PressureUnit::InHg => "inHg",
+            PressureUnit::MmHg => "mmHg",
+        }
+    }
+}
+
+/// The units temperatures can be displayed in.
+/// Temperatures are always stored in Fahrenheit; the unit only changes how they are shown and edited
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TemperatureUnit {
+    Fahrenheit,
+    Celsius,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Gets the next unit to cycle to
+    ///
+    /// returns the next [TemperatureUnit]
+    pub fn next(self) -> TemperatureUnit {
+        match self {
+            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+            TemperatureUnit::Celsius => TemperatureUnit::Kelvin,
+            TemperatureUnit::Kelvin => TemperatureUnit::Fahrenheit,
+        }
+    }
+
+    /// Gets the symbol put after a temperature
+    ///
+    /// returns `F`, `C` or `K`
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Fahrenheit => "F",
+            TemperatureUnit::Celsius => "C",
+            TemperatureUnit::Kelvin => "K",
+        }
+    }
+
+    /// Converts a stored temperature into this unit
+    ///
+    /// - param fahrenheit: The temperature in whole Fahrenheit
+    ///
+    /// returns the temperature in this unit, rounded to whole degrees.
+    /// Kelvin reaches past [u8::MAX], so the result is always wider than the stored temperature
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::TemperatureUnit;
+    ///
+    /// assert_eq!(TemperatureUnit::Fahrenheit.from_fahrenheit(80), 80);
+    /// assert_eq!(TemperatureUnit::Celsius.from_fahrenheit(32), 0);
+    /// assert_eq!(TemperatureUnit::Celsius.from_fahrenheit(80), 27); // 26.7C rounds up
+    /// assert_eq!(TemperatureUnit::Celsius.from_fahrenheit(0), -18);
+    /// assert_eq!(TemperatureUnit::Celsius.from_fahrenheit(255), 124);
+    /// assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(32), 273); // Freezing
+    /// assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(72), 295);
+    /// assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(212), 373); // Boiling
+    /// assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(0), 255);
+    /// assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(255), 397);
+    /// ```
+    pub fn from_fahrenheit(self, fahrenheit: u8) -> i16 {
+        match self {
+            TemperatureUnit::Fahrenheit => fahrenheit as i16,
+            TemperatureUnit::Celsius => {
+                let tenths = (fahrenheit as i16 - 32) * 50 / 9;
+                (tenths + if tenths < 0 { -5 } else { 5 }) / 10
+            }
+            TemperatureUnit::Kelvin => {
+                // 0K is -459.67F, so 0F and up is always above 255K
+                let hundredths = (fahrenheit as i32 - 32) * 500 / 9 + 27_315;
+                ((hundredths + 50) / 100) as i16
+            }
+        }
+    }
+
+    /// Converts a temperature in this unit back into whole Fahrenheit for storing
+    ///
+    /// - param value: The temperature in this unit
+    ///
+    /// returns the temperature in Fahrenheit, rounded and clamped to 0F - 255F
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::TemperatureUnit;
+    ///
+    /// assert_eq!(TemperatureUnit::Fahrenheit.to_fahrenheit(80), 80);
+    /// assert_eq!(TemperatureUnit::Celsius.to_fahrenheit(27), 81);
+    /// assert_eq!(TemperatureUnit::Celsius.to_fahrenheit(-40), 0);
+    /// assert_eq!(TemperatureUnit::Celsius.to_fahrenheit(200), 255);
+    ///
+    /// // Whole Celsius survive the trip through Fahrenheit
+    /// for celsius in -17..=123 {
+    ///     let stored = TemperatureUnit::Celsius.to_fahrenheit(celsius);
+    ///     assert_eq!(TemperatureUnit::Celsius.from_fahrenheit(stored), celsius);
+    /// }
+    ///
+    /// assert_eq!(TemperatureUnit::Kelvin.to_fahrenheit(273), 32);
+    /// assert_eq!(TemperatureUnit::Kelvin.to_fahrenheit(295), 72);
+    /// assert_eq!(TemperatureUnit::Kelvin.to_fahrenheit(0), 0);
+    /// assert_eq!(TemperatureUnit::Kelvin.to_fahrenheit(500), 255);
+    ///
+    /// // And so do whole Kelvin
+    /// for kelvin in 255..=397 {
+    ///     let stored = TemperatureUnit::Kelvin.to_fahrenheit(kelvin);
+    ///     assert_eq!(TemperatureUnit::Kelvin.from_fahrenheit(stored), kelvin);
+    /// }
+    /// ```
+    pub fn to_fahrenheit(self, value: i16) -> u8 {
+        let tenths = match self {
+            TemperatureUnit::Fahrenheit => value as i32 * 10,
+            TemperatureUnit::Celsius => value as i32 * 18 + 320,
+            // 273.15K is 32F
+            TemperatureUnit::Kelvin => value as i32 * 18 - 4597,
+        };
+        ((tenths.max(0) + 5) / 10).min(u8::MAX as i32) as u8
+    }
+
+    /// Gets the [RangeLimits] for temperature ranges shown in this unit.
+    /// They apply to temperatures less the [TemperatureUnit::edit_offset]
+    ///
+    /// returns the [RangeLimits]
+    pub fn limits(self) -> &'static RangeLimits {
+        match self {
+            TemperatureUnit::Fahrenheit => &TEMPERATURE_LIMITS,
+            TemperatureUnit::Celsius | TemperatureUnit::Kelvin => &TEMPERATURE_LIMITS_CELSIUS,
+        }
+    }
+
+    /// Gets how far temperatures in this unit are shifted down while being edited.
+    /// Ranges are edited from 0 to 100, which Kelvin never reaches, so Kelvin ranges are
+    /// edited from 273K to 373K; the same steps as Celsius
+    ///
+    /// returns the offset in this unit
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::TemperatureUnit;
+    ///
+    /// assert_eq!(TemperatureUnit::Celsius.edit_offset(), 0);
+    /// let kelvin = TemperatureUnit::Kelvin;
+    /// assert_eq!(kelvin.from_fahrenheit(32) - kelvin.edit_offset(), 0);
+    /// ```
+    pub fn edit_offset(self) -> i16 {
+        match self {
+            TemperatureUnit::Fahrenheit | TemperatureUnit::Celsius => 0,
+            TemperatureUnit::Kelvin => 273,
+        }
+    }
+}
+
+/// The preset units
+///
+/// - **Imperial**: Fahrenheit and inches of mercury
+/// - **Metric**: Celsius and millibars/hPa
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnitSystem {
+    Imperial,
+    Metric,
+}
+
+impl UnitSystem {
+    /// Gets the next preset to cycle to
+    ///
+    /// returns the next [UnitSystem]
+    pub fn next(self) -> UnitSystem {
+        match self {
+            UnitSystem::Imperial => UnitSystem::Metric,
+            UnitSystem::Metric => UnitSystem::Imperial,
+        }
+    }
+
+    /// Gets the unit of each quantity in the preset
+    ///
+    /// returns the [TemperatureUnit] and [PressureUnit]
+    pub fn units(self) -> (TemperatureUnit, PressureUnit) {
+        match self {
+            UnitSystem::Imperial => (TemperatureUnit::Fahrenheit, PressureUnit::InHg),
+            UnitSystem::Metric => (TemperatureUnit::Celsius, PressureUnit::Hpa),
+        }
+    }
+
+    /// Gets the on-screen name of the preset
+    ///
+    /// returns the name
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitSystem::Imperial => LABELS.imperial,
+            UnitSystem::Metric => LABELS.metric,
+        }
+    }
+}
+
+/// The outputs driven through a relay
+///
+/// - **Sprinklers**: The sprinklers (GPIO13)
+/// - **RoofVent**: The roof vent (GPIO14)
+/// - **Mister**: The mister (GPIO15)
+/// - **Buzzer**: The buzzer (GPIO6)
+/// - **HeaterLow**: The first heater stage (GPIO21); on at any [HeatStage](crate::control::HeatStage) but off
+/// - **HeaterHigh**: The second heater stage (GPIO22); only on at full heat
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Output {
+    Sprinklers,
+    RoofVent,
+    Mister,
+    Buzzer,
+    HeaterLow,
+    HeaterHigh,
+}
+
+impl Output {
+    /// Gets the bit of the output in [ActiveLevels::active_low_bits]
+    ///
+    /// returns the bit
+    pub fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The level each output's relay switches on at; true when it switches on while its pin is high.
+/// Relay boards differ, and many switch on while their pin is low, so any mix is allowed.
+/// Outputs are active-high unless configured otherwise
+///
+/// - **sprinklers**: If the sprinkler relay is active-high
+/// - **roof_vent**: If the roof vent relay is active-high
+/// - **mister**: If the mister relay is active-high
+/// - **buzzer**: If the buzzer is active-high
+/// - **heater_low**: If the first heater stage's relay is active-high
+/// - **heater_high**: If the second heater stage's relay is active-high
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ActiveLevels {
+    pub sprinklers: bool,
+    pub roof_vent: bool,
+    pub mister: bool,
+    pub buzzer: bool,
+    pub heater_low: bool,
+    pub heater_high: bool,
+}
+
+impl Default for ActiveLevels {
+    fn default() -> Self {
+        ActiveLevels {
+            sprinklers: true,
+            roof_vent: true,
+            mister: true,
+            buzzer: true,
+            heater_low: true,
+            heater_high: true,
+        }
+    }
+}
+
+impl ActiveLevels {
+    /// Gets the active level of an output
+    ///
+    /// - param output: The [Output]
+    ///
+    /// returns true if it is active-high
+    pub fn get(&self, output: Output) -> bool {
+        match output {
+            Output::Sprinklers => self.sprinklers,
+            Output::RoofVent => self.roof_vent,
+            Output::Mister => self.mister,
+            Output::Buzzer => self.buzzer,
+            Output::HeaterLow => self.heater_low,
+            Output::HeaterHigh => self.heater_high,
+        }
+    }
+
+    /// Sets the active level of an output
+    ///
+    /// - param output: The [Output]
+    /// - param active_high: If it switches on while its pin is high
+    pub fn set(&mut self, output: Output, active_high: bool) {
+        match output {
+            Output::Sprinklers => self.sprinklers = active_high,
+            Output::RoofVent => self.roof_vent = active_high,
+            Output::Mister => self.mister = active_high,
+            Output::Buzzer => self.buzzer = active_high,
+            Output::HeaterLow => self.heater_low = active_high,
+            Output::HeaterHigh => self.heater_high = active_high,
+        }
+    }
+
+    /// Packs the active-low outputs into one bit each. See [Output::mask]
+    ///
+    /// returns the bits; 0 when every output is active-high
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{ActiveLevels, Output};
+    ///
+    /// assert_eq!(ActiveLevels::default().active_low_bits(), 0);
+    ///
+    /// let mut levels = ActiveLevels::default();
+    /// levels.set(Output::RoofVent, false);
+    /// levels.set(Output::Buzzer, false);
+    /// assert_eq!(levels.active_low_bits(), 0b1010);
+    /// assert_eq!(ActiveLevels::from_active_low_bits(0b1010), Some(levels));
+    /// levels.set(Output::HeaterHigh, false);
+    /// assert_eq!(levels.active_low_bits(), 0b10_1010);
+    /// assert_eq!(ActiveLevels::from_active_low_bits(0b10_1010), Some(levels));
+    /// assert_eq!(ActiveLevels::from_active_low_bits(0b100_0000), None); // No such output
+    /// ```
+    pub fn active_low_bits(&self) -> u8 {
+        OUTPUTS
+            .iter()
+            .filter(|output| !self.get(**output))
+            .fold(0, |bits, output| bits | output.mask())
+    }
+
+    /// Unpacks the bits from [ActiveLevels::active_low_bits]
+    ///
+    /// - param bits: The active-low outputs
+    ///
+    /// returns the [ActiveLevels], or None if a bit doesn't belong to an [Output]
+    pub fn from_active_low_bits(bits: u8) -> Option<ActiveLevels> {
+        let mut levels = ActiveLevels::default();
+        let mut known = 0;
+        for output in OUTPUTS {
+            levels.set(output, bits & output.mask() == 0);
+            known |= output.mask();
+        }
+        if bits & !known != 0 {
+            return None;
+        }
+        Some(levels)
+    }
+}
+
+/// Every [Output]
+pub const OUTPUTS: [Output; 6] = [
+    Output::Sprinklers,
+    Output::RoofVent,
+    Output::Mister,
+    Output::Buzzer,
+    Output::HeaterLow,
+    Output::HeaterHigh,
+];
+
+/// Why a range is likely to upset the automation
+///
+/// - **Narrow**: The range is so narrow that the outputs would keep switching
+/// - **Extreme**: A bound is beyond what a greenhouse should reach, so an output would never stop
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RangeWarning {
+    Narrow,
+    Extreme,
+}
+
+impl RangeWarning {
+    /// Gets the on-screen name of the warning
+    ///
+    /// returns the warning's label
+    pub fn label(self) -> &'static str {
+        match self {
+            RangeWarning::Narrow => LABELS.range_narrow,
+            RangeWarning::Extreme => LABELS.range_extreme,
+        }
+    }
+}
+
+/// What counts as a sensible range for a measurement
+///
+/// - **min_span**: The narrowest range that isn't [RangeWarning::Narrow]
+/// - **extremes**: The lowest and highest bounds that aren't [RangeWarning::Extreme]
+pub struct RangeLimits {
+    pub min_span: u8,
+    pub extremes: (u8, u8),
+}
+
+/// Temperature ranges narrower than 4F or reaching past freezing or 110F are suspicious
+pub const TEMPERATURE_LIMITS: RangeLimits = RangeLimits {
+    min_span: 4,
+    extremes: (32, 110),
+};
+
+/// [TEMPERATURE_LIMITS] in Celsius: narrower than 2C or reaching past 0C or 43C
+pub const TEMPERATURE_LIMITS_CELSIUS: RangeLimits = RangeLimits {
+    min_span: 2,
+    extremes: (0, 43),
+};
+
+/// Humidity ranges narrower than 5% or reaching past 10% or 95% are suspicious
+pub const HUMIDITY_LIMITS: RangeLimits = RangeLimits {
+    min_span: 5,
+    extremes: (10, 95),
+};
+
+/// Checks if a range is suspicious. Narrow ranges are reported before extreme ones.
+/// The range may be inverted, since it is checked while it is being edited
+///
+/// - param range: The `(min, max)` range
+/// - param limits: The [RangeLimits] of the measurement
+///
+/// returns the [RangeWarning], or None if the range looks sensible
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::{range_warning, RangeWarning, HUMIDITY_LIMITS, TEMPERATURE_LIMITS};
+///
+/// assert_eq!(range_warning((60, 80), &TEMPERATURE_LIMITS), None);
+/// assert_eq!(range_warning((70, 70), &TEMPERATURE_LIMITS), Some(RangeWarning::Narrow));
+/// assert_eq!(range_warning((70, 73), &TEMPERATURE_LIMITS), Some(RangeWarning::Narrow));
+/// assert_eq!(range_warning((70, 74), &TEMPERATURE_LIMITS), None);
+/// assert_eq!(range_warning((80, 60), &TEMPERATURE_LIMITS), None); // Inverted while editing
+/// assert_eq!(range_warning((20, 80), &TEMPERATURE_LIMITS), Some(RangeWarning::Extreme));
+/// assert_eq!(range_warning((60, 120), &TEMPERATURE_LIMITS), Some(RangeWarning::Extreme));
+///
+/// assert_eq!(range_warning((60, 70), &HUMIDITY_LIMITS), None);
+/// assert_eq!(range_warning((0, 5), &HUMIDITY_LIMITS), Some(RangeWarning::Narrow));
+/// assert_eq!(range_warning((0, 50), &HUMIDITY_LIMITS), Some(RangeWarning::Extreme));
+/// assert_eq!(range_warning((90, 100), &HUMIDITY_LIMITS), Some(RangeWarning::Extreme));
+/// assert_eq!(range_warning((10, 95), &HUMIDITY_LIMITS), None);
+/// ```
+pub fn range_warning(range: (u8, u8), limits: &RangeLimits) -> Option<RangeWarning> {
+    let (low, high) = (range.0.min(range.1), range.0.max(range.1));
+    if high - low < limits.min_span {
+        Some(RangeWarning::Narrow)
+    } else if low < limits.extremes.0 || high > limits.extremes.1 {
+        Some(RangeWarning::Extreme)
+    } else {
+        None
+    }
+}
+
+/// How the readings of two sensors are combined for control decisions
+///
+/// - **Average**: The mean of both sensors
+/// - **Lowest**: The lowest value of each measurement
+/// - **Highest**: The highest value of each measurement
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SensorCombine {
+    Average,
+    Lowest,
+    Highest,
+}
+
+impl SensorCombine {
+    /// Gets the next way to cycle to
+    ///
+    /// returns the next [SensorCombine]
+    pub fn next(self) -> SensorCombine {
+        match self {
+            SensorCombine::Average => SensorCombine::Lowest,
+            SensorCombine::Lowest => SensorCombine::Highest,
+            SensorCombine::Highest => SensorCombine::Average,
+        }
+    }
+
+    /// Gets the on-screen name of the way
+    ///
+    /// returns the short name
+    pub fn label(self) -> &'static str {
+        match self {
+            SensorCombine::Average => LABELS.average,
+            SensorCombine::Lowest => LABELS.lowest,
+            SensorCombine::Highest => LABELS.highest,
+        }
+    }
+}
+
+/// What the actuators do once the sensors have failed and the reading has gone stale
+///
+/// - **Hold**: The vent, mister and heater stay as they were when the sensors failed
+/// - **SafeState**: The vent goes to [Preferences::safe_vent_open] and the mister and heater stop
+/// - **LastGood**: Control carries on from the last good reading
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FaultPolicy {
+    Hold,
+    SafeState,
+    LastGood,
+}
+
+impl FaultPolicy {
+    /// Gets the warning shown while the policy is being applied
+    ///
+    /// returns the warning
+    pub fn warning(self) -> &'static str {
+        match self {
+            FaultPolicy::Hold => LABELS.fault_hold,
+            FaultPolicy::SafeState => LABELS.fault_safe,
+            FaultPolicy::LastGood => LABELS.fault_last_good,
+        }
+    }
+}
+
+/// How CO2 affects the roof vent.
+/// Venting lets out any CO2 that is being added, so venting for CO2 and CO2 enrichment can't both be used
+///
+/// - **Off**: CO2 is shown, but doesn't move the vent
+/// - **Vent**: The vent opens above [Preferences::co2_vent_ppm] to let in fresh air. See [decide_co2_vent](crate::control::decide_co2_vent)
+/// - **Enrich**: CO2 is being added, so it is kept in: CO2 never opens the vent, and neither do humidity or mildew. Only heat still opens it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Co2Policy {
+    Off,
+    Vent,
+    Enrich,
+}
+
+impl Co2Policy {
+    /// Gets the on-screen name of the policy
+    ///
+    /// returns the name
+    pub fn label(self) -> &'static str {
+        match self {
+            Co2Policy::Off => LABELS.off,
+            Co2Policy::Vent => LABELS.co2_vent,
+            Co2Policy::Enrich => LABELS.co2_enrich,
+        }
+    }
+
+    /// Gets the policy after this one, wrapping back to the first
+    ///
+    /// returns the next [Co2Policy]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Co2Policy;
+    ///
+    /// assert_eq!(Co2Policy::Off.next(), Co2Policy::Vent);
+    /// assert_eq!(Co2Policy::Vent.next(), Co2Policy::Enrich);
+    /// assert_eq!(Co2Policy::Enrich.next(), Co2Policy::Off);
+    /// ```
+    pub fn next(self) -> Co2Policy {
+        match self {
+            Co2Policy::Off => Co2Policy::Vent,
+            Co2Policy::Vent => Co2Policy::Enrich,
+            Co2Policy::Enrich => Co2Policy::Off,
+        }
+    }
+}
+
+/// How the temperature ranges are edited and shown. They are always stored as `(min, max)` ranges
+///
+/// - **Range**: As the lowest and highest temperature
+/// - **Setpoint**: As a target temperature and the swing allowed either side of it. See [Setpoint]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TemperatureModel {
+    Range,
+    Setpoint,
+}
+
+/// A temperature range as a target and the swing allowed either side of it.
+/// The vent opens above `target + swing` and closes below `target - swing`
+///
+/// - **target**: The temperature to keep
+/// - **swing**: How far the temperature may move away from the target either way
+/// - **half**: If the target and swing each have another half degree, for ranges an odd number of degrees wide.
+///   The range then reaches one degree higher, to `target + swing + 1`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Setpoint {
+    pub target: u8,
+    pub swing: u8,
+    pub half: bool,
+}
+
+impl Setpoint {
+    /// Converts a `(min, max)` range into a setpoint in its middle.
+    /// A range an odd number of degrees wide has its middle between two degrees,
+    /// so it is kept as a half degree rather than moving either end of the range
+    ///
+    /// - param range: The `(min, max)` range; an inverted range is swapped
+    ///
+    /// returns the [Setpoint]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Setpoint;
+    ///
+    /// assert_eq!(Setpoint::from_range((60, 80)), Setpoint { target: 70, swing: 10, half: false });
+    /// assert_eq!(Setpoint::from_range((80, 60)), Setpoint { target: 70, swing: 10, half: false });
+    /// assert_eq!(Setpoint::from_range((72, 72)), Setpoint { target: 72, swing: 0, half: false });
+    ///
+    /// // 67.5 +- 7.5 keeps all 15 degrees of the range
+    /// let odd = Setpoint::from_range((60, 75));
+    /// assert_eq!(odd, Setpoint { target: 67, swing: 7, half: true });
+    /// assert_eq!(odd.to_range(), (60, 75));
+    ///
+    /// // Every range comes back unchanged
+    /// for min in 0..=u8::MAX {
+    ///     for max in min..=u8::MAX {
+    ///         assert_eq!(Setpoint::from_range((min, max)).to_range(), (min, max));
+    ///     }
+    /// }
+    /// ```
+    pub fn from_range(range: (u8, u8)) -> Setpoint {
+        let (min, max) = (range.0.min(range.1), range.0.max(range.1));
+        let width = max - min;
+        Setpoint {
+            target: min + width / 2,
+            swing: width / 2,
+            half: width % 2 == 1,
+        }
+    }
+
+    /// Converts the setpoint into a `(min, max)` range
+    ///
+    /// returns the range, cut off at 0 and 255
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Setpoint;
+    ///
+    /// assert_eq!(Setpoint { target: 70, swing: 10, half: false }.to_range(), (60, 80));
+    /// assert_eq!(Setpoint { target: 72, swing: 0, half: false }.to_range(), (72, 72));
+    /// assert_eq!(Setpoint { target: 72, swing: 0, half: true }.to_range(), (72, 73));
+    /// assert_eq!(Setpoint { target: 5, swing: 10, half: false }.to_range(), (0, 15));
+    ///
+    /// // Every setpoint that fits comes back unchanged
+    /// for target in 0..=u8::MAX {
+    ///     for swing in 0..=target.min(u8::MAX - target) {
+    ///         for half in [false, true] {
+    ///             if half && target + swing == u8::MAX {
+    ///                 continue;
+    ///             }
+    ///             let setpoint = Setpoint { target, swing, half };
+    ///             assert_eq!(Setpoint::from_range(setpoint.to_range()), setpoint);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn to_range(self) -> (u8, u8) {
+        (
+            self.target.saturating_sub(self.swing),
+            self.target
+                .saturating_add(self.swing)
+                .saturating_add(self.half as u8),
+        )
+    }
+}
+
+/// How dates are shown on screen
+///
+/// - **Numeric**: `DD/MM/YYYY`
+/// - **Named**: `Mon 01 Jan 2024`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DateFormat {
+    Numeric,
+    Named,
+}
+
+/// Gets the abbreviated name of a month
+///
+/// **NOTE: Months outside of 1 - 12 are clamped into range**
+///
+/// - param month: the month, starting at 1
+///
+/// returns the 3 letter name of the month
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::month_name;
+///
+/// assert_eq!(month_name(1), "Jan");
+/// assert_eq!(month_name(2), "Feb");
+/// assert_eq!(month_name(12), "Dec");
+/// assert_eq!(month_name(0), "Jan");
+/// assert_eq!(month_name(13), "Dec");
+/// ```
+pub fn month_name(month: u8) -> &'static str {
+    LABELS.months[month.clamp(1, 12) as usize - 1]
+}
+
+/// Checks if a time falls inside a watering window, to the second
+///
+/// - param date: the current date and time; only the time of day is used
+/// - param window: the start and end of watering in seconds since midnight. See [Preferences::watering_window]
+///
+/// returns if watering should be on. The start is included and the end is not,
+/// so a window waters for exactly `end - start` seconds. A window that starts after it ends wraps past midnight
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::{is_watering_now, DateTime};
+///
+/// let window = (6 * 3600, 6 * 3600 + 30); // 06:00:00 - 06:00:30
+/// let watering_at = |sec: u8, min: u8| is_watering_now(&DateTime(sec, min, 6, 1, 1, 2000), window);
+///
+/// assert!(!is_watering_now(&DateTime(59, 59, 5, 1, 1, 2000), window));
+/// assert!(watering_at(0, 0));
+/// assert!(watering_at(29, 0)); // The 30th second
+/// assert!(!watering_at(30, 0));
+/// assert!(!watering_at(0, 1));
+///
+/// // An empty window never waters
+/// assert!(!is_watering_now(&DateTime(0, 0, 6, 1, 1, 2000), (21_600, 21_600)));
+///
+/// // 22:00 - 02:00 waters through the night
+/// let night = (22 * 3600, 2 * 3600);
+/// assert!(!is_watering_now(&DateTime(59, 59, 21, 1, 1, 2000), night));
+/// assert!(is_watering_now(&DateTime(0, 0, 22, 1, 1, 2000), night));
+/// assert!(is_watering_now(&DateTime(0, 0, 0, 1, 1, 2000), night));
+/// assert!(is_watering_now(&DateTime(59, 59, 1, 1, 1, 2000), night));
+/// assert!(!is_watering_now(&DateTime(0, 0, 2, 1, 1, 2000), night));
+/// assert!(!is_watering_now(&DateTime(0, 0, 12, 1, 1, 2000), night));
+/// ```
+pub fn is_watering_now(date: &DateTime, window: (u32, u32)) -> bool {
+    let now = date.2 as u32 * 3600 + date.1 as u32 * 60 + date.0 as u32;
+    if window.0 <= window.1 {
+        now >= window.0 && now < window.1
+    } else {
+        // The window wraps past midnight
+        now >= window.0 || now < window.1
+    }
+}
+
+/// The current date and time: Sec, Min, Hour, Day, Month, Year
+///
+/// Days and months start at 1
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DateTime(pub u8, pub u8, pub u8, pub u8, pub u8, pub u16);
+
+/// A time of day: Min, Hour
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeOfDay(pub u8, pub u8);
+
+/// The days of the week, starting on Monday
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Gets the [Weekday] from its index, where Monday is 0
+    ///
+    /// **NOTE: Indexes above 6 wrap around the week**
+    ///
+    /// - param index: the day index
+    ///
+    /// returns the matching [Weekday]
+    pub fn from_index(index: u8) -> Weekday {
+        match index % 7 {
+            0 => Weekday::Monday,
+            1 => Weekday::Tuesday,
+            2 => Weekday::Wednesday,
+            3 => Weekday::Thursday,
+            4 => Weekday::Friday,
+            5 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+
+    /// Gets the bit representing this day in a day-of-week mask
+    ///
+    /// returns the mask bit for this day
+    pub fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Gets the abbreviated name of the day
+    ///
+    /// returns the 3 letter name of the day
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Weekday;
+    ///
+    /// assert_eq!(Weekday::Monday.name(), "Mon");
+    /// assert_eq!(Weekday::Thursday.name(), "Thu");
+    /// assert_eq!(Weekday::Sunday.name(), "Sun");
+    /// ```
+    pub fn name(self) -> &'static str {
+        LABELS.weekdays[self as usize]
+    }
+
+    /// Gets the single-letter abbreviation of the day
+    ///
+    /// returns the first letter of the day's name
+    pub fn initial(self) -> char {
+        match self {
+            Weekday::Monday => 'M',
+            Weekday::Tuesday | Weekday::Thursday => 'T',
+            Weekday::Wednesday => 'W',
+            Weekday::Friday => 'F',
+            Weekday::Saturday | Weekday::Sunday => 'S',
+        }
+    }
+}
+
+impl DateTime {
+    /// Calculates if it is leap year
+    ///
+    /// - param year: The current year
+    ///
+    /// returns if the year is leap year
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// assert!(DateTime::is_leap_year(2024));
+    /// assert!(!DateTime::is_leap_year(2023));
+    /// assert!(!DateTime::is_leap_year(1900)); // Centuries aren't
+    /// assert!(DateTime::is_leap_year(2000)); // Unless they divide by 400
+    /// assert!(DateTime::is_leap_year(0));
+    /// ```
+    pub fn is_leap_year(year: u16) -> bool {
+        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    }
+
+    /// Gets the amount of days in the current month
+    ///
+    /// returns the amount of days in the month
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let days = |month: u8, year: u16| DateTime(0, 0, 0, 1, month, year).days_in_month();
+    /// assert_eq!(days(1, 2023), 31);
+    /// assert_eq!(days(4, 2023), 30);
+    /// assert_eq!(days(2, 2023), 28);
+    /// assert_eq!(days(2, 2024), 29);
+    /// assert_eq!(days(2, 2100), 28);
+    /// assert_eq!(days(2, 2400), 29);
+    /// assert_eq!(days(12, 2023), 31);
+    /// ```
+    pub fn days_in_month(&self) -> u8 {
+        match self.4 {
+            2 => {
+                // Feb
+                if Self::is_leap_year(self.5) {
+                    29
+                } else {
+                    28
+                }
+            }
+            4 | 6 | 9 | 11 => 30, // Apr, Jun, Sep, Nov
+            _ => 31,              // Other months
+        }
+    }
 
-/// Preferences defines the consumer-selected range of acceptable values for each category.
-///
-/// - **temperature**: The acceptable temperature range in Fahrenheit
-/// - **humidity**: The acceptable relative humidity percentage range
-/// - **date**: The current date and time: Sec, Min, Hour, Day, Month, Year
-/// - **watering**: The minute and hour range for when watering should occur
-pub struct Preferences {
-    pub temperature: (u8, u8),
-    pub humidity: (u8, u8),
-    pub date: (u8, u8, u8, u8, u8, u16), // Sec, Min, Hour, Day, Month, Year
-    pub watering: Option<(u8, u8, u8, u8)>, // Start (Min, Hour), End (Min, Hour)
+    /// Checks if every field is within its range and the day exists in the month
+    ///
+    /// returns if the date is valid
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// assert!(DateTime(0, 0, 0, 1, 1, 2000).is_valid());
+    /// assert!(DateTime(59, 59, 23, 29, 2, 2024).is_valid()); // Leap day
+    /// assert!(!DateTime(0, 0, 0, 29, 2, 2023).is_valid());
+    /// assert!(!DateTime(0, 0, 0, 30, 2, 2024).is_valid());
+    /// assert!(!DateTime(0, 0, 0, 31, 4, 2024).is_valid());
+    /// assert!(!DateTime(0, 0, 0, 0, 1, 2024).is_valid());
+    /// assert!(!DateTime(0, 0, 0, 1, 13, 2024).is_valid());
+    /// assert!(!DateTime(60, 0, 0, 1, 1, 2024).is_valid());
+    /// assert!(!DateTime(0, 0, 24, 1, 1, 2024).is_valid());
+    /// assert!(!DateTime(0, 0, 0, 1, 1, 10000).is_valid());
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        self.0 < 60
+            && self.1 < 60
+            && self.2 < 24
+            && (1..=12).contains(&self.4)
+            && self.3 >= 1
+            && self.3 <= self.days_in_month()
+            && self.5 <= MAX_YEAR
+    }
+
+    /// Clamps every field into its range, moving days that don't exist in the month
+    /// back to the month's last day
+    ///
+    /// returns the nearest valid [DateTime]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// assert_eq!(DateTime(0, 0, 0, 30, 2, 2024).normalize(), DateTime(0, 0, 0, 29, 2, 2024));
+    /// assert_eq!(DateTime(0, 0, 0, 29, 2, 2023).normalize(), DateTime(0, 0, 0, 28, 2, 2023));
+    /// assert_eq!(DateTime(0, 0, 0, 31, 6, 2024).normalize(), DateTime(0, 0, 0, 30, 6, 2024));
+    /// assert_eq!(DateTime(75, 61, 30, 0, 0, 12000).normalize(), DateTime(59, 59, 23, 1, 1, 9999));
+    /// assert_eq!(DateTime(0, 0, 0, 40, 14, 2024).normalize(), DateTime(0, 0, 0, 31, 12, 2024));
+    ///
+    /// let valid = DateTime(1, 2, 3, 4, 5, 2006);
+    /// assert_eq!(valid.normalize(), valid);
+    /// ```
+    pub fn normalize(&self) -> DateTime {
+        let mut date = DateTime(
+            self.0.min(59),
+            self.1.min(59),
+            self.2.min(23),
+            self.3,
+            self.4.clamp(1, 12),
+            self.5.min(MAX_YEAR),
+        );
+        date.3 = date.3.clamp(1, date.days_in_month());
+        date
+    }
+
+    /// Calculates the day of the week using Sakamoto's method
+    ///
+    /// returns the [Weekday] of the date
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Weekday};
+    ///
+    /// assert_eq!(DateTime(0, 0, 0, 1, 1, 2000).weekday(), Weekday::Saturday);
+    /// assert_eq!(DateTime(0, 0, 0, 29, 2, 2024).weekday(), Weekday::Thursday); // Leap day
+    /// assert_eq!(DateTime(0, 0, 0, 1, 3, 1900).weekday(), Weekday::Thursday); // 1900 is not a leap year
+    /// assert_eq!(DateTime(0, 0, 0, 31, 12, 2023).weekday(), Weekday::Sunday);
+    /// ```
+    pub fn weekday(&self) -> Weekday {
+        const MONTH_OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let month = self.4.clamp(1, 12);
+        // January and February count as the end of the previous year
+        let year = if month < 3 {
+            self.5.saturating_sub(1)
+        } else {
+            self.5
+        } as u32;
+        let sunday_index = (year + year / 4 - year / 100
+            + year / 400
+            + MONTH_OFFSETS[month as usize - 1]
+            + self.3 as u32)
+            % 7;
+        // Shift so that the week starts on Monday
+        Weekday::from_index(((sunday_index + 6) % 7) as u8)
+    }
+
+    /// Gets the time of day, dropping the seconds
+    ///
+    /// returns the [TimeOfDay] of the date
+    pub fn time_of_day(&self) -> TimeOfDay {
+        TimeOfDay(self.1, self.2)
+    }
+
+    /// Counts the days since a fixed point far enough back that no valid date precedes it
+    ///
+    /// returns the day number
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let day = |day: u8, month: u8, year: u16| DateTime(0, 0, 12, day, month, year).day_number();
+    /// assert_eq!(day(2, 6, 2024), day(1, 6, 2024) + 1);
+    /// assert_eq!(day(1, 3, 2024), day(29, 2, 2024) + 1); // Leap day
+    /// assert_eq!(day(1, 3, 2023), day(28, 2, 2023) + 1);
+    /// assert_eq!(day(1, 1, 2025), day(31, 12, 2024) + 1);
+    /// ```
+    pub fn day_number(&self) -> u32 {
+        let month = self.4.clamp(1, 12) as u32;
+        // Count from March so the leap day is the last day of the year
+        let (year, month) = if month < 3 {
+            (self.5 as u32 + 399, month + 9)
+        } else {
+            (self.5 as u32 + 400, month - 3)
+        };
+        year * 365 + year / 4 - year / 100 + year / 400 + (153 * month + 2) / 5 + self.3 as u32
+    }
+
+    /// Counts the seconds from an earlier date to this one
+    ///
+    /// - param earlier: The earlier [DateTime]
+    ///
+    /// returns the seconds between the dates; 0 if `earlier` is actually later
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    ///
+    /// let start = DateTime(30, 59, 23, 28, 2, 2024);
+    /// assert_eq!(DateTime(45, 59, 23, 28, 2, 2024).seconds_since(&start), 15);
+    /// assert_eq!(DateTime(30, 0, 0, 29, 2, 2024).seconds_since(&start), 60); // Leap day
+    /// assert_eq!(DateTime(30, 59, 23, 1, 3, 2024).seconds_since(&start), 2 * 86_400);
+    /// assert_eq!(DateTime(0, 0, 0, 1, 1, 2025).seconds_since(&DateTime(59, 59, 23, 31, 12, 2024)), 1);
+    /// assert_eq!(DateTime(0, 0, 0, 1, 3, 2023).seconds_since(&DateTime(0, 0, 0, 28, 2, 2023)), 86_400);
+    /// assert_eq!(start.seconds_since(&DateTime(45, 59, 23, 28, 2, 2024)), 0);
+    /// assert_eq!(start.seconds_since(&start), 0);
+    /// ```
+    pub fn seconds_since(&self, earlier: &DateTime) -> u32 {
+        let seconds_of_day =
+            |date: &DateTime| date.2 as i64 * 3600 + date.1 as i64 * 60 + date.0 as i64;
+        let days = self.day_number() as i64 - earlier.day_number() as i64;
+        let seconds = days * 86_400 + seconds_of_day(self) - seconds_of_day(earlier);
+        seconds.clamp(0, u32::MAX as i64) as u32
+    }
+}
+
+impl TimeOfDay {
+    /// Counts the minutes since midnight
+    ///
+    /// returns the minutes since midnight
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::TimeOfDay;
+    ///
+    /// assert_eq!(TimeOfDay(0, 0).minutes(), 0);
+    /// assert_eq!(TimeOfDay(30, 6).minutes(), 390);
+    /// assert_eq!(TimeOfDay(59, 23).minutes(), 1439);
+    /// ```
+    pub fn minutes(self) -> u16 {
+        self.1 as u16 * 60 + self.0 as u16
+    }
+
+    /// Clamps the minute and hour into their ranges
+    ///
+    /// returns the nearest valid [TimeOfDay]
+    pub fn normalize(self) -> TimeOfDay {
+        TimeOfDay(self.0.min(59), self.1.min(23))
+    }
 }
 
 impl Default for Preferences {
     fn default() -> Self {
         Preferences {
-            temperature: (60, 80),       // Ideal range is 60F - 80F
+            temperature: (60, 80),       // Ideal range is 60F - 80F during the day
+            temperature_alarm: (40, 95), // Sound the alarm below 40F or above 95F
+            night_temperature: (55, 75), // And 55F - 75F at night
+            temperature_hysteresis: 2,   // Vent until 78F once above 80F
+            heat_full_below: 5,          // Full heat below 55F, low heat below 60F
+            day_start_hour: 6,           // Day starts at 06:00
+            night_start_hour: 20,        // Night starts at 20:00
             humidity: (60, 70),          // Ideal range is 60% - 70%
-            date: (0, 0, 0, 1, 1, 2000), // Date: 00:00:00 Jan 1 2000
-            watering: None,              // No default watering times set
+            humidity_hysteresis: 3,      // Mist until 63% once below 60%
+            mist_pulse: 5,               // Mist for 5 seconds at a time
+            mist_dwell: 30,              // Then let the reading settle for 30 seconds
+            misting_enabled: true,       // Mist when humidity is out of range
+            watering_enabled: true,      // Water on the schedule
+            daytime_only: false,         // Water whenever the schedule says so
+            // Daytime is 06:00 - 20:00
+            daytime_window: (TimeOfDay(0, 6), TimeOfDay(0, 20)),
+            date: DateTime(0, 0, 0, 1, 1, 2000), // Date: 00:00:00 Jan 1 2000
+            watering: None,                      // No default watering times set
+            watering_days: ALL_DAYS,             // Water every day once a time is set
+            watering_seconds: None,              // Water whole minutes
+            low_voltage: 4000,                   // Warn below 4.0V
+            critical_voltage: 3000,              // Shut down below 3.0V
+            safe_vent_open: false,               // Close the vent when power is failing
+            pressure_unit: PressureUnit::Hpa,    // Display pressure in millibars
+            sea_level_pressure: 1013,            // Standard atmosphere
+            pressure_trend_threshold: 5,         // 0.5 mb per hour
+            temp_offset: 0,                      // Sensors are uncalibrated by default
+            humidity_offset: 0,
+            pressure_offset: 0,
+            gas_baseline: 0, // Calibrated during the first minutes
+            log_interval: 5, // About a week of readings fits in the log
+            auto_rotate: false,
+            rotate_interval: 5,
+            date_format: DateFormat::Numeric,
+            contrast: 50,
+            brightness: 100,
+            sensor_combine: SensorCombine::Average,
+            hidden_screens: 0,
+            stale_factor: 5,        // Flash once 5 readings have been missed
+            warmup_secs: 30,        // Let the gas heater settle before acting on readings
+            rain_closes_vent: true, // Keep the rain out
+            unit_system: UnitSystem::Imperial,
+            temperature_unit: TemperatureUnit::Fahrenheit,
+            active_levels: ActiveLevels::default(), // Relays switch on when their pin is high
+            gas_enabled: true,
+            gas_heater_temp: 320, // Heat the gas sensor to 320C
+            gas_heater_ms: 1500,  // For 1.5 seconds
+            self_test: true,
+            escalation_mins: 60, // Out of range for an hour means the automation can't cope
+            recovery_threshold: 3,
+            smoke_samples: 3, // 150ms of smoke before the sprinklers start
+            vent_curve: [(0, 0), (1, 25), (3, 50), (5, 100)],
+            default_screen: Screen::Temperature,
+            mildew_spread: 4, // Within 4F of the dew point
+            mildew_vent: false,
+            dehumidify_vent: true, // Dry out a humid greenhouse
+            passive_buzzer: false, // Active buzzers sound by themselves
+            buzzer_volume: 100,
+            sensor_timeout_ms: 250,
+            fault_policy: FaultPolicy::SafeState, // Old readings can't be trusted to drive the vent
+            temperature_model: TemperatureModel::Range,
+            expander_pins: [None; EXPANDER_PINS], // No expander fitted
+            expander_address: PCF8574_ADDRESS,
+            // The PCF8574 sinks current far better than it sources it, so its relay boards are active-low
+            expander_active_high: false,
+            co2_policy: Co2Policy::Off, // Most greenhouses have no CO2 sensor
+            co2_vent_ppm: 1500,         // Well above what the plants need, so only a build-up vents
+            gdd_base: 50,               // The usual base for warm-season crops
         }
     }
 }
 
 impl Preferences {
-    /// Increments timer by 1 second
+    /// Increments timer by 1 second.
+    /// The clock stops at the last second of [MAX_YEAR]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// let mut ticked = |date: DateTime| {
+    ///     preferences.date = date;
+    ///     preferences.tick_time();
+    ///     preferences.date
+    /// };
+    ///
+    /// assert_eq!(ticked(DateTime(0, 0, 0, 1, 1, 2000)), DateTime(1, 0, 0, 1, 1, 2000));
+    /// // Seconds, minutes and hours roll over
+    /// assert_eq!(ticked(DateTime(59, 0, 0, 1, 1, 2000)), DateTime(0, 1, 0, 1, 1, 2000));
+    /// assert_eq!(ticked(DateTime(59, 59, 0, 1, 1, 2000)), DateTime(0, 0, 1, 1, 1, 2000));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 1, 1, 2000)), DateTime(0, 0, 0, 2, 1, 2000));
+    /// // Months of each length
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 31, 1, 2023)), DateTime(0, 0, 0, 1, 2, 2023));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 30, 4, 2023)), DateTime(0, 0, 0, 1, 5, 2023));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 30, 1, 2023)), DateTime(0, 0, 0, 31, 1, 2023));
+    /// // February in leap and common years
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 28, 2, 2024)), DateTime(0, 0, 0, 29, 2, 2024));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 29, 2, 2024)), DateTime(0, 0, 0, 1, 3, 2024));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 28, 2, 2023)), DateTime(0, 0, 0, 1, 3, 2023));
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 28, 2, 1900)), DateTime(0, 0, 0, 1, 3, 1900));
+    /// // New year
+    /// assert_eq!(ticked(DateTime(59, 59, 23, 31, 12, 2023)), DateTime(0, 0, 0, 1, 1, 2024));
+    ///
+    /// preferences.date = DateTime(59, 59, 23, 31, 12, 9999);
+    /// preferences.tick_time();
+    /// assert_eq!(preferences.date, DateTime(59, 59, 23, 31, 12, 9999));
+    /// ```
     pub fn tick_time(&mut self) {
+        if self.date == DateTime(59, 59, 23, 31, 12, MAX_YEAR) {
+            return;
+        }
+
         self.date.0 += 1;
 
         // Check for rollovers
@@ -71,30 +1440,46 @@ impl Preferences {
                 self.date.5 += 1;
             }
         }
-
-        // Update the date tuple
-        self.date = (
-            self.date.0,
-            self.date.1,
-            self.date.2,
-            self.date.3,
-            self.date.4,
-            self.date.5,
-        );
     }
 
-    /// Gets the date in the `HH:MM:SS DD/MM/YYYY` format
-    /// Since the indexes start at 0 and months and days start at 1,
-    /// the function ensures that 1 is added
+    /// Gets the date in the `HH:MM:SS DD/MM/YYYY` format.
+    /// Every field is zero-padded to its full width
     ///
     /// returns: `(HH:MM:SS, DD/MM/YYYY)`
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// let (time, date) = preferences.get_date_formatted(); // Midnight
+    /// assert_eq!((time.as_str(), date.as_str()), ("00:00:00", "01/01/2000"));
+    ///
+    /// preferences.date = DateTime(5, 7, 9, 3, 4, 2024);
+    /// let (time, date) = preferences.get_date_formatted();
+    /// assert_eq!((time.as_str(), date.as_str()), ("09:07:05", "03/04/2024"));
+    ///
+    /// preferences.date = DateTime(59, 59, 23, 31, 12, 999);
+    /// let (time, date) = preferences.get_date_formatted();
+    /// assert_eq!((time.as_str(), date.as_str()), ("23:59:59", "31/12/0999"));
+    ///
+    /// // The last date the clock reaches
+    /// preferences.date = DateTime(59, 59, 23, 31, 12, 9999);
+    /// let (time, date) = preferences.get_date_formatted();
+    /// assert_eq!((time.as_str(), date.as_str()), ("23:59:59", "31/12/9999"));
+    ///
+    /// // Fields too wide to show are capped instead of overflowing
+    /// preferences.date = DateTime(u8::MAX, u8::MAX, u8::MAX, u8::MAX, u8::MAX, u16::MAX);
+    /// let (time, date) = preferences.get_date_formatted();
+    /// assert_eq!((time.as_str(), date.as_str()), ("99:99:99", "99/99/9999"));
+    /// ```
     pub fn get_date_formatted(&mut self) -> (String<8>, String<10>) {
         // Format the date as a string
         let mut val1: String<8> = String::new();
         let mut val2: String<10> = String::new();
         // Format time
         uwrite!(
-            &mut val1,
+            Truncating(&mut val1),
             "{}:{}:{}",
             Self::pad_number(self.date.2).as_str(),
             Self::pad_number(self.date.1).as_str(),
@@ -104,48 +1489,160 @@ impl Preferences {
 
         // Format date
         uwrite!(
-            &mut val2,
+            Truncating(&mut val2),
             "{}/{}/{}",
             Self::pad_number(self.date.3).as_str(),
             Self::pad_number(self.date.4).as_str(),
-            self.date.5
+            Self::pad_year(self.date.5).as_str()
         )
         .unwrap();
 
         (val1, val2)
     }
 
+    /// Gets the date in the consumer-selected [DateFormat] for the date screen.
+    /// Every field is zero-padded to its full width
+    ///
+    /// returns: `(HH:MM:SS, DD/MM/YYYY)` or `(HH:MM:SS, Mon 01 Jan 2024)`
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateFormat, DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// let (_, date) = preferences.get_date_display();
+    /// assert_eq!(date.as_str(), "01/01/2000");
+    ///
+    /// preferences.date_format = DateFormat::Named;
+    /// let (time, date) = preferences.get_date_display();
+    /// assert_eq!((time.as_str(), date.as_str()), ("00:00:00", "Sat 01 Jan 2000"));
+    ///
+    /// preferences.date = DateTime(0, 30, 18, 29, 2, 2024); // Leap day
+    /// let (time, date) = preferences.get_date_display();
+    /// assert_eq!((time.as_str(), date.as_str()), ("18:30:00", "Thu 29 Feb 2024"));
+    ///
+    /// preferences.date = DateTime(0, 0, 0, 1, 3, 2023); // The day after February 28th
+    /// assert_eq!(preferences.get_date_display().1.as_str(), "Wed 01 Mar 2023");
+    ///
+    /// preferences.date = DateTime(59, 59, 23, 31, 12, 2023);
+    /// assert_eq!(preferences.get_date_display().1.as_str(), "Sun 31 Dec 2023");
+    ///
+    /// preferences.date = DateTime(59, 59, 23, 31, 12, 9999);
+    /// assert_eq!(preferences.get_date_display().1.as_str(), "Fri 31 Dec 9999");
+    /// ```
+    pub fn get_date_display(&mut self) -> (String<8>, String<16>) {
+        let (time, numeric) = self.get_date_formatted();
+        let mut date: String<16> = String::new();
+        match self.date_format {
+            DateFormat::Numeric => {
+                date.push_str(&numeric).unwrap();
+            }
+            DateFormat::Named => {
+                uwrite!(
+                    Truncating(&mut date),
+                    "{} {} {} {}",
+                    self.date.weekday().name(),
+                    Self::pad_number(self.date.3).as_str(),
+                    month_name(self.date.4),
+                    Self::pad_year(self.date.5).as_str()
+                )
+                .unwrap();
+            }
+        }
+        (time, date)
+    }
+
     /// Pads a number with a zero before it if < 10
     ///
-    /// **NOTE: Only supports values <100**
+    /// **NOTE: Values >99 don't fit in two digits and are shown as 99**
     ///
     /// - param num: number to be padded
     ///
     /// returns: [String] with formatted value
-    fn pad_number(num: u8) -> String<2> {
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// assert_eq!(Preferences::pad_number(0), "00");
+    /// assert_eq!(Preferences::pad_number(7), "07");
+    /// assert_eq!(Preferences::pad_number(42), "42");
+    /// assert_eq!(Preferences::pad_number(150), "99");
+    /// ```
+    pub fn pad_number(num: u8) -> String<2> {
         let mut padded = String::new();
+        let num = num.min(99);
         if num < 10 {
-            uwrite!(padded, "0{}", num).unwrap();
+            uwrite!(Truncating(&mut padded), "0{}", num).unwrap();
         } else {
-            uwrite!(padded, "{}", num).unwrap();
+            uwrite!(Truncating(&mut padded), "{}", num).unwrap();
         }
         padded
     }
 
-    /// Calculates if it is leap year
+    /// Pads a year with zeros to four digits
     ///
-    /// - param year: The current year
+    /// **NOTE: Years >9999 don't fit in four digits and are shown as 9999**
     ///
-    /// returns if the year is leap year
-    fn is_leap_year(year: u16) -> bool {
-        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    /// - param year: year to be padded
+    ///
+    /// returns: [String] with formatted year
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// assert_eq!(Preferences::pad_year(2024), "2024");
+    /// assert_eq!(Preferences::pad_year(999), "0999");
+    /// assert_eq!(Preferences::pad_year(5), "0005");
+    /// assert_eq!(Preferences::pad_year(10000), "9999");
+    /// ```
+    pub fn pad_year(year: u16) -> String<4> {
+        let year = year.min(9999);
+        let mut padded = String::new();
+        uwrite!(
+            Truncating(&mut padded),
+            "{}{}",
+            Self::pad_number((year / 100) as u8).as_str(),
+            Self::pad_number((year % 100) as u8).as_str()
+        )
+        .unwrap();
+        padded
     }
 
-    /// Gets the next index for the current day depending on the month and leap year
+    /// Gets the next day of the month depending on the month and leap year.
+    /// Days start at 1 and wrap around between 1 and the last day of the month
     ///
     /// - param increment: If the values are incrementing (not decrementing)
     ///
-    /// returns the next day's index
+    /// returns the next day of the month
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// let mut days_at = |day: u8, month: u8, year: u16| {
+    ///     preferences.date = DateTime(0, 0, 0, day, month, year);
+    ///     (preferences.change_days(false), preferences.change_days(true))
+    /// };
+    ///
+    /// // 31-day month
+    /// assert_eq!(days_at(1, 1, 2023), (31, 2));
+    /// assert_eq!(days_at(31, 1, 2023), (30, 1));
+    /// // 30-day month
+    /// assert_eq!(days_at(1, 4, 2023), (30, 2));
+    /// assert_eq!(days_at(30, 4, 2023), (29, 1));
+    /// // February in a leap year
+    /// assert_eq!(days_at(1, 2, 2024), (29, 2));
+    /// assert_eq!(days_at(29, 2, 2024), (28, 1));
+    /// // February in a common year, including a century that isn't a leap year
+    /// assert_eq!(days_at(1, 2, 2023), (28, 2));
+    /// assert_eq!(days_at(28, 2, 2023), (27, 1));
+    /// assert_eq!(days_at(28, 2, 1900), (27, 1));
+    /// // February in a century that is a leap year
+    /// assert_eq!(days_at(28, 2, 2000), (27, 29));
+    /// ```
     pub fn change_days(&self, increment: bool) -> u8 {
         let days_in_month: u8 = self.get_days_in_month();
         inclusive_iterator(self.date.3, 1, days_in_month, increment)
@@ -155,44 +1652,167 @@ impl Preferences {
     ///
     /// returns the amount of days in the month
     pub fn get_days_in_month(&self) -> u8 {
-        match self.date.4 {
-            2 => {
-                // Feb
-                if Self::is_leap_year(self.date.5) {
-                    29
-                } else {
-                    28
-                }
-            }
-            4 | 6 | 9 | 11 => 30, // Apr, Jun, Sep, Nov
-            _ => 31,              // Other months
-        }
+        self.date.days_in_month()
     }
 
     /// Checks if it is time to enable the sprinklers
     ///
-    /// returns if the current time is within the watering time.
+    /// returns if the current time is within the watering time on a selected day.
+    /// The hours after midnight of an overnight window belong to the day it started on.
     /// Returns false if there is no watering time set
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences, Weekday};
+    ///
+    /// let mut preferences = Preferences::default(); // Jan 1 2000 was a Saturday
+    /// preferences.watering = Some((30, 6, 15, 7)); // 06:30 - 07:15
+    /// let mut watering_at = |hour: u8, minute: u8| {
+    ///     preferences.date = DateTime(0, minute, hour, 1, 1, 2000);
+    ///     preferences.is_watering_time()
+    /// };
+    ///
+    /// assert!(!watering_at(6, 29));
+    /// assert!(watering_at(6, 30)); // Both ends are included
+    /// assert!(watering_at(7, 0));
+    /// assert!(watering_at(7, 15));
+    /// assert!(!watering_at(7, 16));
+    /// assert!(!watering_at(23, 59));
+    ///
+    /// // Only on the selected days
+    /// preferences.watering_days = Weekday::Sunday.mask();
+    /// preferences.date = DateTime(0, 0, 7, 1, 1, 2000);
+    /// assert!(!preferences.is_watering_time());
+    /// preferences.date = DateTime(0, 0, 7, 2, 1, 2000);
+    /// assert!(preferences.is_watering_time());
+    ///
+    /// // Late in the evening
+    /// preferences.watering = Some((0, 22, 59, 23));
+    /// preferences.date = DateTime(0, 59, 23, 2, 1, 2000);
+    /// assert!(preferences.is_watering_time());
+    ///
+    /// // Overnight, counted from the day it starts
+    /// preferences.watering = Some((0, 22, 0, 2)); // 22:00 - 02:00, Sundays
+    /// preferences.date = DateTime(0, 30, 1, 2, 1, 2000); // 01:30 Sunday, Saturday's window
+    /// assert!(!preferences.is_watering_time());
+    /// preferences.date = DateTime(0, 0, 23, 2, 1, 2000); // 23:00 Sunday
+    /// assert!(preferences.is_watering_time());
+    /// preferences.date = DateTime(0, 30, 1, 3, 1, 2000); // 01:30 Monday, Sunday's window
+    /// assert!(preferences.is_watering_time());
+    ///
+    /// // To the second
+    /// preferences.watering = Some((0, 6, 0, 6));
+    /// preferences.watering_seconds = Some((0, 30)); // 06:00:00 - 06:00:30
+    /// preferences.date = DateTime(29, 0, 6, 2, 1, 2000);
+    /// assert!(preferences.is_watering_time());
+    /// preferences.date = DateTime(30, 0, 6, 2, 1, 2000);
+    /// assert!(!preferences.is_watering_time());
+    ///
+    /// preferences.watering = None;
+    /// assert!(!preferences.is_watering_time());
+    /// ```
     pub fn is_watering_time(&self) -> bool {
-        if let Some(watering_time) = self.watering {
-            let current_minutes: u16 = (self.date.2 * 60 + self.date.1) as u16; // Convert current time to total minutes
-            let start_minutes: u16 = (watering_time.1 * 60 + watering_time.0) as u16; // Convert start time to total minutes
-            let end_minutes: u16 = (watering_time.3 * 60 + watering_time.2) as u16; // Convert end time to total minutes
-
-            current_minutes >= start_minutes && current_minutes <= end_minutes
+        let Some(window) = self.watering_window() else {
+            return false;
+        };
+        let time = self.date.2 as u32 * 3600 + self.date.1 as u32 * 60 + self.date.0 as u32;
+        let day = if window.0 > window.1 && time < window.1 {
+            // Past midnight in an overnight window, which started yesterday
+            Weekday::from_index(self.date.weekday() as u8 + 6)
         } else {
-            false
-        }
+            self.date.weekday()
+        };
+        Self::is_watering_day(self.watering_days, day) && is_watering_now(&self.date, window)
+    }
+
+    /// Gets the watering time to the second, as seconds since midnight
+    ///
+    /// returns the start and end of the watering time, with the end excluded; a start after the end wraps past midnight.
+    /// Without [Preferences::watering_seconds] the window runs to the end of the end minute,
+    /// so minute-only times water exactly as they always have. A minute-only window whose end meets its start,
+    /// such as `07:45 - 07:44`, waters all day and is returned as `(0, 86_400)`. Returns None if there is no watering time set
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.watering = Some((0, 6, 1, 6)); // 06:00 - 06:01
+    /// assert_eq!(preferences.watering_window(), Some((21_600, 21_720))); // Both minutes
+    ///
+    /// // Running to the end of the day wraps to midnight
+    /// preferences.watering = Some((0, 6, 59, 23)); // 06:00 - 23:59
+    /// assert_eq!(preferences.watering_window(), Some((21_600, 0)));
+    /// preferences.date = DateTime(59, 59, 23, 1, 1, 2000);
+    /// assert!(preferences.is_watering_time());
+    /// preferences.date = DateTime(0, 0, 0, 2, 1, 2000);
+    /// assert!(!preferences.is_watering_time());
+    ///
+    /// // Nearly a whole day, ending the minute before it starts, is the whole day
+    /// preferences.watering = Some((45, 7, 44, 7)); // 07:45 - 07:44
+    /// assert_eq!(preferences.watering_window(), Some((0, 86_400)));
+    /// for (sec, min) in [(59, 44), (0, 45), (0, 0)] {
+    ///     preferences.date = DateTime(sec, min, 7, 1, 1, 2000);
+    ///     assert!(preferences.is_watering_time());
+    /// }
+    /// preferences.watering = Some((0, 0, 59, 23)); // 00:00 - 23:59
+    /// assert_eq!(preferences.watering_window(), Some((0, 86_400)));
+    ///
+    /// preferences.watering = Some((0, 6, 1, 6));
+    /// preferences.watering_seconds = Some((0, 30)); // 06:00:00 - 06:01:30
+    /// assert_eq!(preferences.watering_window(), Some((21_600, 21_690)));
+    ///
+    /// preferences.watering = None;
+    /// assert_eq!(preferences.watering_window(), None);
+    /// ```
+    pub fn watering_window(&self) -> Option<(u32, u32)> {
+        const DAY_SECS: u32 = 86_400;
+        let (min_low, hr_low, min_high, hr_high) = self.watering?;
+        let start_minutes = TimeOfDay(min_low, hr_low).minutes() as u32;
+        let end_minutes = TimeOfDay(min_high, hr_high).minutes() as u32;
+        Some(match self.watering_seconds {
+            Some((sec_low, sec_high)) => (
+                start_minutes * 60 + sec_low as u32,
+                end_minutes * 60 + sec_high as u32,
+            ),
+            None => {
+                let (start, end) = (start_minutes * 60, (end_minutes + 1) * 60 % DAY_SECS);
+                // The whole end minute is watered, so meeting the start means no time is left out
+                if start == end {
+                    (0, DAY_SECS)
+                } else {
+                    (start, end)
+                }
+            }
+        })
     }
 
     /// Formats the watering time: `HH:MM - HH:MM`
     ///
     /// Returns a [String] of length 16 containing the formatted times
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    /// use gem_rs::rendering::LABELS;
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.watering = Some((30, 6, 0, 7));
+    /// assert_eq!(preferences.format_watering_time().as_str(), "06:30 - 07:00");
+    /// preferences.watering = Some((59, 23, 59, 23));
+    /// assert_eq!(preferences.format_watering_time().as_str(), "23:59 - 23:59");
+    /// preferences.watering = None;
+    /// assert_eq!(preferences.format_watering_time().as_str(), LABELS.none);
+    ///
+    /// // Times too wide to show are capped instead of overflowing
+    /// preferences.watering = Some((u8::MAX, u8::MAX, u8::MAX, u8::MAX));
+    /// assert_eq!(preferences.format_watering_time().as_str(), "99:99 - 99:99");
+    /// ```
     pub fn format_watering_time(&self) -> String<16> {
         let mut str: String<16> = String::new();
         if let Some(watering_time) = self.watering {
             uwrite!(
-                str,
+                Truncating(&mut str),
                 "{}:{} - {}:{}",
                 Self::pad_number(watering_time.1).as_str(),
                 Self::pad_number(watering_time.0).as_str(),
@@ -201,11 +1821,520 @@ impl Preferences {
             )
             .unwrap();
         } else {
-            uwrite!(str, "None").unwrap();
+            uwrite!(Truncating(&mut str), "{}", LABELS.none).unwrap();
+        }
+        str
+    }
+
+    /// Applies a [UnitSystem], setting every unit at once.
+    /// Setpoints are stored in Fahrenheit, so they are shown converted without being changed
+    ///
+    /// - param system: The [UnitSystem] to apply
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{Preferences, PressureUnit, TemperatureUnit, UnitSystem};
+    ///
+    /// let mut preferences = Preferences::default(); // 60F - 80F
+    ///
+    /// preferences.apply_unit_system(UnitSystem::Metric);
+    /// assert_eq!(preferences.temperature_unit, TemperatureUnit::Celsius);
+    /// assert_eq!(preferences.pressure_unit, PressureUnit::Hpa);
+    /// assert_eq!(preferences.format_temperature(preferences.temperature.0).as_str(), "16C");
+    /// assert_eq!(preferences.format_temperature(preferences.temperature.1).as_str(), "27C");
+    /// assert_eq!(preferences.format_temperature(0).as_str(), "-18C");
+    /// assert_eq!(preferences.format_pressure(1013.25).as_str(), "1013 mb");
+    ///
+    /// preferences.apply_unit_system(UnitSystem::Imperial);
+    /// assert_eq!(preferences.temperature_unit, TemperatureUnit::Fahrenheit);
+    /// assert_eq!(preferences.pressure_unit, PressureUnit::InHg);
+    /// assert_eq!(preferences.format_temperature(preferences.temperature.1).as_str(), "80F");
+    /// assert_eq!(preferences.format_pressure(1013.25).as_str(), "29.92 inHg");
+    ///
+    /// // The stored setpoints never change, so switching back and forth can't drift them
+    /// assert_eq!(preferences.temperature, (60, 80));
+    ///
+    /// // Units can still be changed on their own afterwards
+    /// preferences.pressure_unit = PressureUnit::MmHg;
+    /// assert_eq!(preferences.unit_system, UnitSystem::Imperial);
+    /// ```
+    pub fn apply_unit_system(&mut self, system: UnitSystem) {
+        self.unit_system = system;
+        (self.temperature_unit, self.pressure_unit) = system.units();
+    }
+
+    /// Formats a temperature in the selected [TemperatureUnit]: `72F`, `22C` or `295K`
+    ///
+    /// - param fahrenheit: temperature in whole Fahrenheit
+    ///
+    /// Returns a [String] of length 5 containing the formatted temperature
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{Preferences, TemperatureUnit};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// assert_eq!(preferences.format_temperature(72).as_str(), "72F");
+    /// assert_eq!(preferences.format_temperature(u8::MAX).as_str(), "255F");
+    /// preferences.temperature_unit = TemperatureUnit::Celsius;
+    /// assert_eq!(preferences.format_temperature(u8::MAX).as_str(), "124C");
+    /// assert_eq!(preferences.format_temperature(0).as_str(), "-18C");
+    /// preferences.temperature_unit = TemperatureUnit::Kelvin;
+    /// assert_eq!(preferences.format_temperature(72).as_str(), "295K");
+    /// assert_eq!(preferences.format_temperature(u8::MAX).as_str(), "397K");
+    /// ```
+    pub fn format_temperature(&self, fahrenheit: u8) -> String<5> {
+        let mut str: String<5> = String::new();
+        uwrite!(
+            Truncating(&mut str),
+            "{}{}",
+            self.temperature_unit.from_fahrenheit(fahrenheit),
+            self.temperature_unit.symbol()
+        )
+        .unwrap();
+        str
+    }
+
+    /// Formats a pressure in the selected [PressureUnit]: `1013 mb`, `29.92 inHg` or `760 mmHg`
+    ///
+    /// - param hpa: pressure in millibars/hPa
+    ///
+    /// Returns a [String] of length 10 containing the formatted pressure.
+    /// A pressure too wide to show, such as from a failing sensor, is cut short
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{Preferences, PressureUnit};
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.pressure_unit = PressureUnit::InHg;
+    /// assert_eq!(preferences.format_pressure(1013.25).as_str(), "29.92 inHg");
+    /// assert_eq!(preferences.format_pressure(4000.).as_str(), "118.12 inH");
+    /// preferences.pressure_unit = PressureUnit::MmHg;
+    /// assert_eq!(preferences.format_pressure(f32::MAX).as_str(), "65535 mmHg");
+    /// ```
+    pub fn format_pressure(&self, hpa: f32) -> String<10> {
+        let mut str: String<10> = String::new();
+        match self.pressure_unit {
+            PressureUnit::Hpa => uwrite!(Truncating(&mut str), "{} mb", round_positive(hpa) as u16),
+            PressureUnit::InHg => {
+                let hundredths = round_positive(hpa_to_inhg(hpa) * 100.) as u16;
+                uwrite!(
+                    Truncating(&mut str),
+                    "{}.{} inHg",
+                    hundredths / 100,
+                    Self::pad_number((hundredths % 100) as u8).as_str()
+                )
+            }
+            PressureUnit::MmHg => uwrite!(
+                Truncating(&mut str),
+                "{} mmHg",
+                round_positive(hpa_to_mmhg(hpa)) as u16
+            ),
+        }
+        .unwrap();
+        str
+    }
+
+    /// Checks if a day is selected in a day-of-week mask
+    ///
+    /// - param mask: The day-of-week mask; bit 0 is Monday
+    /// - param day: The [Weekday] to check
+    ///
+    /// returns if watering is allowed on the day
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{Preferences, Weekday, ALL_DAYS};
+    ///
+    /// let weekends = Weekday::Saturday.mask() | Weekday::Sunday.mask();
+    /// assert!(Preferences::is_watering_day(weekends, Weekday::Sunday));
+    /// assert!(!Preferences::is_watering_day(weekends, Weekday::Monday));
+    /// assert!(Preferences::is_watering_day(ALL_DAYS, Weekday::Wednesday));
+    /// ```
+    pub fn is_watering_day(mask: u8, day: Weekday) -> bool {
+        mask & day.mask() != 0
+    }
+
+    /// Formats the watering days as one letter per selected day, Ex: `M-W-F--`
+    ///
+    /// returns a [String] of length 7 with a `-` for each unselected day
+    pub fn format_watering_days(&self) -> String<7> {
+        let mut str: String<7> = String::new();
+        for index in 0..7 {
+            let day = Weekday::from_index(index);
+            if Self::is_watering_day(self.watering_days, day) {
+                str.push(day.initial()).unwrap();
+            } else {
+                str.push('-').unwrap();
+            }
         }
         str
     }
 
+    /// Gets how far open the vent should be from the [Preferences::vent_curve].
+    /// Linearly interpolates between the breakpoints, and holds the first or last
+    /// percentage outside of them
+    ///
+    /// - param delta: How many degrees Fahrenheit the temperature is above the top of the active range;
+    ///   negative when below it
+    ///
+    /// returns the percent the vent should be open, rounded to the nearest percent
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// let preferences = Preferences::default(); // 0F: 0%, 1F: 25%, 3F: 50%, 5F: 100%
+    /// // On the breakpoints
+    /// assert_eq!(preferences.vent_percent_for(0), 0);
+    /// assert_eq!(preferences.vent_percent_for(1), 25);
+    /// assert_eq!(preferences.vent_percent_for(3), 50);
+    /// assert_eq!(preferences.vent_percent_for(5), 100);
+    /// // Between them
+    /// assert_eq!(preferences.vent_percent_for(2), 38);
+    /// assert_eq!(preferences.vent_percent_for(4), 75);
+    /// // Outside of the table
+    /// assert_eq!(preferences.vent_percent_for(-10), 0);
+    /// assert_eq!(preferences.vent_percent_for(50), 100);
+    ///
+    /// // A curve that doesn't start closed holds its first percentage below it
+    /// let mut preferences = Preferences::default();
+    /// preferences.vent_curve = [(2, 10), (4, 20), (6, 30), (8, 40)];
+    /// assert_eq!(preferences.vent_percent_for(0), 10);
+    /// assert_eq!(preferences.vent_percent_for(7), 35);
+    /// ```
+    pub fn vent_percent_for(&self, delta: i16) -> u8 {
+        let curve = &self.vent_curve;
+        let (first, last) = (curve[0], curve[VENT_CURVE_POINTS - 1]);
+        if delta <= first.0 as i16 {
+            return first.1;
+        }
+        if delta >= last.0 as i16 {
+            return last.1;
+        }
+        for pair in curve.windows(2) {
+            let ((low, low_percent), (high, high_percent)) = (pair[0], pair[1]);
+            if delta <= high as i16 {
+                let span = high as i16 - low as i16;
+                let rise = high_percent as i16 - low_percent as i16;
+                let offset = delta - low as i16;
+                return (low_percent as i16 + (rise * offset + span / 2) / span) as u8;
+            }
+        }
+        last.1
+    }
+
+    /// Resets every preference, including the date, back to its default value
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// let mut preferences = Preferences::default();
+    /// preferences.temperature = (40, 90);
+    /// preferences.set_default_watering_time();
+    ///
+    /// preferences.reset_to_defaults();
+    /// assert!(preferences == Preferences::default());
+    /// ```
+    pub fn reset_to_defaults(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Checks if anything worth saving changed since a snapshot was taken.
+    /// The clock ticks on its own, so it only counts once it has been set:
+    /// moved back, or forward by more than the time that passed
+    ///
+    /// - param snapshot: The [Preferences] as they were
+    /// - param elapsed_secs: The seconds since the snapshot was taken
+    ///
+    /// returns true if a setting or the clock was changed
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    ///
+    /// let snapshot = Preferences::default();
+    ///
+    /// // An edit screen left without a change, while the clock kept ticking
+    /// let mut preferences = snapshot.clone();
+    /// for _ in 0..30 {
+    ///     preferences.tick_time();
+    /// }
+    /// assert!(!preferences.differs_from(&snapshot, 30));
+    /// assert!(!preferences.differs_from(&snapshot, 31)); // A tick behind
+    ///
+    /// // Changing a setting
+    /// let mut changed = preferences.clone();
+    /// changed.humidity = (40, 60);
+    /// assert!(changed.differs_from(&snapshot, 30));
+    /// // Changing it back again
+    /// changed.humidity = snapshot.humidity;
+    /// assert!(!changed.differs_from(&snapshot, 30));
+    ///
+    /// // Setting the clock
+    /// let mut set = snapshot.clone();
+    /// set.date = DateTime(0, 30, 7, 4, 7, 2025);
+    /// assert!(set.differs_from(&snapshot, 0));
+    /// set.date = snapshot.date;
+    /// assert!(set.differs_from(&snapshot, 3600)); // Set back to where it was an hour ago
+    /// ```
+    pub fn differs_from(&self, snapshot: &Preferences, elapsed_secs: u32) -> bool {
+        let forward = self.date.seconds_since(&snapshot.date);
+        let backward = snapshot.date.seconds_since(&self.date);
+        let clock_set = backward > 0 || forward.abs_diff(elapsed_secs) > CLOCK_SLACK_SECS;
+        let settings = Preferences {
+            date: self.date,
+            ..snapshot.clone()
+        };
+        clock_set || *self != settings
+    }
+
+    /// Fixes values that the edit screens could never produce,
+    /// such as ones from corrupted flash or a remote edit
+    ///
+    /// - Humidity is clamped to 0% - 100%
+    /// - Inverted temperature and humidity ranges are swapped; a watering time that starts after it ends waters overnight
+    /// - The temperature alarm band is widened to contain both temperature ranges
+    /// - The temperature hysteresis and the full heat margin are limited to 20F, and the day and night start hours to 0 - 23
+    /// - Watering hours and minutes are clamped to 0 - 23 and 0 - 59, and watering seconds to 0 - 59
+    /// - Unused bits of the watering days are cleared
+    /// - The log interval is clamped to 1 - 60 minutes
+    /// - Misting pulses are clamped to 1 - 60 seconds and dwells to 0 - 60 seconds
+    /// - Hidden screens that don't exist are cleared, and every screen is shown if all are hidden
+    /// - The stale factor is clamped to 2 - 60 polling intervals
+    /// - The warm-up is limited to 120 seconds
+    /// - The gas heater is clamped to 200 - 400C and 50 - 1800ms. See [is_valid_gas_heater]
+    /// - The escalation time is limited to 240 minutes
+    /// - The recovery threshold is clamped to 1 - 20 failures
+    /// - The smoke confirmation is clamped to 1 - 20 samples
+    /// - The mildew dew point spread is limited to 20F
+    /// - The buzzer volume is clamped to 10 - 100%
+    /// - The sensor timeout is clamped to 50 - 2000ms
+    /// - The CO2 venting threshold is clamped to 600 - 5000ppm
+    /// - The growing degree day base is clamped to 32 - 86F
+    /// - The low supply voltage is clamped to 1800 - 5500mV, and the critical voltage is kept below it so the warning comes first
+    /// - A vent curve that isn't valid is reset to the default curve. See [is_valid_vent_curve]
+    /// - An expander address no PCF8574 can have is reset to [PCF8574_ADDRESS]
+    /// - Dates that don't exist are reset to the default date
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Output, Preferences, ALL_DAYS};
+    ///
+    /// let fixed = |change: fn(&mut Preferences)| {
+    ///     let mut preferences = Preferences::default();
+    ///     change(&mut preferences);
+    ///     preferences.validate_and_fix();
+    ///     preferences
+    /// };
+    ///
+    /// assert_eq!(fixed(|p| p.humidity = (50, 180)).humidity, (50, 100));
+    /// assert_eq!(fixed(|p| p.humidity = (150, 200)).humidity, (100, 100));
+    /// assert_eq!(fixed(|p| p.humidity = (70, 40)).humidity, (40, 70));
+    /// assert_eq!(fixed(|p| p.temperature = (90, 50)).temperature, (50, 90));
+    /// assert_eq!(fixed(|p| p.temperature = (30, 99)).temperature_alarm, (30, 99));
+    /// assert_eq!(fixed(|p| p.night_temperature = (75, 55)).night_temperature, (55, 75));
+    /// assert_eq!(fixed(|p| p.night_temperature = (20, 70)).temperature_alarm, (20, 95));
+    /// assert_eq!(fixed(|p| p.temperature_hysteresis = 50).temperature_hysteresis, 20);
+    /// assert_eq!(fixed(|p| p.heat_full_below = 50).heat_full_below, 20);
+    /// assert_eq!(fixed(|p| p.night_start_hour = 24).night_start_hour, 23);
+    /// assert_eq!(fixed(|p| p.watering = Some((75, 30, 0, 7))).watering, Some((59, 23, 0, 7)));
+    /// assert_eq!(fixed(|p| p.watering = Some((0, 22, 0, 2))).watering, Some((0, 22, 0, 2)));
+    /// assert_eq!(fixed(|p| p.watering = None).watering, None);
+    /// assert_eq!(fixed(|p| p.watering_days = 0xFF).watering_days, ALL_DAYS);
+    /// assert_eq!(fixed(|p| p.log_interval = 0).log_interval, 1);
+    /// assert_eq!(fixed(|p| p.log_interval = 200).log_interval, 60);
+    /// assert_eq!(fixed(|p| p.mist_pulse = 0).mist_pulse, 1);
+    /// assert_eq!(fixed(|p| p.mist_dwell = 90).mist_dwell, 60);
+    /// assert_eq!(fixed(|p| p.date = DateTime(0, 0, 0, 30, 2, 2024)).date, Preferences::default().date);
+    /// assert_eq!(fixed(|p| p.hidden_screens = u32::MAX).hidden_screens, 0);
+    /// assert_eq!(fixed(|p| p.stale_factor = 0).stale_factor, 2);
+    /// assert_eq!(fixed(|p| p.warmup_secs = 200).warmup_secs, 120);
+    /// assert_eq!(fixed(|p| p.gas_heater_temp = 500).gas_heater_temp, 400);
+    /// assert_eq!(fixed(|p| p.gas_heater_ms = 0).gas_heater_ms, 50);
+    /// assert_eq!(fixed(|p| p.gas_heater_ms = 4000).gas_heater_ms, 1800);
+    /// assert_eq!(fixed(|p| p.escalation_mins = 255).escalation_mins, 240);
+    /// assert_eq!(fixed(|p| p.recovery_threshold = 0).recovery_threshold, 1);
+    /// assert_eq!(fixed(|p| p.recovery_threshold = 50).recovery_threshold, 20);
+    /// assert_eq!(fixed(|p| p.smoke_samples = 0).smoke_samples, 1);
+    /// assert_eq!(fixed(|p| p.smoke_samples = 30).smoke_samples, 20);
+    /// assert_eq!(fixed(|p| p.mildew_spread = 50).mildew_spread, 20);
+    /// assert_eq!(fixed(|p| p.buzzer_volume = 0).buzzer_volume, 10);
+    /// assert_eq!(fixed(|p| p.buzzer_volume = 150).buzzer_volume, 100);
+    /// assert_eq!(fixed(|p| p.sensor_timeout_ms = 0).sensor_timeout_ms, 50);
+    /// assert_eq!(fixed(|p| p.sensor_timeout_ms = 5000).sensor_timeout_ms, 2000);
+    /// assert_eq!(fixed(|p| p.gdd_base = 0).gdd_base, 32);
+    /// assert_eq!(fixed(|p| p.gdd_base = 100).gdd_base, 86);
+    /// assert_eq!(fixed(|p| p.low_voltage = 9000).low_voltage, 5500);
+    /// assert_eq!(fixed(|p| p.low_voltage = 2500).critical_voltage, 2400); // Below 3000mV by default
+    /// assert_eq!(fixed(|p| p.critical_voltage = 4000).critical_voltage, 3900);
+    /// assert_eq!(fixed(|p| p.critical_voltage = 3900).critical_voltage, 3900);
+    /// assert_eq!(fixed(|p| p.vent_curve[3] = (2, 100)).vent_curve, Preferences::default().vent_curve);
+    /// assert_eq!(fixed(|p| p.expander_pins[0] = Some(Output::HeaterLow)).expander_pins[0], Some(Output::HeaterLow));
+    ///
+    /// assert_eq!(fixed(|p| p.date = DateTime(1, 2, 3, 4, 5, 2006)).date, DateTime(1, 2, 3, 4, 5, 2006));
+    /// assert!(fixed(|_| {}) == Preferences::default()); // Valid preferences are left alone
+    /// ```
+    pub fn validate_and_fix(&mut self) {
+        self.humidity = (self.humidity.0.min(100), self.humidity.1.min(100));
+        if self.humidity.0 > self.humidity.1 {
+            self.humidity = (self.humidity.1, self.humidity.0);
+        }
+        if self.temperature.0 > self.temperature.1 {
+            self.temperature = (self.temperature.1, self.temperature.0);
+        }
+        if self.night_temperature.0 > self.night_temperature.1 {
+            self.night_temperature = (self.night_temperature.1, self.night_temperature.0);
+        }
+        self.temperature_hysteresis = self.temperature_hysteresis.min(20);
+        self.heat_full_below = self.heat_full_below.min(MAX_HEAT_FULL_BELOW);
+        self.day_start_hour = self.day_start_hour.min(23);
+        self.night_start_hour = self.night_start_hour.min(23);
+        self.normalize_temperature_alarm();
+        self.normalize_watering();
+        self.watering_days &= ALL_DAYS;
+        self.daytime_window = (
+            self.daytime_window.0.normalize(),
+            self.daytime_window.1.normalize(),
+        );
+        self.humidity_hysteresis = self.humidity_hysteresis.min(20);
+        self.mist_pulse = self.mist_pulse.clamp(1, 60);
+        self.mist_dwell = self.mist_dwell.min(60);
+        self.log_interval = self.log_interval.clamp(1, 60);
+        self.rotate_interval = self.rotate_interval.clamp(1, 60);
+        self.contrast = self.contrast.clamp(MIN_CONTRAST, 100);
+        self.brightness = self.brightness.min(100);
+        self.hidden_screens &= Screen::ALL_HIDDEN;
+        if self.hidden_screens == Screen::ALL_HIDDEN {
+            self.hidden_screens = 0;
+        }
+        self.stale_factor = self.stale_factor.clamp(2, 60);
+        self.warmup_secs = self.warmup_secs.min(MAX_WARMUP_SECS);
+        self.gas_heater_temp = self.gas_heater_temp.clamp(MIN_HEATER_TEMP, MAX_HEATER_TEMP);
+        self.gas_heater_ms = self.gas_heater_ms.clamp(MIN_HEATER_MS, MAX_HEATER_MS);
+        self.escalation_mins = self.escalation_mins.min(MAX_ESCALATION_MINS);
+        self.recovery_threshold = self.recovery_threshold.clamp(1, MAX_RECOVERY_THRESHOLD);
+        self.smoke_samples = self.smoke_samples.clamp(1, MAX_SMOKE_SAMPLES);
+        self.mildew_spread = self.mildew_spread.min(MAX_MILDEW_SPREAD);
+        self.buzzer_volume = self.buzzer_volume.clamp(MIN_BUZZER_VOLUME, 100);
+        self.sensor_timeout_ms = self
+            .sensor_timeout_ms
+            .clamp(MIN_SENSOR_TIMEOUT_MS, MAX_SENSOR_TIMEOUT_MS);
+        self.co2_vent_ppm = self.co2_vent_ppm.clamp(MIN_CO2_VENT_PPM, MAX_CO2_VENT_PPM);
+        self.gdd_base = self.gdd_base.clamp(MIN_GDD_BASE, MAX_GDD_BASE);
+        self.low_voltage = self.low_voltage.clamp(MIN_LOW_VOLTAGE, MAX_LOW_VOLTAGE);
+        self.critical_voltage = self.critical_voltage.min(self.low_voltage - VOLTAGE_STEP);
+        if !is_valid_expander_address(self.expander_address) {
+            self.expander_address = PCF8574_ADDRESS;
+        }
+        if !is_valid_vent_curve(&self.vent_curve) {
+            self.vent_curve = Self::default().vent_curve;
+        }
+        if !self.date.is_valid() {
+            self.date = Self::default().date;
+        }
+    }
+
+    /// Fixes the watering time so every field is in range.
+    /// Hours, minutes and seconds are clamped to 0 - 23, 0 - 59 and 0 - 59. A start after the end
+    /// is kept as an overnight window, so `22:00 - 02:00` waters for 4 hours across midnight.
+    /// Only seconds out of order within the same minute are swapped
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// let normalized = |watering| {
+    ///     let mut preferences = Preferences::default();
+    ///     preferences.watering = watering;
+    ///     preferences.normalize_watering();
+    ///     preferences.watering
+    /// };
+    ///
+    /// // (start minute, start hour, end minute, end hour)
+    /// assert_eq!(normalized(Some((0, 22, 0, 2))), Some((0, 22, 0, 2))); // Overnight
+    /// assert_eq!(normalized(Some((45, 7, 15, 7))), Some((45, 7, 15, 7))); // Nearly a whole day
+    /// assert_eq!(normalized(Some((30, 7, 30, 7))), Some((30, 7, 30, 7))); // Equal times
+    /// assert_eq!(normalized(Some((0, 6, 30, 8))), Some((0, 6, 30, 8))); // Already valid
+    /// assert_eq!(normalized(Some((75, 30, 0, 7))), Some((59, 23, 0, 7))); // Out of range
+    /// assert_eq!(normalized(None), None);
+    ///
+    /// // Seconds are clamped, and swapped only within the same minute
+    /// let mut preferences = Preferences::default();
+    /// preferences.watering = Some((0, 6, 0, 6));
+    /// preferences.watering_seconds = Some((45, 90));
+    /// preferences.normalize_watering();
+    /// assert_eq!(preferences.watering_seconds, Some((45, 59)));
+    /// preferences.watering_seconds = Some((45, 15));
+    /// preferences.normalize_watering();
+    /// assert_eq!(preferences.watering_seconds, Some((15, 45)));
+    /// preferences.watering = Some((0, 22, 0, 2));
+    /// preferences.watering_seconds = Some((45, 15));
+    /// preferences.normalize_watering();
+    /// assert_eq!(preferences.watering_seconds, Some((45, 15)));
+    /// ```
+    pub fn normalize_watering(&mut self) {
+        if let Some((min_low, hr_low, min_high, hr_high)) = self.watering {
+            let start = TimeOfDay(min_low, hr_low).normalize();
+            let end = TimeOfDay(min_high, hr_high).normalize();
+            let seconds = self.watering_seconds.map(|(sec_low, sec_high)| {
+                let (sec_low, sec_high) = (sec_low.min(59), sec_high.min(59));
+                if start == end && sec_low > sec_high {
+                    // Out of order within one minute, not a window across midnight
+                    (sec_high, sec_low)
+                } else {
+                    (sec_low, sec_high)
+                }
+            });
+            self.watering = Some((start.0, start.1, end.0, end.1));
+            self.watering_seconds = seconds;
+        }
+    }
+
+    /// Fixes the temperature alarm band so it is at least as wide as the day and night temperature ranges.
+    /// An inverted band is swapped, then each bound is moved out to the temperature ranges
+    /// if it sits inside them, so the alarm never sounds while the vent can still correct the temperature
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    ///
+    /// let normalized = |temperature, temperature_alarm| {
+    ///     let mut preferences = Preferences {
+    ///         temperature,
+    ///         temperature_alarm,
+    ///         night_temperature: temperature,
+    ///         ..Preferences::default()
+    ///     };
+    ///     preferences.normalize_temperature_alarm();
+    ///     preferences.temperature_alarm
+    /// };
+    ///
+    /// assert_eq!(normalized((60, 80), (40, 95)), (40, 95)); // Already wider
+    /// assert_eq!(normalized((60, 80), (60, 80)), (60, 80)); // The same width is allowed
+    /// assert_eq!(normalized((60, 80), (65, 95)), (60, 95)); // The low bound is inside
+    /// assert_eq!(normalized((60, 80), (40, 75)), (40, 80)); // The high bound is inside
+    /// assert_eq!(normalized((60, 80), (70, 72)), (60, 80)); // Both are inside
+    /// assert_eq!(normalized((60, 80), (95, 40)), (40, 95)); // Inverted
+    ///
+    /// // The night range counts too
+    /// let mut preferences = Preferences::default();
+    /// preferences.temperature_alarm = (60, 80);
+    /// preferences.night_temperature = (50, 70);
+    /// preferences.normalize_temperature_alarm();
+    /// assert_eq!(preferences.temperature_alarm, (50, 80));
+    /// ```
+    pub fn normalize_temperature_alarm(&mut self) {
+        let (low, high) = self.temperature_alarm;
+        let (low, high) = if low > high { (high, low) } else { (low, high) };
+        self.temperature_alarm = (
+            low.min(self.temperature.0).min(self.night_temperature.0),
+            high.max(self.temperature.1).max(self.night_temperature.1),
+        );
+    }
+
     /// Sets the watering time from `00:00 to 01:00`
     pub fn set_default_watering_time(&mut self) {
         self.watering = Some((0, 0, 0, 1));
@@ -234,15 +2363,52 @@ impl Preferences {
 ///  );
 /// ```
 pub fn inclusive_iterator(current_val: u8, min_val: u8, max_val: u8, increment: bool) -> u8 {
-    if increment {
-        if current_val == max_val {
-            min_val
-        } else {
-            current_val + 1
-        }
-    } else if current_val == min_val {
-        max_val
-    } else {
-        current_val - 1
+    inclusive_iterator_step(current_val, min_val, max_val, 1, increment)
+}
+
+/// Increments or decrements by a step through a list of integers.
+/// Steps that pass a bound wrap around to the other end of the list,
+/// as if the values were on a clock face.
+/// A current value outside the list is clamped into it first.
+/// A minimum above the maximum is an empty list, so the current value is kept.
+///
+/// - param current_val: the current value
+/// - param min_val: the minimum included value
+/// - param max_val: the maximum included value
+/// - param step: how far to move
+/// - param increment: whether to iterate forwards
+///
+/// returns the integer `step` places away in the sequence
+///
+/// ## Example:
+/// ```rust
+///  use gem_rs::preferences::inclusive_iterator_step;
+///
+///  assert_eq!(inclusive_iterator_step(50, 0, 59, 5, true), 55);
+///  assert_eq!(inclusive_iterator_step(58, 0, 59, 5, true), 3);   // 58 -> 59 -> 0 -> 1 -> 2 -> 3
+///  assert_eq!(inclusive_iterator_step(2, 0, 59, 5, false), 57);  // 2 -> 1 -> 0 -> 59 -> 58 -> 57
+///  assert_eq!(inclusive_iterator_step(3, 1, 12, 27, true), 6);   // Steps larger than the list wrap fully
+///  assert_eq!(inclusive_iterator_step(3, 1, 12, 27, false), 12);
+///  assert_eq!(inclusive_iterator_step(7, 10, 5, 1, true), 7);      // Nothing to step through
+///  assert_eq!(inclusive_iterator_step(7, 10, 5, 1, false), 7);
+/// ```
+pub fn inclusive_iterator_step(
+    current_val: u8,
+    min_val: u8,
+    max_val: u8,
+    step: u8,
+    increment: bool,
+) -> u8 {
+    if min_val > max_val {
+        return current_val;
     }
+    let span: u16 = (max_val - min_val) as u16 + 1;
+    let offset: u16 = (current_val.clamp(min_val, max_val) - min_val) as u16;
+    let step: u16 = step as u16 % span;
+    let next: u16 = if increment {
+        (offset + step) % span
+    } else {
+        (offset + span - step) % span
+    };
+    min_val + next as u8
 }
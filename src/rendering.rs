@@ -1,18 +1,45 @@
-use crate::preferences::{inclusive_iterator, Preferences};
+use crate::control::BandPosition;
+#[cfg(feature = "hardware")]
+use crate::control::{HoldConfirm, HoldStatus, CONFIRM_HOLD_MS};
+#[cfg(feature = "hardware")]
+use crate::preferences::{inclusive_iterator, Preferences, RangeWarning, Weekday};
+#[cfg(feature = "hardware")]
+use crate::timer::TICK_TIME_DELAY;
+#[cfg(feature = "hardware")]
+use crate::watchdog::feed_watchdog;
+#[cfg(feature = "hardware")]
+use core::cell::RefCell;
+use core::convert::Infallible;
+#[cfg(feature = "hardware")]
+use critical_section::Mutex;
+#[cfg(feature = "hardware")]
 use embedded_hal::delay::DelayNs;
+#[cfg(feature = "hardware")]
 use embedded_hal::digital::InputPin;
+#[cfg(feature = "hardware")]
+use embedded_hal::pwm::SetDutyCycle;
+#[cfg(feature = "hardware")]
 use hd44780_driver::bus::FourBitBus;
+#[cfg(feature = "hardware")]
 use hd44780_driver::charset::{CharsetUniversal, EmptyFallback};
+#[cfg(feature = "hardware")]
 use hd44780_driver::memory_map::StandardMemoryMap;
-use hd44780_driver::HD44780;
-use heapless::String;
-use rp_pico::hal::gpio::bank0::{Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio2, Gpio3, Gpio4, Gpio5};
-use rp_pico::hal::gpio::{FunctionSio, Pin, PullDown, SioInput, SioOutput};
+#[cfg(feature = "hardware")]
+use hd44780_driver::{CharacterDefinition, HD44780};
+use heapless::{String, Vec};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::bank0::{
+    Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio18, Gpio19, Gpio2, Gpio3, Gpio4, Gpio5,
+};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::{FunctionNull, FunctionSio, Pin, PullDown, SioInput, SioOutput};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::pwm::{FreeRunning, Pwm1, Slice};
+#[cfg(feature = "hardware")]
 use rp_pico::hal::Timer;
-use ufmt::uwrite;
-
-use panic_probe as _;
+use ufmt::{uWrite, uwrite};
 
+#[cfg(feature = "hardware")]
 pub type Lcd = HD44780<
     FourBitBus<
         Pin<Gpio0, FunctionSio<SioOutput>, PullDown>,
@@ -26,22 +53,935 @@ pub type Lcd = HD44780<
     EmptyFallback<CharsetUniversal>,
 >;
 
+/// The amount of characters that fit on a line of the [Lcd]
+pub const LCD_WIDTH: usize = 16;
+/// The lowest contrast setting; any lower and the text can't be seen to raise it again
+pub const MIN_CONTRAST: u8 = 20;
+
+/// Writes into a [String], dropping whatever doesn't fit.
+/// Used for everything shown on the [Lcd], so a value wider than expected is cut short
+/// instead of panicking. Its error is [Infallible], so unwrapping a write can't panic
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::Truncating;
+/// use heapless::String;
+/// use ufmt::uwrite;
+///
+/// let mut text: String<8> = String::new();
+/// uwrite!(Truncating(&mut text), "{}: {}%", "Humidity", 100u8).unwrap();
+/// assert_eq!(text.as_str(), "Humidity");
+///
+/// let mut text: String<8> = String::new();
+/// uwrite!(Truncating(&mut text), "{}: {}%", "Hum", 100u8).unwrap();
+/// assert_eq!(text.as_str(), "Hum: 100");
+///
+/// // Writes past the end are dropped too
+/// uwrite!(Truncating(&mut text), "!").unwrap();
+/// assert_eq!(text.as_str(), "Hum: 100");
+/// ```
+pub struct Truncating<'a, const N: usize>(pub &'a mut String<N>);
+
+impl<const N: usize> uWrite for Truncating<'_, N> {
+    type Error = Infallible;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Infallible> {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maps a 0 - 100 percent setting onto a PWM duty cycle
+///
+/// **NOTE: Settings above 100 are treated as 100**
+///
+/// - param percent: The setting in percent
+/// - param max_duty: The duty cycle that keeps the output high
+///
+/// returns the duty cycle
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::duty_from_percent;
+///
+/// assert_eq!(duty_from_percent(0, 65535), 0);
+/// assert_eq!(duty_from_percent(50, 65535), 32767);
+/// assert_eq!(duty_from_percent(100, 65535), 65535);
+/// assert_eq!(duty_from_percent(250, 65535), 65535);
+/// assert_eq!(duty_from_percent(100, 1000), 1000);
+/// ```
+pub fn duty_from_percent(percent: u8, max_duty: u16) -> u16 {
+    (percent.min(100) as u32 * max_duty as u32 / 100) as u16
+}
+
+/// Maps the contrast setting onto the PWM duty cycle for the [Lcd]'s V0 pin.
+/// V0 is filtered into a voltage, and the lower it is the darker the text,
+/// so the duty cycle falls as the contrast rises
+///
+/// **NOTE: Settings are clamped between [MIN_CONTRAST] and 100**
+///
+/// - param contrast: The contrast in percent
+/// - param max_duty: The duty cycle that keeps the output high
+///
+/// returns the duty cycle
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::{contrast_duty, MIN_CONTRAST};
+///
+/// assert_eq!(contrast_duty(100, 65535), 0);
+/// assert_eq!(contrast_duty(50, 1000), 500);
+/// assert_eq!(contrast_duty(MIN_CONTRAST, 1000), 800);
+/// assert_eq!(contrast_duty(0, 1000), 800);
+/// assert_eq!(contrast_duty(200, 1000), 0);
+/// ```
+pub fn contrast_duty(contrast: u8, max_duty: u16) -> u16 {
+    duty_from_percent(100 - contrast.clamp(MIN_CONTRAST, 100), max_duty)
+}
+
+/// Drives the [Lcd]'s contrast (V0) and backlight from PWM slice 1.
+/// GPIO18 drives V0 through an RC filter and GPIO19 drives the backlight's transistor
+///
+/// - **slice**: The PWM slice; channel A is the contrast and channel B is the backlight
+#[cfg(feature = "hardware")]
+pub struct DisplayPwm {
+    slice: Slice<Pwm1, FreeRunning>,
+}
+
+#[cfg(feature = "hardware")]
+impl DisplayPwm {
+    /// Creates a new instance of DisplayPwm
+    ///
+    /// - param slice: PWM slice 1
+    /// - param contrast_pin: GPIO18, wired to V0
+    /// - param backlight_pin: GPIO19, wired to the backlight
+    ///
+    /// returns a new instance of DisplayPwm
+    pub fn new(
+        mut slice: Slice<Pwm1, FreeRunning>,
+        contrast_pin: Pin<Gpio18, FunctionNull, PullDown>,
+        backlight_pin: Pin<Gpio19, FunctionNull, PullDown>,
+    ) -> DisplayPwm {
+        slice.enable();
+        slice.channel_a.output_to(contrast_pin);
+        slice.channel_b.output_to(backlight_pin);
+        DisplayPwm { slice }
+    }
+
+    /// Sets the contrast and brightness from the [Preferences]
+    ///
+    /// - param preferences: [Preferences] instance
+    pub fn apply(&mut self, preferences: &Preferences) {
+        let contrast = &mut self.slice.channel_a;
+        let duty = contrast_duty(preferences.contrast, contrast.max_duty_cycle());
+        contrast.set_duty_cycle(duty).unwrap();
+        let backlight = &mut self.slice.channel_b;
+        let duty = duty_from_percent(preferences.brightness, backlight.max_duty_cycle());
+        backlight.set_duty_cycle(duty).unwrap();
+    }
+}
+
+/// Every user-facing string shown on the [Lcd].
+/// Labels are plain ASCII, since the [Lcd] has no accented characters.
+///
+/// Short labels are put in front of values, such as `Temp: 72F`, and must stay
+/// within 4 characters; date units must stay within 10 and event kinds within 12. `priming`,
+/// `contrast` and `brightness` must leave room for their values, and day and month names are
+/// 3 letters; everything else must fit on a line.
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::{Labels, ENGLISH, GERMAN, LCD_WIDTH, SPANISH};
+///
+/// for labels in [&ENGLISH, &SPANISH, &GERMAN] {
+///     for label in labels.all() {
+///         assert!(label.is_ascii(), "{label} is not ASCII");
+///         assert!(label.len() <= LCD_WIDTH, "{label} is too wide");
+///     }
+///     for label in labels.short() {
+///         assert!(label.len() <= 4, "{label} is too wide to put in front of a value");
+///     }
+///     for label in labels.date_units() {
+///         assert!(label.len() <= 10, "{label} is too wide to put in front of a date");
+///     }
+///     assert!(labels.priming.len() + ": 10s".len() <= LCD_WIDTH);
+///     assert!(labels.contrast.len() + ": 100%".len() <= LCD_WIDTH);
+///     assert!(labels.brightness.len() + ": 100%".len() <= LCD_WIDTH);
+///     assert!(labels.mist_pulse.len() + ": 60".len() <= LCD_WIDTH);
+///     assert!(labels.mist_dwell.len() + ": 60".len() <= LCD_WIDTH);
+///     assert!(labels.heater.len() + ": 400C".len() <= LCD_WIDTH);
+///     assert!(labels.duration.len() + ": 1800ms".len() <= LCD_WIDTH);
+///     assert!(labels.outputs.len() + " FAIL".len() <= LCD_WIDTH);
+///     assert!(labels.sensor.len() + " FAIL".len() <= LCD_WIDTH);
+///     assert!(labels.smoke.len() + " FAIL".len() <= LCD_WIDTH);
+///     assert!(labels.co2_vent.len() + " >5000ppm".len() <= LCD_WIDTH);
+///     for label in [labels.off, labels.co2_vent, labels.co2_enrich] {
+///         assert!(labels.co2.len() + ": ".len() + label.len() <= LCD_WIDTH);
+///     }
+///     for label in [labels.on, labels.off] {
+///         assert!(labels.gas.len() + ": ".len() + label.len() <= LCD_WIDTH);
+///     }
+///     for label in [labels.average, labels.lowest, labels.highest] {
+///         assert!(label.len() <= 4, "{label} is too wide to put after both readings");
+///     }
+///     // Stats periods sit at the end of the humidity range
+///     for label in [labels.today, labels.all_time] {
+///         assert!(label.len() <= 3, "{label} is too wide to put after the humidity range");
+///     }
+///     // Range warnings sit between the edit cursors behind a `!`
+///     for label in [labels.range_narrow, labels.range_extreme] {
+///         assert!(label.len() <= 12, "{label} is too wide to fit between the cursors");
+///     }
+///     for label in labels.event_kinds() {
+///         assert!(label.len() <= 12, "{label} is too wide to put after an event number");
+///     }
+///     // Names are abbreviated to 3 letters so named dates line up
+///     for name in labels.weekdays.iter().chain(labels.months.iter()) {
+///         assert!(name.is_ascii() && name.len() == 3, "{name} is not a 3 letter name");
+///     }
+/// }
+/// ```
+pub struct Labels {
+    pub temperature: &'static str,
+    pub humidity: &'static str,
+    pub pressure: &'static str,
+    pub calibration: &'static str,
+    pub supply: &'static str,
+    pub minimum: &'static str,
+    pub alarm: &'static str,
+    pub day_band: &'static str,
+    pub night_band: &'static str,
+    pub gas: &'static str,
+    pub air_quality: &'static str,
+    pub altitude: &'static str,
+    pub sea_level: &'static str,
+    pub days: &'static str,
+    pub minute: &'static str,
+    pub hour: &'static str,
+    pub day: &'static str,
+    pub month: &'static str,
+    pub year: &'static str,
+    pub none: &'static str,
+    pub fire: &'static str,
+    pub low_voltage: &'static str,
+    pub temperature_alarm: &'static str,
+    pub low_power: &'static str,
+    pub contrast: &'static str,
+    pub brightness: &'static str,
+    pub average: &'static str,
+    pub lowest: &'static str,
+    pub highest: &'static str,
+    pub sensor_degraded: &'static str,
+    pub warming_up: &'static str,
+    pub rain: &'static str,
+    pub imperial: &'static str,
+    pub metric: &'static str,
+    pub mist_pulse: &'static str,
+    pub mist_dwell: &'static str,
+    pub range_narrow: &'static str,
+    pub range_extreme: &'static str,
+    pub today: &'static str,
+    pub all_time: &'static str,
+    pub events: &'static str,
+    pub no_events: &'static str,
+    pub alarm_on: &'static str,
+    pub alarm_off: &'static str,
+    pub sensor_fault: &'static str,
+    pub power_warning: &'static str,
+    pub watering_start: &'static str,
+    pub watering_stop: &'static str,
+    pub prime: &'static str,
+    pub prime_prompt: &'static str,
+    pub priming: &'static str,
+    pub watering_held: &'static str,
+    pub factory_reset: &'static str,
+    pub calibrating: &'static str,
+    pub reset_prompt: &'static str,
+    pub confirm_prompt: &'static str,
+    pub confirm_hint: &'static str,
+    pub hold_hint: &'static str,
+    pub remove_prompt: &'static str,
+    pub on: &'static str,
+    pub off: &'static str,
+    pub heater: &'static str,
+    pub duration: &'static str,
+    pub self_test: &'static str,
+    pub self_test_failed: &'static str,
+    pub outputs: &'static str,
+    pub sensor: &'static str,
+    pub smoke: &'static str,
+    pub escalated: &'static str,
+    pub mildew: &'static str,
+    pub test_alarm: &'static str,
+    pub test_alarm_prompt: &'static str,
+    pub fault_hold: &'static str,
+    pub fault_safe: &'static str,
+    pub fault_last_good: &'static str,
+    pub swing: &'static str,
+    pub rising: &'static str,
+    pub steady: &'static str,
+    pub falling: &'static str,
+    pub co2: &'static str,
+    pub co2_vent: &'static str,
+    pub co2_enrich: &'static str,
+    pub gdd: &'static str,
+    pub weekdays: [&'static str; 7],
+    pub months: [&'static str; 12],
+}
+
+impl Labels {
+    /// Gets the labels put in front of values
+    ///
+    /// returns the short labels
+    pub fn short(&self) -> [&'static str; 16] {
+        [
+            self.temperature,
+            self.humidity,
+            self.pressure,
+            self.calibration,
+            self.supply,
+            self.minimum,
+            self.alarm,
+            self.day_band,
+            self.night_band,
+            self.gas,
+            self.air_quality,
+            self.altitude,
+            self.sea_level,
+            self.days,
+            self.co2,
+            self.gdd,
+        ]
+    }
+
+    /// Gets the labels put in front of date units
+    ///
+    /// returns the date unit labels
+    pub fn date_units(&self) -> [&'static str; 5] {
+        [self.minute, self.hour, self.day, self.month, self.year]
+    }
+
+    /// Gets the labels for each kind of event, which are put after the event's number
+    ///
+    /// returns the event kind labels
+    pub fn event_kinds(&self) -> [&'static str; 6] {
+        [
+            self.alarm_on,
+            self.alarm_off,
+            self.sensor_fault,
+            self.power_warning,
+            self.watering_start,
+            self.watering_stop,
+        ]
+    }
+
+    /// Gets every label
+    ///
+    /// returns all the labels
+    pub fn all(&self) -> [&'static str; 83] {
+        [
+            self.temperature,
+            self.humidity,
+            self.pressure,
+            self.calibration,
+            self.supply,
+            self.minimum,
+            self.alarm,
+            self.day_band,
+            self.night_band,
+            self.gas,
+            self.air_quality,
+            self.altitude,
+            self.sea_level,
+            self.days,
+            self.minute,
+            self.hour,
+            self.day,
+            self.month,
+            self.year,
+            self.none,
+            self.fire,
+            self.low_voltage,
+            self.temperature_alarm,
+            self.low_power,
+            self.contrast,
+            self.brightness,
+            self.average,
+            self.lowest,
+            self.highest,
+            self.sensor_degraded,
+            self.warming_up,
+            self.rain,
+            self.imperial,
+            self.metric,
+            self.mist_pulse,
+            self.mist_dwell,
+            self.range_narrow,
+            self.range_extreme,
+            self.today,
+            self.all_time,
+            self.events,
+            self.no_events,
+            self.alarm_on,
+            self.alarm_off,
+            self.sensor_fault,
+            self.power_warning,
+            self.watering_start,
+            self.watering_stop,
+            self.prime,
+            self.prime_prompt,
+            self.priming,
+            self.watering_held,
+            self.factory_reset,
+            self.calibrating,
+            self.reset_prompt,
+            self.confirm_prompt,
+            self.confirm_hint,
+            self.hold_hint,
+            self.remove_prompt,
+            self.on,
+            self.off,
+            self.heater,
+            self.duration,
+            self.self_test,
+            self.self_test_failed,
+            self.outputs,
+            self.sensor,
+            self.smoke,
+            self.escalated,
+            self.mildew,
+            self.test_alarm,
+            self.test_alarm_prompt,
+            self.fault_hold,
+            self.fault_safe,
+            self.fault_last_good,
+            self.swing,
+            self.rising,
+            self.steady,
+            self.falling,
+            self.co2,
+            self.co2_vent,
+            self.co2_enrich,
+            self.gdd,
+        ]
+    }
+}
+
+/// English labels; used unless another `lang-*` feature is enabled
+pub const ENGLISH: Labels = Labels {
+    temperature: "Temp",
+    humidity: "RH",
+    pressure: "PRS",
+    calibration: "Cal",
+    supply: "VSYS",
+    minimum: "Min",
+    alarm: "Alrm",
+    day_band: "Day",
+    night_band: "Nite",
+    gas: "Gas",
+    air_quality: "AQI",
+    altitude: "ALT",
+    sea_level: "SL",
+    days: "Days",
+    minute: "Minute",
+    hour: "Hour",
+    day: "Day",
+    month: "Month",
+    year: "Year",
+    none: "None",
+    fire: "Fire Present",
+    low_voltage: "Low Voltage",
+    temperature_alarm: "Temp Alarm",
+    low_power: "LOW POWER",
+    contrast: "Contrast",
+    brightness: "Light",
+    average: "Avg",
+    lowest: "Low",
+    highest: "High",
+    sensor_degraded: "ONE SENSOR DOWN",
+    warming_up: "WARMING UP",
+    rain: "RAIN",
+    imperial: "Imperial",
+    metric: "Metric",
+    mist_pulse: "Pulse secs",
+    mist_dwell: "Dwell secs",
+    range_narrow: "Too narrow",
+    range_extreme: "Extreme",
+    today: "Day",
+    all_time: "All",
+    events: "Event History",
+    no_events: "No Events",
+    alarm_on: "Fire Alarm",
+    alarm_off: "Fire Cleared",
+    sensor_fault: "Sensor Fault",
+    power_warning: "Low Voltage",
+    watering_start: "Water On",
+    watering_stop: "Water Off",
+    prime: "Test Sprinklers",
+    prime_prompt: "Run 10s test?",
+    priming: "Priming",
+    watering_held: "Night: no water",
+    factory_reset: "Factory Reset",
+    calibrating: "Calibrating",
+    reset_prompt: "Reset settings?",
+    confirm_prompt: "Are you sure?",
+    confirm_hint: "SEL=Yes UP=No",
+    hold_hint: "Hold SELECT",
+    remove_prompt: "Remove window?",
+    on: "On",
+    off: "Off",
+    heater: "Heater",
+    duration: "Duration",
+    self_test: "Self test",
+    self_test_failed: "SELF TEST FAILED",
+    outputs: "Outputs",
+    sensor: "Sensor",
+    smoke: "Smoke",
+    escalated: "!OUT OF RANGE!",
+    mildew: "MILDEW RISK",
+    test_alarm: "TEST ALARM",
+    test_alarm_prompt: "Test fire alarm?",
+    fault_hold: "FAULT: HOLDING",
+    fault_safe: "FAULT: SAFE",
+    fault_last_good: "FAULT: LAST DATA",
+    swing: "Swing",
+    rising: "^ Rising",
+    steady: "- Steady",
+    falling: "v Falling",
+    co2: "CO2",
+    co2_vent: "Vent",
+    co2_enrich: "Enrich",
+    gdd: "GDD",
+    weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+};
+
+/// Spanish labels; enabled with the `lang-es` feature
+pub const SPANISH: Labels = Labels {
+    temperature: "Temp",
+    humidity: "HR",
+    pressure: "PRS",
+    calibration: "Cal",
+    supply: "VSYS",
+    minimum: "Min",
+    alarm: "Alrm",
+    day_band: "Dia",
+    night_band: "Noc",
+    gas: "Gas",
+    air_quality: "ICA",
+    altitude: "ALT",
+    sea_level: "NM",
+    days: "Dias",
+    minute: "Minuto",
+    hour: "Hora",
+    day: "Dia",
+    month: "Mes",
+    year: "Anio",
+    none: "Ninguno",
+    fire: "Hay Fuego",
+    low_voltage: "Voltaje Bajo",
+    temperature_alarm: "Alarma Temp",
+    low_power: "SIN ENERGIA",
+    contrast: "Contraste",
+    brightness: "Luz",
+    average: "Prom",
+    lowest: "Min",
+    highest: "Max",
+    sensor_degraded: "SENSOR CAIDO",
+    warming_up: "CALENTANDO",
+    rain: "LLUVIA",
+    imperial: "Imperial",
+    metric: "Metrico",
+    mist_pulse: "Pulso seg",
+    mist_dwell: "Pausa seg",
+    range_narrow: "Muy estrecho",
+    range_extreme: "Extremo",
+    today: "Hoy",
+    all_time: "Tot",
+    events: "Historial",
+    no_events: "Sin Eventos",
+    alarm_on: "Alarma Fuego",
+    alarm_off: "Sin Fuego",
+    sensor_fault: "Fallo Sensor",
+    power_warning: "Voltaje Bajo",
+    watering_start: "Riego Inicio",
+    watering_stop: "Riego Fin",
+    prime: "Probar Riego",
+    prime_prompt: "Probar 10s?",
+    priming: "Probando",
+    watering_held: "Noche: sin riego",
+    factory_reset: "Restablecer",
+    calibrating: "Calibrando",
+    reset_prompt: "Restablecer?",
+    confirm_prompt: "Esta seguro?",
+    confirm_hint: "SEL=Si UP=No",
+    hold_hint: "Mantener SELECT",
+    remove_prompt: "Quitar horario?",
+    on: "Encendido",
+    off: "Apagado",
+    heater: "Calentador",
+    duration: "Duracion",
+    self_test: "Autoprueba",
+    self_test_failed: "AUTOPRUEBA FALLO",
+    outputs: "Salidas",
+    sensor: "Sensor",
+    smoke: "Humo",
+    escalated: "!FUERA DE RANGO!",
+    mildew: "RIESGO DE MOHO",
+    test_alarm: "PRUEBA ALARMA",
+    test_alarm_prompt: "Probar alarma?",
+    fault_hold: "FALLO: MANTENER",
+    fault_safe: "FALLO: SEGURO",
+    fault_last_good: "FALLO: ULT DATO",
+    swing: "Margen",
+    rising: "^ Subiendo",
+    steady: "- Estable",
+    falling: "v Bajando",
+    co2: "CO2",
+    co2_vent: "Airear",
+    co2_enrich: "Enriquecer",
+    gdd: "GDC",
+    weekdays: ["Lun", "Mar", "Mie", "Jue", "Vie", "Sab", "Dom"],
+    months: [
+        "Ene", "Feb", "Mar", "Abr", "May", "Jun", "Jul", "Ago", "Sep", "Oct", "Nov", "Dic",
+    ],
+};
+
+/// German labels; enabled with the `lang-de` feature
+pub const GERMAN: Labels = Labels {
+    temperature: "Temp",
+    humidity: "RF",
+    pressure: "LD",
+    calibration: "Kal",
+    supply: "VSYS",
+    minimum: "Min",
+    alarm: "Alrm",
+    day_band: "Tag",
+    night_band: "Ncht",
+    gas: "Gas",
+    air_quality: "LQI",
+    altitude: "HOEH",
+    sea_level: "NN",
+    days: "Tage",
+    minute: "Minute",
+    hour: "Stunde",
+    day: "Tag",
+    month: "Monat",
+    year: "Jahr",
+    none: "Keine",
+    fire: "Feuer Erkannt",
+    low_voltage: "Unterspannung",
+    temperature_alarm: "Temp Alarm",
+    low_power: "STROM KRITISCH",
+    contrast: "Kontrast",
+    brightness: "Licht",
+    average: "Mitt",
+    lowest: "Min",
+    highest: "Max",
+    sensor_degraded: "SENSOR GESTOERT",
+    warming_up: "AUFWAERMEN",
+    rain: "REGEN",
+    imperial: "Imperial",
+    metric: "Metrisch",
+    mist_pulse: "Puls Sek",
+    mist_dwell: "Pause Sek",
+    range_narrow: "Zu eng",
+    range_extreme: "Extrem",
+    today: "Tag",
+    all_time: "Ges",
+    events: "Ereignisse",
+    no_events: "Keine Events",
+    alarm_on: "Feueralarm",
+    alarm_off: "Feuer Aus",
+    sensor_fault: "Sensorfehler",
+    power_warning: "Spg. niedrig",
+    watering_start: "Giessen An",
+    watering_stop: "Giessen Aus",
+    prime: "Beregnungstest",
+    prime_prompt: "10s testen?",
+    priming: "Test",
+    watering_held: "Nachts: Pause",
+    factory_reset: "Werkseinstellung",
+    calibrating: "Kalibriere",
+    reset_prompt: "Zuruecksetzen?",
+    confirm_prompt: "Sind Sie sicher?",
+    confirm_hint: "SEL=Ja UP=Nein",
+    hold_hint: "SELECT halten",
+    remove_prompt: "Zeit loeschen?",
+    on: "An",
+    off: "Aus",
+    heater: "Heizer",
+    duration: "Dauer",
+    self_test: "Selbsttest",
+    self_test_failed: "TEST FEHLERHAFT",
+    outputs: "Ausgaenge",
+    sensor: "Sensor",
+    smoke: "Rauch",
+    escalated: "!AUSSER BEREICH!",
+    mildew: "SCHIMMELGEFAHR",
+    test_alarm: "ALARMTEST",
+    test_alarm_prompt: "Alarm testen?",
+    fault_hold: "FEHLER: HALTEN",
+    fault_safe: "FEHLER: SICHER",
+    fault_last_good: "FEHLER: ALTWERT",
+    swing: "Spanne",
+    rising: "^ Steigend",
+    steady: "- Stabil",
+    falling: "v Fallend",
+    co2: "CO2",
+    co2_vent: "Lueften",
+    co2_enrich: "Anreichern",
+    gdd: "GDD",
+    weekdays: ["Mon", "Die", "Mit", "Don", "Fre", "Sam", "Son"],
+    months: [
+        "Jan", "Feb", "Mar", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+};
+
+/// The labels for the language selected by the `lang-*` features
+#[cfg(feature = "lang-de")]
+pub const LABELS: &Labels = &GERMAN;
+/// The labels for the language selected by the `lang-*` features
+#[cfg(all(feature = "lang-es", not(feature = "lang-de")))]
+pub const LABELS: &Labels = &SPANISH;
+/// The labels for the language selected by the `lang-*` features
+#[cfg(not(any(feature = "lang-es", feature = "lang-de")))]
+pub const LABELS: &Labels = &ENGLISH;
+
+/// The character positions of a line that have to be written to turn one text into another.
+/// Both are padded with spaces to [LCD_WIDTH]; anything past it isn't shown
+///
+/// - param old: The text shown now; empty when unknown, so every position is written
+/// - param new: The text to show
+///
+/// returns the `(column, character)` writes, in column order
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::line_diff;
+///
+/// // Only the changed digit is written
+/// let writes = line_diff("Temp: 72F       ", "Temp: 73F");
+/// assert_eq!(writes.as_slice(), &[(7, '3')]);
+/// // A shorter line blanks what is left of the longer one
+/// let writes = line_diff("Temp: 100F      ", "Temp: 99F");
+/// assert_eq!(writes.as_slice(), &[(6, '9'), (7, '9'), (8, 'F'), (9, ' ')]);
+/// // A longer line fills in the padding
+/// let writes = line_diff("Hum: 5%         ", "Hum: 55%");
+/// assert_eq!(writes.as_slice(), &[(6, '5'), (7, '%')]);
+/// // Nothing changed
+/// assert!(line_diff("Rain            ", "Rain").is_empty());
+/// // An unknown line is written in full
+/// assert_eq!(line_diff("", "Rain").len(), 16);
+/// ```
+pub fn line_diff(old: &str, new: &str) -> Vec<(u8, char), LCD_WIDTH> {
+    let mut old = old.chars();
+    let mut new = new.chars();
+    let mut writes = Vec::new();
+    for column in 0..LCD_WIDTH as u8 {
+        let shown = old.next();
+        let wanted = new.next().unwrap_or(' ');
+        if shown != Some(wanted) {
+            writes.push((column, wanted)).unwrap();
+        }
+    }
+    writes
+}
+
+/// The amount of lines on the [Lcd]
+pub const LCD_ROWS: usize = 2;
+
+/// A copy of what each line of the [Lcd] shows, so [render_screen] only writes what changed
+///
+/// - **lines**: The top and bottom line, padded to [LCD_WIDTH]; empty when unknown,
+///   such as after something was drawn over it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShadowBuffer {
+    lines: [String<LCD_WIDTH>; LCD_ROWS],
+}
+
+impl ShadowBuffer {
+    /// Creates a buffer that knows neither line
+    ///
+    /// returns the new [ShadowBuffer]
+    pub const fn new() -> ShadowBuffer {
+        ShadowBuffer {
+            lines: [String::new(), String::new()],
+        }
+    }
+
+    /// Records the text about to be shown on a line. See [line_diff]
+    ///
+    /// - param top_line: if the text goes on the top line
+    /// - param line: The text
+    ///
+    /// returns the `(column, character)` writes needed to show it
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::rendering::ShadowBuffer;
+    ///
+    /// let mut buffer = ShadowBuffer::new();
+    /// assert_eq!(buffer.update(true, "Temp: 72F").len(), 16);
+    /// assert!(buffer.update(true, "Temp: 72F").is_empty()); // Unchanged
+    /// assert_eq!(buffer.update(true, "Temp: 73F").as_slice(), &[(7, '3')]);
+    /// assert_eq!(buffer.update(false, "Temp: 73F").len(), 16); // Each line is tracked on its own
+    ///
+    /// buffer.invalidate(true);
+    /// assert_eq!(buffer.update(true, "Temp: 73F").len(), 16); // Drawn over, so written in full
+    /// ```
+    pub fn update(&mut self, top_line: bool, line: &str) -> Vec<(u8, char), LCD_WIDTH> {
+        let shown = &mut self.lines[if top_line { 0 } else { 1 }];
+        let writes = line_diff(shown, line);
+        shown.clear();
+        for character in line.chars().chain(core::iter::repeat(' ')).take(LCD_WIDTH) {
+            // Characters wider than a byte can run out of room; they are written again next time
+            if shown.push(character).is_err() {
+                shown.clear();
+                break;
+            }
+        }
+        writes
+    }
+
+    /// Records text drawn over part of a line without going through [ShadowBuffer::update],
+    /// so the next update knows to write over it
+    ///
+    /// - param top_line: if the text was drawn on the top line
+    /// - param column: The column the text starts at
+    /// - param text: The text drawn
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::rendering::ShadowBuffer;
+    ///
+    /// let mut buffer = ShadowBuffer::new();
+    /// buffer.update(false, "(60F, 80F)");
+    /// buffer.overlay(false, 13, " 5m");
+    /// // The age is cleared when the line no longer has it
+    /// assert_eq!(buffer.update(false, "(60F, 80F)").as_slice(), &[(14, ' '), (15, ' ')]);
+    /// ```
+    pub fn overlay(&mut self, top_line: bool, column: u8, text: &str) {
+        let shown = &mut self.lines[if top_line { 0 } else { 1 }];
+        if shown.is_empty() {
+            return;
+        }
+        let mut text = text.chars();
+        let mut line: String<LCD_WIDTH> = String::new();
+        for (index, character) in shown.chars().enumerate() {
+            let character = if index >= column as usize {
+                text.next().unwrap_or(character)
+            } else {
+                character
+            };
+            if line.push(character).is_err() {
+                line.clear();
+                break;
+            }
+        }
+        *shown = line;
+    }
+
+    /// Forgets what a line shows, so it is written in full next time
+    ///
+    /// - param top_line: if the top line is forgotten
+    pub fn invalidate(&mut self, top_line: bool) {
+        self.lines[if top_line { 0 } else { 1 }].clear();
+    }
+}
+
+impl Default for ShadowBuffer {
+    fn default() -> Self {
+        ShadowBuffer::new()
+    }
+}
+
+/// What [render_screen] last wrote to the [Lcd]
+#[cfg(feature = "hardware")]
+static SHADOW: Mutex<RefCell<ShadowBuffer>> = Mutex::new(RefCell::new(ShadowBuffer::new()));
+
+/// Forgets what a line of the [Lcd] shows. Called by anything that draws over a line
+/// without going through [render_screen]
+///
+/// - param top_line: if the top line is forgotten
+#[cfg(feature = "hardware")]
+fn invalidate_line(top_line: bool) {
+    critical_section::with(|cs| SHADOW.borrow_ref_mut(cs).invalidate(top_line));
+}
+
+/// Draws text over part of a line of the [Lcd], recording it so [render_screen] can write over it later
+///
+/// - param text: text to draw
+/// - param top_line: if the text goes on the top line
+/// - param column: The column the text starts at
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+fn draw_over(text: &str, top_line: bool, column: u8, lcd: &mut Lcd, delay: &mut Timer) {
+    critical_section::with(|cs| SHADOW.borrow_ref_mut(cs).overlay(top_line, column, text));
+    lcd.set_cursor_xy((column, if top_line { 0 } else { 1 }), delay)
+        .unwrap();
+    lcd.write_str(text, delay).unwrap();
+}
+
+/// Clears the [Lcd] and forgets what both lines showed, so they are written in full again
+///
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn clear_display(lcd: &mut Lcd, delay: &mut Timer) {
+    lcd.clear(delay).unwrap();
+    invalidate_line(true);
+    invalidate_line(false);
+}
+
+/// Writes characters to a line of the [Lcd], only moving the cursor past the ones left alone
+///
+/// - param writes: The `(column, character)` writes; see [line_diff]
+/// - param top_line: if the top line is written to
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+fn write_changes(writes: &[(u8, char)], top_line: bool, lcd: &mut Lcd, delay: &mut Timer) {
+    let row = if top_line { 0 } else { 1 };
+    let mut cursor = None;
+    for &(column, character) in writes {
+        if cursor != Some(column) {
+            lcd.set_cursor_xy((column, row), delay).unwrap();
+        }
+        lcd.write_str(character.encode_utf8(&mut [0; 4]), delay)
+            .unwrap();
+        cursor = Some(column + 1);
+    }
+}
+
 /// Basic function for rendering text onto the LCD.
-/// It only clears the screen when the top line is written to
+/// Only the characters that changed are written, which keeps the screen from flickering.
+/// A new top line blanks the bottom line, as the screen it belonged to is gone
 ///
 /// - param line: text to render
 /// - param top_line: if the top line is to be written to
 /// - param lcd: [Lcd] instance
+#[cfg(feature = "hardware")]
 pub fn render_screen(line: &str, top_line: bool, lcd: &mut Lcd, delay: &mut Timer) {
-    // Set cursor to the correct line
-    if top_line {
-        // Reset screen
-        lcd.clear(delay).unwrap();
-        lcd.set_cursor_pos(0, delay).unwrap();
-    } else {
-        lcd.set_cursor_xy((0, 1), delay).unwrap();
-    }
-    lcd.write_str(line, delay).unwrap();
+    let (writes, bottom_writes) = critical_section::with(|cs| {
+        let mut shadow = SHADOW.borrow_ref_mut(cs);
+        let writes = shadow.update(top_line, line);
+        let bottom_writes = if top_line && !writes.is_empty() {
+            shadow.update(false, "")
+        } else {
+            Vec::new()
+        };
+        (writes, bottom_writes)
+    });
+    write_changes(&writes, top_line, lcd, delay);
+    write_changes(&bottom_writes, false, lcd, delay);
 }
 
 /// Renders the Preferences on screen with a `^` cursor
@@ -50,14 +990,14 @@ pub fn render_screen(line: &str, top_line: bool, lcd: &mut Lcd, delay: &mut Time
 /// - param left_cursor: If the lower bound is selected
 /// - param lcd: [Lcd] instance
 /// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
 pub fn render_edit_screen<const N: usize>(
     line: &String<N>,
     left_cursor: bool,
     lcd: &mut Lcd,
     delay: &mut Timer,
 ) {
-    // Clear
-    lcd.clear(delay).unwrap();
+    clear_display(lcd, delay);
 
     // Write top info
     lcd.set_cursor_pos(0, delay).unwrap();
@@ -72,20 +1012,31 @@ pub fn render_edit_screen<const N: usize>(
     }
 }
 
+/// Renders a [RangeWarning] on the bottom line, between the cursors of [render_edit_screen]
+///
+/// - param warning: The [RangeWarning] to show
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn render_range_warning(warning: RangeWarning, lcd: &mut Lcd, delay: &mut Timer) {
+    draw_over("!", false, 2, lcd, delay);
+    draw_over(warning.label(), false, 3, lcd, delay);
+}
+
 /// Renders the Preferences watering editing screen with a `^` cursor
 ///
 /// - param line: The preferences line
 /// - param index: If index of the element being edited
 /// - param lcd: [Lcd] instance
 /// - param delay: Timer instance
+#[cfg(feature = "hardware")]
 pub fn render_watering_edit_screen<const N: usize>(
     line: &String<N>,
     index: i32,
     lcd: &mut Lcd,
     delay: &mut Timer,
 ) {
-    // Clear
-    lcd.clear(delay).unwrap();
+    clear_display(lcd, delay);
 
     // Write top info
     lcd.set_cursor_pos(0, delay).unwrap();
@@ -115,9 +1066,9 @@ pub fn render_watering_edit_screen<const N: usize>(
 ///
 /// - param line: The date line
 /// - param lcd: [Lcd] instance
+#[cfg(feature = "hardware")]
 pub fn render_date_edit_screen<const N: usize>(line: &String<N>, lcd: &mut Lcd, delay: &mut Timer) {
-    // Clear
-    lcd.clear(delay).unwrap();
+    clear_display(lcd, delay);
 
     // Write date segment
     lcd.set_cursor_pos(0, delay).unwrap();
@@ -132,12 +1083,221 @@ pub fn render_date_edit_screen<const N: usize>(line: &String<N>, lcd: &mut Lcd,
 /// - param active: whether to add a `^`
 /// - param bottom_pos: the x-coordinate on the bottom row
 /// - param lcd: [Lcd] instance
+#[cfg(feature = "hardware")]
 pub fn render_selector(active: bool, bottom_pos: u8, lcd: &mut Lcd, delay: &mut Timer) {
-    lcd.set_cursor_xy((bottom_pos, 1), delay).unwrap();
-    if active {
-        lcd.write_str("^", delay).unwrap();
+    draw_over(
+        if active { "^" } else { " " },
+        false,
+        bottom_pos,
+        lcd,
+        delay,
+    );
+}
+
+/// Renders a frame of the measurement indicator in the top right corner.
+/// The top line is written again once the measurement is read, which clears it
+///
+/// - param frame: The frame to show; see [crate::timer::PROGRESS_FRAMES]
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn render_progress(frame: &str, lcd: &mut Lcd, delay: &mut Timer) {
+    draw_over(frame, true, LCD_WIDTH as u8 - 1, lcd, delay);
+}
+
+/// The custom glyphs loaded into the [Lcd]'s CGRAM by [load_glyphs], in slot order.
+/// Each row is 5 pixels wide, with the top row first
+#[cfg(feature = "hardware")]
+const GLYPHS: [[u8; 8]; 3] = [
+    // Up arrow
+    [
+        0b00100, 0b01110, 0b10101, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000,
+    ],
+    // Down arrow
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b10101, 0b01110, 0b00100, 0b00000,
+    ],
+    // Check mark
+    [
+        0b00000, 0b00001, 0b00011, 0b10110, 0b11100, 0b01000, 0b00000, 0b00000,
+    ],
+];
+
+/// Loads the custom [GLYPHS] into the [Lcd]'s CGRAM; must be called once after the [Lcd] is set up
+///
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn load_glyphs(lcd: &mut Lcd, delay: &mut Timer) {
+    for (slot, glyph) in GLYPHS.iter().enumerate() {
+        let mut pattern = [0; 10];
+        pattern[..glyph.len()].copy_from_slice(glyph);
+        lcd.define_custom_character(
+            slot as u8,
+            &CharacterDefinition { pattern, cursor: 0 },
+            delay,
+        )
+        .unwrap();
+    }
+}
+
+/// Gets the custom glyph shown for a [BandPosition]
+///
+/// - param position: The [BandPosition] of a reading
+///
+/// returns the CGRAM slot of the glyph; see [GLYPHS]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::BandPosition;
+/// use gem_rs::rendering::band_glyph;
+///
+/// assert_eq!(band_glyph(BandPosition::Above), 0); // Up arrow
+/// assert_eq!(band_glyph(BandPosition::Below), 1); // Down arrow
+/// assert_eq!(band_glyph(BandPosition::Within), 2); // Check mark
+/// ```
+pub fn band_glyph(position: BandPosition) -> u8 {
+    match position {
+        BandPosition::Above => 0,
+        BandPosition::Below => 1,
+        BandPosition::Within => 2,
+    }
+}
+
+/// Renders the glyph of a [BandPosition] on the top line, right after the reading. See [band_glyph]
+///
+/// - param position: The [BandPosition] of the reading
+/// - param column: The column after the reading; nothing is drawn past the edge of the [Lcd]
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn render_band_icon(position: BandPosition, column: usize, lcd: &mut Lcd, delay: &mut Timer) {
+    if column >= LCD_WIDTH {
+        return;
+    }
+    let glyph = char::from(band_glyph(position));
+    draw_over(
+        glyph.encode_utf8(&mut [0; 4]),
+        true,
+        column as u8,
+        lcd,
+        delay,
+    );
+}
+
+/// Formats the age of a reading in its largest whole unit, such as `45s`, `12m`, `3h` or `9d`
+///
+/// - param age_secs: The seconds since the reading was taken
+///
+/// returns the formatted age; at most 3 characters, so ages past 99 days show as `99d`
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::format_age;
+///
+/// assert_eq!(format_age(0).as_str(), "0s");
+/// assert_eq!(format_age(59).as_str(), "59s");
+/// assert_eq!(format_age(60).as_str(), "1m");
+/// assert_eq!(format_age(3599).as_str(), "59m");
+/// assert_eq!(format_age(3600).as_str(), "1h");
+/// assert_eq!(format_age(86_399).as_str(), "23h");
+/// assert_eq!(format_age(86_400).as_str(), "1d");
+/// assert_eq!(format_age(u32::MAX).as_str(), "99d");
+/// ```
+pub fn format_age(age_secs: u32) -> String<3> {
+    let mut age: String<3> = String::new();
+    match age_secs {
+        0..=59 => uwrite!(Truncating(&mut age), "{}s", age_secs).unwrap(),
+        60..=3599 => uwrite!(Truncating(&mut age), "{}m", age_secs / 60).unwrap(),
+        3600..=86_399 => uwrite!(Truncating(&mut age), "{}h", age_secs / 3600).unwrap(),
+        _ => uwrite!(Truncating(&mut age), "{}d", (age_secs / 86_400).min(99)).unwrap(),
+    }
+    age
+}
+
+/// Renders the age of the readings in the bottom right corner. See [format_age]
+///
+/// - param age_secs: The seconds since the reading was taken
+/// - param visible: if the age is shown; cleared to flash stale readings
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn render_age(age_secs: u32, visible: bool, lcd: &mut Lcd, delay: &mut Timer) {
+    let age = format_age(age_secs);
+    let mut text: String<3> = String::new();
+    for _ in age.len()..3 {
+        text.push(' ').unwrap();
+    }
+    if visible {
+        text.push_str(&age).unwrap();
     } else {
-        lcd.write_str(" ", delay).unwrap();
+        text.push_str(&"   "[..age.len()]).unwrap();
+    }
+    draw_over(&text, false, LCD_WIDTH as u8 - 3, lcd, delay);
+}
+
+/// The [Lcd]'s built-in full block character
+#[cfg(feature = "hardware")]
+const BAR_BLOCK: u8 = 0xFF;
+
+/// Maps a value onto the amount of filled columns of a bar spanning the [Lcd]
+///
+/// **NOTE: Values outside of the range clamp to an empty or full bar**
+///
+/// - param value: The value to show
+/// - param min: The value of an empty bar
+/// - param max: The value of a full bar
+///
+/// returns the filled columns, from 0 to [LCD_WIDTH]; rounded to the nearest column.
+/// An empty range is full once the value reaches it
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::rendering::bar_columns;
+///
+/// assert_eq!(bar_columns(60, 60, 80), 0);
+/// assert_eq!(bar_columns(70, 60, 80), 8);
+/// assert_eq!(bar_columns(80, 60, 80), 16);
+/// assert_eq!(bar_columns(65, 60, 80), 4);
+/// assert_eq!(bar_columns(61, 60, 80), 1); // 0.8 columns rounds up
+/// // Out of range values clamp
+/// assert_eq!(bar_columns(40, 60, 80), 0);
+/// assert_eq!(bar_columns(95, 60, 80), 16);
+/// // An empty range is either empty or full
+/// assert_eq!(bar_columns(69, 70, 70), 0);
+/// assert_eq!(bar_columns(70, 70, 70), 16);
+/// assert_eq!(bar_columns(75, 80, 60), 16);
+/// ```
+pub fn bar_columns(value: u8, min: u8, max: u8) -> u8 {
+    if max <= min {
+        return if value >= min { LCD_WIDTH as u8 } else { 0 };
+    }
+    let value = value.clamp(min, max);
+    let span = (max - min) as u16;
+    (((value - min) as u16 * LCD_WIDTH as u16 + span / 2) / span) as u8
+}
+
+/// Renders a bar across a line of the [Lcd] showing where a value sits within a range.
+/// See [bar_columns]
+///
+/// - param top_line: if the bar goes on the top line
+/// - param value: The value to show
+/// - param min: The value of an empty bar
+/// - param max: The value of a full bar
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn render_bar(top_line: bool, value: u8, min: u8, max: u8, lcd: &mut Lcd, delay: &mut Timer) {
+    let filled = bar_columns(value, min, max);
+    invalidate_line(top_line);
+    lcd.set_cursor_xy((0, if top_line { 0 } else { 1 }), delay)
+        .unwrap();
+    for column in 0..LCD_WIDTH as u8 {
+        if column < filled {
+            lcd.write_byte(BAR_BLOCK, delay).unwrap();
+        } else {
+            lcd.write_str(" ", delay).unwrap();
+        }
     }
 }
 
@@ -164,7 +1324,7 @@ pub fn render_selector(active: bool, bottom_pos: u8, lcd: &mut Lcd, delay: &mut
 /// use gem_rs::rendering::{render_time_config_screen, Lcd};
 ///
 /// let mut preferences = Preferences::default();
-/// let mut info_str: heapless::String<11>; // Must be a heapless String with size 11
+/// let mut info_str: heapless::String<16>; // Must be a heapless String with size 16
 /// let mut lcd: Lcd;
 /// let mut delay: Timer;
 /// let mut up_button;     // GPIO
@@ -186,9 +1346,10 @@ pub fn render_selector(active: bool, bottom_pos: u8, lcd: &mut Lcd, delay: &mut
 ///  );
 /// ```
 #[allow(clippy::too_many_arguments)]
+#[cfg(feature = "hardware")]
 pub fn render_time_config_screen(
     unit: &str,
-    info_str: &mut String<11>,
+    info_str: &mut String<16>,
     min: u8,
     max: u8,
     mut preference: u8,
@@ -203,7 +1364,7 @@ pub fn render_time_config_screen(
     let mut update_date: bool = false;
     loop {
         if refresh {
-            uwrite!(info_str, "{}: {}", unit, preference).unwrap();
+            uwrite!(Truncating(info_str), "{}: {}", unit, preference).unwrap();
             render_date_edit_screen(info_str, lcd, delay);
             info_str.clear();
             refresh = false;
@@ -213,6 +1374,7 @@ pub fn render_time_config_screen(
 
         if update_date {
             preferences.tick_time();
+            feed_watchdog();
         }
         update_date = !update_date;
 
@@ -228,3 +1390,229 @@ pub fn render_time_config_screen(
     }
     preference
 }
+
+/// Renders the configuration screen for toggling which days watering occurs on.
+/// UP or DOWN toggles the selected day and SELECT moves on to the next day
+///
+/// - param info_str: [String] for data
+/// - param preferences: [Preferences] instance
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+/// - param up_button: Up button instance
+/// - param down_button: Down button instance
+/// - param select_button: Select button instance
+#[cfg(feature = "hardware")]
+pub fn render_days_config_screen(
+    info_str: &mut String<16>,
+    preferences: &mut Preferences,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+    up_button: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    down_button: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    select_button: &mut Pin<Gpio12, FunctionSio<SioInput>, PullDown>,
+) {
+    let mut update_date: bool = false;
+    for index in 0..7 {
+        let mut refresh: bool = true;
+        loop {
+            if refresh {
+                uwrite!(
+                    Truncating(info_str),
+                    "{}: {}",
+                    LABELS.days,
+                    preferences.format_watering_days().as_str()
+                )
+                .unwrap();
+                render_screen(info_str, true, lcd, delay);
+                render_selector(true, LABELS.days.len() as u8 + 2 + index, lcd, delay);
+                info_str.clear();
+                refresh = false;
+            }
+
+            delay.delay_ms(500);
+
+            if update_date {
+                preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if up_button.is_high().unwrap() || down_button.is_high().unwrap() {
+                preferences.watering_days ^= Weekday::from_index(index).mask();
+                refresh = true;
+            } else if select_button.is_high().unwrap() {
+                break;
+            }
+        }
+    }
+}
+
+/// Renders a yes/no prompt and waits for an answer.
+/// SELECT confirms while UP or DOWN cancels
+///
+/// - param prompt: The question to ask
+/// - param preferences: [Preferences] instance
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+/// - param up_button: Up button instance
+/// - param down_button: Down button instance
+/// - param select_button: Select button instance
+///
+/// returns if the prompt was confirmed
+#[cfg(feature = "hardware")]
+pub fn render_confirm_screen(
+    prompt: &str,
+    preferences: &mut Preferences,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+    up_button: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    down_button: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    select_button: &mut Pin<Gpio12, FunctionSio<SioInput>, PullDown>,
+) -> bool {
+    render_screen(prompt, true, lcd, delay);
+    render_screen(LABELS.confirm_hint, false, lcd, delay);
+
+    let mut update_date: bool = false;
+    loop {
+        delay.delay_ms(500);
+
+        if update_date {
+            preferences.tick_time();
+            feed_watchdog();
+        }
+        update_date = !update_date;
+
+        if up_button.is_high().unwrap() || down_button.is_high().unwrap() {
+            return false;
+        } else if select_button.is_high().unwrap() {
+            return true;
+        }
+    }
+}
+
+/// The milliseconds between each check of the buttons while [confirm_hold] waits
+#[cfg(feature = "hardware")]
+const CONFIRM_POLL_MS: u16 = 100;
+
+/// Renders a prompt for a destructive action and waits for SELECT to be held for
+/// [CONFIRM_HOLD_MS], filling a bar on the bottom line as it is held. UP or DOWN cancels.
+/// See [HoldConfirm]
+///
+/// - param prompt: The question to ask
+/// - param preferences: [Preferences] instance
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+/// - param up_button: Up button instance
+/// - param down_button: Down button instance
+/// - param select_button: Select button instance
+///
+/// returns if the action was confirmed
+#[cfg(feature = "hardware")]
+pub fn confirm_hold(
+    prompt: &str,
+    preferences: &mut Preferences,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+    up_button: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    down_button: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    select_button: &mut Pin<Gpio12, FunctionSio<SioInput>, PullDown>,
+) -> bool {
+    render_screen(prompt, true, lcd, delay);
+    render_screen(LABELS.hold_hint, false, lcd, delay);
+
+    let mut hold = HoldConfirm::default();
+    let mut showing_bar = false;
+    let mut tick_ms: u16 = 0;
+    loop {
+        delay.delay_ms(CONFIRM_POLL_MS as u32);
+
+        tick_ms += CONFIRM_POLL_MS;
+        if tick_ms >= TICK_TIME_DELAY {
+            tick_ms -= TICK_TIME_DELAY;
+            preferences.tick_time();
+            feed_watchdog();
+        }
+
+        let cancel = up_button.is_high().unwrap() || down_button.is_high().unwrap();
+        match hold.step(CONFIRM_POLL_MS, select_button.is_high().unwrap(), cancel) {
+            HoldStatus::Waiting => {
+                if showing_bar {
+                    render_screen(LABELS.hold_hint, false, lcd, delay);
+                    showing_bar = false;
+                }
+            }
+            HoldStatus::Holding(held_ms) => {
+                let percent = (held_ms as u32 * 100 / CONFIRM_HOLD_MS as u32) as u8;
+                render_bar(false, percent, 0, 100, lcd, delay);
+                showing_bar = true;
+            }
+            HoldStatus::Confirmed => return true,
+            HoldStatus::Cancelled => return false,
+        }
+    }
+}
+
+/// Renders configuration screens for signed calibration offsets
+///
+/// - param unit: The name of the offset; Ex: Temp
+/// - param info_str: [String] for data
+/// - param min: The minimum offset
+/// - param max: The maximum offset
+/// - param preference: Current offset being assigned
+/// - param preferences: [Preferences] instance
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+/// - param up_button: Up button instance
+/// - param down_button: Down button instance
+/// - param select_button: Select button instance
+///
+/// returns the inputted offset after modification
+#[allow(clippy::too_many_arguments)]
+#[cfg(feature = "hardware")]
+pub fn render_offset_config_screen(
+    unit: &str,
+    info_str: &mut String<16>,
+    min: i16,
+    max: i16,
+    mut preference: i16,
+    preferences: &mut Preferences,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+    up_button: &mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    down_button: &mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    select_button: &mut Pin<Gpio12, FunctionSio<SioInput>, PullDown>,
+) -> i16 {
+    let mut refresh: bool = true;
+    let mut update_date: bool = false;
+    loop {
+        if refresh {
+            uwrite!(Truncating(info_str), "{}: {}", unit, preference).unwrap();
+            render_date_edit_screen(info_str, lcd, delay);
+            info_str.clear();
+            refresh = false;
+        }
+
+        delay.delay_ms(500);
+
+        if update_date {
+            preferences.tick_time();
+            feed_watchdog();
+        }
+        update_date = !update_date;
+
+        if up_button.is_high().unwrap() {
+            if preference < max {
+                preference += 1;
+            }
+            refresh = true;
+        } else if down_button.is_high().unwrap() {
+            if preference > min {
+                preference -= 1;
+            }
+            refresh = true;
+        } else if select_button.is_high().unwrap() {
+            break;
+        }
+    }
+    preference
+}
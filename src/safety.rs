@@ -0,0 +1,798 @@
+use crate::buzzer::Tone;
+use crate::control::{
+    active_temperature, classify_band, decide_actuators, fault_actuators, is_temperature_alarm,
+    Actuators, BandPosition, HeatStage, MistPulser, OutOfBandTracker,
+};
+use crate::preferences::{ActiveLevels, Output};
+use crate::shared::{Requests, Status};
+use crate::timer::SENSOR_DELAY;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use embedded_hal::digital::OutputPin;
+
+/// The milliseconds between each pass of the [SafetyMonitor] on core1
+pub const SAFETY_POLL_MS: u16 = 50;
+
+/// The state each actuator is driven to when the greenhouse can no longer be controlled normally
+///
+/// - **sprinklers**: If the sprinklers are on
+/// - **mister**: If the mister is on
+/// - **roof_vent**: If the roof vent is open
+/// - **buzzer**: If the buzzer is sounding
+/// - **heater**: If both heater stages are on
+///
+/// **NOTE:** There is no fan output on this board; only these actuators are driven
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SafeState {
+    pub sprinklers: bool,
+    pub mister: bool,
+    pub roof_vent: bool,
+    pub buzzer: bool,
+    pub heater: bool,
+}
+
+/// Gets the safe state for when power is failing.
+/// Watering, misting and heating are stopped and the buzzer is silenced to save what power is left,
+/// while the vent is left in the consumer-selected position.
+///
+/// - param vent_open: If the roof vent should be left open
+///
+/// returns the [SafeState] to drive the outputs to
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::{safe_state, SafeState};
+///
+/// assert_eq!(
+///     safe_state(false),
+///     SafeState { sprinklers: false, mister: false, roof_vent: false, buzzer: false, heater: false }
+/// );
+/// assert!(safe_state(true).roof_vent);
+/// ```
+pub fn safe_state(vent_open: bool) -> SafeState {
+    SafeState {
+        sprinklers: false,
+        mister: false,
+        roof_vent: vent_open,
+        buzzer: false,
+        heater: false,
+    }
+}
+
+impl SafeState {
+    /// Gets if an output is on in the safe state
+    ///
+    /// - param output: The [Output]
+    ///
+    /// returns true if the output is on
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Output;
+    /// use gem_rs::safety::safe_state;
+    ///
+    /// assert!(safe_state(true).get(Output::RoofVent));
+    /// assert!(!safe_state(true).get(Output::HeaterHigh));
+    /// assert!(!safe_state(false).get(Output::RoofVent));
+    /// ```
+    pub fn get(&self, output: Output) -> bool {
+        match output {
+            Output::Sprinklers => self.sprinklers,
+            Output::RoofVent => self.roof_vent,
+            Output::Mister => self.mister,
+            Output::Buzzer => self.buzzer,
+            Output::HeaterLow | Output::HeaterHigh => self.heater,
+        }
+    }
+}
+
+/// Checks if the supply voltage has dropped below the critical threshold
+///
+/// - param supply_mv: The current supply voltage in millivolts
+/// - param critical_mv: The critical supply voltage in millivolts
+///
+/// returns if the device is browning out
+pub fn is_brownout(supply_mv: u16, critical_mv: u16) -> bool {
+    supply_mv < critical_mv
+}
+
+/// Gets the level to drive an output's pin to
+///
+/// - param on: If the output should be on
+/// - param active_high: If the output's relay switches on while its pin is high
+///
+/// returns true if the pin should be high
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::output_level;
+///
+/// // Active-high
+/// assert!(output_level(true, true));
+/// assert!(!output_level(false, true));
+/// // Active-low
+/// assert!(!output_level(true, false));
+/// assert!(output_level(false, false));
+/// ```
+pub fn output_level(on: bool, active_high: bool) -> bool {
+    on == active_high
+}
+
+/// Drives an output on or off, whatever level its relay switches on at.
+/// Every actuator is driven through this rather than setting its pin directly. See [output_level]
+///
+/// - param pin: The output's Pin
+/// - param on: If the output should be on
+/// - param active_high: If the output's relay switches on while its pin is high
+pub fn drive(pin: &mut impl OutputPin, on: bool, active_high: bool) {
+    pin.set_state(output_level(on, active_high).into()).unwrap();
+}
+
+/// Drives every actuator to the given [SafeState]
+///
+/// - param state: The [SafeState] to apply
+/// - param levels: The [ActiveLevels] of the outputs
+/// - param sprinklers: Sprinkler Pin
+/// - param mister: Mister Pin
+/// - param roof_vent: Roof vent Pin
+/// - param buzzer: Buzzer Pin
+/// - param heater_low: First heater stage Pin
+/// - param heater_high: Second heater stage Pin
+#[allow(clippy::too_many_arguments)]
+pub fn apply_safe_state(
+    state: &SafeState,
+    levels: &ActiveLevels,
+    sprinklers: &mut impl OutputPin,
+    mister: &mut impl OutputPin,
+    roof_vent: &mut impl OutputPin,
+    buzzer: &mut impl OutputPin,
+    heater_low: &mut impl OutputPin,
+    heater_high: &mut impl OutputPin,
+) {
+    drive(sprinklers, state.sprinklers, levels.sprinklers);
+    drive(mister, state.mister, levels.mister);
+    drive(roof_vent, state.roof_vent, levels.roof_vent);
+    drive(buzzer, state.buzzer, levels.buzzer);
+    drive(heater_low, state.heater, levels.heater_low);
+    drive(heater_high, state.heater, levels.heater_high);
+}
+
+/// The GPIO driving the buzzer
+pub const BUZZER_GPIO: u8 = 6;
+/// The GPIO function that hands a pin to the SIO
+#[cfg(feature = "hardware")]
+const SIO_FUNCSEL: u8 = 5;
+/// The GPIO driving the sprinklers
+pub const SPRINKLERS_GPIO: u8 = 13;
+/// The GPIO driving the roof vent
+pub const ROOF_VENT_GPIO: u8 = 14;
+/// The GPIO driving the mister
+pub const MISTER_GPIO: u8 = 15;
+/// The GPIO driving the first heater stage
+pub const HEATER_LOW_GPIO: u8 = 21;
+/// The GPIO driving the second heater stage
+pub const HEATER_HIGH_GPIO: u8 = 22;
+
+/// If [safe_shutdown] leaves the roof vent open; kept in step with [Preferences::safe_vent_open](crate::preferences::Preferences)
+static SHUTDOWN_VENT_OPEN: AtomicBool = AtomicBool::new(false);
+/// The [ActiveLevels::active_low_bits] [safe_shutdown] drives the outputs with
+static SHUTDOWN_ACTIVE_LOW: AtomicU8 = AtomicU8::new(0);
+
+/// Sets where [safe_shutdown] leaves the roof vent, and the levels it drives each output with.
+/// It is kept outside of the [Preferences](crate::preferences::Preferences), as a panic may strike while they are borrowed
+///
+/// - param vent_open: If the roof vent should be left open
+/// - param levels: The [ActiveLevels] of the outputs
+pub fn configure_shutdown(vent_open: bool, levels: &ActiveLevels) {
+    SHUTDOWN_VENT_OPEN.store(vent_open, Ordering::Relaxed);
+    SHUTDOWN_ACTIVE_LOW.store(levels.active_low_bits(), Ordering::Relaxed);
+}
+
+/// Gets the [SafeState] [safe_shutdown] drives the outputs to
+///
+/// returns the configured [SafeState] and the [ActiveLevels] to drive it with
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::ActiveLevels;
+/// use gem_rs::safety::{configure_shutdown, safe_state, shutdown_state};
+///
+/// // Nothing is configured until the preferences are loaded, so the vent is closed
+/// assert_eq!(shutdown_state(), (safe_state(false), ActiveLevels::default()));
+///
+/// let levels = ActiveLevels { mister: false, ..ActiveLevels::default() };
+/// configure_shutdown(true, &levels);
+/// assert_eq!(shutdown_state(), (safe_state(true), levels));
+/// configure_shutdown(false, &ActiveLevels::default());
+/// assert_eq!(shutdown_state(), (safe_state(false), ActiveLevels::default()));
+/// ```
+pub fn shutdown_state() -> (SafeState, ActiveLevels) {
+    (
+        safe_state(SHUTDOWN_VENT_OPEN.load(Ordering::Relaxed)),
+        ActiveLevels::from_active_low_bits(SHUTDOWN_ACTIVE_LOW.load(Ordering::Relaxed))
+            .unwrap_or_default(),
+    )
+}
+
+/// Gets the GPIO bits to set and clear to drive every actuator to a [SafeState]
+///
+/// - param state: The [SafeState] to apply
+/// - param levels: The [ActiveLevels] of the outputs. See [output_level]
+///
+/// returns the bits to drive high and the bits to drive low
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::ActiveLevels;
+/// use gem_rs::safety::{gpio_masks, safe_state, SafeState};
+///
+/// let active_high = ActiveLevels::default();
+/// let heater = 1 << 21 | 1 << 22;
+/// // Everything off
+/// assert_eq!(
+///     gpio_masks(&safe_state(false), &active_high),
+///     (0, 1 << 6 | 1 << 13 | 1 << 14 | 1 << 15 | heater)
+/// );
+/// // Vent left open
+/// assert_eq!(
+///     gpio_masks(&safe_state(true), &active_high),
+///     (1 << 14, 1 << 6 | 1 << 13 | 1 << 15 | heater)
+/// );
+///
+/// let all_on = SafeState { sprinklers: true, mister: true, roof_vent: true, buzzer: true, heater: true };
+/// assert_eq!(gpio_masks(&all_on, &active_high), (1 << 6 | 1 << 13 | 1 << 14 | 1 << 15 | heater, 0));
+///
+/// // An active-low sprinkler relay is switched off by driving its pin high
+/// let levels = ActiveLevels { sprinklers: false, ..active_high };
+/// assert_eq!(
+///     gpio_masks(&safe_state(false), &levels),
+///     (1 << 13, 1 << 6 | 1 << 14 | 1 << 15 | heater)
+/// );
+/// ```
+pub fn gpio_masks(state: &SafeState, levels: &ActiveLevels) -> (u32, u32) {
+    [
+        (BUZZER_GPIO, state.buzzer, levels.buzzer),
+        (SPRINKLERS_GPIO, state.sprinklers, levels.sprinklers),
+        (ROOF_VENT_GPIO, state.roof_vent, levels.roof_vent),
+        (MISTER_GPIO, state.mister, levels.mister),
+        (HEATER_LOW_GPIO, state.heater, levels.heater_low),
+        (HEATER_HIGH_GPIO, state.heater, levels.heater_high),
+    ]
+    .iter()
+    .fold((0, 0), |(high, low), (gpio, on, active_high)| {
+        if output_level(*on, *active_high) {
+            (high | 1 << gpio, low)
+        } else {
+            (high, low | 1 << gpio)
+        }
+    })
+}
+
+/// Drives every actuator to the [shutdown_state] by writing the GPIO registers directly,
+/// as whoever owns the pins may be the one panicking.
+/// Called by the panic handler once the other core is stopped, so nothing drives the outputs again
+#[cfg(feature = "hardware")]
+pub fn safe_shutdown() {
+    let (state, levels) = shutdown_state();
+    let (high, low) = gpio_masks(&state, &levels);
+    // Safety: the set and clear registers only touch the given bits, so nothing else is disturbed
+    let sio = unsafe { &*rp_pico::pac::SIO::ptr() };
+    sio.gpio_out_clr().write(|w| unsafe { w.bits(low) });
+    sio.gpio_out_set().write(|w| unsafe { w.bits(high) });
+    // The buzzer is played by PWM, so hand its pin back to the SIO to silence it
+    sio.gpio_oe_set()
+        .write(|w| unsafe { w.bits(1 << BUZZER_GPIO) });
+    let io_bank0 = unsafe { &*rp_pico::pac::IO_BANK0::ptr() };
+    io_bank0
+        .gpio(BUZZER_GPIO as usize)
+        .gpio_ctrl()
+        .modify(|_, w| unsafe { w.funcsel().bits(SIO_FUNCSEL) });
+}
+
+/// Checks if the temperature alarm beeps at a point of its pattern.
+/// It beeps twice at the start of every sensor poll, so it isn't mistaken for the single low voltage chirp
+///
+/// - param phase_ms: The milliseconds since the alarm started
+///
+/// returns if the buzzer should be on
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::alarm_beep;
+///
+/// assert!(alarm_beep(0));
+/// assert!(!alarm_beep(150));
+/// assert!(alarm_beep(250));
+/// assert!(!alarm_beep(300));
+/// assert!(!alarm_beep(1999));
+/// assert!(alarm_beep(2000)); // The pattern repeats every poll
+/// ```
+pub fn alarm_beep(phase_ms: u32) -> bool {
+    matches!(phase_ms % SENSOR_DELAY as u32, 0..=99 | 200..=299)
+}
+
+/// Checks if the escalation alarm beeps at a point of its pattern.
+/// It gives one long beep at the start of every sensor poll, unlike the double beep of the temperature alarm
+///
+/// - param phase_ms: The milliseconds since the escalation started
+///
+/// returns if the buzzer should be on
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::escalation_beep;
+///
+/// assert!(escalation_beep(0));
+/// assert!(escalation_beep(499));
+/// assert!(!escalation_beep(500));
+/// assert!(escalation_beep(2000)); // The pattern repeats every poll
+/// ```
+pub fn escalation_beep(phase_ms: u32) -> bool {
+    phase_ms % SENSOR_DELAY as u32 <= 499
+}
+
+/// The state core1 drives each output to
+///
+/// - **roof_vent**: If the roof vent is open
+/// - **sprinklers**: If the sprinklers are on
+/// - **mister**: If the mister is on
+/// - **buzzer**: If the buzzer is sounding
+/// - **tone**: The [Tone] a passive buzzer plays while sounding
+/// - **heat**: The [HeatStage] the heater runs at
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Outputs {
+    pub roof_vent: bool,
+    pub sprinklers: bool,
+    pub mister: bool,
+    pub buzzer: bool,
+    pub tone: Tone,
+    pub heat: HeatStage,
+}
+
+impl Outputs {
+    /// Gets if an output is on
+    ///
+    /// - param output: The [Output]
+    ///
+    /// returns true if the output is on
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::control::HeatStage;
+    /// use gem_rs::preferences::Output;
+    /// use gem_rs::safety::Outputs;
+    ///
+    /// let low = Outputs { heat: HeatStage::Low, ..Outputs::default() };
+    /// assert!(low.get(Output::HeaterLow) && !low.get(Output::HeaterHigh));
+    /// let high = Outputs { heat: HeatStage::High, ..Outputs::default() };
+    /// assert!(high.get(Output::HeaterLow) && high.get(Output::HeaterHigh));
+    /// assert!(!Outputs::default().get(Output::HeaterLow));
+    /// ```
+    pub fn get(&self, output: Output) -> bool {
+        match output {
+            Output::Sprinklers => self.sprinklers,
+            Output::RoofVent => self.roof_vent,
+            Output::Mister => self.mister,
+            Output::Buzzer => self.buzzer,
+            Output::HeaterLow => self.heat >= HeatStage::Low,
+            Output::HeaterHigh => self.heat == HeatStage::High,
+        }
+    }
+}
+
+/// Confirms the smoke detector line before the fire response starts or stops,
+/// so a one-sample glitch neither sets off the sprinklers nor stops them in the middle of a fire.
+/// The line must read the other way for [Preferences::smoke_samples](crate::preferences::Preferences::smoke_samples)
+/// samples in a row; the sample that confirms it takes effect straight away
+///
+/// - **confirmed**: If smoke is confirmed
+/// - **disagreeing**: How many samples in a row have disagreed with `confirmed`
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::SmokeFilter;
+///
+/// let mut filter = SmokeFilter::default();
+/// // A single spike is ignored
+/// assert!(!filter.step(true, 3));
+/// assert!(!filter.step(false, 3));
+///
+/// // Three in a row confirm it
+/// assert!(!filter.step(true, 3));
+/// assert!(!filter.step(true, 3));
+/// assert!(filter.step(true, 3));
+///
+/// // A dropout doesn't stop the response, but the line staying clear does
+/// assert!(filter.step(false, 3));
+/// assert!(filter.step(true, 3));
+/// assert!(filter.step(false, 3));
+/// assert!(filter.step(false, 3));
+/// assert!(!filter.step(false, 3));
+///
+/// // 1 sample reacts to every read
+/// assert!(filter.step(true, 1));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SmokeFilter {
+    confirmed: bool,
+    disagreeing: u8,
+}
+
+impl SmokeFilter {
+    /// Takes the next sample of the smoke detector line
+    ///
+    /// - param line_high: If the smoke detector line is high
+    /// - param samples: How many samples in a row confirm a change
+    ///
+    /// returns if smoke is confirmed
+    pub fn step(&mut self, line_high: bool, samples: u8) -> bool {
+        if line_high == self.confirmed {
+            self.disagreeing = 0;
+        } else {
+            self.disagreeing = self.disagreeing.saturating_add(1);
+            if self.disagreeing >= samples {
+                self.confirmed = line_high;
+                self.disagreeing = 0;
+            }
+        }
+        self.confirmed
+    }
+}
+
+/// How long the fire response runs for during an alarm test, in milliseconds
+pub const ALARM_TEST_MS: u16 = 5000;
+
+/// Why the fire response is running
+///
+/// - **Smoke**: The [SmokeFilter] confirmed smoke
+/// - **Test**: A technician started an [AlarmTest]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FireTrigger {
+    Smoke,
+    Test,
+}
+
+/// Decides if the fire response runs. Real smoke and alarm tests both go through here,
+/// so a test drives the outputs exactly as a fire would. Smoke always wins, so a test can't hide a fire
+///
+/// - param smoke: If smoke is confirmed by the [SmokeFilter]
+/// - param test: If an [AlarmTest] is running
+///
+/// returns the [FireTrigger], or None if there is no fire response
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::{fire_trigger, FireTrigger};
+///
+/// assert_eq!(fire_trigger(false, false), None);
+/// assert_eq!(fire_trigger(true, false), Some(FireTrigger::Smoke));
+/// assert_eq!(fire_trigger(false, true), Some(FireTrigger::Test));
+/// // Real smoke during a test is a real fire
+/// assert_eq!(fire_trigger(true, true), Some(FireTrigger::Smoke));
+/// ```
+pub fn fire_trigger(smoke: bool, test: bool) -> Option<FireTrigger> {
+    if smoke {
+        Some(FireTrigger::Smoke)
+    } else if test {
+        Some(FireTrigger::Test)
+    } else {
+        None
+    }
+}
+
+/// Counts down an alarm test on core0, which runs the fire response for [ALARM_TEST_MS] without smoke.
+/// A real fire ends the test for good, so it doesn't carry on as a test once the smoke clears
+///
+/// - **remaining_ms**: How much longer the test runs for
+/// - **starting**: If the test has just been started; the step after it carries the time spent confirming, so it isn't counted
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::safety::{AlarmTest, ALARM_TEST_MS};
+///
+/// let mut test = AlarmTest::default();
+/// assert!(!test.step(100, false));
+///
+/// test.start();
+/// assert!(test.step(3000, false)); // Holding SELECT to confirm doesn't count
+/// assert!(test.step(ALARM_TEST_MS - 1, false));
+/// assert!(!test.step(1, false)); // Over, so normal control takes back over
+///
+/// // Real smoke aborts it
+/// test.start();
+/// assert!(test.step(100, false));
+/// assert!(!test.step(100, true));
+/// assert!(!test.step(100, false));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct AlarmTest {
+    remaining_ms: u16,
+    starting: bool,
+}
+
+impl AlarmTest {
+    /// Starts the test, or starts it over if it is running
+    pub fn start(&mut self) {
+        self.remaining_ms = ALARM_TEST_MS;
+        self.starting = true;
+    }
+
+    /// Advances the test
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    /// - param fire: If core1 reports a real fire
+    ///
+    /// returns if the test is still running
+    pub fn step(&mut self, elapsed_ms: u16, fire: bool) -> bool {
+        if fire {
+            self.remaining_ms = 0;
+        } else if !self.starting {
+            self.remaining_ms = self.remaining_ms.saturating_sub(elapsed_ms);
+        }
+        self.starting = false;
+        self.remaining_ms > 0
+    }
+}
+
+/// Decides the outputs on core1 from the smoke detector, the rain sensor and the [Requests] of core0.
+/// The smoke alarm comes first, then the brownout [SafeState], then normal control
+///
+/// - **actuators**: The [Actuators] decided on the last pass
+/// - **mist_pulser**: Pulses the mister while the humidity calls for it
+/// - **alarm_ms**: How long the temperature alarm has been sounding in milliseconds
+/// - **temperature_excursion**: How long the temperature has been out of its active range
+/// - **humidity_excursion**: How long the humidity has been out of its range
+/// - **escalation_ms**: How long the escalation alarm has been sounding in milliseconds
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::buzzer::Tone;
+/// use gem_rs::control::HeatStage;
+/// use gem_rs::safety::{Outputs, SafetyMonitor, SAFETY_POLL_MS};
+/// use gem_rs::shared::Requests;
+/// use gem_rs::preferences::{FaultPolicy, Preferences};
+///
+/// let mut requests = Requests::new(&Preferences::default()); // 55F - 75F at midnight, alarm below 40F or above 95F
+/// let mut monitor = SafetyMonitor::default();
+///
+/// // Nothing is driven until core0 has a warmed up reading
+/// let (outputs, status) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert_eq!(outputs, Outputs::default());
+/// assert!(!status.fire && !status.temperature_alarm);
+///
+/// // Too hot opens the vent
+/// requests.reading = Some((85, 65));
+/// assert!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.roof_vent);
+///
+/// // Smoke closes the vent and runs the sprinklers and buzzer, whatever else is going on
+/// let (outputs, status) = monitor.step(&requests, true, false, SAFETY_POLL_MS);
+/// assert!(!outputs.roof_vent && outputs.sprinklers && outputs.buzzer);
+/// assert_eq!(outputs.tone, Tone::Fire);
+/// assert!(status.fire && !status.watering && !status.alarm_test);
+///
+/// // An alarm test drives the same outputs, but isn't reported as a fire
+/// requests.alarm_test = true;
+/// let (test_outputs, status) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert_eq!(test_outputs, outputs);
+/// assert!(status.alarm_test && !status.fire && !status.watering);
+/// // Unless there is real smoke
+/// let (_, status) = monitor.step(&requests, true, false, SAFETY_POLL_MS);
+/// assert!(status.fire && !status.alarm_test);
+/// requests.alarm_test = false;
+/// assert!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.roof_vent);
+///
+/// // A brownout leaves the vent where the preferences say
+/// requests.safe_state = true;
+/// let (outputs, _) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert_eq!(outputs, Outputs::default());
+/// requests.safe_state = false;
+///
+/// // Priming and manual watering run the sprinklers
+/// requests.priming = true;
+/// let (outputs, status) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert!(outputs.sprinklers && status.watering);
+/// requests.priming = false;
+///
+/// // Rain closes the vent and is reported while the override is on
+/// requests.reading = Some((85, 65));
+/// let (outputs, status) = monitor.step(&requests, false, true, SAFETY_POLL_MS);
+/// assert!(!outputs.roof_vent && status.rain);
+/// requests.preferences.rain_closes_vent = false;
+/// let (outputs, status) = monitor.step(&requests, false, true, SAFETY_POLL_MS);
+/// assert!(outputs.roof_vent && !status.rain);
+///
+/// // Once the sensors fail, the fault policy takes over; by default the vent goes to its safe state
+/// requests.sensor_fault = true;
+/// assert!(!monitor.step(&requests, false, false, SAFETY_POLL_MS).0.roof_vent);
+/// requests.preferences.fault_policy = FaultPolicy::LastGood;
+/// assert!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.roof_vent);
+/// requests.preferences.fault_policy = FaultPolicy::SafeState;
+/// requests.sensor_fault = false;
+///
+/// // Too cold runs the heater, harder the colder it is, but a fire or an alarm test stops it
+/// requests.reading = Some((52, 65));
+/// assert_eq!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.heat, HeatStage::Low);
+/// requests.reading = Some((45, 65));
+/// assert_eq!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.heat, HeatStage::High);
+/// let (outputs, _) = monitor.step(&requests, true, false, SAFETY_POLL_MS);
+/// assert_eq!(outputs.heat, HeatStage::Off);
+/// assert!(outputs.sprinklers);
+/// requests.alarm_test = true;
+/// assert_eq!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.heat, HeatStage::Off);
+/// requests.alarm_test = false;
+/// // It carries on once the fire is out
+/// assert_eq!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.heat, HeatStage::High);
+///
+/// // Leaving the alarm band beeps, starting straight away
+/// requests.reading = Some((99, 65));
+/// let (outputs, status) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert!(outputs.buzzer && status.temperature_alarm);
+/// assert_eq!(outputs.tone, Tone::Heat);
+/// assert!(monitor.step(&requests, false, false, 100).0.buzzer);
+/// assert!(!monitor.step(&requests, false, false, SAFETY_POLL_MS).0.buzzer); // Between the beeps
+/// requests.reading = Some((30, 65));
+/// assert_eq!(monitor.step(&requests, false, false, SAFETY_POLL_MS).0.tone, Tone::Frost);
+///
+/// // Staying out of range for longer than the escalation time escalates until it is back in range
+/// requests.reading = Some((85, 65));
+/// requests.preferences.escalation_mins = 1;
+/// let mut monitor = SafetyMonitor::default();
+/// assert!(!monitor.step(&requests, false, false, SAFETY_POLL_MS).1.escalated);
+/// for _ in 0..59 {
+///     assert!(!monitor.step(&requests, false, false, 1000).1.escalated);
+/// }
+/// let (outputs, status) = monitor.step(&requests, false, false, 1000);
+/// assert!(outputs.buzzer && status.escalated && !status.temperature_alarm);
+/// assert_eq!(outputs.tone, Tone::Notification);
+/// requests.reading = Some((70, 65));
+/// let (outputs, status) = monitor.step(&requests, false, false, SAFETY_POLL_MS);
+/// assert!(!outputs.buzzer && !status.escalated);
+/// ```
+#[derive(Default)]
+pub struct SafetyMonitor {
+    actuators: Actuators,
+    mist_pulser: MistPulser,
+    alarm_ms: u32,
+    temperature_excursion: OutOfBandTracker,
+    humidity_excursion: OutOfBandTracker,
+    escalation_ms: u32,
+}
+
+impl SafetyMonitor {
+    /// Runs one pass of the monitor
+    ///
+    /// - param requests: The latest [Requests] from core0
+    /// - param smoke: If smoke is confirmed by the [SmokeFilter]
+    /// - param rain: If the rain sensor is wet
+    /// - param elapsed_ms: The milliseconds since the last pass
+    ///
+    /// returns the [Outputs] to drive and the [Status] to report to core0
+    pub fn step(
+        &mut self,
+        requests: &Requests,
+        smoke: bool,
+        rain: bool,
+        elapsed_ms: u16,
+    ) -> (Outputs, Status) {
+        let preferences = &requests.preferences;
+        // Nothing is decided until core0 has a reading to act on
+        self.actuators = match requests.reading {
+            Some((temperature, humidity)) => fault_actuators(
+                preferences.fault_policy,
+                requests.sensor_fault,
+                self.actuators,
+                decide_actuators(
+                    temperature,
+                    humidity,
+                    requests.co2,
+                    rain,
+                    self.actuators,
+                    preferences,
+                ),
+                preferences.safe_vent_open,
+            ),
+            None => Actuators::default(),
+        };
+        let misting = self.mist_pulser.step(
+            elapsed_ms,
+            self.actuators.mister,
+            preferences.mist_pulse,
+            preferences.mist_dwell,
+        );
+
+        let temperature_alarm = requests.reading.is_some_and(|(temperature, _)| {
+            is_temperature_alarm(temperature, preferences.temperature_alarm)
+        });
+        // Readings out of range for too long mean the automation can't cope
+        let (temperature, humidity) = match requests.reading {
+            Some((temperature, humidity)) => (
+                classify_band(temperature, active_temperature(preferences).1),
+                classify_band(humidity, preferences.humidity),
+            ),
+            None => (BandPosition::Within, BandPosition::Within),
+        };
+        let limit = preferences.escalation_mins;
+        let temperature_escalated = self
+            .temperature_excursion
+            .step(elapsed_ms, temperature, limit);
+        let humidity_escalated = self.humidity_excursion.step(elapsed_ms, humidity, limit);
+        let escalated = temperature_escalated || humidity_escalated;
+
+        // The temperature alarm is more urgent, so its pattern wins
+        let beeping = if temperature_alarm {
+            alarm_beep(self.alarm_ms)
+        } else {
+            escalated && escalation_beep(self.escalation_ms)
+        };
+        if temperature_alarm {
+            self.alarm_ms = self.alarm_ms.saturating_add(elapsed_ms as u32);
+        } else {
+            // Start the pattern from the first beep next time
+            self.alarm_ms = 0;
+        }
+        if escalated {
+            self.escalation_ms = self.escalation_ms.saturating_add(elapsed_ms as u32);
+        } else {
+            self.escalation_ms = 0;
+        }
+
+        let fire = fire_trigger(smoke, requests.alarm_test);
+        // Each alarm has its own tone, so a passive buzzer tells them apart
+        let tone = match requests.reading {
+            _ if fire.is_some() => Tone::Fire,
+            Some((temperature, _)) if temperature_alarm => {
+                if temperature < preferences.temperature_alarm.0 {
+                    Tone::Frost
+                } else {
+                    Tone::Heat
+                }
+            }
+            _ => Tone::Notification,
+        };
+        let outputs = if fire.is_some() {
+            // Drench the fire, starve it of air and stop feeding it heat
+            Outputs {
+                roof_vent: false,
+                sprinklers: true,
+                mister: misting,
+                buzzer: true,
+                tone,
+                heat: HeatStage::Off,
+            }
+        } else if requests.safe_state {
+            let state = safe_state(preferences.safe_vent_open);
+            Outputs {
+                roof_vent: state.roof_vent,
+                sprinklers: state.sprinklers,
+                mister: state.mister,
+                buzzer: state.buzzer,
+                tone,
+                heat: if state.heater {
+                    HeatStage::High
+                } else {
+                    HeatStage::Off
+                },
+            }
+        } else {
+            Outputs {
+                roof_vent: self.actuators.roof_vent,
+                sprinklers: self.actuators.sprinklers
+                    || requests.manual_watering
+                    || requests.priming,
+                mister: misting,
+                buzzer: requests.buzzer || beeping,
+                tone,
+                heat: self.actuators.heat,
+            }
+        };
+        let status = Status {
+            fire: fire == Some(FireTrigger::Smoke),
+            alarm_test: fire == Some(FireTrigger::Test),
+            temperature_alarm,
+            // Sprinklers run by the fire response aren't watering
+            watering: outputs.sprinklers && fire.is_none(),
+            rain: rain && preferences.rain_closes_vent,
+            escalated,
+        };
+        (outputs, status)
+    }
+}
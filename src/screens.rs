@@ -0,0 +1,2235 @@
+#[cfg(feature = "hardware")]
+use crate::control::{
+    active_temperature, classify_band, prime_step, vent_opening, PrimeStatus, PRIME_MS,
+};
+#[cfg(feature = "hardware")]
+use crate::events::EventLog;
+use crate::preferences::DateTime;
+#[cfg(feature = "hardware")]
+use crate::preferences::{
+    inclusive_iterator, range_warning, Co2Policy, Preferences, RangeLimits, Setpoint,
+    TemperatureModel, TemperatureUnit, HUMIDITY_LIMITS, MAX_CO2_VENT_PPM, MAX_GDD_BASE,
+    MAX_HEATER_MS, MAX_HEATER_TEMP, MAX_LOW_VOLTAGE, MAX_YEAR, MIN_CO2_VENT_PPM, MIN_GDD_BASE,
+    MIN_HEATER_MS, MIN_HEATER_TEMP, MIN_LOW_VOLTAGE, VOLTAGE_STEP,
+};
+#[cfg(feature = "hardware")]
+use crate::rendering::{
+    confirm_hold, render_band_icon, render_confirm_screen, render_date_edit_screen,
+    render_days_config_screen, render_edit_screen, render_offset_config_screen,
+    render_range_warning, render_screen, render_selector, render_time_config_screen,
+    render_watering_edit_screen, DisplayPwm, Lcd, Truncating, LABELS, MIN_CONTRAST,
+};
+#[cfg(all(feature = "hardware", feature = "altitude"))]
+use crate::sensors::altitude_m;
+#[cfg(feature = "hardware")]
+use crate::sensors::{
+    aqi_from_resistance, get_humidity, get_temperature, PressureHistory, Reading,
+};
+#[cfg(feature = "hardware")]
+use crate::shared;
+#[cfg(feature = "hardware")]
+use crate::stats::{Extremes, SensorStats};
+#[cfg(feature = "hardware")]
+use crate::timer::CountDownTimer;
+#[cfg(feature = "hardware")]
+use crate::watchdog::feed_watchdog;
+#[cfg(feature = "hardware")]
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "hardware")]
+use embedded_hal::digital::InputPin;
+#[cfg(feature = "hardware")]
+use heapless::String;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::bank0::{Gpio10, Gpio11, Gpio12};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::{FunctionSio, Pin, PullDown, SioInput};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::Timer;
+#[cfg(feature = "hardware")]
+use ufmt::uwrite;
+
+// Hidden screens are kept as bits of a u32, one per screen code
+const _: () = {
+    let mut index = 0;
+    while index < Screen::ALL.len() {
+        assert!(Screen::ALL[index].code() < 32);
+        index += 1;
+    }
+};
+
+/// Every screen that can be cycled through with the UP and DOWN buttons
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Screen {
+    Temperature,
+    Humidity,
+    Pressure,
+    Sensors,
+    Date,
+    Watering,
+    Prime,
+    Status,
+    Events,
+    FactoryReset,
+    Calibration,
+    Display,
+    Gas,
+    #[cfg(feature = "altitude")]
+    Altitude,
+    Stats,
+    Units,
+    Co2,
+    Gdd,
+}
+
+impl Screen {
+    /// Every [Screen] in the order they are cycled through
+    pub const ALL: &'static [Screen] = &[
+        Screen::Temperature,
+        Screen::Humidity,
+        Screen::Pressure,
+        Screen::Sensors,
+        Screen::Date,
+        Screen::Watering,
+        Screen::Prime,
+        Screen::Status,
+        Screen::Events,
+        Screen::FactoryReset,
+        Screen::Calibration,
+        Screen::Display,
+        Screen::Gas,
+        #[cfg(feature = "altitude")]
+        Screen::Altitude,
+        Screen::Stats,
+        Screen::Units,
+        Screen::Co2,
+        Screen::Gdd,
+    ];
+
+    /// The screens the dashboard rotates through
+    pub const DASHBOARD: &'static [Screen] = &[
+        Screen::Temperature,
+        Screen::Humidity,
+        Screen::Pressure,
+        Screen::Date,
+    ];
+
+    /// A mask with every screen in [Screen::ALL] hidden
+    pub const ALL_HIDDEN: u32 = {
+        let mut mask = 0;
+        let mut index = 0;
+        while index < Self::ALL.len() {
+            mask |= Self::ALL[index].mask();
+            index += 1;
+        }
+        mask
+    };
+
+    /// Gets the position of the screen in [Screen::ALL]
+    pub(crate) fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|screen| *screen == self)
+            .unwrap_or(0)
+    }
+
+    /// Gets the code the screen is saved as, in the hidden screens mask and as the default screen.
+    /// Codes never change and don't depend on the enabled features, unlike positions in [Screen::ALL],
+    /// so preferences saved by a build with or without the `altitude` screen load the same in the other
+    ///
+    /// returns the screen's code
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// let codes = [
+    ///     (Screen::Temperature, 0),
+    ///     (Screen::Humidity, 1),
+    ///     (Screen::Pressure, 2),
+    ///     (Screen::Sensors, 3),
+    ///     (Screen::Date, 4),
+    ///     (Screen::Watering, 5),
+    ///     (Screen::Prime, 6),
+    ///     (Screen::Status, 7),
+    ///     (Screen::Events, 8),
+    ///     (Screen::FactoryReset, 9),
+    ///     (Screen::Calibration, 10),
+    ///     (Screen::Display, 11),
+    ///     (Screen::Gas, 12),
+    ///     (Screen::Stats, 13),
+    ///     (Screen::Units, 14),
+    ///     (Screen::Co2, 15),
+    ///     (Screen::Gdd, 16),
+    /// ];
+    /// for (screen, code) in codes {
+    ///     assert_eq!(screen.code(), code);
+    ///     assert_eq!(Screen::from_code(code), Some(screen));
+    ///     assert_eq!(screen.mask(), 1 << code);
+    /// }
+    /// // The altitude screen's code is only known to builds with it
+    /// assert_eq!(Screen::from_code(17).is_some(), cfg!(feature = "altitude"));
+    /// assert_eq!(Screen::from_code(18), None);
+    /// ```
+    pub const fn code(self) -> u8 {
+        match self {
+            Screen::Temperature => 0,
+            Screen::Humidity => 1,
+            Screen::Pressure => 2,
+            Screen::Sensors => 3,
+            Screen::Date => 4,
+            Screen::Watering => 5,
+            Screen::Prime => 6,
+            Screen::Status => 7,
+            Screen::Events => 8,
+            Screen::FactoryReset => 9,
+            Screen::Calibration => 10,
+            Screen::Display => 11,
+            Screen::Gas => 12,
+            Screen::Stats => 13,
+            Screen::Units => 14,
+            Screen::Co2 => 15,
+            Screen::Gdd => 16,
+            #[cfg(feature = "altitude")]
+            Screen::Altitude => 17,
+        }
+    }
+
+    /// Finds a screen by its [Screen::code]
+    ///
+    /// - param code: The saved code
+    ///
+    /// returns the [Screen], or None if no screen in this build has that code
+    pub fn from_code(code: u8) -> Option<Screen> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|screen| screen.code() == code)
+    }
+
+    /// Gets the screen's bit in a hidden screens mask. See [Screen::code]
+    ///
+    /// returns the mask with only this screen hidden
+    pub const fn mask(self) -> u32 {
+        1 << self.code()
+    }
+
+    /// Checks if the screen is shown
+    ///
+    /// - param hidden: The hidden screens mask
+    ///
+    /// returns true if the screen isn't hidden
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::{DateTime, Preferences};
+    /// use gem_rs::screens::Screen;
+    ///
+    /// // Hiding the clock for an external RTC display (`HIDE DATE`)
+    /// let mut preferences = Preferences::default();
+    /// preferences.hidden_screens = Screen::Date.mask();
+    /// assert!(!Screen::Date.is_visible(preferences.hidden_screens));
+    /// assert!(Screen::Temperature.is_visible(preferences.hidden_screens));
+    ///
+    /// // Neither the buttons nor the dashboard land on it, and its edit can't be reached
+    /// let mut screen = Screen::Temperature;
+    /// for _ in 0..Screen::ALL.len() * 2 {
+    ///     screen = screen.next_visible(preferences.hidden_screens);
+    ///     assert_ne!(screen, Screen::Date);
+    ///     assert_ne!(screen.prev_visible(preferences.hidden_screens), Screen::Date);
+    ///     assert_ne!(screen.next_dashboard(preferences.hidden_screens), Screen::Date);
+    /// }
+    /// assert_eq!(Screen::Pressure.next_dashboard(preferences.hidden_screens), Screen::Temperature);
+    ///
+    /// // The clock keeps ticking for the watering schedule
+    /// preferences.watering = Some((0, 7, 15, 7)); // 07:00 - 07:15
+    /// preferences.date = DateTime(59, 59, 6, 1, 1, 2000);
+    /// assert!(!preferences.is_watering_time());
+    /// preferences.tick_time();
+    /// assert_eq!(preferences.date, DateTime(0, 0, 7, 1, 1, 2000));
+    /// assert!(preferences.is_watering_time());
+    /// ```
+    pub fn is_visible(self, hidden: u32) -> bool {
+        hidden & self.mask() == 0
+    }
+
+    /// Checks if the screen shows a sensor reading, so the age of the reading is shown with it
+    ///
+    /// returns true for the temperature, humidity and pressure screens
+    pub fn shows_age(self) -> bool {
+        matches!(
+            self,
+            Screen::Temperature | Screen::Humidity | Screen::Pressure
+        )
+    }
+
+    /// Gets how often the screen is drawn again while it is shown
+    ///
+    /// returns [Cadence::EverySecond] for the clock and [Cadence::OnData] for every other screen
+    pub fn cadence(self) -> Cadence {
+        match self {
+            Screen::Date => Cadence::EverySecond,
+            _ => Cadence::OnData,
+        }
+    }
+
+    /// Gets the name used for the screen over UART
+    ///
+    /// returns the screen's name
+    pub fn name(self) -> &'static str {
+        match self {
+            Screen::Temperature => "TEMP",
+            Screen::Humidity => "HUM",
+            Screen::Pressure => "PRESSURE",
+            Screen::Sensors => "SENSORS",
+            Screen::Date => "DATE",
+            Screen::Watering => "WATERING",
+            Screen::Prime => "PRIME",
+            Screen::Status => "STATUS",
+            Screen::Events => "EVENTS",
+            Screen::FactoryReset => "RESET",
+            Screen::Calibration => "CALIBRATION",
+            Screen::Display => "DISPLAY",
+            Screen::Gas => "GAS",
+            #[cfg(feature = "altitude")]
+            Screen::Altitude => "ALTITUDE",
+            Screen::Stats => "STATS",
+            Screen::Units => "UNITS",
+            Screen::Co2 => "CO2",
+            Screen::Gdd => "GDD",
+        }
+    }
+
+    /// Finds a screen by its [Screen::name], ignoring case
+    ///
+    /// - param name: The screen's name
+    ///
+    /// returns the [Screen], or None if no screen has that name
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// assert_eq!(Screen::from_name("gas"), Some(Screen::Gas));
+    /// assert_eq!(Screen::from_name("SOIL"), None);
+    /// for screen in Screen::ALL {
+    ///     assert_eq!(Screen::from_name(screen.name()), Some(*screen));
+    /// }
+    /// ```
+    pub fn from_name(name: &str) -> Option<Screen> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|screen| screen.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Gets the next screen, wrapping back to the first
+    ///
+    /// returns the next [Screen]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// assert_eq!(Screen::Temperature.next(), Screen::Humidity);
+    /// assert_eq!(Screen::ALL[Screen::ALL.len() - 1].next(), Screen::Temperature);
+    /// ```
+    pub fn next(self) -> Screen {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    /// Gets the previous screen, wrapping around to the last
+    ///
+    /// returns the previous [Screen]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// assert_eq!(Screen::Humidity.prev(), Screen::Temperature);
+    /// assert_eq!(Screen::Temperature.prev(), Screen::ALL[Screen::ALL.len() - 1]);
+    /// assert_eq!(Screen::Date.next().prev(), Screen::Date);
+    /// ```
+    pub fn prev(self) -> Screen {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Gets the next screen that isn't hidden, wrapping back to the first
+    ///
+    /// **NOTE: If every other screen is hidden, the screen stays the same**
+    ///
+    /// - param hidden: The hidden screens mask
+    ///
+    /// returns the next visible [Screen]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// // Nothing hidden behaves like next()
+    /// assert_eq!(Screen::Temperature.next_visible(0), Screen::Humidity);
+    ///
+    /// // Only the temperature, humidity and gas screens are shown
+    /// let shown = Screen::Temperature.mask() | Screen::Humidity.mask() | Screen::Gas.mask();
+    /// let hidden = Screen::ALL_HIDDEN & !shown;
+    /// let mut screen = Screen::Temperature;
+    /// let mut cycle = [screen; 4];
+    /// for step in cycle.iter_mut() {
+    ///     screen = screen.next_visible(hidden);
+    ///     *step = screen;
+    /// }
+    /// assert_eq!(cycle, [Screen::Humidity, Screen::Gas, Screen::Temperature, Screen::Humidity]);
+    /// assert_eq!(Screen::Temperature.prev_visible(hidden), Screen::Gas);
+    ///
+    /// // A hidden screen moves on to the next visible one
+    /// assert_eq!(Screen::Pressure.next_visible(hidden), Screen::Gas);
+    ///
+    /// // A single visible screen is the only stop
+    /// let hidden = Screen::ALL_HIDDEN & !Screen::Date.mask();
+    /// assert_eq!(Screen::Date.next_visible(hidden), Screen::Date);
+    /// assert_eq!(Screen::Date.prev_visible(hidden), Screen::Date);
+    /// ```
+    pub fn next_visible(self, hidden: u32) -> Screen {
+        let mut screen = self;
+        for _ in 0..Self::ALL.len() {
+            screen = screen.next();
+            if screen.is_visible(hidden) {
+                return screen;
+            }
+        }
+        self
+    }
+
+    /// Gets the previous screen that isn't hidden, wrapping around to the last
+    ///
+    /// **NOTE: If every other screen is hidden, the screen stays the same**
+    ///
+    /// - param hidden: The hidden screens mask
+    ///
+    /// returns the previous visible [Screen]
+    pub fn prev_visible(self, hidden: u32) -> Screen {
+        let mut screen = self;
+        for _ in 0..Self::ALL.len() {
+            screen = screen.prev();
+            if screen.is_visible(hidden) {
+                return screen;
+            }
+        }
+        self
+    }
+
+    /// Gets the next visible screen of the [Screen::DASHBOARD], wrapping back to the first.
+    /// Screens outside of the dashboard go to the first visible dashboard screen
+    ///
+    /// **NOTE: If every dashboard screen is hidden, the screen stays the same**
+    ///
+    /// - param hidden: The hidden screens mask
+    ///
+    /// returns the next dashboard [Screen]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// assert_eq!(Screen::Temperature.next_dashboard(0), Screen::Humidity);
+    /// assert_eq!(Screen::Date.next_dashboard(0), Screen::Temperature);
+    /// assert_eq!(Screen::FactoryReset.next_dashboard(0), Screen::Temperature);
+    ///
+    /// // Hidden screens are skipped
+    /// let hidden = Screen::Humidity.mask() | Screen::Pressure.mask();
+    /// assert_eq!(Screen::Temperature.next_dashboard(hidden), Screen::Date);
+    /// assert_eq!(Screen::Date.next_dashboard(Screen::ALL_HIDDEN), Screen::Date);
+    /// ```
+    pub fn next_dashboard(self, hidden: u32) -> Screen {
+        let start = Self::DASHBOARD
+            .iter()
+            .position(|screen| *screen == self)
+            .map_or(0, |index| index + 1);
+        (0..Self::DASHBOARD.len())
+            .map(|offset| Self::DASHBOARD[(start + offset) % Self::DASHBOARD.len()])
+            .find(|screen| screen.is_visible(hidden))
+            .unwrap_or(self)
+    }
+
+    /// Gets this screen if it is shown, otherwise the next visible one.
+    /// Used to land on a screen that may have been hidden since it was chosen
+    ///
+    /// - param hidden: The hidden screens mask
+    ///
+    /// returns the visible [Screen]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::screens::Screen;
+    ///
+    /// assert_eq!(Screen::Date.or_next_visible(0), Screen::Date);
+    ///
+    /// // A hidden start screen falls back to the next one shown
+    /// assert_eq!(Screen::Date.or_next_visible(Screen::Date.mask()), Screen::Date.next());
+    /// let hidden = Screen::Date.mask() | Screen::Date.next().mask();
+    /// assert_eq!(Screen::Date.or_next_visible(hidden), Screen::Date.next().next());
+    ///
+    /// // Wrapping around to the first screen
+    /// let last = Screen::ALL[Screen::ALL.len() - 1];
+    /// assert_eq!(last.or_next_visible(last.mask()), Screen::Temperature);
+    ///
+    /// // The only screen shown
+    /// let hidden = Screen::ALL_HIDDEN & !Screen::Gas.mask();
+    /// assert_eq!(Screen::Date.or_next_visible(hidden), Screen::Gas);
+    /// ```
+    pub fn or_next_visible(self, hidden: u32) -> Screen {
+        if self.is_visible(hidden) {
+            self
+        } else {
+            self.next_visible(hidden)
+        }
+    }
+
+    /// Gets the [ScreenHandler] that renders and edits this screen
+    ///
+    /// returns the screen's [ScreenHandler]
+    #[cfg(feature = "hardware")]
+    pub fn handler(self) -> &'static dyn ScreenHandler {
+        match self {
+            Screen::Temperature => &TemperatureScreen,
+            Screen::Humidity => &HumidityScreen,
+            Screen::Pressure => &PressureScreen,
+            Screen::Sensors => &SensorsScreen,
+            Screen::Date => &DateScreen,
+            Screen::Watering => &WateringScreen,
+            Screen::Prime => &PrimeScreen,
+            Screen::Status => &StatusScreen,
+            Screen::Events => &EventsScreen,
+            Screen::FactoryReset => &FactoryResetScreen,
+            Screen::Calibration => &CalibrationScreen,
+            Screen::Display => &DisplayScreen,
+            Screen::Gas => &GasScreen,
+            #[cfg(feature = "altitude")]
+            Screen::Altitude => &AltitudeScreen,
+            Screen::Stats => &StatsScreen,
+            Screen::Units => &UnitsScreen,
+            Screen::Co2 => &Co2Screen,
+            Screen::Gdd => &GddScreen,
+        }
+    }
+}
+
+/// How often a shown [Screen] is drawn again, so the LCD is only written when something changed
+///
+/// - **EverySecond**: The screen shows the clock, so it is drawn every second
+/// - **OnData**: The screen shows readings, so it is only drawn when fresh data arrives
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cadence {
+    EverySecond,
+    OnData,
+}
+
+/// Checks if a shown screen should be drawn again.
+/// Button presses and screen changes always draw the screen, so this only covers a screen left alone
+///
+/// - param cadence: the screen's [Cadence]
+/// - param elapsed_secs: seconds on the clock since the screen was last drawn, or None if it never was
+/// - param fresh_data: if a new reading arrived since the screen was last drawn
+///
+/// returns true if the screen should be drawn now
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::screens::{needs_render, Cadence};
+///
+/// // Screens are always drawn the first time
+/// assert!(needs_render(Cadence::EverySecond, None, false));
+/// assert!(needs_render(Cadence::OnData, None, false));
+///
+/// // The clock is drawn once the second changes, whether or not there is data
+/// assert!(!needs_render(Cadence::EverySecond, Some(0), false));
+/// assert!(!needs_render(Cadence::EverySecond, Some(0), true));
+/// assert!(needs_render(Cadence::EverySecond, Some(1), false));
+/// assert!(needs_render(Cadence::EverySecond, Some(5), false));
+///
+/// // Readings are only drawn when they change, however long ago they were drawn
+/// assert!(!needs_render(Cadence::OnData, Some(1), false));
+/// assert!(!needs_render(Cadence::OnData, Some(60), false));
+/// assert!(needs_render(Cadence::OnData, Some(0), true));
+/// assert!(needs_render(Cadence::OnData, Some(2), true));
+/// ```
+pub fn needs_render(cadence: Cadence, elapsed_secs: Option<u32>, fresh_data: bool) -> bool {
+    match (cadence, elapsed_secs) {
+        (_, None) => true,
+        (Cadence::EverySecond, Some(secs)) => secs > 0,
+        (Cadence::OnData, Some(_)) => fresh_data,
+    }
+}
+
+/// When each [Screen] was last drawn, by the clock
+#[derive(Default)]
+pub struct RenderTimes {
+    last: [Option<DateTime>; Screen::ALL.len()],
+}
+
+impl RenderTimes {
+    /// Checks if a shown screen should be drawn again. See [needs_render]
+    ///
+    /// - param screen: the shown [Screen]
+    /// - param now: the current date and time
+    /// - param fresh_data: if a new reading just arrived
+    ///
+    /// returns true if the screen should be drawn now
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    /// use gem_rs::screens::{RenderTimes, Screen};
+    ///
+    /// let mut times = RenderTimes::default();
+    /// let now = DateTime(30, 0, 12, 1, 1, 2000);
+    /// assert!(times.should_render(Screen::Date, &now, false));
+    /// times.rendered(Screen::Date, &now);
+    /// times.rendered(Screen::Temperature, &now);
+    ///
+    /// assert!(!times.should_render(Screen::Date, &now, false));
+    /// assert!(!times.should_render(Screen::Temperature, &now, false));
+    ///
+    /// let later = DateTime(31, 0, 12, 1, 1, 2000);
+    /// assert!(times.should_render(Screen::Date, &later, false));
+    /// assert!(!times.should_render(Screen::Temperature, &later, false));
+    /// assert!(times.should_render(Screen::Temperature, &later, true));
+    ///
+    /// // Setting the clock back still redraws it
+    /// assert!(times.should_render(Screen::Date, &DateTime(0, 0, 9, 1, 1, 2000), false));
+    /// ```
+    pub fn should_render(&self, screen: Screen, now: &DateTime, fresh_data: bool) -> bool {
+        let elapsed_secs = self.last[screen.index()].map(|last| {
+            if *now == last {
+                0
+            } else {
+                now.seconds_since(&last).max(1)
+            }
+        });
+        needs_render(screen.cadence(), elapsed_secs, fresh_data)
+    }
+
+    /// Records that a screen was just drawn
+    ///
+    /// - param screen: the drawn [Screen]
+    /// - param now: the current date and time
+    pub fn rendered(&mut self, screen: Screen, now: &DateTime) {
+        self.last[screen.index()] = Some(*now);
+    }
+}
+
+/// Everything a [ScreenHandler] may need to render or edit a screen
+///
+/// - **lcd**: [Lcd] instance
+/// - **delay**: [Timer] instance
+/// - **up_button**: Up button instance
+/// - **down_button**: Down button instance
+/// - **select_button**: Select button instance
+/// - **preferences**: [Preferences] instance
+/// - **data**: The latest [Reading]
+/// - **pressure_history**: [PressureHistory] instance
+/// - **sensors**: Each fitted sensor's latest raw [Reading], or None if it failed
+/// - **sensor_addresses**: The I2C address each sensor was found on, or None if it isn't known
+/// - **stats**: The daily and all-time [SensorStats]
+/// - **events**: [EventLog] instance
+/// - **display**: [DisplayPwm] instance
+/// - **supply_mv**: The latest supply voltage in millivolts
+/// - **co2**: The latest CO2 in ppm, or None if no CO2 sensor is fitted or it has gone stale
+#[cfg(feature = "hardware")]
+pub struct Context<'a> {
+    pub lcd: &'a mut Lcd,
+    pub delay: &'a mut Timer,
+    pub up_button: &'a mut Pin<Gpio10, FunctionSio<SioInput>, PullDown>,
+    pub down_button: &'a mut Pin<Gpio11, FunctionSio<SioInput>, PullDown>,
+    pub select_button: &'a mut Pin<Gpio12, FunctionSio<SioInput>, PullDown>,
+    pub preferences: &'a mut Preferences,
+    pub data: &'a Reading,
+    pub pressure_history: &'a PressureHistory,
+    pub sensors: &'a [Option<Reading>],
+    pub sensor_addresses: [Option<u8>; 2],
+    pub stats: &'a SensorStats,
+    pub events: &'a EventLog,
+    pub display: &'a mut DisplayPwm,
+    pub supply_mv: u16,
+    pub co2: Option<u16>,
+}
+
+/// Renders and edits a single [Screen]
+#[cfg(feature = "hardware")]
+pub trait ScreenHandler {
+    /// Renders the screen's data onto the [Lcd]
+    ///
+    /// - param ctx: [Context] instance
+    fn render(&self, ctx: &mut Context);
+
+    /// Runs the screen's configuration after SELECT is pressed.
+    /// Screens have no configuration by default
+    ///
+    /// - param ctx: [Context] instance
+    fn edit(&self, _ctx: &mut Context) {}
+}
+
+/// Edits a `(min, max)` range from 0 to 100, lower bound first.
+/// Suspicious ranges show a warning, but can still be confirmed
+///
+/// - param ctx: [Context] instance
+/// - param range: Gets the range being edited from the [Preferences]
+/// - param label: Put in front of the range to tell it apart from others; empty for none
+/// - param suffix: The unit appended to each bound; Ex: `%`
+/// - param offset: Added to each bound when it is shown
+/// - param limits: The [RangeLimits] used to warn about the range
+#[cfg(feature = "hardware")]
+fn edit_range(
+    ctx: &mut Context,
+    range: fn(&mut Preferences) -> &mut (u8, u8),
+    label: &str,
+    suffix: &str,
+    offset: u16,
+    limits: &RangeLimits,
+) {
+    let mut editing_lower: bool = true;
+    let mut update_date: bool = false;
+    let mut refresh: bool = true;
+    let mut info_str: String<16> = String::new();
+    for _ in 0..2 {
+        loop {
+            if refresh {
+                let (low, high) = *range(ctx.preferences);
+                if !label.is_empty() {
+                    uwrite!(Truncating(&mut info_str), "{} ", label).unwrap();
+                }
+                uwrite!(
+                    Truncating(&mut info_str),
+                    "{}{} - {}{}",
+                    low as u16 + offset,
+                    suffix,
+                    high as u16 + offset,
+                    suffix
+                )
+                .unwrap();
+                render_edit_screen(&info_str, editing_lower, ctx.lcd, ctx.delay);
+                if let Some(warning) = range_warning((low, high), limits) {
+                    render_range_warning(warning, ctx.lcd, ctx.delay);
+                }
+                info_str.clear();
+                refresh = false;
+            }
+
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            let bounds = range(ctx.preferences);
+            if ctx.up_button.is_high().unwrap() {
+                if editing_lower {
+                    if bounds.0 < 100 {
+                        bounds.0 += 1;
+                    }
+                } else if bounds.1 < 100 {
+                    bounds.1 += 1;
+                }
+                refresh = true;
+            } else if ctx.down_button.is_high().unwrap() {
+                if editing_lower {
+                    if bounds.0 > 0 {
+                        bounds.0 -= 1;
+                    }
+                } else if bounds.1 > 0 {
+                    bounds.1 -= 1;
+                }
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                editing_lower = false;
+                render_selector(false, 15, ctx.lcd, ctx.delay);
+                refresh = true;
+                break;
+            }
+        }
+    }
+    // Check legality
+    let bounds = range(ctx.preferences);
+    if bounds.0 > bounds.1 {
+        core::mem::swap(&mut bounds.0, &mut bounds.1);
+    }
+}
+
+/// Edits a value in fixed steps; UP and DOWN step it and SELECT confirms
+///
+/// - param ctx: [Context] instance
+/// - param label: The name of the value
+/// - param suffix: The unit appended to the value; Ex: `ms`
+/// - param value: The current value
+/// - param limits: The smallest and largest allowed value
+/// - param step: How much each press changes the value by
+///
+/// returns the value after modification, within the limits
+#[cfg(feature = "hardware")]
+fn edit_stepped(
+    ctx: &mut Context,
+    label: &str,
+    suffix: &str,
+    mut value: u16,
+    limits: (u16, u16),
+    step: u16,
+) -> u16 {
+    let mut update_date: bool = false;
+    let mut refresh: bool = true;
+    let mut info_str: String<16> = String::new();
+    value = value.clamp(limits.0, limits.1);
+    loop {
+        if refresh {
+            uwrite!(Truncating(&mut info_str), "{}: {}{}", label, value, suffix).unwrap();
+            render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+            info_str.clear();
+            refresh = false;
+        }
+        ctx.delay.delay_ms(500);
+
+        if update_date {
+            ctx.preferences.tick_time();
+            feed_watchdog();
+        }
+        update_date = !update_date;
+
+        if ctx.up_button.is_high().unwrap() {
+            value = value.saturating_add(step).min(limits.1);
+            refresh = true;
+        } else if ctx.down_button.is_high().unwrap() {
+            value = value.saturating_sub(step).max(limits.0);
+            refresh = true;
+        } else if ctx.select_button.is_high().unwrap() {
+            return value;
+        }
+    }
+}
+
+/// Edits a `(min, max)` range from 0 to 100 as a [Setpoint]: the target first, then the swing either side of it.
+/// Both step by whole degrees, so the half degree of an odd-width range is kept
+///
+/// - param ctx: [Context] instance
+/// - param range: Gets the range being edited from the [Preferences]
+/// - param label: The name of the target
+/// - param offset: Added to the target when it is shown
+#[cfg(feature = "hardware")]
+fn edit_setpoint(
+    ctx: &mut Context,
+    range: fn(&mut Preferences) -> &mut (u8, u8),
+    label: &str,
+    offset: u16,
+) {
+    let setpoint = Setpoint::from_range(*range(ctx.preferences));
+    let half = if setpoint.half { ".5" } else { "" };
+    let target = edit_stepped(
+        ctx,
+        label,
+        half,
+        setpoint.target as u16 + offset,
+        (offset, 100 + offset),
+        1,
+    ) - offset;
+    let swing = edit_stepped(ctx, LABELS.swing, half, setpoint.swing as u16, (0, 50), 1);
+    let edited = Setpoint {
+        target: target as u8,
+        swing: swing as u8,
+        half: setpoint.half,
+    };
+    if edited != setpoint {
+        let (low, high) = edited.to_range();
+        *range(ctx.preferences) = (low, high.min(100));
+    }
+}
+
+/// Edits a temperature range in the selected [TemperatureUnit](crate::preferences::TemperatureUnit),
+/// as a range or a [Setpoint] depending on the [TemperatureModel].
+/// Ranges are stored in Fahrenheit, so a bound left alone keeps its exact stored value
+///
+/// - param ctx: [Context] instance
+/// - param range: Gets the range being edited from the [Preferences]
+/// - param label: Put in front of the range to tell it apart from others
+#[cfg(feature = "hardware")]
+fn edit_temperature_range(
+    ctx: &mut Context,
+    range: fn(&mut Preferences) -> &mut (u8, u8),
+    label: &str,
+) {
+    let unit = ctx.preferences.temperature_unit;
+    let offset = unit.edit_offset();
+    let stored = *range(ctx.preferences);
+    // The range is edited in place in the shown unit, then converted back
+    let shown = (
+        (unit.from_fahrenheit(stored.0) - offset).clamp(0, 100) as u8,
+        (unit.from_fahrenheit(stored.1) - offset).clamp(0, 100) as u8,
+    );
+    *range(ctx.preferences) = shown;
+    match ctx.preferences.temperature_model {
+        TemperatureModel::Range => edit_range(ctx, range, label, "", offset as u16, unit.limits()),
+        TemperatureModel::Setpoint => edit_setpoint(ctx, range, label, offset as u16),
+    }
+    let edited = *range(ctx.preferences);
+    let convert = |edited: u8, shown: u8, stored: u8| {
+        if edited == shown {
+            stored
+        } else {
+            unit.to_fahrenheit(edited as i16 + offset)
+        }
+    };
+    *range(ctx.preferences) = if edited == (shown.1, shown.0) {
+        // Swapped because it was inverted, but otherwise untouched
+        (stored.1, stored.0)
+    } else {
+        (
+            convert(edited.0, shown.0, stored.0),
+            convert(edited.1, shown.1, stored.1),
+        )
+    };
+}
+
+#[cfg(feature = "hardware")]
+struct TemperatureScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for TemperatureScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        let temperature = get_temperature(ctx.data);
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}",
+            LABELS.temperature,
+            ctx.preferences.format_temperature(temperature).as_str()
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        // Show the range the vent is keeping right now
+        let (band, (low, high)) = active_temperature(ctx.preferences);
+        render_band_icon(
+            classify_band(temperature, (low, high)),
+            data_str.len(),
+            ctx.lcd,
+            ctx.delay,
+        );
+        data_str.clear();
+        let unit = ctx.preferences.temperature_unit;
+        match ctx.preferences.temperature_model {
+            TemperatureModel::Range => uwrite!(
+                Truncating(&mut data_str),
+                "{} {}-{}",
+                band.label(),
+                unit.from_fahrenheit(low),
+                ctx.preferences.format_temperature(high).as_str()
+            )
+            .unwrap(),
+            TemperatureModel::Setpoint => {
+                // The setpoint is worked out in the shown unit, so the swing is in its degrees
+                let offset = unit.edit_offset();
+                let shown = |fahrenheit| {
+                    (unit.from_fahrenheit(fahrenheit) - offset).clamp(0, u8::MAX as i16) as u8
+                };
+                let setpoint = Setpoint::from_range((shown(low), shown(high)));
+                let half = if setpoint.half { ".5" } else { "" };
+                uwrite!(
+                    Truncating(&mut data_str),
+                    "{} {}{}{}+-{}{}",
+                    band.label(),
+                    setpoint.target as i16 + offset,
+                    half,
+                    unit.symbol(),
+                    setpoint.swing,
+                    half
+                )
+                .unwrap()
+            }
+        }
+        // Above the range, show how far the vent curve calls for the vent to open
+        if temperature > high {
+            uwrite!(
+                Truncating(&mut data_str),
+                " {}%",
+                vent_opening(temperature, ctx.preferences)
+            )
+            .unwrap();
+        }
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        edit_temperature_range(
+            ctx,
+            |preferences| &mut preferences.temperature,
+            LABELS.day_band,
+        );
+        edit_temperature_range(
+            ctx,
+            |preferences| &mut preferences.night_temperature,
+            LABELS.night_band,
+        );
+        edit_temperature_range(
+            ctx,
+            |preferences| &mut preferences.temperature_alarm,
+            LABELS.alarm,
+        );
+        // The alarm band can't be narrower than the ranges the vent keeps
+        ctx.preferences.normalize_temperature_alarm();
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct HumidityScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for HumidityScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        let humidity = get_humidity(ctx.data);
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}%",
+            LABELS.humidity,
+            humidity
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        render_band_icon(
+            classify_band(humidity, ctx.preferences.humidity),
+            data_str.len(),
+            ctx.lcd,
+            ctx.delay,
+        );
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "({}%, {}%)",
+            ctx.preferences.humidity.0,
+            ctx.preferences.humidity.1
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        edit_range(
+            ctx,
+            |preferences| &mut preferences.humidity,
+            "",
+            "%",
+            0,
+            &HUMIDITY_LIMITS,
+        );
+
+        let mut info_str: String<16> = String::new();
+        ctx.preferences.mist_pulse = render_offset_config_screen(
+            LABELS.mist_pulse,
+            &mut info_str,
+            1,
+            60,
+            ctx.preferences.mist_pulse as i16,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) as u8;
+
+        ctx.preferences.mist_dwell = render_offset_config_screen(
+            LABELS.mist_dwell,
+            &mut info_str,
+            0,
+            60,
+            ctx.preferences.mist_dwell as i16,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) as u8;
+
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct PressureScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for PressureScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}",
+            LABELS.pressure,
+            ctx.preferences.format_pressure(ctx.data.pressure).as_str()
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        render_screen(
+            ctx.pressure_history
+                .trend(ctx.preferences.pressure_trend_threshold)
+                .label(),
+            false,
+            ctx.lcd,
+            ctx.delay,
+        );
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Cycle through the units
+        ctx.preferences.pressure_unit = ctx.preferences.pressure_unit.next();
+    }
+}
+
+/// Gets the hex digit for the low 4 bits of a value
+///
+/// - param nibble: The value; only its low 4 bits are used
+///
+/// returns the uppercase hex digit
+#[cfg(feature = "hardware")]
+fn hex_digit(nibble: u8) -> char {
+    char::from_digit((nibble & 0xF) as u32, 16)
+        .unwrap()
+        .to_ascii_uppercase()
+}
+
+#[cfg(feature = "hardware")]
+struct SensorsScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for SensorsScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        for (index, reading) in ctx.sensors.iter().enumerate() {
+            // Each sensor is named by the address it was found on, so the strapping can be checked
+            match ctx.sensor_addresses.get(index).copied().flatten() {
+                Some(address) => uwrite!(
+                    Truncating(&mut data_str),
+                    "{}{}",
+                    hex_digit(address >> 4),
+                    hex_digit(address & 0xF)
+                )
+                .unwrap(),
+                None => uwrite!(Truncating(&mut data_str), "{}", index + 1).unwrap(),
+            }
+            match reading {
+                Some(reading) => {
+                    let reading = reading.with_offsets(
+                        ctx.preferences.temp_offset,
+                        ctx.preferences.humidity_offset,
+                        ctx.preferences.pressure_offset,
+                    );
+                    uwrite!(
+                        Truncating(&mut data_str),
+                        " {} {}%",
+                        ctx.preferences
+                            .format_temperature(get_temperature(&reading))
+                            .as_str(),
+                        get_humidity(&reading)
+                    )
+                    .unwrap();
+                }
+                None => uwrite!(Truncating(&mut data_str), " {}", LABELS.sensor_fault).unwrap(),
+            }
+            if index == 0 {
+                // Combining only matters while both sensors are working
+                if ctx.sensors.len() > 1 && ctx.sensors.iter().all(Option::is_some) {
+                    uwrite!(
+                        Truncating(&mut data_str),
+                        " {}",
+                        ctx.preferences.sensor_combine.label()
+                    )
+                    .unwrap();
+                }
+                render_screen(&data_str, true, ctx.lcd, ctx.delay);
+            } else {
+                render_screen(&data_str, false, ctx.lcd, ctx.delay);
+            }
+            data_str.clear();
+        }
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Cycle through the ways of combining the sensors
+        ctx.preferences.sensor_combine = ctx.preferences.sensor_combine.next();
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct UnitsScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for UnitsScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        let system = ctx.preferences.unit_system;
+        uwrite!(Truncating(&mut data_str), "{}", system.label()).unwrap();
+        // Mark units changed on their own since the preset was applied
+        if system.units()
+            != (
+                ctx.preferences.temperature_unit,
+                ctx.preferences.pressure_unit,
+            )
+        {
+            data_str.push('*').unwrap();
+        }
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{} {}",
+            ctx.preferences.temperature_unit.symbol(),
+            ctx.preferences.pressure_unit.symbol()
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Flip between the presets
+        let system = ctx.preferences.unit_system.next();
+        ctx.preferences.apply_unit_system(system);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct StatsScreen;
+
+#[cfg(feature = "hardware")]
+impl StatsScreen {
+    /// Renders the extremes of a period, with the period's label at the end of the bottom line
+    ///
+    /// - param ctx: [Context] instance
+    /// - param extremes: The [Extremes] of the period; None if nothing has been read yet
+    /// - param period: The period's label
+    fn render_extremes(ctx: &mut Context, extremes: Option<Extremes>, period: &str) {
+        let mut data_str: String<16> = String::new();
+        match extremes {
+            Some(extremes) => uwrite!(
+                Truncating(&mut data_str),
+                "{} {}-{}",
+                LABELS.temperature,
+                ctx.preferences
+                    .temperature_unit
+                    .from_fahrenheit(extremes.temperature.min),
+                ctx.preferences
+                    .format_temperature(extremes.temperature.max)
+                    .as_str()
+            )
+            .unwrap(),
+            None => uwrite!(Truncating(&mut data_str), "{} -", LABELS.temperature).unwrap(),
+        }
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+
+        match extremes {
+            Some(extremes) => uwrite!(
+                Truncating(&mut data_str),
+                "{} {}-{}%",
+                LABELS.humidity,
+                extremes.humidity.min,
+                extremes.humidity.max
+            )
+            .unwrap(),
+            None => uwrite!(Truncating(&mut data_str), "{} -", LABELS.humidity).unwrap(),
+        }
+        while data_str.len() + period.len() < data_str.capacity() {
+            data_str.push(' ').unwrap();
+        }
+        data_str.push_str(period).unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for StatsScreen {
+    fn render(&self, ctx: &mut Context) {
+        let daily = ctx.stats.daily;
+        Self::render_extremes(ctx, daily, LABELS.today);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // UP and DOWN switch between today's and the all-time extremes
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut all_time: bool = true;
+        loop {
+            if refresh {
+                let stats = *ctx.stats;
+                if all_time {
+                    Self::render_extremes(ctx, stats.all_time, LABELS.all_time);
+                } else {
+                    Self::render_extremes(ctx, stats.daily, LABELS.today);
+                }
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() || ctx.down_button.is_high().unwrap() {
+                all_time = !all_time;
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct DateScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for DateScreen {
+    fn render(&self, ctx: &mut Context) {
+        let (time, date) = ctx.preferences.get_date_display();
+        render_screen(&time, true, ctx.lcd, ctx.delay);
+        render_screen(&date, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut info_str: String<16> = String::new();
+
+        ctx.preferences.date.1 = render_time_config_screen(
+            LABELS.minute,
+            &mut info_str,
+            0,
+            59,
+            ctx.preferences.date.1,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        );
+        info_str.clear();
+
+        ctx.preferences.date.2 = render_time_config_screen(
+            LABELS.hour,
+            &mut info_str,
+            0,
+            23,
+            ctx.preferences.date.2,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        );
+        info_str.clear();
+
+        ctx.preferences.date.3 = render_time_config_screen(
+            LABELS.day,
+            &mut info_str,
+            1,
+            ctx.preferences.get_days_in_month(),
+            ctx.preferences.date.3,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        );
+        info_str.clear();
+
+        ctx.preferences.date.4 = render_time_config_screen(
+            LABELS.month,
+            &mut info_str,
+            1,
+            12,
+            ctx.preferences.date.4,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        );
+        info_str.clear();
+
+        // Year
+        loop {
+            if refresh {
+                uwrite!(
+                    Truncating(&mut info_str),
+                    "{}: {}",
+                    LABELS.year,
+                    ctx.preferences.date.5
+                )
+                .unwrap();
+                render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+                info_str.clear();
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() {
+                if ctx.preferences.date.5 < MAX_YEAR {
+                    ctx.preferences.date.5 += 1;
+                }
+                refresh = true;
+            } else if ctx.down_button.is_high().unwrap() {
+                if ctx.preferences.date.5 != 0 {
+                    ctx.preferences.date.5 -= 1;
+                }
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+
+        // Move days that don't exist in the chosen month, such as Feb 30
+        ctx.preferences.date = ctx.preferences.date.normalize();
+
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct WateringScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for WateringScreen {
+    fn render(&self, ctx: &mut Context) {
+        render_screen(
+            &ctx.preferences.format_watering_time(),
+            true,
+            ctx.lcd,
+            ctx.delay,
+        );
+        if ctx.preferences.watering.is_some() {
+            render_screen(
+                &ctx.preferences.format_watering_days(),
+                false,
+                ctx.lcd,
+                ctx.delay,
+            );
+        }
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut remove: bool = false;
+        for index in 0..4 {
+            loop {
+                if refresh {
+                    render_watering_edit_screen(
+                        &ctx.preferences.format_watering_time(),
+                        index,
+                        ctx.lcd,
+                        ctx.delay,
+                    );
+                    refresh = false;
+                }
+
+                ctx.delay.delay_ms(500);
+
+                if update_date {
+                    ctx.preferences.tick_time();
+                    feed_watchdog();
+                }
+                update_date = !update_date;
+
+                if ctx.up_button.is_high().unwrap() && ctx.down_button.is_high().unwrap() {
+                    if ctx.preferences.watering.is_none()
+                        || confirm_hold(
+                            LABELS.remove_prompt,
+                            ctx.preferences,
+                            ctx.lcd,
+                            ctx.delay,
+                            ctx.up_button,
+                            ctx.down_button,
+                            ctx.select_button,
+                        )
+                    {
+                        remove = true;
+                        break;
+                    }
+                    // Kept; show the window again
+                    refresh = true;
+                    continue;
+                }
+
+                if ctx.up_button.is_high().unwrap() {
+                    if ctx.preferences.watering.is_none() {
+                        ctx.preferences.set_default_watering_time();
+                    } else if let Some((
+                        ref mut min_low,
+                        ref mut hr_low,
+                        ref mut min_high,
+                        ref mut hr_high,
+                    )) = ctx.preferences.watering
+                    {
+                        match index {
+                            0 => *hr_low = inclusive_iterator(*hr_low, 0, 23, true),
+                            1 => *min_low = inclusive_iterator(*min_low, 0, 59, true),
+                            2 => *hr_high = inclusive_iterator(*hr_high, 0, 23, true),
+                            3 => *min_high = inclusive_iterator(*min_high, 0, 59, true),
+                            _ => {}
+                        }
+                    }
+                    refresh = true;
+                } else if ctx.down_button.is_high().unwrap() {
+                    if ctx.preferences.watering.is_none() {
+                        ctx.preferences.set_default_watering_time();
+                    } else if let Some((
+                        ref mut min_low,
+                        ref mut hr_low,
+                        ref mut min_high,
+                        ref mut hr_high,
+                    )) = ctx.preferences.watering
+                    {
+                        match index {
+                            0 => *hr_low = inclusive_iterator(*hr_low, 0, 23, false),
+                            1 => *min_low = inclusive_iterator(*min_low, 0, 59, false),
+                            2 => *hr_high = inclusive_iterator(*hr_high, 0, 23, false),
+                            3 => *min_high = inclusive_iterator(*min_high, 0, 59, false),
+                            _ => {}
+                        }
+                    }
+                    refresh = true;
+                } else if ctx.select_button.is_high().unwrap() {
+                    remove = ctx.preferences.watering.is_none();
+                    refresh = true;
+                    break;
+                }
+            }
+            if remove {
+                break;
+            }
+        }
+        // Check legality
+        if remove {
+            ctx.preferences.watering = None;
+        } else {
+            ctx.preferences.normalize_watering();
+
+            // Choose which days to water on
+            let mut days_str: String<16> = String::new();
+            render_days_config_screen(
+                &mut days_str,
+                ctx.preferences,
+                ctx.lcd,
+                ctx.delay,
+                ctx.up_button,
+                ctx.down_button,
+                ctx.select_button,
+            );
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct PrimeScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for PrimeScreen {
+    fn render(&self, ctx: &mut Context) {
+        render_screen(LABELS.prime, true, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        if !render_confirm_screen(
+            LABELS.prime_prompt,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) {
+            return;
+        }
+
+        let mut update_date: bool = false;
+        let mut info_str: String<16> = String::new();
+        let mut countdown = CountDownTimer::new(PRIME_MS);
+        // Core1 runs the sprinklers while priming is requested
+        shared::request(|requests| requests.priming = true);
+        loop {
+            // Round up so the countdown ends on 1s
+            uwrite!(
+                Truncating(&mut info_str),
+                "{}: {}s",
+                LABELS.priming,
+                countdown.remaining().div_ceil(1000)
+            )
+            .unwrap();
+            render_screen(&info_str, true, ctx.lcd, ctx.delay);
+            info_str.clear();
+
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            match prime_step(
+                &mut countdown,
+                500,
+                ctx.select_button.is_high().unwrap(),
+                shared::status().fire,
+            ) {
+                PrimeStatus::Running => {}
+                // The smoke alarm on core1 has already taken the sprinklers over
+                PrimeStatus::Finished | PrimeStatus::Cancelled | PrimeStatus::Aborted => break,
+            }
+        }
+        // Core1 returns the sprinklers to automatic control
+        shared::request(|requests| requests.priming = false);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct StatusScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for StatusScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}mV",
+            LABELS.supply,
+            ctx.supply_mv
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}mV",
+            LABELS.minimum,
+            ctx.preferences.low_voltage
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Low voltage threshold
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut info_str: String<16> = String::new();
+        loop {
+            if refresh {
+                uwrite!(
+                    Truncating(&mut info_str),
+                    "{}: {}mV",
+                    LABELS.minimum,
+                    ctx.preferences.low_voltage
+                )
+                .unwrap();
+                render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+                info_str.clear();
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() {
+                if ctx.preferences.low_voltage < MAX_LOW_VOLTAGE {
+                    ctx.preferences.low_voltage += VOLTAGE_STEP;
+                }
+                refresh = true;
+            } else if ctx.down_button.is_high().unwrap() {
+                // The warning has to stay above the critical cutoff to ever be seen
+                let lowest = MIN_LOW_VOLTAGE.max(ctx.preferences.critical_voltage + VOLTAGE_STEP);
+                if ctx.preferences.low_voltage >= lowest + VOLTAGE_STEP {
+                    ctx.preferences.low_voltage -= VOLTAGE_STEP;
+                }
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct EventsScreen;
+
+#[cfg(feature = "hardware")]
+impl EventsScreen {
+    /// Renders an event, counting back from the newest
+    ///
+    /// - param ctx: [Context] instance
+    /// - param index: how many events back to go, where 0 is the newest
+    fn render_event(ctx: &mut Context, index: usize) {
+        if let Some(event) = ctx.events.newest(index) {
+            render_screen(
+                &event.format_kind(index as u8 + 1),
+                true,
+                ctx.lcd,
+                ctx.delay,
+            );
+            render_screen(&event.format_timestamp(), false, ctx.lcd, ctx.delay);
+        } else {
+            render_screen(LABELS.events, true, ctx.lcd, ctx.delay);
+            render_screen(LABELS.no_events, false, ctx.lcd, ctx.delay);
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for EventsScreen {
+    fn render(&self, ctx: &mut Context) {
+        Self::render_event(ctx, 0);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Scroll through the events; UP goes back in time and DOWN goes forward
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut index: usize = 0;
+        loop {
+            if refresh {
+                Self::render_event(ctx, index);
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() {
+                if index + 1 < ctx.events.len() {
+                    index += 1;
+                }
+                refresh = true;
+            } else if ctx.down_button.is_high().unwrap() {
+                index = index.saturating_sub(1);
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct FactoryResetScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for FactoryResetScreen {
+    fn render(&self, ctx: &mut Context) {
+        render_screen(LABELS.factory_reset, true, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Ask, then make SELECT be held, to avoid accidental wipes
+        if render_confirm_screen(
+            LABELS.reset_prompt,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) && confirm_hold(
+            LABELS.confirm_prompt,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) {
+            ctx.preferences.reset_to_defaults();
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct CalibrationScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for CalibrationScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: T{}F H{}%",
+            LABELS.calibration,
+            ctx.preferences.temp_offset,
+            ctx.preferences.humidity_offset
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {} mb",
+            LABELS.pressure,
+            ctx.preferences.pressure_offset
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        let mut info_str: String<16> = String::new();
+
+        ctx.preferences.temp_offset = render_offset_config_screen(
+            LABELS.temperature,
+            &mut info_str,
+            -20,
+            20,
+            ctx.preferences.temp_offset as i16,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) as i8;
+
+        ctx.preferences.humidity_offset = render_offset_config_screen(
+            LABELS.humidity,
+            &mut info_str,
+            -20,
+            20,
+            ctx.preferences.humidity_offset as i16,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        ) as i8;
+
+        ctx.preferences.pressure_offset = render_offset_config_screen(
+            LABELS.pressure,
+            &mut info_str,
+            -50,
+            50,
+            ctx.preferences.pressure_offset,
+            ctx.preferences,
+            ctx.lcd,
+            ctx.delay,
+            ctx.up_button,
+            ctx.down_button,
+            ctx.select_button,
+        );
+
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+/// Edits a display setting in steps of 5%, applying each step as it is made
+///
+/// - param ctx: [Context] instance
+/// - param label: The setting's label
+/// - param min: The lowest the setting can go
+/// - param setting: Gets the setting being edited from the [Preferences]
+#[cfg(feature = "hardware")]
+fn edit_display_setting(
+    ctx: &mut Context,
+    label: &str,
+    min: u8,
+    setting: fn(&mut Preferences) -> &mut u8,
+) {
+    let mut update_date: bool = false;
+    let mut refresh: bool = true;
+    let mut info_str: String<16> = String::new();
+    loop {
+        if refresh {
+            uwrite!(
+                Truncating(&mut info_str),
+                "{}: {}%",
+                label,
+                *setting(ctx.preferences)
+            )
+            .unwrap();
+            render_edit_screen(&info_str, true, ctx.lcd, ctx.delay);
+            info_str.clear();
+            ctx.display.apply(ctx.preferences);
+            refresh = false;
+        }
+
+        ctx.delay.delay_ms(500);
+
+        if update_date {
+            ctx.preferences.tick_time();
+            feed_watchdog();
+        }
+        update_date = !update_date;
+
+        let value = setting(ctx.preferences);
+        if ctx.up_button.is_high().unwrap() {
+            *value = value.saturating_add(5).min(100);
+            refresh = true;
+        } else if ctx.down_button.is_high().unwrap() {
+            *value = value.saturating_sub(5).max(min);
+            refresh = true;
+        } else if ctx.select_button.is_high().unwrap() {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct DisplayScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for DisplayScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}%",
+            LABELS.contrast,
+            ctx.preferences.contrast
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}%",
+            LABELS.brightness,
+            ctx.preferences.brightness
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        edit_display_setting(ctx, LABELS.contrast, MIN_CONTRAST, |preferences| {
+            &mut preferences.contrast
+        });
+        edit_display_setting(ctx, LABELS.brightness, 0, |preferences| {
+            &mut preferences.brightness
+        });
+        render_selector(false, 0, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct GasScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for GasScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        if !ctx.preferences.gas_enabled {
+            uwrite!(Truncating(&mut data_str), "{}: {}", LABELS.gas, LABELS.off).unwrap();
+            render_screen(&data_str, true, ctx.lcd, ctx.delay);
+            return;
+        }
+        if ctx.preferences.gas_baseline == 0 {
+            render_screen(LABELS.calibrating, true, ctx.lcd, ctx.delay);
+        } else {
+            uwrite!(
+                Truncating(&mut data_str),
+                "{}: {}",
+                LABELS.air_quality,
+                aqi_from_resistance(
+                    ctx.data.gas_resistance,
+                    ctx.preferences.gas_baseline,
+                    ctx.data.humidity
+                )
+            )
+            .unwrap();
+            render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        }
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}kOhm",
+            LABELS.gas,
+            ctx.data.gas_resistance / 1000
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // UP or DOWN switches the gas measurement on or off
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut info_str: String<16> = String::new();
+        loop {
+            if refresh {
+                let state = if ctx.preferences.gas_enabled {
+                    LABELS.on
+                } else {
+                    LABELS.off
+                };
+                uwrite!(Truncating(&mut info_str), "{}: {}", LABELS.gas, state).unwrap();
+                render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+                info_str.clear();
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() || ctx.down_button.is_high().unwrap() {
+                ctx.preferences.gas_enabled = !ctx.preferences.gas_enabled;
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+
+        // The heater only matters while gas is measured
+        if ctx.preferences.gas_enabled {
+            ctx.preferences.gas_heater_temp = edit_stepped(
+                ctx,
+                LABELS.heater,
+                "C",
+                ctx.preferences.gas_heater_temp,
+                (MIN_HEATER_TEMP, MAX_HEATER_TEMP),
+                10,
+            );
+            ctx.preferences.gas_heater_ms = edit_stepped(
+                ctx,
+                LABELS.duration,
+                "ms",
+                ctx.preferences.gas_heater_ms,
+                (MIN_HEATER_MS, MAX_HEATER_MS),
+                50,
+            );
+        }
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct Co2Screen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for Co2Screen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        match ctx.co2 {
+            Some(ppm) => uwrite!(Truncating(&mut data_str), "{}: {}ppm", LABELS.co2, ppm).unwrap(),
+            None => uwrite!(Truncating(&mut data_str), "{}: {}", LABELS.co2, LABELS.none).unwrap(),
+        }
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        let policy = ctx.preferences.co2_policy;
+        uwrite!(Truncating(&mut data_str), "{}", policy.label()).unwrap();
+        if policy == Co2Policy::Vent {
+            uwrite!(
+                Truncating(&mut data_str),
+                " >{}ppm",
+                ctx.preferences.co2_vent_ppm
+            )
+            .unwrap();
+        }
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // UP or DOWN cycles through the CO2 policies
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut info_str: String<16> = String::new();
+        loop {
+            if refresh {
+                uwrite!(
+                    Truncating(&mut info_str),
+                    "{}: {}",
+                    LABELS.co2,
+                    ctx.preferences.co2_policy.label()
+                )
+                .unwrap();
+                render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+                info_str.clear();
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() || ctx.down_button.is_high().unwrap() {
+                ctx.preferences.co2_policy = ctx.preferences.co2_policy.next();
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+
+        // The threshold only matters while CO2 is vented
+        if ctx.preferences.co2_policy == Co2Policy::Vent {
+            ctx.preferences.co2_vent_ppm = edit_stepped(
+                ctx,
+                LABELS.co2,
+                "ppm",
+                ctx.preferences.co2_vent_ppm,
+                (MIN_CO2_VENT_PPM, MAX_CO2_VENT_PPM),
+                100,
+            );
+        }
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(feature = "hardware")]
+struct GddScreen;
+
+#[cfg(feature = "hardware")]
+impl ScreenHandler for GddScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        let unit = ctx.preferences.temperature_unit;
+        // A Celsius or Kelvin degree is 9/5 of a Fahrenheit one, so there are fewer degree days
+        let tenths = match unit {
+            TemperatureUnit::Fahrenheit => ctx.stats.gdd,
+            TemperatureUnit::Celsius | TemperatureUnit::Kelvin => {
+                (ctx.stats.gdd as u64 * 5 / 9) as u32
+            }
+        };
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {}.{}{}",
+            LABELS.gdd,
+            tenths / 10,
+            tenths % 10,
+            unit.symbol()
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {} >{}",
+            LABELS.days,
+            ctx.stats.gdd_days,
+            ctx.preferences
+                .format_temperature(ctx.preferences.gdd_base)
+                .as_str()
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // The base is edited in the shown unit, so it is only converted back if it was changed
+        let unit = ctx.preferences.temperature_unit;
+        let shown = |fahrenheit: u8| unit.from_fahrenheit(fahrenheit) as u16;
+        let base = shown(ctx.preferences.gdd_base);
+        let edited = edit_stepped(
+            ctx,
+            LABELS.gdd,
+            unit.symbol(),
+            base,
+            (shown(MIN_GDD_BASE), shown(MAX_GDD_BASE)),
+            1,
+        );
+        if edited != base {
+            ctx.preferences.gdd_base = unit.to_fahrenheit(edited as i16);
+        }
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
+
+#[cfg(all(feature = "hardware", feature = "altitude"))]
+struct AltitudeScreen;
+
+#[cfg(all(feature = "hardware", feature = "altitude"))]
+impl ScreenHandler for AltitudeScreen {
+    fn render(&self, ctx: &mut Context) {
+        let mut data_str: String<16> = String::new();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {} m",
+            LABELS.altitude,
+            altitude_m(ctx.data.pressure, ctx.preferences.sea_level_pressure as f32) as i16
+        )
+        .unwrap();
+        render_screen(&data_str, true, ctx.lcd, ctx.delay);
+        data_str.clear();
+        uwrite!(
+            Truncating(&mut data_str),
+            "{}: {} mb",
+            LABELS.sea_level,
+            ctx.preferences.sea_level_pressure
+        )
+        .unwrap();
+        render_screen(&data_str, false, ctx.lcd, ctx.delay);
+    }
+
+    fn edit(&self, ctx: &mut Context) {
+        // Sea-level reference pressure
+        let mut update_date: bool = false;
+        let mut refresh: bool = true;
+        let mut info_str: String<16> = String::new();
+        loop {
+            if refresh {
+                uwrite!(
+                    Truncating(&mut info_str),
+                    "{}: {} mb",
+                    LABELS.sea_level,
+                    ctx.preferences.sea_level_pressure
+                )
+                .unwrap();
+                render_date_edit_screen(&info_str, ctx.lcd, ctx.delay);
+                info_str.clear();
+                refresh = false;
+            }
+            ctx.delay.delay_ms(500);
+
+            if update_date {
+                ctx.preferences.tick_time();
+                feed_watchdog();
+            }
+            update_date = !update_date;
+
+            if ctx.up_button.is_high().unwrap() {
+                if ctx.preferences.sea_level_pressure < 1100 {
+                    ctx.preferences.sea_level_pressure += 1;
+                }
+                refresh = true;
+            } else if ctx.down_button.is_high().unwrap() {
+                if ctx.preferences.sea_level_pressure > 900 {
+                    ctx.preferences.sea_level_pressure -= 1;
+                }
+                refresh = true;
+            } else if ctx.select_button.is_high().unwrap() {
+                break;
+            }
+        }
+        render_selector(false, 7, ctx.lcd, ctx.delay);
+    }
+}
@@ -0,0 +1,326 @@
+#[cfg(feature = "hardware")]
+use crate::buzzer::{Buzzer, Tone};
+#[cfg(feature = "hardware")]
+use crate::preferences::Preferences;
+use crate::rendering::LABELS;
+#[cfg(feature = "hardware")]
+use crate::rendering::{render_screen, Lcd, Truncating, LCD_WIDTH};
+#[cfg(feature = "hardware")]
+use crate::safety::drive;
+use crate::sensors::Reading;
+#[cfg(feature = "hardware")]
+use crate::sensors::SensorSource;
+#[cfg(feature = "hardware")]
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "hardware")]
+use embedded_hal::digital::{InputPin, OutputPin};
+#[cfg(feature = "hardware")]
+use heapless::String;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::fugit::MicrosDurationU32;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::watchdog::Watchdog;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::Timer;
+#[cfg(feature = "hardware")]
+use ufmt::uwrite;
+
+/// How long each output is switched on for during the self-test, in milliseconds
+pub const PULSE_MS: u32 = 300;
+/// How long each result is shown for, in milliseconds
+#[cfg(feature = "hardware")]
+const RESULT_MS: u32 = 1000;
+/// How long a step of the self-test may take before the watchdog resets the board, in milliseconds.
+/// Just under the longest period the RP2040's watchdog supports
+pub const SELF_TEST_WATCHDOG_MS: u32 = 8000;
+
+/// The parts of the greenhouse checked by the self-test
+///
+/// - **Outputs**: The sprinklers, roof vent, mister and buzzer; pulsed so they can be watched,
+///   as there is no way to read them back
+/// - **Sensor**: The BME680, which must respond with plausible values
+/// - **SmokeDetector**: The smoke detector, which must read its idle level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Component {
+    Outputs = 1,
+    Sensor = 2,
+    SmokeDetector = 3,
+}
+
+impl Component {
+    /// Gets a component from the step recorded by [run_self_test]
+    ///
+    /// - param step: The recorded step; 0 when no step is running
+    ///
+    /// returns the [Component], or None if no step is running
+    pub fn from_step(step: u32) -> Option<Component> {
+        match step {
+            1 => Some(Component::Outputs),
+            2 => Some(Component::Sensor),
+            3 => Some(Component::SmokeDetector),
+            _ => None,
+        }
+    }
+
+    /// Gets the on-screen name of the component
+    ///
+    /// returns the name
+    pub fn label(self) -> &'static str {
+        match self {
+            Component::Outputs => LABELS.outputs,
+            Component::Sensor => LABELS.sensor,
+            Component::SmokeDetector => LABELS.smoke,
+        }
+    }
+}
+
+/// Checks if a [Reading] is one the BME680 could have measured.
+/// A sensor that fails to answer reads as all zeros, which is caught by the pressure
+///
+/// - param reading: The [Reading] to check
+/// - param gas_enabled: If gas is measured, so a resistance is expected
+///
+/// returns true if the temperature is within -40C - 85C, the humidity within 0% - 100%,
+/// the pressure within 300 - 1100 mb and, if gas is measured, the resistance is above 0
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::selftest::is_plausible_reading;
+/// use gem_rs::sensors::Reading;
+///
+/// let reading = Reading { temperature: 21.5, humidity: 55., pressure: 1013., gas_resistance: 50_000 };
+/// assert!(is_plausible_reading(&reading, true));
+///
+/// // A sensor that didn't answer
+/// assert!(!is_plausible_reading(&Reading::default(), false));
+/// assert!(!is_plausible_reading(&Reading { temperature: 90., ..reading }, true));
+/// assert!(!is_plausible_reading(&Reading { humidity: 101., ..reading }, true));
+/// assert!(!is_plausible_reading(&Reading { pressure: 1200., ..reading }, true));
+/// assert!(!is_plausible_reading(&Reading { temperature: f32::NAN, ..reading }, true));
+/// // No resistance is only expected with gas turned off
+/// assert!(!is_plausible_reading(&Reading { gas_resistance: 0, ..reading }, true));
+/// assert!(is_plausible_reading(&Reading { gas_resistance: 0, ..reading }, false));
+/// ```
+pub fn is_plausible_reading(reading: &Reading, gas_enabled: bool) -> bool {
+    (-40.0..=85.0).contains(&reading.temperature)
+        && (0.0..=100.0).contains(&reading.humidity)
+        && (300.0..=1100.0).contains(&reading.pressure)
+        && (!gas_enabled || reading.gas_resistance > 0)
+}
+
+/// Checks if the smoke detector line reads its idle level; it is pulled low and goes high on smoke
+///
+/// - param line_high: If the smoke detector line is high
+///
+/// returns true if the line is idle
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::selftest::is_smoke_idle;
+///
+/// assert!(is_smoke_idle(false));
+/// assert!(!is_smoke_idle(true));
+/// ```
+pub fn is_smoke_idle(line_high: bool) -> bool {
+    !line_high
+}
+
+/// The results of the self-test
+///
+/// - **sensor**: If the BME680 responded with a plausible [Reading]
+/// - **smoke_detector**: If the smoke detector read its idle level
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SelfTestReport {
+    pub sensor: bool,
+    pub smoke_detector: bool,
+}
+
+impl SelfTestReport {
+    /// Gets the first critical [Component] that failed
+    ///
+    /// returns the [Component], or None if the self-test passed
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::selftest::{Component, SelfTestReport};
+    ///
+    /// let passed = SelfTestReport { sensor: true, smoke_detector: true };
+    /// assert_eq!(passed.failure(), None);
+    /// let no_sensor = SelfTestReport { sensor: false, ..passed };
+    /// assert_eq!(no_sensor.failure(), Some(Component::Sensor));
+    /// let smoke = SelfTestReport { smoke_detector: false, ..passed };
+    /// assert_eq!(smoke.failure(), Some(Component::SmokeDetector));
+    /// ```
+    pub fn failure(&self) -> Option<Component> {
+        if !self.sensor {
+            Some(Component::Sensor)
+        } else if !self.smoke_detector {
+            Some(Component::SmokeDetector)
+        } else {
+            None
+        }
+    }
+}
+
+/// Gets the step of the self-test that hung, if the watchdog reset the board in the middle of it
+///
+/// - param watchdog_reset: If the watchdog caused the last reset
+/// - param step: The step recorded in the watchdog's scratch register
+///
+/// returns the [Component] being tested when the board was reset
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::selftest::{hung_component, Component};
+///
+/// assert_eq!(hung_component(true, 2), Some(Component::Sensor));
+/// // The self-test finished, so something else tripped the watchdog
+/// assert_eq!(hung_component(true, 0), None);
+/// // A power cycle leaves stale steps behind
+/// assert_eq!(hung_component(false, 2), None);
+/// ```
+pub fn hung_component(watchdog_reset: bool, step: u32) -> Option<Component> {
+    if watchdog_reset {
+        Component::from_step(step)
+    } else {
+        None
+    }
+}
+
+/// Records the running step in the watchdog's first scratch register, which survives a watchdog reset
+///
+/// - param component: The [Component] being tested, or None once the self-test is over
+#[cfg(feature = "hardware")]
+fn record_step(component: Option<Component>) {
+    // Safety: scratch0 isn't used by the bootrom or the HAL
+    let watchdog = unsafe { &*rp_pico::pac::WATCHDOG::ptr() };
+    watchdog
+        .scratch0()
+        .write(|w| unsafe { w.bits(component.map_or(0, |component| component as u32)) });
+}
+
+/// Gets the step of the self-test that hung before the last reset. See [hung_component]
+///
+/// returns the [Component] being tested when the watchdog reset the board
+#[cfg(feature = "hardware")]
+pub fn last_hung_component() -> Option<Component> {
+    let watchdog = unsafe { &*rp_pico::pac::WATCHDOG::ptr() };
+    let component = hung_component(
+        watchdog.reason().read().timer().bit_is_set(),
+        watchdog.scratch0().read().bits(),
+    );
+    record_step(None);
+    component
+}
+
+/// Renders the result of a step on the bottom line
+///
+/// - param component: The [Component] tested
+/// - param passed: If it passed
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+fn render_result(component: Component, passed: bool, lcd: &mut Lcd, delay: &mut Timer) {
+    render_screen(LABELS.self_test, true, lcd, delay);
+    let mut line: String<LCD_WIDTH> = String::new();
+    uwrite!(
+        Truncating(&mut line),
+        "{} {}",
+        component.label(),
+        if passed { "OK" } else { "FAIL" }
+    )
+    .unwrap();
+    render_screen(&line, false, lcd, delay);
+    delay.delay_ms(RESULT_MS);
+}
+
+/// Tests the outputs, sensor and smoke detector, showing each result on the [Lcd].
+/// Each step runs under the watchdog, so a sensor that hangs the bus resets the board;
+/// the hung step is then reported by [last_hung_component] instead of being run again
+///
+/// - param preferences: [Preferences] instance
+/// - param source: The [SensorSource] to test
+/// - param smoke_detector: Smoke detector Pin
+/// - param outputs: Sprinkler, roof vent and mister Pins
+/// - param buzzer: [Buzzer] instance
+/// - param watchdog: [Watchdog] instance; stopped again once the self-test is over
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+///
+/// returns the [SelfTestReport]
+#[allow(clippy::too_many_arguments)]
+#[cfg(feature = "hardware")]
+pub fn run_self_test<S: SensorSource>(
+    preferences: &Preferences,
+    source: &mut S,
+    smoke_detector: &mut impl InputPin,
+    sprinklers: &mut impl OutputPin,
+    roof_vent: &mut impl OutputPin,
+    mister: &mut impl OutputPin,
+    buzzer: &mut Buzzer,
+    watchdog: &mut Watchdog,
+    lcd: &mut Lcd,
+    delay: &mut Timer,
+) -> SelfTestReport {
+    let levels = &preferences.active_levels;
+    watchdog.start(MicrosDurationU32::millis(SELF_TEST_WATCHDOG_MS));
+
+    // Pulse each output in turn so they can be watched
+    record_step(Some(Component::Outputs));
+    render_screen(LABELS.self_test, true, lcd, delay);
+    render_screen(Component::Outputs.label(), false, lcd, delay);
+    drive(sprinklers, true, levels.sprinklers);
+    delay.delay_ms(PULSE_MS);
+    drive(sprinklers, false, levels.sprinklers);
+    drive(roof_vent, true, levels.roof_vent);
+    delay.delay_ms(PULSE_MS);
+    drive(roof_vent, false, levels.roof_vent);
+    drive(mister, true, levels.mister);
+    delay.delay_ms(PULSE_MS);
+    drive(mister, false, levels.mister);
+    let (passive, volume) = (preferences.passive_buzzer, preferences.buzzer_volume);
+    buzzer.play(Some(Tone::Notification), passive, volume);
+    delay.delay_ms(PULSE_MS);
+    buzzer.play(None, passive, volume);
+    watchdog.feed();
+
+    record_step(Some(Component::Sensor));
+    let sensor = match source.start() {
+        Ok(ms) => {
+            delay.delay_ms(ms as u32);
+            source
+                .read()
+                .is_ok_and(|reading| is_plausible_reading(&reading, preferences.gas_enabled))
+        }
+        Err(_) => false,
+    };
+    watchdog.feed();
+    render_result(Component::Sensor, sensor, lcd, delay);
+    watchdog.feed();
+
+    record_step(Some(Component::SmokeDetector));
+    let smoke_detector = is_smoke_idle(smoke_detector.is_high().unwrap());
+    render_result(Component::SmokeDetector, smoke_detector, lcd, delay);
+
+    record_step(None);
+    watchdog.disable();
+    SelfTestReport {
+        sensor,
+        smoke_detector,
+    }
+}
+
+/// Shows the fault screen for a failed [Component] and stops.
+/// The outputs are left off, as nothing can be trusted to drive them
+///
+/// - param component: The [Component] that failed
+/// - param lcd: [Lcd] instance
+/// - param delay: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn halt_on_fault(component: Component, lcd: &mut Lcd, delay: &mut Timer) -> ! {
+    render_screen(LABELS.self_test_failed, true, lcd, delay);
+    render_screen(component.label(), false, lcd, delay);
+    loop {
+        delay.delay_ms(1000);
+    }
+}
@@ -1,97 +1,1571 @@
-use bme680::{Bme680, FieldData, FieldDataCondition, PowerMode};
+#[cfg(feature = "hardware")]
+use bme680::{
+    Bme680, FieldData, I2CAddress, IIRFilterSize, OversamplingSetting, PowerMode, Settings,
+    SettingsBuilder,
+};
+use core::cell::Cell;
+#[cfg(feature = "hardware")]
+use core::cell::RefCell;
+#[cfg(feature = "hardware")]
+use core::time::Duration;
 use embedded_hal::delay::DelayNs;
+#[cfg(feature = "hardware")]
 use embedded_hal::digital::OutputPin;
+use embedded_hal::i2c::{self, ErrorKind};
+#[cfg(feature = "hardware")]
+use embedded_hal::i2c::{ErrorType, Operation};
+#[cfg(feature = "hardware")]
+use embedded_hal_0_2::adc::OneShot;
+#[cfg(feature = "hardware")]
+use embedded_hal_bus::i2c::RefCellDevice;
+use heapless::HistoryBuffer;
+#[cfg(feature = "hardware")]
 use i2c_pio::I2C;
-use rp_pico::hal::gpio::bank0::{Gpio6, Gpio8, Gpio9};
-use rp_pico::hal::gpio::{FunctionNull, FunctionSio, Pin, PullDown, SioOutput};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::adc::AdcPin;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::bank0::{Gpio29, Gpio6, Gpio8, Gpio9};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::{FunctionNull, FunctionSio, Pin, PullDown, PullNone, SioInput, SioOutput};
+#[cfg(feature = "hardware")]
 use rp_pico::hal::pio::SM0;
-use rp_pico::hal::Timer;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::{Adc, Timer};
+#[cfg(feature = "hardware")]
 use rp_pico::pac::PIO0;
 
-use panic_probe as _;
+use crate::preferences::{DateTime, Preferences, SensorCombine};
+use crate::rendering::LABELS;
+#[cfg(feature = "hardware")]
+use crate::watchdog::feed_watchdog;
 
-pub type Bme<'a> = Bme680<
-    I2C<'a, PIO0, SM0, Pin<Gpio8, FunctionNull, PullDown>, Pin<Gpio9, FunctionNull, PullDown>>,
-    Timer,
->;
+#[cfg(feature = "hardware")]
+pub type I2cBus<'a> =
+    I2C<'a, PIO0, SM0, Pin<Gpio8, FunctionNull, PullDown>, Pin<Gpio9, FunctionNull, PullDown>>;
 
-/// Gets [FieldData] from the BME sensor
+/// Both BME680s share the [I2cBus], each on its own address, and give up on it at the [SensorDeadline]
+#[cfg(feature = "hardware")]
+pub type Bme<'a, 'b> = Bme680<TimedI2c<'b, RefCellDevice<'b, I2cBus<'a>>>, Timer>;
+
+#[cfg(feature = "hardware")]
+pub type BuzzerPin = Pin<Gpio6, FunctionSio<SioOutput>, PullDown>;
+
+#[cfg(feature = "hardware")]
+pub type VsysPin = AdcPin<Pin<Gpio29, FunctionSio<SioInput>, PullNone>>;
+
+/// The ADC reference voltage in millivolts
+pub const ADC_REFERENCE_MV: u32 = 3300;
+/// The amount of steps in a 12-bit ADC reading
+pub const ADC_RESOLUTION: u32 = 4096;
+/// The on-board divider feeds VSYS/3 into GPIO29
+pub const VSYS_DIVIDER: u32 = 3;
+
+/// A single set of readings from a [SensorSource]
+///
+/// - **temperature**: The temperature in Celsius
+/// - **humidity**: The relative humidity percentage
+/// - **pressure**: The pressure in millibars/hPa
+/// - **gas_resistance**: The gas sensor resistance in ohms
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Reading {
+    pub temperature: f32,
+    pub humidity: f32,
+    pub pressure: f32,
+    pub gas_resistance: u32,
+}
+
+impl Reading {
+    /// Applies the consumer's calibration offsets.
+    /// Humidity is clamped to 0% - 100% and pressure to 0 or more
+    ///
+    /// - param temp_offset: Temperature offset in Fahrenheit
+    /// - param humidity_offset: Relative humidity offset in percent
+    /// - param pressure_offset: Pressure offset in millibars/hPa
+    ///
+    /// returns the calibrated [Reading]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::sensors::{get_humidity, get_pressure, get_temperature, Reading};
+    ///
+    /// let raw = Reading { temperature: 20., humidity: 50., pressure: 1000., ..Reading::default() }; // 68F
+    /// let calibrated = raw.with_offsets(-2, 3, 13);
+    /// assert_eq!(get_temperature(&calibrated), 66);
+    /// assert_eq!(get_humidity(&calibrated), 53);
+    /// assert_eq!(get_pressure(&calibrated), 1013);
+    ///
+    /// // Offsets never push readings out of range
+    /// let extreme = Reading { temperature: 20., humidity: 98., pressure: 10., ..Reading::default() };
+    /// assert_eq!(get_humidity(&extreme.with_offsets(0, 5, 0)), 100);
+    /// assert_eq!(get_humidity(&raw.with_offsets(0, -60, 0)), 0);
+    /// assert_eq!(get_pressure(&extreme.with_offsets(0, 0, -20)), 0);
+    /// ```
+    pub fn with_offsets(
+        self,
+        temp_offset: i8,
+        humidity_offset: i8,
+        pressure_offset: i16,
+    ) -> Reading {
+        Reading {
+            temperature: self.temperature + temp_offset as f32 * (5. / 9.),
+            humidity: (self.humidity + humidity_offset as f32).clamp(0., 100.),
+            pressure: (self.pressure + pressure_offset as f32).max(0.),
+            gas_resistance: self.gas_resistance,
+        }
+    }
+}
+
+#[cfg(feature = "hardware")]
+impl From<&FieldData> for Reading {
+    fn from(data: &FieldData) -> Self {
+        Reading {
+            temperature: data.temperature_celsius(),
+            humidity: data.humidity_percent(),
+            pressure: data.pressure_hpa(),
+            gas_resistance: data.gas_resistance_ohm(),
+        }
+    }
+}
+
+/// The sensor could not be prepared for a measurement
+#[derive(Debug)]
+pub struct SensorError;
+
+/// How long the BME680 takes in milliseconds for the temperature, pressure and humidity readings
+pub const TPH_MEASUREMENT_MS: u16 = 100;
+
+/// The deadline of the sensor operation running on the [I2cBus], on the hardware timer.
+/// It is armed around each operation; once it passes, every bus transaction fails,
+/// so a misbehaving bus ends the operation with a [SensorError] instead of holding up the loop
+///
+/// **NOTE: The deadline only catches stalls between transactions, as it is checked before each one.
+/// A single transaction that never returns isn't cut short by it; the main loop then stops feeding
+/// the watchdog, which resets the board after `LOOP_WATCHDOG_MS` (see the watchdog module)**
+///
+/// - **timeout_ms**: How long an operation may take in milliseconds
+/// - **expires_us**: When the running operation expires in microseconds, or None if none is running
+///
+/// ## Example:
+/// ```rust
+/// use core::cell::Cell;
+/// use gem_rs::sensors::{SensorDeadline, SensorError};
+///
+/// let deadline = SensorDeadline::new(250);
+/// // Nothing is running, so nothing can expire
+/// assert!(!deadline.has_expired(u64::MAX));
+///
+/// // Simulated operations that make a transaction every 100ms, checking the deadline first
+/// let clock = Cell::new(0u64);
+/// let operation = |transactions: u8| {
+///     for _ in 0..transactions {
+///         if deadline.has_expired(clock.get()) {
+///             return Err(SensorError);
+///         }
+///         clock.set(clock.get() + 100_000);
+///     }
+///     Ok(())
+/// };
+///
+/// // A fast operation finishes well within the time
+/// assert!(deadline.run(clock.get(), 0, || operation(2)).is_ok());
+/// // A slow one finds the deadline passed on its fourth transaction and gives up
+/// assert!(deadline.run(clock.get(), 0, || operation(10)).is_err());
+/// assert!(!deadline.has_expired(u64::MAX)); // Disarmed again afterwards
+///
+/// // A read waiting on a 1.5s gas measurement gets the measurement's time as well
+/// assert!(deadline.run(clock.get(), 1600, || operation(18)).is_ok());
+/// assert!(deadline.run(clock.get(), 1600, || operation(20)).is_err());
+///
+/// // The timeout follows the preferences
+/// deadline.set_timeout_ms(1000);
+/// assert!(deadline.run(clock.get(), 0, || operation(10)).is_ok());
+/// ```
+pub struct SensorDeadline {
+    timeout_ms: Cell<u16>,
+    expires_us: Cell<Option<u64>>,
+}
+
+impl SensorDeadline {
+    /// Creates a new instance of SensorDeadline, with nothing running
+    ///
+    /// - param timeout_ms: How long an operation may take in milliseconds
+    ///
+    /// returns a new instance of SensorDeadline
+    pub fn new(timeout_ms: u16) -> SensorDeadline {
+        SensorDeadline {
+            timeout_ms: Cell::new(timeout_ms),
+            expires_us: Cell::new(None),
+        }
+    }
+
+    /// Sets how long the next operations may take. See [Preferences::sensor_timeout_ms]
+    ///
+    /// - param timeout_ms: The timeout in milliseconds
+    pub fn set_timeout_ms(&self, timeout_ms: u16) {
+        self.timeout_ms.set(timeout_ms);
+    }
+
+    /// Checks if the running operation has run out of time
+    ///
+    /// - param now_us: The hardware timer in microseconds
+    ///
+    /// returns true if an operation is running and its deadline has passed
+    pub fn has_expired(&self, now_us: u64) -> bool {
+        self.expires_us
+            .get()
+            .is_some_and(|expires_us| now_us >= expires_us)
+    }
+
+    /// Runs a sensor operation under the deadline
+    ///
+    /// - param now_us: The hardware timer in microseconds when the operation starts
+    /// - param wait_ms: How long the operation may legitimately wait on a measurement in milliseconds, on top of the timeout
+    /// - param operation: The operation
+    ///
+    /// returns the result of the operation
+    pub fn run<T>(
+        &self,
+        now_us: u64,
+        wait_ms: u16,
+        operation: impl FnOnce() -> Result<T, SensorError>,
+    ) -> Result<T, SensorError> {
+        let budget_ms = self.timeout_ms.get() as u64 + wait_ms as u64;
+        self.expires_us.set(Some(now_us + budget_ms * 1000));
+        let result = operation();
+        self.expires_us.set(None);
+        result
+    }
+}
+
+/// Why a [TimedI2c] transaction failed
+///
+/// - **Bus**: The bus itself failed
+/// - **TimedOut**: The [SensorDeadline] passed, so the transaction wasn't started
+#[derive(Debug)]
+pub enum TimedI2cError<E> {
+    Bus(E),
+    TimedOut,
+}
+
+impl<E: i2c::Error> i2c::Error for TimedI2cError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            TimedI2cError::Bus(error) => error.kind(),
+            TimedI2cError::TimedOut => ErrorKind::Other,
+        }
+    }
+}
+
+/// An I2C device that stops talking once the [SensorDeadline] passes.
+/// Only the gaps between transactions are timed; a transaction that hangs on the bus
+/// is left to the loop watchdog, which resets the board after [crate::watchdog::LOOP_WATCHDOG_MS]
+///
+/// - **i2c**: The I2C device
+/// - **deadline**: The [SensorDeadline] shared by the sensors on the bus
+/// - **timer**: The hardware timer the deadline is kept on
+#[cfg(feature = "hardware")]
+pub struct TimedI2c<'b, I> {
+    i2c: I,
+    deadline: &'b SensorDeadline,
+    timer: Timer,
+}
+
+#[cfg(feature = "hardware")]
+impl<I: ErrorType> ErrorType for TimedI2c<'_, I> {
+    type Error = TimedI2cError<I::Error>;
+}
+
+#[cfg(feature = "hardware")]
+impl<I: i2c::I2c> i2c::I2c for TimedI2c<'_, I> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if self.deadline.has_expired(self.timer.get_counter().ticks()) {
+            return Err(TimedI2cError::TimedOut);
+        }
+        self.i2c
+            .transaction(address, operations)
+            .map_err(TimedI2cError::Bus)
+    }
+}
+
+/// The BME680's gas measurement settings
+///
+/// - **enabled**: If gas is measured at all
+/// - **heater_temp**: The temperature the gas sensor is heated to in Celsius
+/// - **heater_ms**: How long the gas sensor is heated for in milliseconds
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GasSettings {
+    pub enabled: bool,
+    pub heater_temp: u16,
+    pub heater_ms: u16,
+}
+
+impl GasSettings {
+    /// Gets the gas settings chosen in the [Preferences]
+    ///
+    /// - param preferences: [Preferences] instance
+    ///
+    /// returns the [GasSettings]
+    pub fn new(preferences: &Preferences) -> GasSettings {
+        GasSettings {
+            enabled: preferences.gas_enabled,
+            heater_temp: preferences.gas_heater_temp,
+            heater_ms: preferences.gas_heater_ms,
+        }
+    }
+
+    /// Gets how long a BME680 measurement takes with these settings
+    ///
+    /// returns the milliseconds; the gas heater, if enabled, plus the [TPH_MEASUREMENT_MS]
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::Preferences;
+    /// use gem_rs::sensors::GasSettings;
+    ///
+    /// let mut gas = GasSettings::new(&Preferences::default());
+    /// assert_eq!(gas.measurement_ms(), 1600);
+    /// gas.enabled = false;
+    /// assert_eq!(gas.measurement_ms(), 100);
+    /// ```
+    pub fn measurement_ms(&self) -> u16 {
+        if self.enabled {
+            self.heater_ms + TPH_MEASUREMENT_MS
+        } else {
+            TPH_MEASUREMENT_MS
+        }
+    }
+}
+
+/// Builds the BME680 settings for the given [GasSettings]
+///
+/// - param gas: [GasSettings] instance
+///
+/// returns the settings to give the sensor
+#[cfg(feature = "hardware")]
+pub fn bme_settings(gas: &GasSettings) -> Settings {
+    SettingsBuilder::new()
+        .with_humidity_oversampling(OversamplingSetting::OS2x)
+        .with_pressure_oversampling(OversamplingSetting::OS4x)
+        .with_temperature_oversampling(OversamplingSetting::OS8x)
+        .with_temperature_filter(IIRFilterSize::Size3)
+        .with_temperature_offset(-8.9)
+        .with_gas_measurement(
+            Duration::from_millis(gas.heater_ms as u64),
+            gas.heater_temp,
+            25,
+        )
+        .with_run_gas(gas.enabled)
+        .build()
+}
+
+/// Anything that can provide [Reading]s to the control loop.
+/// A measurement is started, then read once it has had time to complete
+pub trait SensorSource {
+    /// Starts a new measurement. Sources that measure instantly don't need to start
+    ///
+    /// returns how long the measurement takes in milliseconds,
+    /// or [SensorError] if the sensor could not be prepared
+    fn start(&mut self) -> Result<u16, SensorError> {
+        Ok(0)
+    }
+
+    /// Applies new [GasSettings]; must not be called while a measurement is running.
+    /// Sources without a gas sensor ignore them
+    ///
+    /// - param gas: [GasSettings] instance
+    ///
+    /// returns [SensorError] if the sensor could not be configured
+    fn configure(&mut self, _gas: &GasSettings) -> Result<(), SensorError> {
+        Ok(())
+    }
+
+    /// Tries to bring the source back after it kept failing.
+    /// Sources that can't lock up have nothing to recover
+    ///
+    /// returns [SensorError] if the source is still unreachable
+    fn recover(&mut self) -> Result<(), SensorError> {
+        Ok(())
+    }
+
+    /// Reads the measurement started by [SensorSource::start]
+    ///
+    /// returns the [Reading], or [SensorError] if there is none
+    fn read(&mut self) -> Result<Reading, SensorError>;
+}
+
+/// The addresses a BME680 can be strapped to, in the order they are probed.
+/// The first sensor has always sat at 0x77, so it is tried first
+#[cfg(feature = "hardware")]
+pub const BME_ADDRESSES: [I2CAddress; 2] = [I2CAddress::Secondary, I2CAddress::Primary];
+
+/// Finds the first address a sensor responds on
+///
+/// - param addresses: The addresses to try, in order
+/// - param init: Sets up a sensor at an address, returning None if nothing responds
+///
+/// returns the address that responded, its sensor and the addresses after it, which are left for a second sensor,
+/// or [SensorError] if no address responded
+///
+/// ## Example:
+/// ```rust
+/// use bme680::I2CAddress;
+/// use gem_rs::sensors::{probe_address, BME_ADDRESSES};
+///
+/// // A mock bus with a single sensor strapped to 0x76
+/// let mut probed = Vec::new();
+/// let (address, sensor, remaining) = probe_address(&BME_ADDRESSES, |address| {
+///     probed.push(address.addr());
+///     matches!(address, I2CAddress::Primary).then_some("sensor")
+/// })
+/// .unwrap();
+/// assert_eq!(address.addr(), 0x76);
+/// assert_eq!(sensor, "sensor");
+/// assert!(remaining.is_empty());
+/// assert_eq!(probed, [0x77, 0x76]);
+///
+/// // Strapped to 0x77, the other address is left for a second sensor
+/// let (address, _, remaining) =
+///     probe_address(&BME_ADDRESSES, |address| matches!(address, I2CAddress::Secondary).then_some(())).unwrap();
+/// assert_eq!(address.addr(), 0x77);
+/// assert_eq!(remaining.len(), 1);
+/// assert_eq!(remaining[0].addr(), 0x76);
+/// assert!(probe_address(remaining, |_| None::<()>).is_err());
+///
+/// // Nothing on the bus
+/// assert!(probe_address(&BME_ADDRESSES, |_| None::<()>).is_err());
+/// ```
+#[cfg(feature = "hardware")]
+pub fn probe_address<T>(
+    addresses: &[I2CAddress],
+    mut init: impl FnMut(I2CAddress) -> Option<T>,
+) -> Result<(I2CAddress, T, &[I2CAddress]), SensorError> {
+    addresses
+        .iter()
+        .enumerate()
+        .find_map(|(index, address)| {
+            init(*address).map(|sensor| (*address, sensor, &addresses[index + 1..]))
+        })
+        .ok_or(SensorError)
+}
+
+/// Sets up a BME680 on the shared I2C bus
+///
+/// - param i2c_bus: The shared [I2cBus]
+/// - param deadline: The [SensorDeadline] shared by the sensors
+/// - param delay: [Timer] instance
+/// - param address: The sensor's I2C address
+/// - param gas: The [GasSettings] to start with
+///
+/// returns the configured sensor, or None if it did not respond in time
+#[cfg(feature = "hardware")]
+fn init_bme<'a, 'b>(
+    i2c_bus: &'b RefCell<I2cBus<'a>>,
+    deadline: &'b SensorDeadline,
+    delay: &mut Timer,
+    address: I2CAddress,
+    gas: &GasSettings,
+) -> Option<Bme<'a, 'b>> {
+    let i2c = TimedI2c {
+        i2c: RefCellDevice::new(i2c_bus),
+        deadline,
+        timer: *delay,
+    };
+    deadline
+        .run(delay.get_counter().ticks(), 0, || {
+            let mut bme = Bme680::init(i2c, delay, address).map_err(|_| SensorError)?;
+            bme.set_sensor_settings(delay, bme_settings(gas))
+                .map_err(|_| SensorError)?;
+            bme.set_sensor_mode(delay, PowerMode::ForcedMode)
+                .map_err(|_| SensorError)?;
+            Ok(bme)
+        })
+        .ok()
+}
+
+/// [SensorSource] backed by the BME680
+#[cfg(feature = "hardware")]
+pub struct Bme680Source<'a, 'b> {
+    bme: Bme<'a, 'b>,
+    i2c_bus: &'b RefCell<I2cBus<'a>>,
+    deadline: &'b SensorDeadline,
+    address: I2CAddress,
+    delayer: Timer,
+    gas: GasSettings,
+}
+
+#[cfg(feature = "hardware")]
+impl<'a, 'b> Bme680Source<'a, 'b> {
+    /// Sets up a BME680 and creates a new instance of Bme680Source for it
+    ///
+    /// - param i2c_bus: The shared [I2cBus]; kept to set the sensor up again after [recover_i2c_bus]
+    /// - param deadline: The [SensorDeadline] every operation on the sensor runs under
+    /// - param address: The sensor's address
+    /// - param delayer: BME sensor delay
+    /// - param gas: The [GasSettings] to use
+    ///
+    /// returns a new instance of Bme680Source, or None if the sensor doesn't respond
+    pub fn init(
+        i2c_bus: &'b RefCell<I2cBus<'a>>,
+        deadline: &'b SensorDeadline,
+        address: I2CAddress,
+        mut delayer: Timer,
+        gas: GasSettings,
+    ) -> Option<Bme680Source<'a, 'b>> {
+        let bme = init_bme(i2c_bus, deadline, &mut delayer, address, &gas)?;
+        Some(Self {
+            bme,
+            i2c_bus,
+            deadline,
+            address,
+            delayer,
+            gas,
+        })
+    }
+
+    /// Gets the address the sensor responded on
+    ///
+    /// returns the sensor's [I2CAddress]
+    pub fn address(&self) -> I2CAddress {
+        self.address
+    }
+}
+
+#[cfg(feature = "hardware")]
+impl SensorSource for Bme680Source<'_, '_> {
+    fn start(&mut self) -> Result<u16, SensorError> {
+        let now_us = self.delayer.get_counter().ticks();
+        self.deadline
+            .run(now_us, 0, || prep_bme(&mut self.bme, &mut self.delayer))?;
+        Ok(self.gas.measurement_ms())
+    }
+
+    fn configure(&mut self, gas: &GasSettings) -> Result<(), SensorError> {
+        let now_us = self.delayer.get_counter().ticks();
+        self.deadline.run(now_us, 0, || {
+            self.bme
+                .set_sensor_settings(&mut self.delayer, bme_settings(gas))
+                .map_err(|_| SensorError)
+        })?;
+        self.gas = *gas;
+        Ok(())
+    }
+
+    fn recover(&mut self) -> Result<(), SensorError> {
+        recover_i2c_bus(&mut self.delayer);
+        self.bme = init_bme(
+            self.i2c_bus,
+            self.deadline,
+            &mut self.delayer,
+            self.address,
+            &self.gas,
+        )
+        .ok_or(SensorError)?;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        let now_us = self.delayer.get_counter().ticks();
+        // The measurement may still be finishing, so the read is also given the gas heater's time
+        let (data, _) = self.deadline.run(now_us, self.gas.measurement_ms(), || {
+            self.bme
+                .get_sensor_data(&mut self.delayer)
+                .map_err(|_| SensorError)
+        })?;
+        let mut reading = Reading::from(&data);
+        // Nothing was measured, so don't pass on a stale resistance
+        if !self.gas.enabled {
+            reading.gas_resistance = 0;
+        }
+        Ok(reading)
+    }
+}
+
+/// Combines the readings of two sensors into one.
+/// If one sensor failed, the healthy sensor's [Reading] is used on its own
+///
+/// - param first: The first sensor's [Reading], or None if it failed
+/// - param second: The second sensor's [Reading], or None if it failed or isn't fitted
+/// - param combine: How the readings are combined
+///
+/// returns the combined [Reading], or None if both sensors failed
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::SensorCombine;
+/// use gem_rs::sensors::{combine_readings, Reading};
+///
+/// let cold = Reading { temperature: 18., humidity: 70., pressure: 1000., gas_resistance: 40_000 };
+/// let hot = Reading { temperature: 30., humidity: 50., pressure: 1002., gas_resistance: 60_000 };
+///
+/// let average = combine_readings(Some(cold), Some(hot), SensorCombine::Average).unwrap();
+/// assert_eq!(average, Reading { temperature: 24., humidity: 60., pressure: 1001., gas_resistance: 50_000 });
+/// let lowest = combine_readings(Some(cold), Some(hot), SensorCombine::Lowest).unwrap();
+/// assert_eq!(lowest, Reading { temperature: 18., humidity: 50., pressure: 1000., gas_resistance: 40_000 });
+/// let highest = combine_readings(Some(cold), Some(hot), SensorCombine::Highest).unwrap();
+/// assert_eq!(highest, Reading { temperature: 30., humidity: 70., pressure: 1002., gas_resistance: 60_000 });
+///
+/// // A failed sensor falls back to the healthy one
+/// assert_eq!(combine_readings(None, Some(hot), SensorCombine::Average), Some(hot));
+/// assert_eq!(combine_readings(Some(cold), None, SensorCombine::Lowest), Some(cold));
+/// assert_eq!(combine_readings(None, None, SensorCombine::Highest), None);
+/// ```
+pub fn combine_readings(
+    first: Option<Reading>,
+    second: Option<Reading>,
+    combine: SensorCombine,
+) -> Option<Reading> {
+    let (first, second) = match (first, second) {
+        (Some(first), Some(second)) => (first, second),
+        (first, second) => return first.or(second),
+    };
+    Some(match combine {
+        SensorCombine::Average => Reading {
+            temperature: (first.temperature + second.temperature) / 2.,
+            humidity: (first.humidity + second.humidity) / 2.,
+            pressure: (first.pressure + second.pressure) / 2.,
+            gas_resistance: ((first.gas_resistance as u64 + second.gas_resistance as u64) / 2)
+                as u32,
+        },
+        SensorCombine::Lowest => Reading {
+            temperature: first.temperature.min(second.temperature),
+            humidity: first.humidity.min(second.humidity),
+            pressure: first.pressure.min(second.pressure),
+            gas_resistance: first.gas_resistance.min(second.gas_resistance),
+        },
+        SensorCombine::Highest => Reading {
+            temperature: first.temperature.max(second.temperature),
+            humidity: first.humidity.max(second.humidity),
+            pressure: first.pressure.max(second.pressure),
+            gas_resistance: first.gas_resistance.max(second.gas_resistance),
+        },
+    })
+}
+
+/// [SensorSource] that combines a second, optional sensor with the first
+/// using [combine_readings]. It only fails once every fitted sensor has failed
+///
+/// - **first**: The first [SensorSource]
+/// - **second**: The second [SensorSource], or None if it isn't fitted
+/// - **combine**: How the readings are combined
+/// - **started**: If each sensor started its latest measurement
+/// - **readings**: Each sensor's latest [Reading], or None if it failed
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::{DualSource, Reading, SensorError, SensorSource};
+///
+/// // Always returns the same reading, or fails if there is none
+/// struct Fixed(Option<Reading>);
+/// impl SensorSource for Fixed {
+///     fn read(&mut self) -> Result<Reading, SensorError> {
+///         self.0.ok_or(SensorError)
+///     }
+/// }
+///
+/// let reading = Reading { temperature: 20., humidity: 50., ..Reading::default() };
+/// let mut source = DualSource::new(Fixed(Some(reading)), Some(Fixed(None)));
+/// assert_eq!(source.read().unwrap(), reading);
+/// assert!(source.degraded());
+/// assert_eq!(source.readings(), [Some(reading), None]);
+///
+/// // A single sensor is never degraded
+/// let mut single = DualSource::new(Fixed(Some(reading)), None::<Fixed>);
+/// assert_eq!(single.read().unwrap(), reading);
+/// assert!(!single.degraded());
+/// assert_eq!(single.readings(), [Some(reading)]);
+///
+/// // Both failing is still an error
+/// let mut broken = DualSource::new(Fixed(None), Some(Fixed(None)));
+/// assert!(broken.read().is_err());
+///
+/// // A sensor that can't start is left out of the reading
+/// struct Unprepared;
+/// impl SensorSource for Unprepared {
+///     fn start(&mut self) -> Result<u16, SensorError> {
+///         Err(SensorError)
+///     }
+///     fn read(&mut self) -> Result<Reading, SensorError> {
+///         Ok(Reading::default())
+///     }
+/// }
+/// let mut source = DualSource::new(Unprepared, Some(Fixed(Some(reading))));
+/// assert_eq!(source.start().unwrap(), 0);
+/// assert_eq!(source.read().unwrap(), reading);
+/// assert!(source.degraded());
+/// let mut source = DualSource::new(Unprepared, None::<Fixed>);
+/// assert!(source.start().is_err());
+/// ```
+pub struct DualSource<A: SensorSource, B: SensorSource> {
+    first: A,
+    second: Option<B>,
+    combine: SensorCombine,
+    started: [bool; 2],
+    readings: [Option<Reading>; 2],
+}
+
+impl<A: SensorSource, B: SensorSource> DualSource<A, B> {
+    /// Creates a new instance of DualSource
+    ///
+    /// - param first: The first [SensorSource]
+    /// - param second: The second [SensorSource], or None if it isn't fitted
+    ///
+    /// returns a new instance of DualSource
+    pub fn new(first: A, second: Option<B>) -> DualSource<A, B> {
+        Self {
+            first,
+            second,
+            combine: SensorCombine::Average,
+            started: [true; 2],
+            readings: [None; 2],
+        }
+    }
+
+    /// Sets how the readings are combined
+    ///
+    /// - param combine: [SensorCombine] instance
+    pub fn set_combine(&mut self, combine: SensorCombine) {
+        self.combine = combine;
+    }
+
+    /// Gets each fitted sensor's latest [Reading]
+    ///
+    /// returns the [Reading] of each fitted sensor in order, or None for any that failed
+    pub fn readings(&self) -> &[Option<Reading>] {
+        let fitted = if self.second.is_some() { 2 } else { 1 };
+        &self.readings[..fitted]
+    }
+
+    /// Checks if a fitted sensor failed on the last read while the other kept working
+    ///
+    /// returns true if only one sensor is being used
+    pub fn degraded(&self) -> bool {
+        self.second.is_some() && self.readings[0].is_some() != self.readings[1].is_some()
+    }
+}
+
+impl<A: SensorSource, B: SensorSource> SensorSource for DualSource<A, B> {
+    fn start(&mut self) -> Result<u16, SensorError> {
+        let first = self.first.start();
+        let second = self.second.as_mut().map(|second| second.start());
+        self.started = [first.is_ok(), matches!(second, Some(Ok(_)))];
+        match (first, second) {
+            (Ok(first), Some(Ok(second))) => Ok(first.max(second)),
+            (Ok(ms), _) | (_, Some(Ok(ms))) => Ok(ms),
+            _ => Err(SensorError),
+        }
+    }
+
+    fn configure(&mut self, gas: &GasSettings) -> Result<(), SensorError> {
+        // A second sensor that can't be configured shows up as degraded once it is read
+        if let Some(second) = self.second.as_mut() {
+            second.configure(gas).ok();
+        }
+        self.first.configure(gas)
+    }
+
+    fn recover(&mut self) -> Result<(), SensorError> {
+        let first = self.first.recover();
+        match self.second.as_mut().map(|second| second.recover()) {
+            // Either sensor coming back is enough to carry on
+            Some(Ok(())) => Ok(()),
+            _ => first,
+        }
+    }
+
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        // Sensors that didn't start have nothing to read
+        let first = if self.started[0] {
+            self.first.read().ok()
+        } else {
+            None
+        };
+        let second = match self.second.as_mut() {
+            Some(second) if self.started[1] => second.read().ok(),
+            _ => None,
+        };
+        self.readings = [first, second];
+        combine_readings(self.readings[0], self.readings[1], self.combine).ok_or(SensorError)
+    }
+}
+
+/// The I2C address of the SCD40/SCD41 CO2 sensors; it can't be changed
+pub const SCD4X_ADDRESS: u8 = 0x62;
+/// How often an SCD4x has a new measurement in milliseconds; much slower than the BME680s are polled
+pub const SCD4X_INTERVAL_MS: u16 = 5000;
+/// Starts measuring every [SCD4X_INTERVAL_MS]
+const SCD4X_START_PERIODIC: u16 = 0x21B1;
+/// Stops measuring; it takes [SCD4X_STOP_MS]
+const SCD4X_STOP_PERIODIC: u16 = 0x3F86;
+/// Asks if a new measurement is ready
+const SCD4X_DATA_READY: u16 = 0xE4B8;
+/// Reads the CO2, temperature and humidity
+const SCD4X_READ_MEASUREMENT: u16 = 0xEC05;
+/// How long the SCD4x takes to stop measuring in milliseconds
+const SCD4X_STOP_MS: u16 = 500;
+/// How long the SCD4x takes to answer a read command in milliseconds
+const SCD4X_COMMAND_MS: u32 = 1;
+
+/// Calculates the CRC-8 the SCD4x sends after each 16-bit word
+///
+/// - param word: The word's two bytes, most significant first
+///
+/// returns the CRC
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::sensirion_crc;
+///
+/// // The datasheet's example
+/// assert_eq!(sensirion_crc([0xBE, 0xEF]), 0x92);
+/// ```
+pub fn sensirion_crc(word: [u8; 2]) -> u8 {
+    word.iter().fold(0xFF, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Gets one word of an SCD4x response, checking its CRC
+///
+/// - param response: The response; each word is sent as its two bytes followed by their [sensirion_crc]
+/// - param index: Which word to get
+///
+/// returns the word, or None if it is missing or its CRC doesn't match
+pub fn scd4x_word(response: &[u8], index: usize) -> Option<u16> {
+    match response.get(index * 3..index * 3 + 3)? {
+        [high, low, crc] if sensirion_crc([*high, *low]) == *crc => {
+            Some(u16::from_be_bytes([*high, *low]))
+        }
+        _ => None,
+    }
+}
+
+/// Gets the CO2 from an SCD4x measurement.
+/// The temperature and humidity are checked along with it, but the BME680s measure those
+///
+/// - param response: The response to a measurement read: the CO2, temperature and humidity words
+///
+/// returns the CO2 in ppm, or None if any word is corrupt
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::{parse_co2, sensirion_crc};
+///
+/// let word = |value: u16| {
+///     let [high, low] = value.to_be_bytes();
+///     [high, low, sensirion_crc([high, low])]
+/// };
+/// let mut response = [0; 9];
+/// response[..3].copy_from_slice(&word(850));
+/// response[3..6].copy_from_slice(&word(0x6667));
+/// response[6..].copy_from_slice(&word(0x5EB9));
+/// assert_eq!(parse_co2(&response), Some(850));
+///
+/// // A flipped bit anywhere throws the measurement away
+/// response[7] ^= 1;
+/// assert_eq!(parse_co2(&response), None);
+/// response[7] ^= 1;
+/// response[0] ^= 1;
+/// assert_eq!(parse_co2(&response), None);
+/// ```
+pub fn parse_co2(response: &[u8; 9]) -> Option<u16> {
+    let words = [0, 1, 2].map(|index| scd4x_word(response, index));
+    if words.iter().any(Option::is_none) {
+        return None;
+    }
+    words[0]
+}
+
+/// Checks the SCD4x's answer to asking if a new measurement is ready
+///
+/// - param status: The status word
+///
+/// returns true if a new measurement is ready; any of the lowest 11 bits are set
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::is_co2_ready;
 ///
-/// - param bme: [Bme] sensor instance
-/// - param delayer: BME sensor delay
+/// assert!(!is_co2_ready(0x8000));
+/// assert!(is_co2_ready(0x8006));
+/// assert!(is_co2_ready(0x0001));
+/// ```
+pub fn is_co2_ready(status: u16) -> bool {
+    status & 0x07FF != 0
+}
+
+/// A Sensirion SCD40/SCD41 CO2 sensor measuring on its own every [SCD4X_INTERVAL_MS]
+///
+/// - **i2c**: The sensor's I2C device
+pub struct Scd4x<I> {
+    i2c: I,
+}
+
+impl<I: i2c::I2c> Scd4x<I> {
+    /// Sets up an SCD4x and starts it measuring.
+    /// It is stopped first, as it still measures after the controller resets and won't take the start then
+    ///
+    /// - param i2c: The sensor's I2C device
+    /// - param delay: Delay instance
+    ///
+    /// returns the sensor, or None if it didn't respond
+    pub fn init(i2c: I, delay: &mut impl DelayNs) -> Option<Scd4x<I>> {
+        let mut scd4x = Scd4x { i2c };
+        scd4x.send(SCD4X_STOP_PERIODIC).ok()?;
+        delay.delay_ms(SCD4X_STOP_MS as u32);
+        scd4x.send(SCD4X_START_PERIODIC).ok()?;
+        Some(scd4x)
+    }
+
+    /// Sends a command
+    ///
+    /// - param command: The command
+    ///
+    /// returns [SensorError] if the sensor didn't acknowledge it
+    fn send(&mut self, command: u16) -> Result<(), SensorError> {
+        self.i2c
+            .write(SCD4X_ADDRESS, &command.to_be_bytes())
+            .map_err(|_| SensorError)
+    }
+
+    /// Sends a command and reads its response
+    ///
+    /// - param command: The command
+    /// - param response: Where the response is read into
+    /// - param delay: Delay instance
+    ///
+    /// returns [SensorError] if the sensor didn't answer
+    fn query(
+        &mut self,
+        command: u16,
+        response: &mut [u8],
+        delay: &mut impl DelayNs,
+    ) -> Result<(), SensorError> {
+        self.send(command)?;
+        delay.delay_ms(SCD4X_COMMAND_MS);
+        self.i2c
+            .read(SCD4X_ADDRESS, response)
+            .map_err(|_| SensorError)
+    }
+
+    /// Reads the CO2 once a new measurement is ready.
+    /// The sensor is polled more often than it measures, so most polls find nothing new
+    ///
+    /// - param delay: Delay instance
+    ///
+    /// returns the CO2 in ppm, None if nothing new has been measured, or [SensorError] if the sensor didn't answer or its data is corrupt
+    pub fn read_co2_ppm(&mut self, delay: &mut impl DelayNs) -> Result<Option<u16>, SensorError> {
+        let mut status = [0; 3];
+        self.query(SCD4X_DATA_READY, &mut status, delay)?;
+        if !is_co2_ready(scd4x_word(&status, 0).ok_or(SensorError)?) {
+            return Ok(None);
+        }
+        let mut response = [0; 9];
+        self.query(SCD4X_READ_MEASUREMENT, &mut response, delay)?;
+        parse_co2(&response).map(Some).ok_or(SensorError)
+    }
+}
+
+/// The SCD4x shares the [I2cBus] with the BME680s, and gives up on it at the [SensorDeadline]
+#[cfg(feature = "hardware")]
+pub type Co2Sensor<'a, 'b> = Scd4x<TimedI2c<'b, RefCellDevice<'b, I2cBus<'a>>>>;
+
+/// Sets up an SCD4x on the shared I2C bus; it is optional, so nothing is wrong if it doesn't respond
+///
+/// - param i2c_bus: The shared [I2cBus]
+/// - param deadline: The [SensorDeadline] shared by the sensors
+/// - param delay: [Timer] instance
+///
+/// returns the sensor, or None if it did not respond in time
+#[cfg(feature = "hardware")]
+pub fn init_co2_sensor<'a, 'b>(
+    i2c_bus: &'b RefCell<I2cBus<'a>>,
+    deadline: &'b SensorDeadline,
+    delay: &mut Timer,
+) -> Option<Co2Sensor<'a, 'b>> {
+    let i2c = TimedI2c {
+        i2c: RefCellDevice::new(i2c_bus),
+        deadline,
+        timer: *delay,
+    };
+    deadline
+        .run(delay.get_counter().ticks(), SCD4X_STOP_MS, || {
+            Scd4x::init(i2c, delay).ok_or(SensorError)
+        })
+        .ok()
+}
+
+/// The GPIO carrying the [I2cBus] data line
+pub const I2C_SDA_GPIO: u8 = 8;
+/// The GPIO carrying the [I2cBus] clock line
+pub const I2C_SCL_GPIO: u8 = 9;
+/// The clock pulses sent to free the bus; enough for a sensor stuck mid-byte to finish it
+pub const I2C_RECOVERY_PULSES: u8 = 9;
+
+/// Counts failed sensor starts and reads in a row, so the bus is only recovered when failures keep happening
+///
+/// - **consecutive**: The failures since the last good reading
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::FailureCounter;
+///
+/// let mut failures = FailureCounter::default();
+/// assert!(!failures.record(false, 3));
+/// assert!(!failures.record(false, 3));
+/// assert!(failures.record(false, 3)); // The third in a row calls for a recovery
+/// assert_eq!(failures.consecutive(), 0); // And starts counting again
+///
+/// // A good reading in between starts over
+/// assert!(!failures.record(false, 3));
+/// assert!(!failures.record(true, 3));
+/// assert!(!failures.record(false, 3));
+/// assert!(!failures.record(false, 3));
+/// assert_eq!(failures.consecutive(), 2);
+///
+/// // A threshold of 0 is treated as 1
+/// let mut failures = FailureCounter::default();
+/// assert!(failures.record(false, 0));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FailureCounter {
+    consecutive: u8,
+}
+
+impl FailureCounter {
+    /// Records the outcome of a sensor start or read
+    ///
+    /// - param ok: If it succeeded
+    /// - param threshold: How many failures in a row call for a recovery
+    ///
+    /// returns true if the bus should be recovered now
+    pub fn record(&mut self, ok: bool, threshold: u8) -> bool {
+        if ok {
+            self.consecutive = 0;
+            return false;
+        }
+        self.consecutive = self.consecutive.saturating_add(1);
+        if self.consecutive >= threshold.max(1) {
+            self.consecutive = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the failures since the last good reading or recovery
+    ///
+    /// returns the failure count
+    pub fn consecutive(&self) -> u8 {
+        self.consecutive
+    }
+}
+
+/// Frees an [I2cBus] held by a sensor stuck mid-transfer, with its data line pulled low.
+/// The lines are briefly taken from the PIO and driven by the SIO: the clock is pulsed
+/// until the sensor lets go of the data line, then a STOP is sent to reset it
+///
+/// **NOTE: Both lines are open-drain; they are driven low or released to the pull-ups, never driven high**
+///
+/// - param delayer: [Timer] instance
+#[cfg(feature = "hardware")]
+pub fn recover_i2c_bus(delayer: &mut Timer) {
+    const FUNCSEL_SIO: u8 = 5;
+    const FUNCSEL_PIO0: u8 = 6;
+    const HALF_PERIOD_US: u32 = 5; // 100kHz
+    let sda = 1 << I2C_SDA_GPIO;
+    let scl = 1 << I2C_SCL_GPIO;
+    // Safety: only the bus pins are touched, and they are handed back to the PIO afterwards
+    let io = unsafe { &*rp_pico::pac::IO_BANK0::ptr() };
+    let sio = unsafe { &*rp_pico::pac::SIO::ptr() };
+    let release = |mask: u32| sio.gpio_oe_clr().write(|w| unsafe { w.bits(mask) });
+    let pull_low = |mask: u32| sio.gpio_oe_set().write(|w| unsafe { w.bits(mask) });
+
+    release(sda | scl);
+    sio.gpio_out_clr().write(|w| unsafe { w.bits(sda | scl) });
+    for gpio in [I2C_SDA_GPIO, I2C_SCL_GPIO] {
+        io.gpio(gpio as usize)
+            .gpio_ctrl()
+            .modify(|_, w| unsafe { w.funcsel().bits(FUNCSEL_SIO) });
+    }
+
+    for _ in 0..I2C_RECOVERY_PULSES {
+        if sio.gpio_in().read().bits() & sda != 0 {
+            break;
+        }
+        pull_low(scl);
+        delayer.delay_us(HALF_PERIOD_US);
+        release(scl);
+        delayer.delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP: the data line rises while the clock is high
+    pull_low(scl);
+    delayer.delay_us(HALF_PERIOD_US);
+    pull_low(sda);
+    delayer.delay_us(HALF_PERIOD_US);
+    release(scl);
+    delayer.delay_us(HALF_PERIOD_US);
+    release(sda);
+    delayer.delay_us(HALF_PERIOD_US);
+
+    for gpio in [I2C_SDA_GPIO, I2C_SCL_GPIO] {
+        io.gpio(gpio as usize)
+            .gpio_ctrl()
+            .modify(|_, w| unsafe { w.funcsel().bits(FUNCSEL_PIO0) });
+    }
+}
+
+/// Counts a failed start or read of a [SensorSource].
+/// Once too many fail in a row the source is recovered; if it is still unreachable, an alarm is sounded
+///
+/// - param source: [SensorSource] instance
+/// - param failures: [FailureCounter] instance
+/// - param threshold: How many failures in a row call for a recovery
+/// - param delayer: Alarm delay
 /// - param alarm: Buzzer Pin
+/// - param on_fault: Called once before the alarm starts sounding
+///
+/// returns true if the source was recovered
+#[cfg(feature = "hardware")]
+pub fn handle_sensor_failure<S: SensorSource>(
+    source: &mut S,
+    failures: &mut FailureCounter,
+    threshold: u8,
+    delayer: &mut Timer,
+    alarm: &mut impl OutputPin,
+    on_fault: impl FnOnce(),
+) -> bool {
+    if !failures.record(false, threshold) {
+        return false;
+    }
+    match source.recover() {
+        Ok(()) => true,
+        Err(_) => sound_fault_alarm(delayer, alarm, on_fault),
+    }
+}
+
+/// Sounds the sensor fault alarm forever
 ///
-/// returns [FieldData]
-pub fn get_bme_data(
-    bme: &mut Bme,
+/// - param delayer: Alarm delay
+/// - param alarm: Buzzer Pin
+/// - param on_fault: Called once before the alarm starts sounding
+#[cfg(feature = "hardware")]
+fn sound_fault_alarm(
     delayer: &mut Timer,
-    alarm: &mut Pin<Gpio6, FunctionSio<SioOutput>, PullDown>,
-) -> FieldData {
-    prep_bme(bme, delayer, alarm);
-    bme.get_sensor_data(delayer)
-        .unwrap_or((FieldData::default(), FieldDataCondition::Unchanged))
-        .0
+    alarm: &mut impl OutputPin,
+    on_fault: impl FnOnce(),
+) -> ! {
+    on_fault();
+    // The alarm sounds until the board is power cycled, so the watchdog mustn't reset it
+    loop {
+        feed_watchdog();
+        alarm.set_high().unwrap();
+        delayer.delay_ms(500);
+        alarm.set_low().unwrap();
+        delayer.delay_ms(1000);
+    }
+}
+
+/// The minutes between each pressure sample
+pub const PRESSURE_SAMPLE_MINUTES: u8 = 15;
+/// The amount of pressure samples kept; 3 hours worth
+pub const PRESSURE_HISTORY_LEN: usize = 12;
+
+/// Which way the barometric pressure is heading
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PressureTrend {
+    Rising,
+    Steady,
+    Falling,
+}
+
+impl PressureTrend {
+    /// Gets the on-screen indicator for the trend
+    ///
+    /// returns an arrow and the name of the trend
+    pub fn label(self) -> &'static str {
+        match self {
+            PressureTrend::Rising => LABELS.rising,
+            PressureTrend::Steady => LABELS.steady,
+            PressureTrend::Falling => LABELS.falling,
+        }
+    }
+}
+
+/// Contains the recent pressure samples, taken every [PRESSURE_SAMPLE_MINUTES]
+///
+/// - **samples**: The pressure samples in tenths of a millibar/hPa
+/// - **last_minute**: The minute the last sample was taken on
+pub struct PressureHistory {
+    samples: HistoryBuffer<u16, PRESSURE_HISTORY_LEN>,
+    last_minute: Option<u8>,
+}
+
+impl Default for PressureHistory {
+    fn default() -> Self {
+        PressureHistory {
+            samples: HistoryBuffer::new(),
+            last_minute: None,
+        }
+    }
+}
+
+impl PressureHistory {
+    /// Records a sample if one is due.
+    /// The first sample is taken immediately, then one every [PRESSURE_SAMPLE_MINUTES]
+    ///
+    /// - param date: The current [DateTime]
+    /// - param pressure: The current pressure in millibars/hPa
+    pub fn update(&mut self, date: &DateTime, pressure: f32) {
+        let due = self.samples.recent().is_none()
+            || (date.1 % PRESSURE_SAMPLE_MINUTES == 0 && self.last_minute != Some(date.1));
+        if due {
+            self.samples.write(round_positive(pressure * 10.) as u16);
+            self.last_minute = Some(date.1);
+        }
+    }
+
+    /// Calculates the trend over the recorded history
+    ///
+    /// - param threshold: The change in tenths of a millibar/hPa per hour that counts as a trend
+    ///
+    /// returns the [PressureTrend]; [PressureTrend::Steady] until two samples exist
+    pub fn trend(&self, threshold: u8) -> PressureTrend {
+        match (self.samples.oldest_ordered().next(), self.samples.recent()) {
+            (Some(oldest), Some(newest)) => classify_trend(
+                *oldest,
+                *newest,
+                (self.samples.len() as u16 - 1) * PRESSURE_SAMPLE_MINUTES as u16,
+                threshold,
+            ),
+            _ => PressureTrend::Steady,
+        }
+    }
+}
+
+/// Classifies the change in pressure between two samples
+///
+/// - param oldest: The oldest pressure sample in tenths of a millibar/hPa
+/// - param newest: The newest pressure sample in tenths of a millibar/hPa
+/// - param span_minutes: The minutes between the two samples
+/// - param threshold: The change in tenths of a millibar/hPa per hour that counts as a trend
+///
+/// returns the [PressureTrend]; [PressureTrend::Steady] if no time has passed
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::{classify_trend, PressureTrend};
+///
+/// // 1013.0 -> 1015.0 over 3 hours is ~0.7 hPa/hour
+/// assert_eq!(classify_trend(10130, 10150, 180, 5), PressureTrend::Rising);
+/// // 1013.0 -> 1012.0 over 3 hours is ~0.3 hPa/hour
+/// assert_eq!(classify_trend(10130, 10120, 180, 5), PressureTrend::Steady);
+/// // 1013.0 -> 1011.5 over 1 hour
+/// assert_eq!(classify_trend(10130, 10115, 60, 5), PressureTrend::Falling);
+/// // A single sample has no trend yet
+/// assert_eq!(classify_trend(10130, 10130, 0, 5), PressureTrend::Steady);
+/// ```
+pub fn classify_trend(oldest: u16, newest: u16, span_minutes: u16, threshold: u8) -> PressureTrend {
+    if span_minutes == 0 {
+        return PressureTrend::Steady;
+    }
+    let per_hour = (newest as i32 - oldest as i32) * 60 / span_minutes as i32;
+    if per_hour >= threshold as i32 {
+        PressureTrend::Rising
+    } else if per_hour <= -(threshold as i32) {
+        PressureTrend::Falling
+    } else {
+        PressureTrend::Steady
+    }
+}
+
+/// The minutes of clean air used to calibrate the gas baseline
+pub const GAS_BURN_IN_MINUTES: u32 = 5;
+/// The ideal relative humidity for air quality
+const AQI_HUMIDITY_BASELINE: f32 = 40.;
+/// How much of the air quality index comes from humidity; the rest comes from gas
+const AQI_HUMIDITY_WEIGHT: f32 = 25.;
+
+/// Averages the gas resistance over the burn-in period to find the clean-air baseline
+///
+/// - **sum**: The sum of the gas resistance samples so far
+/// - **samples**: The amount of samples taken so far
+#[derive(Default)]
+pub struct GasBaseline {
+    sum: u64,
+    samples: u32,
+}
+
+impl GasBaseline {
+    /// Adds a gas resistance sample to the burn-in
+    ///
+    /// - param resistance: The gas resistance in ohms
+    /// - param poll_ms: The milliseconds between each sample
+    ///
+    /// returns the baseline in ohms once the burn-in period has passed
+    pub fn update(&mut self, resistance: u32, poll_ms: u16) -> Option<u32> {
+        self.sum += resistance as u64;
+        self.samples += 1;
+        if self.samples * poll_ms as u32 >= GAS_BURN_IN_MINUTES * 60_000 {
+            Some((self.sum / self.samples as u64) as u32)
+        } else {
+            None
+        }
+    }
+}
+
+/// Calculates an air quality index where 100 is the cleanest air.
+///
+/// `25%` of the index is based on how far humidity is from the ideal `40%`:
+/// - Above 40%: `(100 - humidity) / 60 * 25`
+/// - Otherwise: `humidity / 40 * 25`
+///
+/// The other `75%` is based on the gas resistance; pollutants lower the resistance:
+/// - Below the baseline: `current / baseline * 75`
+/// - Otherwise: `75`
+///
+/// - param current: The current gas resistance in ohms
+/// - param baseline: The clean-air gas resistance in ohms
+/// - param humidity: The relative humidity percentage
+///
+/// returns the air quality index from 0 to 100
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::aqi_from_resistance;
+///
+/// assert_eq!(aqi_from_resistance(50_000, 50_000, 40.), 100); // Clean air at ideal humidity
+/// assert_eq!(aqi_from_resistance(25_000, 50_000, 40.), 63); // Half the clean-air resistance
+/// assert_eq!(aqi_from_resistance(60_000, 50_000, 70.), 88); // Humid, but clean
+/// assert_eq!(aqi_from_resistance(0, 50_000, 0.), 0);
+/// ```
+pub fn aqi_from_resistance(current: u32, baseline: u32, humidity: f32) -> u8 {
+    let humidity = humidity.clamp(0., 100.);
+    let humidity_score = if humidity > AQI_HUMIDITY_BASELINE {
+        (100. - humidity) / (100. - AQI_HUMIDITY_BASELINE) * AQI_HUMIDITY_WEIGHT
+    } else {
+        humidity / AQI_HUMIDITY_BASELINE * AQI_HUMIDITY_WEIGHT
+    };
+    let gas_weight = 100. - AQI_HUMIDITY_WEIGHT;
+    let gas_score = if current < baseline {
+        current as f32 / baseline as f32 * gas_weight
+    } else {
+        gas_weight
+    };
+    round_positive(humidity_score + gas_score) as u8
 }
 
 /// Gets temperature in Fahrenheit
 ///
-/// - param data: [FieldData] from [get_bme_data()]
+/// - param data: [Reading] from [SensorSource::read]
 ///
 /// returns the current temperature in Fahrenheit
-pub fn get_temperature(data: &FieldData) -> u8 {
-    (data.temperature_celsius() * (9. / 5.) + 32.) as u8
+pub fn get_temperature(data: &Reading) -> u8 {
+    celsius_to_fahrenheit(data.temperature)
 }
 
 /// Gets percent humidity (whole number)
 ///
-/// - param data: [FieldData] from [get_bme_data()]
+/// - param data: [Reading] from [SensorSource::read]
 ///
 /// returns the current relative humidity as a percentage (non-decimal)
 ///
 /// ## Example:
 /// ```rust
-/// use bme680::FieldData;
-/// use rp_pico::hal::gpio::bank0::Gpio6;
-/// use rp_pico::hal::gpio::{FunctionSio, Pin, PullDown, SioOutput};
-/// use rp_pico::hal::Timer;
-/// use gem_rs::sensors::{get_bme_data, get_humidity, Bme};
+/// use gem_rs::sensors::{get_humidity, Reading};
 ///
 ///
-/// let data = FieldData::default(); // This is representing `get_bme_data()`
+/// let data = Reading::default(); // This is representing `SensorSource::read()`
 /// let humidity = get_humidity(&data); // Ex: let humidity = 50
 /// print!("Humidity: {}%", humidity); // "Humidity: 50%"
 /// ```
-pub fn get_humidity(data: &FieldData) -> u8 {
-    data.humidity_percent() as u8
+pub fn get_humidity(data: &Reading) -> u8 {
+    humidity_to_percent(data.humidity)
 }
 
 /// Gets atmospheric pressure in millibars
 ///
-/// - param data: [FieldData] from [get_bme_data()]
+/// - param data: [Reading] from [SensorSource::read]
 ///
 /// returns the pressure in millibars/hPa
-pub fn get_pressure(data: &FieldData) -> u16 {
-    data.pressure_hpa() as u16
+pub fn get_pressure(data: &Reading) -> u16 {
+    pressure_to_hpa(data.pressure)
+}
+
+/// Rounds a non-negative value to the nearest whole number.
+/// Negative values round to 0
+///
+/// - param value: the value to round
+///
+/// returns the rounded value
+pub(crate) fn round_positive(value: f32) -> f32 {
+    if value <= 0. {
+        0.
+    } else {
+        value + 0.5
+    }
+}
+
+/// Converts Celsius to whole degrees Fahrenheit
+///
+/// - param celsius: temperature in Celsius
+///
+/// returns the temperature in Fahrenheit, rounded and clamped to 0F - 255F
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::celsius_to_fahrenheit;
+///
+/// assert_eq!(celsius_to_fahrenheit(0.), 32);
+/// assert_eq!(celsius_to_fahrenheit(100.), 212);
+/// assert_eq!(celsius_to_fahrenheit(21.), 70); // 69.8F rounds up
+/// assert_eq!(celsius_to_fahrenheit(-40.), 0); // -40F clamps to 0F
+/// assert_eq!(celsius_to_fahrenheit(200.), 255); // 392F clamps to 255F
+/// ```
+pub fn celsius_to_fahrenheit(celsius: f32) -> u8 {
+    round_positive(celsius * (9. / 5.) + 32.) as u8
+}
+
+/// Converts a relative humidity reading to a whole percentage
+///
+/// - param humidity: relative humidity percentage
+///
+/// returns the relative humidity, rounded and clamped to 0% - 100%
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::humidity_to_percent;
+///
+/// assert_eq!(humidity_to_percent(49.5), 50);
+/// assert_eq!(humidity_to_percent(49.4), 49);
+/// assert_eq!(humidity_to_percent(-3.), 0);
+/// assert_eq!(humidity_to_percent(104.), 100);
+/// ```
+pub fn humidity_to_percent(humidity: f32) -> u8 {
+    round_positive(humidity).min(100.) as u8
+}
+
+/// Converts a pressure reading to whole millibars/hPa
+///
+/// - param pressure: pressure in millibars/hPa
+///
+/// returns the pressure, rounded and clamped to 0 - 65535 hPa
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::pressure_to_hpa;
+///
+/// assert_eq!(pressure_to_hpa(1013.25), 1013);
+/// assert_eq!(pressure_to_hpa(1013.5), 1014);
+/// assert_eq!(pressure_to_hpa(-1.), 0);
+/// ```
+pub fn pressure_to_hpa(pressure: f32) -> u16 {
+    round_positive(pressure) as u16
+}
+
+/// Converts millibars/hPa to inches of mercury
+///
+/// - param hpa: pressure in millibars/hPa
+///
+/// returns the pressure in inHg
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::hpa_to_inhg;
+///
+/// assert!((hpa_to_inhg(1013.25) - 29.92).abs() < 0.01); // Standard atmosphere
+/// assert!((hpa_to_inhg(1000.) - 29.53).abs() < 0.01);
+/// ```
+pub fn hpa_to_inhg(hpa: f32) -> f32 {
+    hpa * 0.029_529_983
+}
+
+/// Converts millibars/hPa to millimeters of mercury
+///
+/// - param hpa: pressure in millibars/hPa
+///
+/// returns the pressure in mmHg
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::hpa_to_mmhg;
+///
+/// assert!((hpa_to_mmhg(1013.25) - 760.).abs() < 0.1); // Standard atmosphere
+/// assert!((hpa_to_mmhg(1000.) - 750.06).abs() < 0.1);
+/// ```
+pub fn hpa_to_mmhg(hpa: f32) -> f32 {
+    hpa * 0.750_061_7
+}
+
+/// Estimates the altitude using the international barometric formula
+///
+/// - param pressure_hpa: The current pressure in millibars/hPa
+/// - param sea_level_hpa: The pressure at sea level in millibars/hPa
+///
+/// returns the altitude above sea level in meters
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::altitude_m;
+///
+/// assert!(altitude_m(1013.25, 1013.25).abs() < 0.5);
+/// assert!((altitude_m(899., 1013.25) - 1000.).abs() < 5.);
+/// assert!((altitude_m(1020., 1013.25) + 56.).abs() < 5.); // Below sea level
+/// ```
+#[cfg(feature = "altitude")]
+pub fn altitude_m(pressure_hpa: f32, sea_level_hpa: f32) -> f32 {
+    44330. * (1. - libm::powf(pressure_hpa / sea_level_hpa, 1. / 5.255))
 }
 
 /// Sets the sensor's mode to Forced.
 /// This should be called before getting data.
-/// If there is an error setting up, an alarm is sounded.
 ///
 /// - param bme: [Bme] sensor reference
 /// - param delayer: BME delay
-/// - param alarm: Buzzer Pin
-pub fn prep_bme(
-    bme: &mut Bme,
-    delayer: &mut Timer,
-    alarm: &mut Pin<Gpio6, FunctionSio<SioOutput>, PullDown>,
-) {
-    if bme.set_sensor_mode(delayer, PowerMode::ForcedMode).is_err() {
-        loop {
-            alarm.set_high().unwrap();
-            delayer.delay_ms(500);
-            alarm.set_low().unwrap();
-            delayer.delay_ms(1000);
-        }
-    }
+///
+/// returns [SensorError] if the sensor could not be set up
+#[cfg(feature = "hardware")]
+pub fn prep_bme(bme: &mut Bme, delayer: &mut Timer) -> Result<(), SensorError> {
+    bme.set_sensor_mode(delayer, PowerMode::ForcedMode)
+        .map_err(|_| SensorError)
+}
+
+/// Converts a raw 12-bit ADC reading of the VSYS divider into the supply voltage
+///
+/// - param raw: the raw ADC reading
+///
+/// returns the supply voltage in millivolts
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::sensors::adc_to_millivolts;
+///
+/// assert_eq!(adc_to_millivolts(0), 0);
+/// assert_eq!(adc_to_millivolts(2048), 4950); // Half scale is half of 3.3V * 3
+/// assert_eq!(adc_to_millivolts(4095), 9897); // Full scale
+/// ```
+pub fn adc_to_millivolts(raw: u16) -> u16 {
+    (raw as u32 * ADC_REFERENCE_MV * VSYS_DIVIDER / ADC_RESOLUTION) as u16
+}
+
+/// Reads the supply voltage (VSYS) through the ADC
+///
+/// - param adc: [Adc] instance
+/// - param vsys: [VsysPin] instance
+///
+/// returns the supply voltage in millivolts, or None if the read failed
+#[cfg(feature = "hardware")]
+pub fn read_supply_voltage_mv(adc: &mut Adc, vsys: &mut VsysPin) -> Option<u16> {
+    let raw: u16 = adc.read(vsys).ok()?;
+    Some(adc_to_millivolts(raw))
 }
@@ -0,0 +1,197 @@
+use crate::events::EventKind;
+use crate::preferences::Preferences;
+use core::cell::RefCell;
+use core::convert::Infallible;
+use critical_section::Mutex;
+use embedded_hal::digital::{ErrorType, OutputPin};
+
+/// What core0 asks of the [SafetyMonitor](crate::safety::SafetyMonitor) on core1
+///
+/// - **preferences**: A copy of the latest [Preferences]
+/// - **reading**: The latest temperature and humidity, or None while the sensor warms up
+/// - **co2**: The latest CO2 in ppm, or None while it warms up, has gone stale or no CO2 sensor is fitted
+/// - **manual_watering**: If watering was started over UART
+/// - **priming**: If the sprinklers are being primed from the watering screen
+/// - **buzzer**: If core0 is sounding the buzzer itself, such as for the low voltage chirp
+/// - **safe_state**: If the supply is browning out, so the outputs should be made safe
+/// - **alarm_test**: If an [AlarmTest](crate::safety::AlarmTest) is running, so the fire response runs without smoke
+/// - **sensor_fault**: If the sensors have failed and the reading has gone stale, so the [FaultPolicy](crate::preferences::FaultPolicy) applies
+#[derive(Clone, PartialEq)]
+pub struct Requests {
+    pub preferences: Preferences,
+    pub reading: Option<(u8, u8)>,
+    pub co2: Option<u16>,
+    pub manual_watering: bool,
+    pub priming: bool,
+    pub buzzer: bool,
+    pub safe_state: bool,
+    pub alarm_test: bool,
+    pub sensor_fault: bool,
+}
+
+impl Requests {
+    /// Creates requests that leave every output off
+    ///
+    /// - param preferences: The [Preferences] to start with
+    ///
+    /// returns the new [Requests]
+    pub fn new(preferences: &Preferences) -> Requests {
+        Requests {
+            preferences: preferences.clone(),
+            reading: None,
+            co2: None,
+            manual_watering: false,
+            priming: false,
+            buzzer: false,
+            safe_state: false,
+            alarm_test: false,
+            sensor_fault: false,
+        }
+    }
+}
+
+/// What core1 reports back to core0 after each pass
+///
+/// - **fire**: If the smoke detector is triggered
+/// - **temperature_alarm**: If the temperature is outside of the alarm band
+/// - **watering**: If the sprinklers are watering
+/// - **rain**: If rain is holding the roof vent closed
+/// - **escalated**: If the temperature or humidity has been out of range for longer than [Preferences::escalation_mins]
+/// - **alarm_test**: If the fire response is running for an alarm test rather than smoke
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Status {
+    pub fire: bool,
+    pub temperature_alarm: bool,
+    pub watering: bool,
+    pub rain: bool,
+    pub escalated: bool,
+    pub alarm_test: bool,
+}
+
+/// The state shared between the two cores.
+///
+/// Core0 owns the LCD, buttons, UART, sensors and flash, and writes [Requests].
+/// Core1 owns the smoke detector and every actuator, and writes the [Status].
+/// Each side only writes its own half, and both halves are swapped whole inside a critical section,
+/// which on the RP2040 takes a hardware spinlock, so neither core ever sees a half-written update.
+/// Critical sections are kept to a copy so neither core holds up the other
+static SHARED: Mutex<RefCell<Option<(Requests, Status)>>> = Mutex::new(RefCell::new(None));
+
+/// Sets up the shared state; must be called before core1 is started
+///
+/// - param preferences: The [Preferences] loaded at boot
+pub fn init(preferences: &Preferences) {
+    critical_section::with(|cs| {
+        SHARED
+            .borrow_ref_mut(cs)
+            .replace((Requests::new(preferences), Status::default()));
+    });
+}
+
+/// Updates the [Requests] sent to core1; does nothing before [init]
+///
+/// - param f: Changes the requests
+pub fn request(f: impl FnOnce(&mut Requests)) {
+    critical_section::with(|cs| {
+        if let Some((requests, _)) = SHARED.borrow_ref_mut(cs).as_mut() {
+            f(requests);
+        }
+    });
+}
+
+/// Gets a copy of the latest [Requests]
+///
+/// returns the requests, or None before [init]
+pub fn requests() -> Option<Requests> {
+    critical_section::with(|cs| {
+        SHARED
+            .borrow_ref(cs)
+            .as_ref()
+            .map(|(requests, _)| requests.clone())
+    })
+}
+
+/// Reports the [Status] of core1 back to core0; does nothing before [init]
+///
+/// - param status: The latest [Status]
+pub fn report(status: Status) {
+    critical_section::with(|cs| {
+        if let Some((_, shared)) = SHARED.borrow_ref_mut(cs).as_mut() {
+            *shared = status;
+        }
+    });
+}
+
+/// Gets the latest [Status] of core1
+///
+/// returns the status, which is all clear before [init]
+pub fn status() -> Status {
+    critical_section::with(|cs| {
+        SHARED
+            .borrow_ref(cs)
+            .as_ref()
+            .map(|(_, status)| *status)
+            .unwrap_or_default()
+    })
+}
+
+/// Gets the events to log when the [Status] changes
+///
+/// - param previous: The [Status] seen last
+/// - param current: The latest [Status]
+///
+/// returns the fire and watering events, if either changed
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::events::EventKind;
+/// use gem_rs::shared::{status_events, Status};
+///
+/// let quiet = Status::default();
+/// assert_eq!(status_events(quiet, quiet), [None, None]);
+///
+/// let fire = Status { fire: true, ..quiet };
+/// assert_eq!(status_events(quiet, fire), [Some(EventKind::AlarmOn), None]);
+/// assert_eq!(status_events(fire, quiet), [Some(EventKind::AlarmOff), None]);
+///
+/// let watering = Status { watering: true, ..quiet };
+/// assert_eq!(status_events(quiet, watering), [None, Some(EventKind::WateringStart)]);
+/// assert_eq!(status_events(watering, quiet), [None, Some(EventKind::WateringStop)]);
+///
+/// // The temperature alarm, rain, escalation and alarm tests aren't logged
+/// let hot = Status { temperature_alarm: true, rain: true, escalated: true, alarm_test: true, ..quiet };
+/// assert_eq!(status_events(quiet, hot), [None, None]);
+/// ```
+pub fn status_events(previous: Status, current: Status) -> [Option<EventKind>; 2] {
+    let fire = match (previous.fire, current.fire) {
+        (false, true) => Some(EventKind::AlarmOn),
+        (true, false) => Some(EventKind::AlarmOff),
+        _ => None,
+    };
+    let watering = match (previous.watering, current.watering) {
+        (false, true) => Some(EventKind::WateringStart),
+        (true, false) => Some(EventKind::WateringStop),
+        _ => None,
+    };
+    [fire, watering]
+}
+
+/// Sounds the buzzer on core1 from core0, by setting [Requests::buzzer]
+#[derive(Default)]
+pub struct BuzzerRequest;
+
+impl ErrorType for BuzzerRequest {
+    type Error = Infallible;
+}
+
+impl OutputPin for BuzzerRequest {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        request(|requests| requests.buzzer = false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        request(|requests| requests.buzzer = true);
+        Ok(())
+    }
+}
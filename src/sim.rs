@@ -0,0 +1,120 @@
+use crate::sensors::{Reading, SensorError, SensorSource};
+
+/// A temperature ramp from 20C (68F) to 32C (~90F) at a steady 65% humidity
+pub const TEMPERATURE_RAMP: [Reading; 7] = [
+    reading(20., 65.),
+    reading(22., 65.),
+    reading(24., 65.),
+    reading(26., 65.),
+    reading(28., 65.),
+    reading(30., 65.),
+    reading(32., 65.),
+];
+
+/// A brief humidity spike at a steady 21C (~70F)
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{decide_actuators, Actuators};
+/// use gem_rs::preferences::Preferences;
+/// use gem_rs::sensors::{get_humidity, get_temperature, SensorSource};
+/// use gem_rs::sim::{ScriptedSource, HUMIDITY_SPIKE};
+///
+/// let mut preferences = Preferences::default(); // 60% - 70%, 3% hysteresis
+/// preferences.date.2 = 12;
+/// let mut source = ScriptedSource::new(&HUMIDITY_SPIKE);
+///
+/// let mut actuators = Actuators::default();
+/// let mut vent = [false; 5];
+/// let mut mister = [false; 5];
+/// for index in 0..5 {
+///     let reading = source.read().unwrap();
+///     actuators = decide_actuators(
+///         get_temperature(&reading),
+///         get_humidity(&reading),
+///         None,
+///         false,
+///         actuators,
+///         &preferences,
+///     );
+///     vent[index] = actuators.roof_vent;
+///     mister[index] = actuators.mister;
+/// }
+/// // The vent dries out the spike and closes once back inside the range, and the mister never runs
+/// assert_eq!(vent, [false, true, true, true, false]);
+/// assert_eq!(mister, [false; 5]);
+/// ```
+pub const HUMIDITY_SPIKE: [Reading; 5] = [
+    reading(21., 65.),
+    reading(21., 80.),
+    reading(21., 95.),
+    reading(21., 80.),
+    reading(21., 65.),
+];
+
+/// Creates a [Reading] at sea-level pressure in clean air
+const fn reading(temperature: f32, humidity: f32) -> Reading {
+    Reading {
+        temperature,
+        humidity,
+        pressure: 1013.25,
+        gas_resistance: 50_000,
+    }
+}
+
+/// [SensorSource] that plays back a scripted sequence of [Reading]s.
+/// Once the script ends, the last [Reading] is repeated.
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::control::{decide_actuators, Actuators};
+/// use gem_rs::preferences::Preferences;
+/// use gem_rs::sensors::{get_humidity, get_temperature, SensorSource};
+/// use gem_rs::sim::{ScriptedSource, TEMPERATURE_RAMP};
+///
+/// let mut preferences = Preferences::default();
+/// preferences.date.2 = 12; // Midday, so the vent opens above 80F
+/// let mut source = ScriptedSource::new(&TEMPERATURE_RAMP);
+///
+/// let mut actuators = Actuators::default();
+/// let mut vent = [false; 7];
+/// for state in vent.iter_mut() {
+///     let reading = source.read().unwrap();
+///     actuators = decide_actuators(
+///         get_temperature(&reading),
+///         get_humidity(&reading),
+///         None,
+///         false,
+///         actuators,
+///         &preferences,
+///     );
+///     *state = actuators.roof_vent;
+/// }
+/// // 26C (78F) keeps the vent closed, 28C (82F) opens it
+/// assert_eq!(vent, [false, false, false, false, true, true, true]);
+/// ```
+pub struct ScriptedSource<'a> {
+    script: &'a [Reading],
+    index: usize,
+}
+
+impl<'a> ScriptedSource<'a> {
+    /// Creates a new instance of ScriptedSource
+    ///
+    /// - param script: The [Reading]s to play back in order
+    ///
+    /// returns a new instance of ScriptedSource
+    pub fn new(script: &'a [Reading]) -> ScriptedSource<'a> {
+        Self { script, index: 0 }
+    }
+}
+
+impl SensorSource for ScriptedSource<'_> {
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        let reading = self.script.get(self.index).ok_or(SensorError)?;
+        if self.index + 1 < self.script.len() {
+            self.index += 1;
+        }
+        Ok(*reading)
+    }
+}
@@ -0,0 +1,478 @@
+use crate::flash::{is_erased, Storage, SECTOR_SIZE};
+use crate::persist::crc32;
+use crate::preferences::DateTime;
+
+/// The version of the [SensorStats] byte layout; bumped whenever the layout changes
+pub const STATS_VERSION: u8 = 2;
+/// The amount of bytes [SensorStats] are encoded into
+pub const STATS_LEN: usize = 20;
+/// Where the stats region starts, counted from the start of flash.
+/// It sits right after the event store, in the last 8KB of the 2MB flash
+pub const STATS_FLASH_OFFSET: u32 = 0x1F_E000;
+/// The amount of sectors stats writes rotate through
+pub const STATS_SECTORS: u32 = 2;
+/// The size of the stats region in bytes
+pub const STATS_REGION_LEN: u32 = STATS_SECTORS * SECTOR_SIZE;
+/// The size of a saved stats slot in bytes
+pub const STATS_SLOT_LEN: usize = 32;
+/// The amount of slots that fit in a sector
+pub const STATS_SLOTS_PER_SECTOR: u32 = SECTOR_SIZE / STATS_SLOT_LEN as u32;
+/// The amount of slots writes rotate through
+pub const STATS_SLOTS: u32 = STATS_SECTORS * STATS_SLOTS_PER_SECTOR;
+
+// A slot holds a sequence number, the stats and a CRC
+const _: () = assert!(4 + STATS_LEN + 4 <= STATS_SLOT_LEN);
+
+/// The lowest and highest of a value
+///
+/// - **min**: The lowest value seen
+/// - **max**: The highest value seen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MinMax {
+    pub min: u8,
+    pub max: u8,
+}
+
+impl MinMax {
+    /// Widens the range to include a value
+    ///
+    /// - param value: The new value
+    ///
+    /// returns the widened [MinMax]
+    fn with(self, value: u8) -> MinMax {
+        MinMax {
+            min: self.min.min(value),
+            max: self.max.max(value),
+        }
+    }
+}
+
+/// The extremes of the readings over some period
+///
+/// - **temperature**: The temperature extremes in Fahrenheit
+/// - **humidity**: The relative humidity extremes in percent
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Extremes {
+    pub temperature: MinMax,
+    pub humidity: MinMax,
+}
+
+impl Extremes {
+    /// Folds a reading into the extremes
+    ///
+    /// - param extremes: The extremes so far; None if nothing has been read yet
+    /// - param temperature: The temperature in Fahrenheit
+    /// - param humidity: The relative humidity in percent
+    ///
+    /// returns the updated [Extremes]
+    fn update(extremes: Option<Extremes>, temperature: u8, humidity: u8) -> Extremes {
+        match extremes {
+            Some(extremes) => Extremes {
+                temperature: extremes.temperature.with(temperature),
+                humidity: extremes.humidity.with(humidity),
+            },
+            None => Extremes {
+                temperature: MinMax {
+                    min: temperature,
+                    max: temperature,
+                },
+                humidity: MinMax {
+                    min: humidity,
+                    max: humidity,
+                },
+            },
+        }
+    }
+}
+
+/// Works out the growing degree days of a day: `max(0, (max + min) / 2 - base)`
+///
+/// - param temperature: The day's temperature extremes in Fahrenheit
+/// - param base: The base temperature in Fahrenheit, below which the crop doesn't grow
+///
+/// returns the day's growing degree days in tenths, so odd sums keep their half degree
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::stats::{growing_degree_days, MinMax};
+///
+/// // (80 + 60) / 2 - 50 = 20
+/// assert_eq!(growing_degree_days(MinMax { min: 60, max: 80 }, 50), 200);
+/// // (75 + 56) / 2 - 50 = 15.5
+/// assert_eq!(growing_degree_days(MinMax { min: 56, max: 75 }, 50), 155);
+/// // (86 + 70) / 2 - 86 would be -8, so the day adds nothing
+/// assert_eq!(growing_degree_days(MinMax { min: 70, max: 86 }, 86), 0);
+/// // A mean right on the base adds nothing either
+/// assert_eq!(growing_degree_days(MinMax { min: 40, max: 60 }, 50), 0);
+/// assert_eq!(growing_degree_days(MinMax { min: 255, max: 255 }, 32), 2230);
+/// ```
+pub fn growing_degree_days(temperature: MinMax, base: u8) -> u16 {
+    ((temperature.min as u16 + temperature.max as u16) * 5).saturating_sub(base as u16 * 10)
+}
+
+/// The daily and all-time extremes of the readings, and the growing degree days (GDD) built up from them.
+/// The daily extremes start over on the first reading of each day, while the all-time ones are kept.
+/// The day being left behind adds its [growing_degree_days] to the total as it starts over,
+/// as long as the new reading is from the next day
+///
+/// - **day**: The day, month and year the daily extremes belong to
+/// - **daily**: The extremes since midnight; None until the first reading of the day
+/// - **all_time**: The extremes since the stats were first saved; None until the first reading
+/// - **gdd**: The growing degree days in Fahrenheit since they were last reset, in tenths
+/// - **gdd_days**: The amount of days added to [SensorStats::gdd]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::DateTime;
+/// use gem_rs::stats::{MinMax, SensorStats};
+///
+/// let mut stats = SensorStats::default();
+/// assert!(stats.update(&DateTime(0, 0, 14, 1, 6, 2024), 75, 60, 50));
+/// assert!(stats.update(&DateTime(0, 30, 23, 1, 6, 2024), 90, 40, 50));
+/// assert!(!stats.update(&DateTime(0, 45, 23, 1, 6, 2024), 80, 50, 50)); // Nothing new
+/// assert_eq!(stats.daily.unwrap().temperature, MinMax { min: 75, max: 90 });
+/// assert_eq!((stats.gdd, stats.gdd_days), (0, 0));
+///
+/// // The daily extremes start over after midnight, but the all-time ones are kept
+/// assert!(stats.update(&DateTime(2, 0, 0, 2, 6, 2024), 70, 55, 50));
+/// let daily = stats.daily.unwrap();
+/// assert_eq!(daily.temperature, MinMax { min: 70, max: 70 });
+/// assert_eq!(daily.humidity, MinMax { min: 55, max: 55 });
+/// let all_time = stats.all_time.unwrap();
+/// assert_eq!(all_time.temperature, MinMax { min: 70, max: 90 });
+/// assert_eq!(all_time.humidity, MinMax { min: 40, max: 60 });
+/// // The first day added (90 + 75) / 2 - 50 = 32.5 GDD
+/// assert_eq!((stats.gdd, stats.gdd_days), (325, 1));
+///
+/// // Setting the clock to another day starts over too, but the unfinished day adds nothing
+/// assert!(stats.update(&DateTime(0, 0, 12, 1, 6, 2024), 72, 55, 50));
+/// assert_eq!(stats.daily.unwrap().temperature, MinMax { min: 72, max: 72 });
+/// assert_eq!((stats.gdd, stats.gdd_days), (325, 1));
+/// // Neither does one followed by days without readings
+/// assert!(stats.update(&DateTime(0, 0, 12, 30, 6, 2024), 80, 55, 50));
+/// assert_eq!((stats.gdd, stats.gdd_days), (325, 1));
+/// // The next day is still counted across the end of a month, adding 80 - 50 = 30 GDD
+/// assert!(stats.update(&DateTime(0, 0, 6, 1, 7, 2024), 60, 55, 50));
+/// assert_eq!((stats.gdd, stats.gdd_days), (625, 2));
+///
+/// // Resetting the GDD keeps the extremes
+/// stats.reset_gdd();
+/// assert_eq!((stats.gdd, stats.gdd_days), (0, 0));
+/// assert!(stats.daily.is_some());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SensorStats {
+    pub day: (u8, u8, u16),
+    pub daily: Option<Extremes>,
+    pub all_time: Option<Extremes>,
+    pub gdd: u32,
+    pub gdd_days: u16,
+}
+
+impl Default for SensorStats {
+    fn default() -> Self {
+        SensorStats {
+            day: (0, 0, 0), // Matches no real date, so the first reading starts the day
+            daily: None,
+            all_time: None,
+            gdd: 0,
+            gdd_days: 0,
+        }
+    }
+}
+
+impl SensorStats {
+    /// Folds a new reading into the stats, starting the daily extremes over if the day has changed.
+    /// Only a reading from the next day adds the finished day to the GDD; after the clock is set to
+    /// another day or days pass without readings, the partial day is dropped instead.
+    /// Stats loaded at boot are updated the same way, so a restart on another day starts the daily extremes over
+    ///
+    /// - param date: When the reading was taken
+    /// - param temperature: The temperature in Fahrenheit
+    /// - param humidity: The relative humidity in percent
+    /// - param gdd_base: The base temperature in Fahrenheit the finished day's [growing_degree_days] are counted from
+    ///
+    /// returns true if the stats changed and should be saved
+    pub fn update(&mut self, date: &DateTime, temperature: u8, humidity: u8, gdd_base: u8) -> bool {
+        let before = *self;
+        let day = (date.3, date.4, date.5);
+        if day != self.day {
+            // Carry the finished day into the total before its extremes start over
+            let previous = DateTime(0, 0, 0, self.day.0, self.day.1, self.day.2);
+            let next_day = date.day_number() == previous.day_number() + 1;
+            if let Some(daily) = self.daily.filter(|_| next_day) {
+                self.gdd = self
+                    .gdd
+                    .saturating_add(growing_degree_days(daily.temperature, gdd_base) as u32);
+                self.gdd_days = self.gdd_days.saturating_add(1);
+            }
+            self.day = day;
+            self.daily = None;
+        }
+        self.daily = Some(Extremes::update(self.daily, temperature, humidity));
+        self.all_time = Some(Extremes::update(self.all_time, temperature, humidity));
+        *self != before
+    }
+
+    /// Starts the growing degree days over, such as when a new crop is planted
+    pub fn reset_gdd(&mut self) {
+        self.gdd = 0;
+        self.gdd_days = 0;
+    }
+
+    /// Encodes the stats into the byte layout stored in flash.
+    /// Multibyte values are little-endian.
+    ///
+    /// | Offset | Field                                           |
+    /// |--------|-------------------------------------------------|
+    /// | 0      | layout version                                  |
+    /// | 1..5   | day (day, month, year)                          |
+    /// | 5      | bit 0: daily is set, bit 1: all_time is set     |
+    /// | 6..10  | daily (temperature min, max, humidity min, max) |
+    /// | 10..14 | all_time (same as daily)                        |
+    /// | 14..18 | gdd                                             |
+    /// | 18..20 | gdd_days                                        |
+    ///
+    /// returns the encoded bytes
+    pub fn to_bytes(&self) -> [u8; STATS_LEN] {
+        let mut bytes = [0u8; STATS_LEN];
+        bytes[0] = STATS_VERSION;
+        bytes[1] = self.day.0;
+        bytes[2] = self.day.1;
+        bytes[3..5].copy_from_slice(&self.day.2.to_le_bytes());
+        bytes[5] = self.daily.is_some() as u8 | (self.all_time.is_some() as u8) << 1;
+        for (start, extremes) in [(6, self.daily), (10, self.all_time)] {
+            if let Some(extremes) = extremes {
+                bytes[start] = extremes.temperature.min;
+                bytes[start + 1] = extremes.temperature.max;
+                bytes[start + 2] = extremes.humidity.min;
+                bytes[start + 3] = extremes.humidity.max;
+            }
+        }
+        bytes[14..18].copy_from_slice(&self.gdd.to_le_bytes());
+        bytes[18..20].copy_from_slice(&self.gdd_days.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes stats from the byte layout written by [SensorStats::to_bytes]
+    ///
+    /// - param bytes: the encoded stats
+    ///
+    /// returns the decoded [SensorStats], or None if they are from another layout or hold an impossible range
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::preferences::DateTime;
+    /// use gem_rs::stats::{SensorStats, STATS_LEN};
+    ///
+    /// let mut stats = SensorStats::default();
+    /// assert_eq!(SensorStats::from_bytes(&stats.to_bytes()), Some(stats));
+    /// stats.update(&DateTime(0, 0, 9, 3, 7, 2024), 65, 80, 50);
+    /// stats.update(&DateTime(0, 0, 15, 3, 7, 2024), 88, 45, 50);
+    /// stats.update(&DateTime(0, 0, 9, 4, 7, 2024), 70, 60, 50);
+    /// assert_eq!(stats.gdd, 265);
+    /// assert_eq!(SensorStats::from_bytes(&stats.to_bytes()), Some(stats));
+    ///
+    /// let mut bytes = stats.to_bytes();
+    /// bytes[0] = 0xFF; // Unknown version
+    /// assert_eq!(SensorStats::from_bytes(&bytes), None);
+    /// let mut bytes = stats.to_bytes();
+    /// bytes[6] = 99; // The low is above the high
+    /// assert_eq!(SensorStats::from_bytes(&bytes), None);
+    /// assert_eq!(SensorStats::from_bytes(&[0u8; STATS_LEN - 1]), None);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Option<SensorStats> {
+        if bytes.len() != STATS_LEN || bytes[0] != STATS_VERSION || bytes[5] > 0b11 {
+            return None;
+        }
+        let extremes = |start: usize, set: bool| -> Result<Option<Extremes>, ()> {
+            if !set {
+                return Ok(None);
+            }
+            let temperature = MinMax {
+                min: bytes[start],
+                max: bytes[start + 1],
+            };
+            let humidity = MinMax {
+                min: bytes[start + 2],
+                max: bytes[start + 3],
+            };
+            if temperature.min > temperature.max || humidity.min > humidity.max {
+                return Err(());
+            }
+            Ok(Some(Extremes {
+                temperature,
+                humidity,
+            }))
+        };
+        Some(SensorStats {
+            day: (bytes[1], bytes[2], u16::from_le_bytes([bytes[3], bytes[4]])),
+            daily: extremes(6, bytes[5] & 0b01 != 0).ok()?,
+            all_time: extremes(10, bytes[5] & 0b10 != 0).ok()?,
+            gdd: u32::from_le_bytes([bytes[14], bytes[15], bytes[16], bytes[17]]),
+            gdd_days: u16::from_le_bytes([bytes[18], bytes[19]]),
+        })
+    }
+}
+
+/// Encodes a saved stats slot.
+/// The slot holds the sequence number, the [SensorStats::to_bytes] layout,
+/// then a CRC of both; the rest is left erased
+///
+/// - param sequence: the slot's write number, counting up with every save
+/// - param stats: [SensorStats] instance
+///
+/// returns the encoded slot
+fn encode_slot(sequence: u32, stats: &SensorStats) -> [u8; STATS_SLOT_LEN] {
+    let mut slot = [0xFF; STATS_SLOT_LEN];
+    slot[0..4].copy_from_slice(&sequence.to_le_bytes());
+    slot[4..4 + STATS_LEN].copy_from_slice(&stats.to_bytes());
+    let crc = crc32(&slot[..4 + STATS_LEN]);
+    slot[4 + STATS_LEN..8 + STATS_LEN].copy_from_slice(&crc.to_le_bytes());
+    slot
+}
+
+/// Decodes a slot written by [encode_slot]
+///
+/// - param slot: the encoded slot
+///
+/// returns the sequence number and [SensorStats],
+/// or None if the slot is erased, half-written or from another layout
+fn decode_slot(slot: &[u8; STATS_SLOT_LEN]) -> Option<(u32, SensorStats)> {
+    let sequence = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]);
+    let crc = &slot[4 + STATS_LEN..8 + STATS_LEN];
+    if sequence == u32::MAX || crc32(&slot[..4 + STATS_LEN]).to_le_bytes() != crc {
+        return None;
+    }
+    Some((sequence, SensorStats::from_bytes(&slot[4..4 + STATS_LEN])?))
+}
+
+/// Saves and loads [SensorStats] with wear-leveling, the same way as
+/// [crate::persist::PreferenceStore]. Every save goes to the next slot,
+/// and a sector is only erased once the writes wrap back around to it
+///
+/// - **storage**: The [Storage] holding the slots
+/// - **next_slot**: The slot the next save is written to
+/// - **next_sequence**: The sequence number of the next save
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::flash::Storage;
+/// use gem_rs::preferences::DateTime;
+/// use gem_rs::stats::{MinMax, SensorStats, StatsStore, STATS_REGION_LEN, STATS_SLOTS};
+///
+/// struct MemoryStorage(Vec<u8>);
+///
+/// impl Storage for MemoryStorage {
+///     fn read(&self, offset: u32, buffer: &mut [u8]) {
+///         let offset = offset as usize;
+///         buffer.copy_from_slice(&self.0[offset..offset + buffer.len()]);
+///     }
+///     fn erase_sector(&mut self, offset: u32) {
+///         let offset = offset as usize;
+///         self.0[offset..offset + 4096].fill(0xFF);
+///     }
+///     fn program(&mut self, offset: u32, data: &[u8]) {
+///         for (index, byte) in data.iter().enumerate() {
+///             self.0[offset as usize + index] &= byte; // Flash can only clear bits
+///         }
+///     }
+/// }
+///
+/// // The first boot finds nothing saved
+/// let mut store = StatsStore::new(MemoryStorage(vec![0xFF; STATS_REGION_LEN as usize]));
+/// let mut stats = store.load().unwrap_or_default();
+/// assert_eq!(stats, SensorStats::default());
+///
+/// // Save enough times to wrap around every sector; each reading lands on a new day
+/// for reading in 0..STATS_SLOTS + 3 {
+///     let day = DateTime(0, 0, 12, 1 + (reading % 28) as u8, 6, 2024);
+///     assert!(stats.update(&day, 50 + (reading % 40) as u8, 50, 50));
+///     store.save(&stats);
+/// }
+/// assert_eq!(store.load(), Some(stats));
+///
+/// // A restart the next day keeps the all-time extremes and merges in new readings
+/// let store = StatsStore::new(store.into_storage());
+/// let mut loaded = store.load().unwrap();
+/// assert_eq!(loaded.day, (7, 6, 2024));
+/// assert!(loaded.update(&DateTime(0, 0, 8, 8, 6, 2024), 45, 70, 50));
+/// assert_eq!(loaded.daily.unwrap().temperature, MinMax { min: 45, max: 45 });
+/// assert_eq!(loaded.all_time.unwrap().temperature, MinMax { min: 45, max: 89 });
+/// assert_eq!(loaded.all_time.unwrap().humidity, MinMax { min: 50, max: 70 });
+/// ```
+pub struct StatsStore<S: Storage> {
+    storage: S,
+    next_slot: u32,
+    next_sequence: u32,
+}
+
+impl<S: Storage> StatsStore<S> {
+    /// Opens the store, finding the newest save in the [Storage]
+    ///
+    /// - param storage: [Storage] instance
+    ///
+    /// returns a new [StatsStore]
+    pub fn new(storage: S) -> StatsStore<S> {
+        let mut store = StatsStore {
+            storage,
+            next_slot: 0,
+            next_sequence: 0,
+        };
+        if let Some((index, sequence, _)) = store.newest() {
+            store.next_slot = (index + 1) % STATS_SLOTS;
+            store.next_sequence = sequence.wrapping_add(1);
+        }
+        store
+    }
+
+    /// Gives back the [Storage]
+    ///
+    /// returns the [Storage] instance
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+
+    /// Finds the newest valid save; erased, half-written and corrupt slots are skipped
+    ///
+    /// returns the index, sequence number and [SensorStats] of the newest save
+    fn newest(&self) -> Option<(u32, u32, SensorStats)> {
+        let mut newest: Option<(u32, u32, SensorStats)> = None;
+        for index in 0..STATS_SLOTS {
+            let mut slot = [0u8; STATS_SLOT_LEN];
+            self.storage.read(index * STATS_SLOT_LEN as u32, &mut slot);
+            if let Some((sequence, stats)) = decode_slot(&slot) {
+                if newest.map_or(true, |(_, newest, _)| sequence > newest) {
+                    newest = Some((index, sequence, stats));
+                }
+            }
+        }
+        newest
+    }
+
+    /// Loads the newest saved stats
+    ///
+    /// returns the saved [SensorStats], or None if nothing valid has been saved
+    pub fn load(&self) -> Option<SensorStats> {
+        self.newest().map(|(_, _, stats)| stats)
+    }
+
+    /// Saves the stats into the next slot,
+    /// erasing the next sector first if the writes have wrapped into it
+    ///
+    /// - param stats: [SensorStats] instance
+    pub fn save(&mut self, stats: &SensorStats) {
+        if self.next_slot % STATS_SLOTS_PER_SECTOR == 0 {
+            let sector = self.next_slot / STATS_SLOTS_PER_SECTOR * SECTOR_SIZE;
+            if !is_erased(&self.storage, sector, SECTOR_SIZE) {
+                self.storage.erase_sector(sector);
+            }
+        }
+        self.storage.program(
+            self.next_slot * STATS_SLOT_LEN as u32,
+            &encode_slot(self.next_sequence, stats),
+        );
+        self.next_slot = (self.next_slot + 1) % STATS_SLOTS;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+    }
+}
@@ -1,5 +1,3 @@
-use panic_probe as _;
-
 /// Contains a value that is decremented every millisecond
 ///
 /// - **target_ms**: The current milliseconds remaining
@@ -29,6 +27,27 @@ pub const TICK_TIME_DELAY: u16 = 1000;
 /// The delay in milliseconds between querying sensors
 pub const SENSOR_DELAY: u16 = 2000;
 
+/// Checks if a reading has gone unrefreshed for too many polling intervals
+///
+/// - param age_secs: The seconds since the reading was taken
+/// - param poll_ms: The milliseconds between readings; see [SENSOR_DELAY]
+/// - param factor: How many polling intervals the reading may age before it is stale
+///
+/// returns true if the reading is older than `factor` polling intervals
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::timer::is_stale;
+///
+/// assert!(!is_stale(10, 2000, 5));
+/// assert!(is_stale(11, 2000, 5));
+/// assert!(is_stale(2, 1500, 1));
+/// assert!(is_stale(u32::MAX, 2000, 60)); // Very old readings don't overflow
+/// ```
+pub fn is_stale(age_secs: u32, poll_ms: u16, factor: u8) -> bool {
+    age_secs.saturating_mul(1000) > poll_ms as u32 * factor as u32
+}
+
 impl CountDownTimer {
     /// Creates a new instances of CountDownTimer
     ///
@@ -43,9 +62,36 @@ impl CountDownTimer {
     ///
     /// **NOTE:** This function should be called every millisecond
     pub fn tick(&mut self) {
-        if self.target_ms > 0 {
-            self.target_ms -= 1;
-        }
+        self.tick_by(1);
+    }
+
+    /// Updates the CountDownTimer by several milliseconds at once
+    ///
+    /// - param ms: The amount of milliseconds that have passed
+    ///
+    /// returns the milliseconds that passed after the CountDownTimer hit 0
+    ///
+    /// ## Example:
+    /// ```rust
+    /// use gem_rs::timer::CountDownTimer;
+    ///
+    /// let mut countdown = CountDownTimer::new(1000);
+    /// assert_eq!(countdown.tick_by(400), 0);
+    /// assert_eq!(countdown.remaining(), 600);
+    /// assert_eq!(countdown.tick_by(650), 50); // Overshot by 50ms
+    /// assert!(countdown.is_finished());
+    /// ```
+    pub fn tick_by(&mut self, ms: u16) -> u16 {
+        let overshoot = ms.saturating_sub(self.target_ms);
+        self.target_ms = self.target_ms.saturating_sub(ms);
+        overshoot
+    }
+
+    /// Gets the milliseconds left until the CountDownTimer hits 0
+    ///
+    /// returns the remaining milliseconds
+    pub fn remaining(&self) -> u16 {
+        self.target_ms
     }
 
     /// Sets the waiting time for the CountDownTimer
@@ -62,3 +108,284 @@ impl CountDownTimer {
         self.target_ms == 0
     }
 }
+
+/// Gets how long the main loop may sleep before a [CountDownTimer] needs attention.
+/// The button countdown only counts while it is running, since button presses wake the loop by themselves
+///
+/// - param button_cd: button countdown
+/// - param sensor_cd: sensor countdown
+/// - param time_cd: uptime countdown
+///
+/// returns the milliseconds to sleep
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::timer::{sleep_duration, CountDownTimer};
+///
+/// let idle_buttons = CountDownTimer::new(0);
+/// assert_eq!(sleep_duration(&idle_buttons, &CountDownTimer::new(1500), &CountDownTimer::new(700)), 700);
+///
+/// let pressed_buttons = CountDownTimer::new(200);
+/// assert_eq!(sleep_duration(&pressed_buttons, &CountDownTimer::new(1500), &CountDownTimer::new(700)), 200);
+/// ```
+pub fn sleep_duration(
+    button_cd: &CountDownTimer,
+    sensor_cd: &CountDownTimer,
+    time_cd: &CountDownTimer,
+) -> u16 {
+    let next = sensor_cd.remaining().min(time_cd.remaining());
+    if button_cd.is_finished() {
+        next
+    } else {
+        next.min(button_cd.remaining())
+    }
+}
+
+/// Turns a free-running microsecond counter into elapsed milliseconds.
+/// Leftover microseconds are carried over, so no time is lost between laps
+///
+/// - **last_us**: The counter value the last lap was counted up to
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::timer::Stopwatch;
+///
+/// let mut stopwatch = Stopwatch::new(1_000);
+/// assert_eq!(stopwatch.lap_ms(3_600), 2);  // 2.6ms have passed
+/// assert_eq!(stopwatch.lap_ms(4_000), 1);  // The leftover 0.6ms is not lost
+/// assert_eq!(stopwatch.lap_ms(4_999), 0);
+/// ```
+pub struct Stopwatch {
+    last_us: u64,
+}
+
+impl Stopwatch {
+    /// Creates a new instance of Stopwatch
+    ///
+    /// - param now_us: The current counter value in microseconds
+    ///
+    /// returns a new instance of Stopwatch
+    pub fn new(now_us: u64) -> Stopwatch {
+        Self { last_us: now_us }
+    }
+
+    /// Counts the whole milliseconds since the last lap
+    ///
+    /// - param now_us: The current counter value in microseconds
+    ///
+    /// returns the elapsed milliseconds, capped at [u16::MAX]
+    pub fn lap_ms(&mut self, now_us: u64) -> u16 {
+        let ms = now_us.saturating_sub(self.last_us) / 1000;
+        self.last_us += ms * 1000;
+        ms.min(u16::MAX as u64) as u16
+    }
+}
+
+/// The seconds without a button press before auto-rotation resumes
+pub const ROTATE_IDLE_SECS: u16 = 30;
+
+/// Decides when the dashboard advances on its own.
+/// It is counted against the clock, one tick per second, and pauses for
+/// [ROTATE_IDLE_SECS] after every button press
+///
+/// - **idle_secs**: The seconds since the last button press
+/// - **shown_secs**: The seconds the current screen has been shown while rotating
+/// - **due**: If the screen should advance
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::timer::{AutoRotate, ROTATE_IDLE_SECS};
+///
+/// let mut rotate = AutoRotate::default();
+/// // Nothing happens until the buttons have been idle
+/// for _ in 0..ROTATE_IDLE_SECS {
+///     rotate.tick(true, 5);
+///     assert!(!rotate.take_due());
+/// }
+/// // Then the screen advances every 5 seconds
+/// for _ in 0..4 {
+///     rotate.tick(true, 5);
+///     assert!(!rotate.take_due());
+/// }
+/// rotate.tick(true, 5);
+/// assert!(rotate.take_due());
+/// assert!(!rotate.take_due());
+///
+/// // A button press pauses the rotation until the buttons are idle again
+/// rotate.press();
+/// for _ in 0..ROTATE_IDLE_SECS + 4 {
+///     rotate.tick(true, 5);
+///     assert!(!rotate.take_due());
+/// }
+/// rotate.tick(true, 5);
+/// assert!(rotate.take_due());
+///
+/// // Disabled rotation never advances
+/// let mut disabled = AutoRotate::default();
+/// for _ in 0..ROTATE_IDLE_SECS + 60 {
+///     disabled.tick(false, 5);
+///     assert!(!disabled.take_due());
+/// }
+/// ```
+#[derive(Default)]
+pub struct AutoRotate {
+    idle_secs: u16,
+    shown_secs: u8,
+    due: bool,
+}
+
+impl AutoRotate {
+    /// Pauses the rotation after a button press
+    pub fn press(&mut self) {
+        self.idle_secs = 0;
+        self.shown_secs = 0;
+        self.due = false;
+    }
+
+    /// Counts a second of the clock
+    ///
+    /// - param enabled: If auto-rotation is turned on
+    /// - param interval: The seconds each screen is shown for
+    pub fn tick(&mut self, enabled: bool, interval: u8) {
+        if !enabled {
+            self.shown_secs = 0;
+            return;
+        }
+        if self.idle_secs < ROTATE_IDLE_SECS {
+            self.idle_secs += 1;
+            return;
+        }
+        self.shown_secs += 1;
+        if self.shown_secs >= interval {
+            self.shown_secs = 0;
+            self.due = true;
+        }
+    }
+
+    /// Checks if the screen should advance, clearing the request
+    ///
+    /// returns if the screen should advance
+    pub fn take_due(&mut self) -> bool {
+        core::mem::take(&mut self.due)
+    }
+}
+
+/// The milliseconds each frame of the measurement indicator is shown for
+pub const PROGRESS_FRAME_MS: u16 = 250;
+/// The frames of the measurement indicator, cycled in order
+pub const PROGRESS_FRAMES: [&str; 4] = [".", "o", "O", "o"];
+
+/// Times a sensor measurement so the loop keeps running while it completes,
+/// and steps the frames of the indicator shown meanwhile.
+/// It is counted with the milliseconds elapsed on each pass of the loop
+///
+/// - **remaining_ms**: The milliseconds left in the measurement
+/// - **frame_ms**: The milliseconds left until the next frame
+/// - **frame**: The index of the current frame in [PROGRESS_FRAMES]
+/// - **running**: If a measurement is in progress
+/// - **done**: If a measurement finished and hasn't been read yet
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::timer::{Measurement, PROGRESS_FRAMES};
+///
+/// let mut measurement = Measurement::default();
+/// assert_eq!(measurement.step(100), None);
+/// assert!(!measurement.take_done());
+///
+/// measurement.start(1000);
+/// assert_eq!(measurement.frame(), PROGRESS_FRAMES[0]);
+/// assert_eq!(measurement.step(100), None);
+/// assert_eq!(measurement.sleep_ms(), 150);
+/// assert_eq!(measurement.step(150), Some(PROGRESS_FRAMES[1]));
+/// assert_eq!(measurement.step(250), Some(PROGRESS_FRAMES[2]));
+/// assert_eq!(measurement.step(300), Some(PROGRESS_FRAMES[3])); // Overshot by 50ms
+/// assert_eq!(measurement.sleep_ms(), 200);
+/// assert!(!measurement.take_done());
+///
+/// // The indicator stops once the measurement is done
+/// assert_eq!(measurement.step(200), None);
+/// assert_eq!(measurement.sleep_ms(), 0);
+/// assert!(measurement.take_done());
+/// assert!(!measurement.take_done());
+/// assert_eq!(measurement.sleep_ms(), u16::MAX);
+///
+/// // Frames wrap around during long measurements
+/// measurement.start(2000);
+/// for frame in 1..6 {
+///     assert_eq!(measurement.step(250), Some(PROGRESS_FRAMES[frame % PROGRESS_FRAMES.len()]));
+/// }
+/// ```
+#[derive(Default)]
+pub struct Measurement {
+    remaining_ms: u16,
+    frame_ms: u16,
+    frame: usize,
+    running: bool,
+    done: bool,
+}
+
+impl Measurement {
+    /// Starts timing a measurement
+    ///
+    /// - param ms: How long the measurement takes in milliseconds
+    pub fn start(&mut self, ms: u16) {
+        self.remaining_ms = ms;
+        self.frame_ms = PROGRESS_FRAME_MS;
+        self.frame = 0;
+        self.running = true;
+        self.done = false;
+    }
+
+    /// Counts the time passed since the last step
+    ///
+    /// - param elapsed_ms: The milliseconds since the last step
+    ///
+    /// returns the next frame of the indicator, if it is time to show it
+    pub fn step(&mut self, elapsed_ms: u16) -> Option<&'static str> {
+        if !self.running {
+            return None;
+        }
+        self.remaining_ms = self.remaining_ms.saturating_sub(elapsed_ms);
+        if self.remaining_ms == 0 {
+            self.running = false;
+            self.done = true;
+            return None;
+        }
+        if elapsed_ms < self.frame_ms {
+            self.frame_ms -= elapsed_ms;
+            return None;
+        }
+        let overshoot = (elapsed_ms - self.frame_ms) % PROGRESS_FRAME_MS;
+        self.frame_ms = PROGRESS_FRAME_MS - overshoot;
+        self.frame = (self.frame + 1) % PROGRESS_FRAMES.len();
+        Some(self.frame())
+    }
+
+    /// Gets the current frame of the indicator
+    ///
+    /// returns the frame
+    pub fn frame(&self) -> &'static str {
+        PROGRESS_FRAMES[self.frame]
+    }
+
+    /// Gets how long the loop may sleep before the measurement needs attention
+    ///
+    /// returns the milliseconds to sleep; 0 if a finished measurement is waiting to be read
+    pub fn sleep_ms(&self) -> u16 {
+        if self.done {
+            0
+        } else if self.running {
+            self.frame_ms.min(self.remaining_ms)
+        } else {
+            u16::MAX
+        }
+    }
+
+    /// Checks if a measurement finished, clearing it
+    ///
+    /// returns if the measurement is ready to be read
+    pub fn take_done(&mut self) -> bool {
+        core::mem::take(&mut self.done)
+    }
+}
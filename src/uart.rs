@@ -0,0 +1,1095 @@
+use crate::expander::{is_valid_expander_address, EXPANDER_PINS};
+use crate::persist::{
+    decode_config_blob, encode_config_blob, BlobError, DecodeError, CONFIG_BLOB_LEN,
+    PREFERENCES_LEN,
+};
+use crate::preferences::{
+    is_valid_gas_heater, is_valid_vent_curve, Co2Policy, DateTime, FaultPolicy, Output,
+    Preferences, TemperatureModel, TemperatureUnit, UnitSystem, MAX_CO2_VENT_PPM,
+    MAX_ESCALATION_MINS, MAX_GDD_BASE, MAX_HEAT_FULL_BELOW, MAX_MILDEW_SPREAD,
+    MAX_RECOVERY_THRESHOLD, MAX_SENSOR_TIMEOUT_MS, MAX_SMOKE_SAMPLES, MAX_WARMUP_SECS,
+    MIN_BUZZER_VOLUME, MIN_CO2_VENT_PPM, MIN_GDD_BASE, MIN_SENSOR_TIMEOUT_MS, VENT_CURVE_POINTS,
+};
+use crate::screens::Screen;
+use heapless::String;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::bank0::{Gpio16, Gpio17};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::gpio::{FunctionUart, Pin, PullDown};
+#[cfg(feature = "hardware")]
+use rp_pico::hal::pac::UART0;
+#[cfg(feature = "hardware")]
+use rp_pico::hal::uart::{Enabled, UartPeripheral};
+use ufmt::uwrite;
+
+/// UART0 on GPIO16 (TX) and GPIO17 (RX)
+#[cfg(feature = "hardware")]
+pub type Uart = UartPeripheral<
+    Enabled,
+    UART0,
+    (
+        Pin<Gpio16, FunctionUart, PullDown>,
+        Pin<Gpio17, FunctionUart, PullDown>,
+    ),
+>;
+
+/// The longest command line accepted, not counting the line ending; room for `SET CONFIG <blob>`
+pub const LINE_LEN: usize = 16 + CONFIG_BLOB_LEN;
+/// The longest reply sent back, not counting the line ending; room for `CONFIG <blob>`
+pub const REPLY_LEN: usize = 8 + CONFIG_BLOB_LEN;
+
+/// A command received over UART
+///
+/// - **GetTemp**: `GET TEMP`; Replies with the temperature range
+/// - **SetTemp**: `SET TEMP <min> <max>`; Sets the temperature range in Fahrenheit, whatever the LCD shows
+/// - **SetNightTemp**: `SET NIGHT <min> <max>`; Sets the night temperature range in Fahrenheit
+/// - **SetDayNight**: `SET DAYNIGHT <day hour> <night hour>`; Sets the hours the day and night temperature ranges take over
+/// - **GetHumidity**: `GET HUM`; Replies with the humidity range
+/// - **SetHumidity**: `SET HUM <min> <max>`; Sets the humidity range in percent
+/// - **GetTime**: `GET TIME`; Replies with the date and time
+/// - **SetTime**: `SET TIME HH:MM:SS DD/MM/YYYY`; Sets the date and time
+/// - **WaterNow**: `WATER NOW <seconds>`; Runs the sprinklers for a number of seconds
+/// - **SetWateringSeconds**: `SET WATERSEC <start> <end>|OFF`; Sets the seconds the watering time starts and ends on, or waters whole minutes
+/// - **GetLog**: `GET LOG`; Streams the data log as CSV
+/// - **GetConfig**: `GET CONFIG`; Replies with every preference as a config blob. See [encode_config_blob]
+/// - **SetConfig**: `SET CONFIG <blob>`; Replaces every preference but the clock with a validated config blob. See [decode_config_blob]
+/// - **SetLogInterval**: `SET LOG <minutes>`; Sets the minutes between logged readings
+/// - **SetWarmup**: `SET WARMUP <seconds>`; Sets how long after boot readings aren't acted on
+/// - **SetStaleFactor**: `SET STALE <intervals>`; Sets how many polling intervals a reading may age before its age flashes
+/// - **SetRainVent**: `SET RAIN ON|OFF`; Sets if rain holds the roof vent closed
+/// - **SetUnits**: `SET UNITS IMPERIAL|METRIC`; Applies a [UnitSystem] preset
+/// - **SetTemperatureUnit**: `SET TUNIT F|C|K`; Sets only the [TemperatureUnit] shown on the LCD
+/// - **SetTemperatureModel**: `SET TMODEL RANGE|SETPOINT`; Sets if the temperature ranges are edited as a range or a [Setpoint](crate::preferences::Setpoint)
+/// - **SetExpanderPin**: `SET EXPANDER <pin> SPRINKLERS|VENT|MISTER|BUZZER|HEATLOW|HEATHIGH|OFF`; Sets the output a PCF8574 pin follows, or leaves it unused
+/// - **SetExpanderAddress**: `SET EXPANDER ADDR <address>`; Sets the PCF8574's I2C address, in decimal or as `0x20`
+/// - **SetExpanderLevel**: `SET EXPANDER HIGH|LOW`; Sets the level the PCF8574's relays switch on at
+/// - **SetGas**: `SET GAS ON|OFF`; Sets if the BME680 measures gas
+/// - **SetHeater**: `SET HEATER <celsius> <ms>`; Sets the gas heater temperature and duration
+/// - **SetEscalation**: `SET ESCALATE <minutes>`; Sets how long readings may stay out of range before the alarm escalates; 0 turns it off
+/// - **SetRecovery**: `SET RECOVERY <failures>`; Sets how many sensor failures in a row recover the I2C bus
+/// - **SetHeatFullBelow**: `SET HEATFULL <F>`; Sets how far below the temperature range the heater runs at full heat
+/// - **SetSmokeSamples**: `SET SMOKE <samples>`; Sets how many smoke detector samples in a row confirm smoke
+/// - **SetSensorTimeout**: `SET TIMEOUT <ms>`; Sets how long a sensor operation may take before it is abandoned
+/// - **SetCo2**: `SET CO2 OFF|ENRICH` or `SET CO2 VENT [<ppm>]`; Sets the [Co2Policy], and the CO2 the vent opens above
+/// - **SetGddBase**: `SET GDD <F>`; Sets the base temperature growing degree days are counted from
+/// - **ResetGdd**: `RESET GDD`; Starts the growing degree days over, such as for a new crop
+/// - **SetFaultPolicy**: `SET FAULT HOLD|SAFE|LAST`; Sets the [FaultPolicy] applied once the sensors fail
+/// - **SetSelfTest**: `SET SELFTEST ON|OFF`; Sets if the outputs, sensor and smoke detector are tested at boot
+/// - **SetActiveLevel**: `SET ACTIVE SPRINKLERS|VENT|MISTER|BUZZER|HEATLOW|HEATHIGH HIGH|LOW`; Sets the level an output's relay switches on at
+/// - **SetMildew**: `SET MILDEW <F> [VENT]`; Sets how close to the dew point warns of mildew, 0 turns it off, and if the vent is opened for it
+/// - **SetDehumidify**: `SET DEHUMIDIFY ON|OFF`; Sets if the roof vent opens while the humidity is above its range
+/// - **SetVentCurve**: `SET VENTCURVE <F> <percent> <F> <percent> <F> <percent> <F> <percent>`; Sets the vent curve breakpoints. See [is_valid_vent_curve]
+/// - **SetBuzzer**: `SET BUZZER ACTIVE|PASSIVE`; Sets if the buzzer is passive, so alarms are played as tones
+/// - **SetVolume**: `SET VOLUME <percent>`; Sets how loud a passive buzzer plays
+/// - **SetDefaultScreen**: `SET START <screen>`; Sets the screen shown after boot by its [Screen::name]
+/// - **ShowScreen**: `SHOW <screen>`; Shows a screen by its [Screen::name]
+/// - **HideScreen**: `HIDE <screen>`; Hides a screen by its [Screen::name]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    GetTemp,
+    SetTemp(u8, u8),
+    SetNightTemp(u8, u8),
+    SetDayNight(u8, u8),
+    GetHumidity,
+    SetHumidity(u8, u8),
+    GetTime,
+    SetTime(DateTime),
+    WaterNow(u16),
+    SetWateringSeconds(Option<(u8, u8)>),
+    GetLog,
+    GetConfig,
+    SetConfig([u8; PREFERENCES_LEN]),
+    SetLogInterval(u8),
+    SetStaleFactor(u8),
+    SetWarmup(u8),
+    SetRainVent(bool),
+    SetUnits(UnitSystem),
+    SetTemperatureUnit(TemperatureUnit),
+    SetTemperatureModel(TemperatureModel),
+    SetActiveLevel(Output, bool),
+    SetExpanderPin(u8, Option<Output>),
+    SetExpanderAddress(u8),
+    SetExpanderLevel(bool),
+    SetGas(bool),
+    SetHeater(u16, u16),
+    SetSelfTest(bool),
+    SetEscalation(u8),
+    SetRecovery(u8),
+    SetHeatFullBelow(u8),
+    SetSmokeSamples(u8),
+    SetSensorTimeout(u16),
+    SetMildew(u8, bool),
+    SetDehumidify(bool),
+    SetVentCurve([(u8, u8); VENT_CURVE_POINTS]),
+    SetCo2(Co2Policy, Option<u16>),
+    SetGddBase(u8),
+    ResetGdd,
+    SetFaultPolicy(FaultPolicy),
+    SetBuzzer(bool),
+    SetVolume(u8),
+    SetDefaultScreen(Screen),
+    ShowScreen(Screen),
+    HideScreen(Screen),
+}
+
+/// Why a command line could not be parsed
+///
+/// - **Empty**: The line has no words
+/// - **UnknownCommand**: The command or its target is not recognized
+/// - **MissingArgument**: The command needs more arguments
+/// - **InvalidArgument**: An argument is not a number or is out of range
+/// - **TooManyArguments**: The command was given extra arguments
+/// - **LineTooLong**: The line did not fit in [LINE_LEN] bytes
+/// - **InvalidConfig**: A config blob was rejected. See [BlobError]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+    TooManyArguments,
+    LineTooLong,
+    InvalidConfig(BlobError),
+}
+
+impl ParseError {
+    /// Gets the error reply sent back for this error
+    ///
+    /// returns the reply text
+    pub fn reply(self) -> &'static str {
+        match self {
+            ParseError::Empty => "ERR EMPTY",
+            ParseError::UnknownCommand => "ERR UNKNOWN COMMAND",
+            ParseError::MissingArgument => "ERR MISSING ARGUMENT",
+            ParseError::InvalidArgument => "ERR INVALID ARGUMENT",
+            ParseError::TooManyArguments => "ERR TOO MANY ARGUMENTS",
+            ParseError::LineTooLong => "ERR LINE TOO LONG",
+            ParseError::InvalidConfig(BlobError::WrongLength) => "ERR CONFIG LENGTH",
+            ParseError::InvalidConfig(BlobError::NotHex) => "ERR CONFIG NOT HEX",
+            ParseError::InvalidConfig(BlobError::BadChecksum) => "ERR CONFIG CHECKSUM",
+            ParseError::InvalidConfig(BlobError::Decode(DecodeError::UnknownVersion)) => {
+                "ERR CONFIG VERSION"
+            }
+            ParseError::InvalidConfig(BlobError::Decode(_)) => "ERR CONFIG VALUE",
+            ParseError::InvalidConfig(BlobError::OutOfRange) => "ERR CONFIG RANGE",
+        }
+    }
+}
+
+/// Parses a single command line. Words are case-insensitive and separated by whitespace
+///
+/// - param line: the command line without its line ending
+///
+/// returns the parsed [Command] or a [ParseError]
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::persist::BlobError;
+/// use gem_rs::preferences::{
+///     Co2Policy, DateTime, FaultPolicy, Output, TemperatureModel, TemperatureUnit, UnitSystem,
+/// };
+/// use gem_rs::screens::Screen;
+/// use gem_rs::uart::{parse_command, Command, ParseError};
+///
+/// assert_eq!(parse_command("GET TEMP"), Ok(Command::GetTemp));
+/// assert_eq!(parse_command("  get   hum "), Ok(Command::GetHumidity));
+/// assert_eq!(parse_command("GET TIME"), Ok(Command::GetTime));
+/// assert_eq!(parse_command("SET TEMP 60 80"), Ok(Command::SetTemp(60, 80)));
+/// assert_eq!(parse_command("SET TEMP 80 60"), Ok(Command::SetTemp(60, 80))); // Inverted ranges are swapped
+/// assert_eq!(parse_command("SET NIGHT 50 70"), Ok(Command::SetNightTemp(50, 70)));
+/// assert_eq!(parse_command("SET DAYNIGHT 7 21"), Ok(Command::SetDayNight(7, 21)));
+/// assert_eq!(parse_command("set hum 40 100"), Ok(Command::SetHumidity(40, 100)));
+/// assert_eq!(
+///     parse_command("SET TIME 13:05:09 29/02/2024"),
+///     Ok(Command::SetTime(DateTime(9, 5, 13, 29, 2, 2024)))
+/// );
+/// assert_eq!(parse_command("WATER NOW 300"), Ok(Command::WaterNow(300)));
+/// assert_eq!(parse_command("SET WATERSEC 0 30"), Ok(Command::SetWateringSeconds(Some((0, 30)))));
+/// assert_eq!(parse_command("set watersec off"), Ok(Command::SetWateringSeconds(None)));
+/// assert_eq!(parse_command("GET LOG"), Ok(Command::GetLog));
+/// assert_eq!(parse_command("get config"), Ok(Command::GetConfig));
+/// assert_eq!(parse_command("SET LOG 15"), Ok(Command::SetLogInterval(15)));
+/// assert_eq!(parse_command("SET STALE 10"), Ok(Command::SetStaleFactor(10)));
+/// assert_eq!(parse_command("SET WARMUP 0"), Ok(Command::SetWarmup(0)));
+/// assert_eq!(parse_command("SET RAIN OFF"), Ok(Command::SetRainVent(false)));
+/// assert_eq!(parse_command("set rain on"), Ok(Command::SetRainVent(true)));
+/// assert_eq!(parse_command("SET UNITS METRIC"), Ok(Command::SetUnits(UnitSystem::Metric)));
+/// assert_eq!(parse_command("set units imperial"), Ok(Command::SetUnits(UnitSystem::Imperial)));
+/// assert_eq!(parse_command("SET TUNIT C"), Ok(Command::SetTemperatureUnit(TemperatureUnit::Celsius)));
+/// assert_eq!(parse_command("SET TUNIT K"), Ok(Command::SetTemperatureUnit(TemperatureUnit::Kelvin)));
+/// assert_eq!(parse_command("SET UNITS SI"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TMODEL SETPOINT"), Ok(Command::SetTemperatureModel(TemperatureModel::Setpoint)));
+/// assert_eq!(parse_command("set tmodel range"), Ok(Command::SetTemperatureModel(TemperatureModel::Range)));
+/// assert_eq!(parse_command("SET TMODEL TARGET"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET ACTIVE VENT LOW"), Ok(Command::SetActiveLevel(Output::RoofVent, false)));
+/// assert_eq!(parse_command("set active buzzer high"), Ok(Command::SetActiveLevel(Output::Buzzer, true)));
+/// assert_eq!(parse_command("SET ACTIVE HEATHIGH LOW"), Ok(Command::SetActiveLevel(Output::HeaterHigh, false)));
+/// assert_eq!(parse_command("SET ACTIVE FAN LOW"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET ACTIVE VENT"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET EXPANDER 0 BUZZER"), Ok(Command::SetExpanderPin(0, Some(Output::Buzzer))));
+/// assert_eq!(parse_command("SET EXPANDER 0 SPRINKLERS"), Ok(Command::SetExpanderPin(0, Some(Output::Sprinklers))));
+/// assert_eq!(parse_command("SET EXPANDER 1 HEATLOW"), Ok(Command::SetExpanderPin(1, Some(Output::HeaterLow))));
+/// assert_eq!(parse_command("set expander 7 off"), Ok(Command::SetExpanderPin(7, None)));
+/// assert_eq!(parse_command("SET EXPANDER ADDR 0x27"), Ok(Command::SetExpanderAddress(0x27)));
+/// assert_eq!(parse_command("SET EXPANDER ADDR 56"), Ok(Command::SetExpanderAddress(0x38)));
+/// assert_eq!(parse_command("SET EXPANDER HIGH"), Ok(Command::SetExpanderLevel(true)));
+/// assert_eq!(parse_command("SET EXPANDER 8 VENT"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET EXPANDER 2 FAN"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET EXPANDER ADDR 0x77"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET EXPANDER 2"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET GAS OFF"), Ok(Command::SetGas(false)));
+/// assert_eq!(parse_command("SET HEATER 300 1000"), Ok(Command::SetHeater(300, 1000)));
+/// assert_eq!(parse_command("SET HEATER 450 1000"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET HEATER 300 4000"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET SELFTEST OFF"), Ok(Command::SetSelfTest(false)));
+/// assert_eq!(parse_command("SET ESCALATE 90"), Ok(Command::SetEscalation(90)));
+/// assert_eq!(parse_command("SET RECOVERY 5"), Ok(Command::SetRecovery(5)));
+/// assert_eq!(parse_command("SET HEATFULL 8"), Ok(Command::SetHeatFullBelow(8)));
+/// assert_eq!(parse_command("SET SMOKE 4"), Ok(Command::SetSmokeSamples(4)));
+/// assert_eq!(parse_command("SET TIMEOUT 500"), Ok(Command::SetSensorTimeout(500)));
+/// assert_eq!(parse_command("SET MILDEW 3"), Ok(Command::SetMildew(3, false)));
+/// assert_eq!(parse_command("SET MILDEW 3 VENT"), Ok(Command::SetMildew(3, true)));
+/// assert_eq!(parse_command("SET DEHUMIDIFY OFF"), Ok(Command::SetDehumidify(false)));
+/// assert_eq!(parse_command("set dehumidify on"), Ok(Command::SetDehumidify(true)));
+/// assert_eq!(
+///     parse_command("SET VENTCURVE 0 0 2 30 4 60 6 100"),
+///     Ok(Command::SetVentCurve([(0, 0), (2, 30), (4, 60), (6, 100)]))
+/// );
+/// assert_eq!(parse_command("SET FAULT HOLD"), Ok(Command::SetFaultPolicy(FaultPolicy::Hold)));
+/// assert_eq!(parse_command("set fault last"), Ok(Command::SetFaultPolicy(FaultPolicy::LastGood)));
+/// assert_eq!(parse_command("SET CO2 VENT 1200"), Ok(Command::SetCo2(Co2Policy::Vent, Some(1200))));
+/// assert_eq!(parse_command("set co2 enrich"), Ok(Command::SetCo2(Co2Policy::Enrich, None)));
+/// assert_eq!(parse_command("SET GDD 40"), Ok(Command::SetGddBase(40)));
+/// assert_eq!(parse_command("reset gdd"), Ok(Command::ResetGdd));
+/// assert_eq!(parse_command("SET BUZZER PASSIVE"), Ok(Command::SetBuzzer(true)));
+/// assert_eq!(parse_command("set buzzer active"), Ok(Command::SetBuzzer(false)));
+/// assert_eq!(parse_command("SET VOLUME 60"), Ok(Command::SetVolume(60)));
+/// assert_eq!(parse_command("SET START DATE"), Ok(Command::SetDefaultScreen(Screen::Date)));
+/// assert_eq!(parse_command("HIDE GAS"), Ok(Command::HideScreen(Screen::Gas)));
+/// assert_eq!(parse_command("show pressure"), Ok(Command::ShowScreen(Screen::Pressure)));
+///
+/// // Malformed input
+/// assert_eq!(parse_command(""), Err(ParseError::Empty));
+/// assert_eq!(parse_command("   "), Err(ParseError::Empty));
+/// assert_eq!(parse_command("FOO"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("GET"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("GET WIND"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("GET TEMP NOW"), Err(ParseError::TooManyArguments));
+/// assert_eq!(parse_command("SET TEMP 60"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET TEMP 60 80 90"), Err(ParseError::TooManyArguments));
+/// assert_eq!(parse_command("SET TEMP sixty 80"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TEMP -5 80"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET HUM 50 101"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TEMP 60 256"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 24:00:00 01/01/2000"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 12:00:00 30/02/2024"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 12:00:00 29/02/2023"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 12:00 01/01/2000"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 12:00:00 01/01/10000"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIME 12:00:00"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("WATER LATER 300"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("WATER NOW 0"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("WATER NOW 99999"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET WATERSEC 0 60"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET WATERSEC 30"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET LOG 0"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET LOG 61"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET STALE 1"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET WARMUP 121"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET ESCALATE 241"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET RECOVERY 0"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET RECOVERY 21"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET HEATFULL 21"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET SMOKE 0"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET SMOKE 21"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIMEOUT 10"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET TIMEOUT 2001"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET RAIN 1"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET DAYNIGHT 6 24"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("HIDE SOIL"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("SET START SOIL"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("SET MILDEW 21"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET MILDEW 3 FAN"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET DEHUMIDIFY FAN"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VENTCURVE 0 0 2 30 2 60 6 100"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VENTCURVE 0 0 2 60 4 30 6 100"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VENTCURVE 0 0 2 30 4 60 6 101"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VENTCURVE 0 0 2 30 4 60"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET FAULT IGNORE"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET CO2 VENT 500"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET CO2 OFF 1200"), Err(ParseError::TooManyArguments));
+/// assert_eq!(parse_command("SET CO2 PUMP"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET GDD 20"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET GDD 90"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("RESET STATS"), Err(ParseError::UnknownCommand));
+/// assert_eq!(parse_command("SET BUZZER LOUD"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VOLUME 5"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SET VOLUME 101"), Err(ParseError::InvalidArgument));
+/// assert_eq!(parse_command("SHOW"), Err(ParseError::MissingArgument));
+/// assert_eq!(parse_command("SET CONFIG"), Err(ParseError::MissingArgument));
+/// assert_eq!(
+///     parse_command("SET CONFIG 1C00"),
+///     Err(ParseError::InvalidConfig(BlobError::WrongLength))
+/// );
+/// ```
+pub fn parse_command(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_whitespace();
+    let verb = words.next().ok_or(ParseError::Empty)?;
+    let target = words.next();
+
+    let command = if verb.eq_ignore_ascii_case("GET") {
+        let target = target.ok_or(ParseError::MissingArgument)?;
+        if target.eq_ignore_ascii_case("TEMP") {
+            Command::GetTemp
+        } else if target.eq_ignore_ascii_case("HUM") {
+            Command::GetHumidity
+        } else if target.eq_ignore_ascii_case("TIME") {
+            Command::GetTime
+        } else if target.eq_ignore_ascii_case("LOG") {
+            Command::GetLog
+        } else if target.eq_ignore_ascii_case("CONFIG") {
+            Command::GetConfig
+        } else {
+            return Err(ParseError::UnknownCommand);
+        }
+    } else if verb.eq_ignore_ascii_case("SET") {
+        let target = target.ok_or(ParseError::MissingArgument)?;
+        if target.eq_ignore_ascii_case("TEMP") {
+            let (low, high) = parse_range(words.next(), words.next(), u8::MAX)?;
+            Command::SetTemp(low, high)
+        } else if target.eq_ignore_ascii_case("NIGHT") {
+            let (low, high) = parse_range(words.next(), words.next(), u8::MAX)?;
+            Command::SetNightTemp(low, high)
+        } else if target.eq_ignore_ascii_case("DAYNIGHT") {
+            let day: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            let night: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if day > 23 || night > 23 {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetDayNight(day, night)
+        } else if target.eq_ignore_ascii_case("HUM") {
+            let (low, high) = parse_range(words.next(), words.next(), 100)?;
+            Command::SetHumidity(low, high)
+        } else if target.eq_ignore_ascii_case("TIME") {
+            Command::SetTime(parse_date_time(words.next(), words.next())?)
+        } else if target.eq_ignore_ascii_case("WATERSEC") {
+            let start = words.next().ok_or(ParseError::MissingArgument)?;
+            if start.eq_ignore_ascii_case("OFF") {
+                Command::SetWateringSeconds(None)
+            } else {
+                let start: u8 = parse_number(start)?;
+                let end: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+                if start > 59 || end > 59 {
+                    return Err(ParseError::InvalidArgument);
+                }
+                Command::SetWateringSeconds(Some((start, end)))
+            }
+        } else if target.eq_ignore_ascii_case("LOG") {
+            let minutes: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(1..=60).contains(&minutes) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetLogInterval(minutes)
+        } else if target.eq_ignore_ascii_case("STALE") {
+            let intervals: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(2..=60).contains(&intervals) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetStaleFactor(intervals)
+        } else if target.eq_ignore_ascii_case("WARMUP") {
+            let seconds: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if seconds > MAX_WARMUP_SECS {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetWarmup(seconds)
+        } else if target.eq_ignore_ascii_case("RAIN") {
+            Command::SetRainVent(parse_switch(
+                words.next().ok_or(ParseError::MissingArgument)?,
+            )?)
+        } else if target.eq_ignore_ascii_case("UNITS") {
+            let system = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::SetUnits(if system.eq_ignore_ascii_case("IMPERIAL") {
+                UnitSystem::Imperial
+            } else if system.eq_ignore_ascii_case("METRIC") {
+                UnitSystem::Metric
+            } else {
+                return Err(ParseError::InvalidArgument);
+            })
+        } else if target.eq_ignore_ascii_case("TUNIT") {
+            let unit = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::SetTemperatureUnit(if unit.eq_ignore_ascii_case("F") {
+                TemperatureUnit::Fahrenheit
+            } else if unit.eq_ignore_ascii_case("C") {
+                TemperatureUnit::Celsius
+            } else if unit.eq_ignore_ascii_case("K") {
+                TemperatureUnit::Kelvin
+            } else {
+                return Err(ParseError::InvalidArgument);
+            })
+        } else if target.eq_ignore_ascii_case("TMODEL") {
+            let model = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::SetTemperatureModel(if model.eq_ignore_ascii_case("RANGE") {
+                TemperatureModel::Range
+            } else if model.eq_ignore_ascii_case("SETPOINT") {
+                TemperatureModel::Setpoint
+            } else {
+                return Err(ParseError::InvalidArgument);
+            })
+        } else if target.eq_ignore_ascii_case("ACTIVE") {
+            let output = parse_output(words.next().ok_or(ParseError::MissingArgument)?)?;
+            let level = words.next().ok_or(ParseError::MissingArgument)?;
+            let active_high = if level.eq_ignore_ascii_case("HIGH") {
+                true
+            } else if level.eq_ignore_ascii_case("LOW") {
+                false
+            } else {
+                return Err(ParseError::InvalidArgument);
+            };
+            Command::SetActiveLevel(output, active_high)
+        } else if target.eq_ignore_ascii_case("EXPANDER") {
+            let pin = words.next().ok_or(ParseError::MissingArgument)?;
+            if pin.eq_ignore_ascii_case("ADDR") {
+                let address = words.next().ok_or(ParseError::MissingArgument)?;
+                let address = match address.strip_prefix("0x") {
+                    Some(hex) => {
+                        u8::from_str_radix(hex, 16).map_err(|_| ParseError::InvalidArgument)?
+                    }
+                    None => parse_number(address)?,
+                };
+                if !is_valid_expander_address(address) {
+                    return Err(ParseError::InvalidArgument);
+                }
+                Command::SetExpanderAddress(address)
+            } else if pin.eq_ignore_ascii_case("HIGH") {
+                Command::SetExpanderLevel(true)
+            } else if pin.eq_ignore_ascii_case("LOW") {
+                Command::SetExpanderLevel(false)
+            } else {
+                let pin: u8 = parse_number(pin)?;
+                if pin as usize >= EXPANDER_PINS {
+                    return Err(ParseError::InvalidArgument);
+                }
+                let output = words.next().ok_or(ParseError::MissingArgument)?;
+                let output = if output.eq_ignore_ascii_case("OFF") {
+                    None
+                } else {
+                    Some(parse_output(output)?)
+                };
+                Command::SetExpanderPin(pin, output)
+            }
+        } else if target.eq_ignore_ascii_case("GAS") {
+            Command::SetGas(parse_switch(
+                words.next().ok_or(ParseError::MissingArgument)?,
+            )?)
+        } else if target.eq_ignore_ascii_case("HEATER") {
+            let temp: u16 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            let ms: u16 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !is_valid_gas_heater(temp, ms) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetHeater(temp, ms)
+        } else if target.eq_ignore_ascii_case("ESCALATE") {
+            let minutes: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if minutes > MAX_ESCALATION_MINS {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetEscalation(minutes)
+        } else if target.eq_ignore_ascii_case("RECOVERY") {
+            let failures: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(1..=MAX_RECOVERY_THRESHOLD).contains(&failures) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetRecovery(failures)
+        } else if target.eq_ignore_ascii_case("HEATFULL") {
+            let below: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if below > MAX_HEAT_FULL_BELOW {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetHeatFullBelow(below)
+        } else if target.eq_ignore_ascii_case("SMOKE") {
+            let samples: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(1..=MAX_SMOKE_SAMPLES).contains(&samples) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetSmokeSamples(samples)
+        } else if target.eq_ignore_ascii_case("TIMEOUT") {
+            let ms: u16 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(MIN_SENSOR_TIMEOUT_MS..=MAX_SENSOR_TIMEOUT_MS).contains(&ms) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetSensorTimeout(ms)
+        } else if target.eq_ignore_ascii_case("MILDEW") {
+            let spread: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if spread > MAX_MILDEW_SPREAD {
+                return Err(ParseError::InvalidArgument);
+            }
+            let vent = match words.next() {
+                Some(word) if word.eq_ignore_ascii_case("VENT") => true,
+                Some(_) => return Err(ParseError::InvalidArgument),
+                None => false,
+            };
+            Command::SetMildew(spread, vent)
+        } else if target.eq_ignore_ascii_case("CO2") {
+            let policy = words.next().ok_or(ParseError::MissingArgument)?;
+            if policy.eq_ignore_ascii_case("VENT") {
+                // The threshold is optional, so the last one is kept
+                let ppm = match words.next() {
+                    Some(ppm) => {
+                        let ppm: u16 = parse_number(ppm)?;
+                        if !(MIN_CO2_VENT_PPM..=MAX_CO2_VENT_PPM).contains(&ppm) {
+                            return Err(ParseError::InvalidArgument);
+                        }
+                        Some(ppm)
+                    }
+                    None => None,
+                };
+                Command::SetCo2(Co2Policy::Vent, ppm)
+            } else if policy.eq_ignore_ascii_case("OFF") {
+                Command::SetCo2(Co2Policy::Off, None)
+            } else if policy.eq_ignore_ascii_case("ENRICH") {
+                Command::SetCo2(Co2Policy::Enrich, None)
+            } else {
+                return Err(ParseError::InvalidArgument);
+            }
+        } else if target.eq_ignore_ascii_case("GDD") {
+            let base: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(MIN_GDD_BASE..=MAX_GDD_BASE).contains(&base) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetGddBase(base)
+        } else if target.eq_ignore_ascii_case("FAULT") {
+            let policy = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::SetFaultPolicy(if policy.eq_ignore_ascii_case("HOLD") {
+                FaultPolicy::Hold
+            } else if policy.eq_ignore_ascii_case("SAFE") {
+                FaultPolicy::SafeState
+            } else if policy.eq_ignore_ascii_case("LAST") {
+                FaultPolicy::LastGood
+            } else {
+                return Err(ParseError::InvalidArgument);
+            })
+        } else if target.eq_ignore_ascii_case("DEHUMIDIFY") {
+            Command::SetDehumidify(parse_switch(
+                words.next().ok_or(ParseError::MissingArgument)?,
+            )?)
+        } else if target.eq_ignore_ascii_case("VENTCURVE") {
+            let mut curve = [(0, 0); VENT_CURVE_POINTS];
+            for point in curve.iter_mut() {
+                let delta: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+                let percent: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+                *point = (delta, percent);
+            }
+            if !is_valid_vent_curve(&curve) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetVentCurve(curve)
+        } else if target.eq_ignore_ascii_case("BUZZER") {
+            let kind = words.next().ok_or(ParseError::MissingArgument)?;
+            if kind.eq_ignore_ascii_case("PASSIVE") {
+                Command::SetBuzzer(true)
+            } else if kind.eq_ignore_ascii_case("ACTIVE") {
+                Command::SetBuzzer(false)
+            } else {
+                return Err(ParseError::InvalidArgument);
+            }
+        } else if target.eq_ignore_ascii_case("VOLUME") {
+            let volume: u8 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+            if !(MIN_BUZZER_VOLUME..=100).contains(&volume) {
+                return Err(ParseError::InvalidArgument);
+            }
+            Command::SetVolume(volume)
+        } else if target.eq_ignore_ascii_case("START") {
+            let name = words.next().ok_or(ParseError::MissingArgument)?;
+            Command::SetDefaultScreen(Screen::from_name(name).ok_or(ParseError::UnknownCommand)?)
+        } else if target.eq_ignore_ascii_case("CONFIG") {
+            let blob = words.next().ok_or(ParseError::MissingArgument)?;
+            let preferences = decode_config_blob(blob).map_err(ParseError::InvalidConfig)?;
+            Command::SetConfig(preferences.to_bytes())
+        } else if target.eq_ignore_ascii_case("SELFTEST") {
+            Command::SetSelfTest(parse_switch(
+                words.next().ok_or(ParseError::MissingArgument)?,
+            )?)
+        } else {
+            return Err(ParseError::UnknownCommand);
+        }
+    } else if verb.eq_ignore_ascii_case("WATER") {
+        let target = target.ok_or(ParseError::MissingArgument)?;
+        if !target.eq_ignore_ascii_case("NOW") {
+            return Err(ParseError::UnknownCommand);
+        }
+        let seconds: u16 = parse_number(words.next().ok_or(ParseError::MissingArgument)?)?;
+        if seconds == 0 {
+            return Err(ParseError::InvalidArgument);
+        }
+        Command::WaterNow(seconds)
+    } else if verb.eq_ignore_ascii_case("RESET") {
+        let target = target.ok_or(ParseError::MissingArgument)?;
+        if !target.eq_ignore_ascii_case("GDD") {
+            return Err(ParseError::UnknownCommand);
+        }
+        Command::ResetGdd
+    } else if verb.eq_ignore_ascii_case("SHOW") || verb.eq_ignore_ascii_case("HIDE") {
+        let target = target.ok_or(ParseError::MissingArgument)?;
+        let screen = Screen::from_name(target).ok_or(ParseError::UnknownCommand)?;
+        if verb.eq_ignore_ascii_case("SHOW") {
+            Command::ShowScreen(screen)
+        } else {
+            Command::HideScreen(screen)
+        }
+    } else {
+        return Err(ParseError::UnknownCommand);
+    };
+
+    if words.next().is_some() {
+        return Err(ParseError::TooManyArguments);
+    }
+    Ok(command)
+}
+
+/// Parses a number argument
+///
+/// - param word: the argument
+///
+/// returns the parsed number
+fn parse_number<T: core::str::FromStr>(word: &str) -> Result<T, ParseError> {
+    word.parse().map_err(|_| ParseError::InvalidArgument)
+}
+
+/// Parses an [Output] argument: `SPRINKLERS`, `VENT`, `MISTER`, `BUZZER`, `HEATLOW` or `HEATHIGH`
+///
+/// - param word: the argument
+///
+/// returns the [Output]
+fn parse_output(word: &str) -> Result<Output, ParseError> {
+    if word.eq_ignore_ascii_case("SPRINKLERS") {
+        Ok(Output::Sprinklers)
+    } else if word.eq_ignore_ascii_case("VENT") {
+        Ok(Output::RoofVent)
+    } else if word.eq_ignore_ascii_case("MISTER") {
+        Ok(Output::Mister)
+    } else if word.eq_ignore_ascii_case("BUZZER") {
+        Ok(Output::Buzzer)
+    } else if word.eq_ignore_ascii_case("HEATLOW") {
+        Ok(Output::HeaterLow)
+    } else if word.eq_ignore_ascii_case("HEATHIGH") {
+        Ok(Output::HeaterHigh)
+    } else {
+        Err(ParseError::InvalidArgument)
+    }
+}
+
+/// Parses an `ON` or `OFF` argument
+///
+/// - param word: the argument
+///
+/// returns true for `ON`
+fn parse_switch(word: &str) -> Result<bool, ParseError> {
+    if word.eq_ignore_ascii_case("ON") {
+        Ok(true)
+    } else if word.eq_ignore_ascii_case("OFF") {
+        Ok(false)
+    } else {
+        Err(ParseError::InvalidArgument)
+    }
+}
+
+/// Parses a `<min> <max>` range; swapped if inverted like the edit screens do
+///
+/// - param low: the first argument, if present
+/// - param high: the second argument, if present
+/// - param max: the largest allowed value
+///
+/// returns the range as `(min, max)`
+fn parse_range(low: Option<&str>, high: Option<&str>, max: u8) -> Result<(u8, u8), ParseError> {
+    let low: u8 = parse_number(low.ok_or(ParseError::MissingArgument)?)?;
+    let high: u8 = parse_number(high.ok_or(ParseError::MissingArgument)?)?;
+    if low > max || high > max {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok((low.min(high), low.max(high)))
+}
+
+/// Parses `HH:MM:SS DD/MM/YYYY`, the same format as [Preferences::get_date_formatted]
+///
+/// - param time: the time argument, if present
+/// - param date: the date argument, if present
+///
+/// returns the parsed [DateTime]
+fn parse_date_time(time: Option<&str>, date: Option<&str>) -> Result<DateTime, ParseError> {
+    let time = time.ok_or(ParseError::MissingArgument)?;
+    let date = date.ok_or(ParseError::MissingArgument)?;
+
+    // Split never yields None for the first part, so missing parts parse as ""
+    let mut time_parts = time.split(':');
+    let hour: u8 = parse_number(time_parts.next().unwrap_or(""))?;
+    let minute: u8 = parse_number(time_parts.next().unwrap_or(""))?;
+    let second: u8 = parse_number(time_parts.next().unwrap_or(""))?;
+
+    let mut date_parts = date.split('/');
+    let day: u8 = parse_number(date_parts.next().unwrap_or(""))?;
+    let month: u8 = parse_number(date_parts.next().unwrap_or(""))?;
+    let year: u16 = parse_number(date_parts.next().unwrap_or(""))?;
+
+    if time_parts.next().is_some() || date_parts.next().is_some() {
+        return Err(ParseError::InvalidArgument);
+    }
+
+    let date_time = DateTime(second, minute, hour, day, month, year);
+    if !date_time.is_valid() {
+        return Err(ParseError::InvalidArgument);
+    }
+    Ok(date_time)
+}
+
+/// Runs a command against the [Preferences].
+/// `WATER NOW`, `GET LOG` and `RESET GDD` only reply here; the caller runs the sprinklers, streams the log
+/// and resets the stats.
+/// The last visible screen can't be hidden. A config blob replaces the preferences all at once,
+/// but keeps the clock, since the blob was taken from another controller at another time
+///
+/// - param command: the [Command] to run
+/// - param preferences: [Preferences] instance
+///
+/// returns the reply to send back
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::preferences::{Co2Policy, Preferences, TemperatureUnit};
+/// use gem_rs::screens::Screen;
+/// use gem_rs::uart::{execute, parse_command};
+///
+/// let mut preferences = Preferences::default();
+/// assert_eq!(execute(&parse_command("GET TEMP").unwrap(), &mut preferences), "TEMP 60 80");
+/// assert_eq!(execute(&parse_command("SET HUM 45 75").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.humidity, (45, 75));
+/// assert_eq!(execute(&parse_command("GET HUM").unwrap(), &mut preferences), "HUM 45 75");
+/// execute(&parse_command("SET TIME 07:30:00 04/07/2025").unwrap(), &mut preferences);
+/// assert_eq!(
+///     execute(&parse_command("GET TIME").unwrap(), &mut preferences),
+///     "TIME 07:30:00 04/07/2025"
+/// );
+///
+/// assert_eq!(execute(&parse_command("HIDE GAS").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.hidden_screens, Screen::Gas.mask());
+/// assert_eq!(execute(&parse_command("SHOW GAS").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.hidden_screens, 0);
+/// preferences.hidden_screens = Screen::ALL_HIDDEN & !Screen::Date.mask();
+/// assert_eq!(execute(&parse_command("HIDE DATE").unwrap(), &mut preferences), "ERR LAST SCREEN");
+/// preferences.hidden_screens = 0;
+/// assert_eq!(execute(&parse_command("HIDE CO2").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.hidden_screens, Screen::Co2.mask());
+///
+/// // Setting the CO2 policy keeps the threshold unless a new one is given
+/// assert_eq!(execute(&parse_command("SET CO2 VENT 1200").unwrap(), &mut preferences), "OK");
+/// assert_eq!(execute(&parse_command("SET CO2 ENRICH").unwrap(), &mut preferences), "OK");
+/// assert_eq!(execute(&parse_command("SET CO2 VENT").unwrap(), &mut preferences), "OK");
+/// assert_eq!((preferences.co2_policy, preferences.co2_vent_ppm), (Co2Policy::Vent, 1200));
+///
+/// assert_eq!(execute(&parse_command("SET VENTCURVE 0 0 2 30 4 60 6 100").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.vent_curve, [(0, 0), (2, 30), (4, 60), (6, 100)]);
+///
+/// // Temperatures over UART stay in Fahrenheit whatever the LCD shows
+/// assert_eq!(execute(&parse_command("SET UNITS METRIC").unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.temperature_unit, TemperatureUnit::Celsius);
+/// assert_eq!(execute(&parse_command("GET TEMP").unwrap(), &mut preferences), "TEMP 60 80");
+///
+/// // Copying every preference from one controller to another
+/// let mut source = Preferences::default();
+/// source.humidity = (50, 65);
+/// source.temp_offset = -3;
+/// let blob = execute(&parse_command("GET CONFIG").unwrap(), &mut source);
+/// let line = format!("SET {blob}");
+/// let clock = preferences.date;
+/// assert_eq!(execute(&parse_command(&line).unwrap(), &mut preferences), "OK");
+/// assert_eq!(preferences.humidity, (50, 65));
+/// assert_eq!(preferences.temp_offset, -3);
+/// assert_eq!(preferences.date, clock);
+///
+/// // A malformed blob is rejected before anything is changed
+/// let mut mistyped = line.clone();
+/// mistyped.pop();
+/// mistyped.push(if line.ends_with('0') { '1' } else { '0' });
+/// assert_eq!(parse_command(&mistyped).unwrap_err().reply(), "ERR CONFIG CHECKSUM");
+/// let truncated = &line[..line.len() - 8];
+/// assert_eq!(parse_command(truncated).unwrap_err().reply(), "ERR CONFIG LENGTH");
+/// ```
+pub fn execute(command: &Command, preferences: &mut Preferences) -> String<REPLY_LEN> {
+    let mut reply: String<REPLY_LEN> = String::new();
+    match *command {
+        Command::GetTemp => uwrite!(
+            reply,
+            "TEMP {} {}",
+            preferences.temperature.0,
+            preferences.temperature.1
+        )
+        .unwrap(),
+        Command::GetHumidity => uwrite!(
+            reply,
+            "HUM {} {}",
+            preferences.humidity.0,
+            preferences.humidity.1
+        )
+        .unwrap(),
+        Command::GetTime => {
+            let (time, date) = preferences.get_date_formatted();
+            uwrite!(reply, "TIME {} {}", time.as_str(), date.as_str()).unwrap();
+        }
+        Command::SetTemp(low, high) => {
+            preferences.temperature = (low, high);
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetNightTemp(low, high) => {
+            preferences.night_temperature = (low, high);
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetDayNight(day, night) => {
+            preferences.day_start_hour = day;
+            preferences.night_start_hour = night;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetHumidity(low, high) => {
+            preferences.humidity = (low, high);
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetTime(date) => {
+            preferences.date = date;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetLogInterval(minutes) => {
+            preferences.log_interval = minutes;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetStaleFactor(intervals) => {
+            preferences.stale_factor = intervals;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetWarmup(seconds) => {
+            preferences.warmup_secs = seconds;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetRainVent(enabled) => {
+            preferences.rain_closes_vent = enabled;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetUnits(system) => {
+            preferences.apply_unit_system(system);
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetTemperatureUnit(unit) => {
+            preferences.temperature_unit = unit;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetExpanderPin(pin, output) => {
+            preferences.expander_pins[pin as usize] = output;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetExpanderAddress(address) => {
+            preferences.expander_address = address;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetExpanderLevel(active_high) => {
+            preferences.expander_active_high = active_high;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetTemperatureModel(model) => {
+            preferences.temperature_model = model;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetActiveLevel(output, active_high) => {
+            preferences.active_levels.set(output, active_high);
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetGas(enabled) => {
+            preferences.gas_enabled = enabled;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetHeater(temp, ms) => {
+            preferences.gas_heater_temp = temp;
+            preferences.gas_heater_ms = ms;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetSelfTest(enabled) => {
+            preferences.self_test = enabled;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetEscalation(minutes) => {
+            preferences.escalation_mins = minutes;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetRecovery(failures) => {
+            preferences.recovery_threshold = failures;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetHeatFullBelow(below) => {
+            preferences.heat_full_below = below;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetSmokeSamples(samples) => {
+            preferences.smoke_samples = samples;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetSensorTimeout(ms) => {
+            preferences.sensor_timeout_ms = ms;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetMildew(spread, vent) => {
+            preferences.mildew_spread = spread;
+            preferences.mildew_vent = vent;
+            reply.push_str("OK").unwrap();
+        }
+        Command::GetConfig => {
+            uwrite!(reply, "CONFIG {}", encode_config_blob(preferences).as_str()).unwrap()
+        }
+        Command::SetConfig(bytes) => match Preferences::from_bytes(&bytes) {
+            Ok(config) => {
+                *preferences = Preferences {
+                    date: preferences.date,
+                    ..config
+                };
+                reply.push_str("OK").unwrap();
+            }
+            Err(_) => reply.push_str("ERR CONFIG VALUE").unwrap(),
+        },
+        Command::SetFaultPolicy(policy) => {
+            preferences.fault_policy = policy;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetGddBase(base) => {
+            preferences.gdd_base = base;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetCo2(policy, ppm) => {
+            preferences.co2_policy = policy;
+            if let Some(ppm) = ppm {
+                preferences.co2_vent_ppm = ppm;
+            }
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetWateringSeconds(seconds) => {
+            preferences.watering_seconds = seconds;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetDehumidify(enabled) => {
+            preferences.dehumidify_vent = enabled;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetVentCurve(curve) => {
+            preferences.vent_curve = curve;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetBuzzer(passive) => {
+            preferences.passive_buzzer = passive;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetVolume(volume) => {
+            preferences.buzzer_volume = volume;
+            reply.push_str("OK").unwrap();
+        }
+        Command::SetDefaultScreen(screen) => {
+            preferences.default_screen = screen;
+            reply.push_str("OK").unwrap();
+        }
+        Command::ShowScreen(screen) => {
+            preferences.hidden_screens &= !screen.mask();
+            reply.push_str("OK").unwrap();
+        }
+        Command::HideScreen(screen) => {
+            let hidden = preferences.hidden_screens | screen.mask();
+            if hidden == Screen::ALL_HIDDEN {
+                reply.push_str("ERR LAST SCREEN").unwrap();
+            } else {
+                preferences.hidden_screens = hidden;
+                reply.push_str("OK").unwrap();
+            }
+        }
+        Command::WaterNow(_) | Command::GetLog | Command::ResetGdd => reply.push_str("OK").unwrap(),
+    }
+    reply
+}
+
+/// Collects received bytes into lines.
+/// Lines end with `\n`; a `\r` is ignored so both `\n` and `\r\n` endings work
+///
+/// ## Example:
+/// ```rust
+/// use gem_rs::uart::{LineBuffer, ParseError, LINE_LEN};
+///
+/// let mut buffer = LineBuffer::default();
+/// let mut lines = b"GET TEMP\r\n".iter().filter_map(|byte| buffer.push(*byte));
+/// assert_eq!(lines.next().unwrap().unwrap(), "GET TEMP");
+///
+/// // Lines that are too long are dropped whole
+/// for _ in 0..LINE_LEN + 1 {
+///     assert!(buffer.push(b'A').is_none());
+/// }
+/// assert_eq!(buffer.push(b'\n'), Some(Err(ParseError::LineTooLong)));
+/// assert_eq!(buffer.push(b'X'), None);
+/// assert_eq!(buffer.push(b'\n').unwrap().unwrap(), "X");
+/// ```
+#[derive(Default)]
+pub struct LineBuffer {
+    line: String<LINE_LEN>,
+    overflowed: bool,
+}
+
+impl LineBuffer {
+    /// Adds a received byte to the current line
+    ///
+    /// - param byte: the received byte
+    ///
+    /// returns the finished line once a line ending arrives,
+    /// or [ParseError::LineTooLong] if it did not fit
+    pub fn push(&mut self, byte: u8) -> Option<Result<String<LINE_LEN>, ParseError>> {
+        match byte {
+            b'\n' => {
+                let line = core::mem::take(&mut self.line);
+                if core::mem::take(&mut self.overflowed) {
+                    Some(Err(ParseError::LineTooLong))
+                } else {
+                    Some(Ok(line))
+                }
+            }
+            b'\r' => None,
+            _ => {
+                // Non-ASCII bytes can't be part of a command
+                let accepted = byte.is_ascii() && self.line.push(byte as char).is_ok();
+                if !accepted {
+                    self.overflowed = true;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Reads every byte waiting on the [Uart] without blocking
+///
+/// - param uart: [Uart] instance
+/// - param buffer: [LineBuffer] instance
+///
+/// returns the first line finished by the received bytes, if any
+#[cfg(feature = "hardware")]
+pub fn poll_line(
+    uart: &Uart,
+    buffer: &mut LineBuffer,
+) -> Option<Result<String<LINE_LEN>, ParseError>> {
+    let mut byte = [0u8; 1];
+    while uart.uart_is_readable() {
+        if uart.read_raw(&mut byte).is_ok() {
+            if let Some(line) = buffer.push(byte[0]) {
+                return Some(line);
+            }
+        }
+    }
+    None
+}
+
+/// Sends a reply line over the [Uart]
+///
+/// - param uart: [Uart] instance
+/// - param reply: the reply without its line ending
+#[cfg(feature = "hardware")]
+pub fn write_reply(uart: &Uart, reply: &str) {
+    uart.write_full_blocking(reply.as_bytes());
+    uart.write_full_blocking(b"\r\n");
+}
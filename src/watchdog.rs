@@ -0,0 +1,38 @@
+use crate::preferences::{MAX_HEATER_MS, MAX_SENSOR_TIMEOUT_MS};
+use crate::selftest::SELF_TEST_WATCHDOG_MS;
+use crate::timer::SENSOR_DELAY;
+use core::cell::RefCell;
+use critical_section::Mutex;
+use rp_pico::hal::fugit::MicrosDurationU32;
+use rp_pico::hal::watchdog::Watchdog;
+
+/// How long core0 may go without feeding the watchdog before it resets the board, in milliseconds.
+/// Covers the longest wait of a pass through the main loop: sleeping until the next reading,
+/// then reading the longest gas measurement on a bus that runs out the longest sensor timeout,
+/// plus a second for flash writes and the LCD
+pub const LOOP_WATCHDOG_MS: u32 =
+    SENSOR_DELAY as u32 + MAX_HEATER_MS as u32 + MAX_SENSOR_TIMEOUT_MS as u32 + 1000;
+
+// The RP2040's watchdog can't count any longer
+const _: () = assert!(LOOP_WATCHDOG_MS <= SELF_TEST_WATCHDOG_MS);
+
+/// The running watchdog; kept here so the screens and prompts that block core0 can feed it too
+static WATCHDOG: Mutex<RefCell<Option<Watchdog>>> = Mutex::new(RefCell::new(None));
+
+/// Starts the watchdog for the main loop with a period of [LOOP_WATCHDOG_MS].
+/// From here on, anything that keeps core0 busy for longer must call [feed_watchdog]
+///
+/// - param watchdog: [Watchdog] instance; the self-test must be over, as it stops the watchdog when it finishes
+pub fn start_loop_watchdog(mut watchdog: Watchdog) {
+    watchdog.start(MicrosDurationU32::millis(LOOP_WATCHDOG_MS));
+    critical_section::with(|cs| WATCHDOG.borrow_ref_mut(cs).replace(watchdog));
+}
+
+/// Feeds the watchdog; does nothing before [start_loop_watchdog]
+pub fn feed_watchdog() {
+    critical_section::with(|cs| {
+        if let Some(watchdog) = WATCHDOG.borrow_ref(cs).as_ref() {
+            watchdog.feed();
+        }
+    });
+}